@@ -0,0 +1,8 @@
+-- Persistent views, stored as the original CREATE VIEW statement and re-planned on use
+CREATE TABLE "view" (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    collection_id BIGINT NOT NULL REFERENCES collection(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    definition VARCHAR NOT NULL,
+    CONSTRAINT view_name_unique UNIQUE(name, collection_id)
+);
@@ -0,0 +1,2 @@
+-- The defining query of materialized views, which are otherwise stored as regular tables
+ALTER TABLE "table" ADD COLUMN definition VARCHAR;
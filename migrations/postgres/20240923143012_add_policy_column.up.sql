@@ -0,0 +1,3 @@
+-- Column masking policies, replacing the value of the column with the policy expression
+-- instead of filtering rows
+ALTER TABLE policy ADD COLUMN column_name VARCHAR;
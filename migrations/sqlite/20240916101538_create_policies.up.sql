@@ -0,0 +1,10 @@
+-- Row-level security policies, restricting the table rows that (some) principals can read
+CREATE TABLE policy (
+    id INTEGER NOT NULL PRIMARY KEY,
+    table_id BIGINT NOT NULL REFERENCES "table"(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    predicate VARCHAR NOT NULL,
+    -- JSON list of the principals that the policy applies to, or NULL for everyone
+    principals VARCHAR,
+    CONSTRAINT policy_name_unique UNIQUE(name, table_id)
+);
@@ -0,0 +1,222 @@
+//! Append-only audit trail of the queries and writes received by the frontends, persisted as
+//! JSON lines to a file that gets rotated once it grows past the configured size.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::auth::Principal;
+use crate::config::schema::AuditLogConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditFrontend {
+    Http,
+    Flight,
+    Postgres,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOperation {
+    Query,
+    Upload,
+    Sync,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditTableVersion {
+    pub table: String,
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEvent {
+    pub timestamp: String,
+    pub principal: String,
+    pub frontend: AuditFrontend,
+    pub operation: AuditOperation,
+    pub database: String,
+    // Set for queries, whereas uploads and syncs name the table they write to instead
+    pub sql: Option<String>,
+    pub target: Option<String>,
+    pub read_only: bool,
+    // Only known for syncs and uploads; SQL writes are executed while planning, which doesn't
+    // report back how many rows they touched
+    pub rows_affected: Option<u64>,
+    // Versions of the tables that the operation wrote to, as of right after it finished
+    pub table_versions: Vec<AuditTableVersion>,
+    pub error: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(
+        frontend: AuditFrontend,
+        operation: AuditOperation,
+        principal: &Principal,
+        database: &str,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            principal: principal.name().to_string(),
+            frontend,
+            operation,
+            database: database.to_string(),
+            sql: None,
+            target: None,
+            read_only: false,
+            rows_affected: None,
+            table_versions: vec![],
+            error: None,
+        }
+    }
+
+    pub fn query(
+        frontend: AuditFrontend,
+        principal: &Principal,
+        database: &str,
+        sql: &str,
+    ) -> Self {
+        Self {
+            sql: Some(sql.to_string()),
+            ..Self::new(frontend, AuditOperation::Query, principal, database)
+        }
+    }
+
+    pub fn with_error(mut self, error: Option<String>) -> Self {
+        self.error = error;
+        self
+    }
+}
+
+struct AuditFile {
+    file: File,
+    size: u64,
+}
+
+pub struct AuditLog {
+    config: AuditLogConfig,
+    current: Mutex<AuditFile>,
+}
+
+impl AuditLog {
+    pub fn try_new(config: &AuditLogConfig) -> io::Result<Self> {
+        let file = Self::open(&config.path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            config: config.clone(),
+            current: Mutex::new(AuditFile { file, size }),
+        })
+    }
+
+    fn open(path: &str) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    // Failing to write out an event shouldn't fail the operation itself, so just log the error
+    pub fn log(&self, event: &AuditEvent) {
+        if let Err(err) = self.write(event) {
+            warn!("Error writing to the audit log {}: {err}", self.config.path);
+        }
+    }
+
+    fn write(&self, event: &AuditEvent) -> io::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+
+        let mut current = self.current.lock().expect("audit log lock poisoned");
+        if current.size > 0
+            && current.size + line.len() as u64 > self.config.max_file_size
+        {
+            *current = self.rotate()?;
+        }
+
+        current.file.write_all(&line)?;
+        current.size += line.len() as u64;
+        Ok(())
+    }
+
+    // Shift the rotated files by one (`<path>` becomes `<path>.1`, `<path>.1` becomes `<path>.2`
+    // and so on), dropping the oldest one, and start over with an empty file
+    fn rotate(&self) -> io::Result<AuditFile> {
+        let path = &self.config.path;
+        let rotated = |n: usize| format!("{path}.{n}");
+
+        if self.config.max_files == 0 {
+            fs::remove_file(path)?;
+        } else {
+            for n in (1..self.config.max_files).rev() {
+                if fs::metadata(rotated(n)).is_ok() {
+                    fs::rename(rotated(n), rotated(n + 1))?;
+                }
+            }
+            fs::rename(path, rotated(1))?;
+        }
+
+        Ok(AuditFile {
+            file: Self::open(path)?,
+            size: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use crate::auth::Principal;
+    use crate::config::schema::AuditLogConfig;
+
+    use super::{AuditEvent, AuditFrontend, AuditLog};
+
+    #[test]
+    fn test_audit_log_rotation() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit.jsonl").to_str().unwrap().to_string();
+
+        let event = |n: usize| {
+            AuditEvent::query(
+                AuditFrontend::Http,
+                &Principal::Anonymous,
+                "default",
+                &format!("SELECT {n}"),
+            )
+        };
+        let line_length = serde_json::to_vec(&event(0)).unwrap().len() as u64 + 1;
+
+        // Fit two events per file, and keep two rotated files around
+        let audit_log = AuditLog::try_new(&AuditLogConfig {
+            path: path.clone(),
+            max_file_size: 2 * line_length,
+            max_files: 2,
+        })
+        .unwrap();
+        for n in 0..7 {
+            audit_log.log(&event(n));
+        }
+
+        let sql = |path: String| -> Vec<String> {
+            fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| {
+                    let event: serde_json::Value = serde_json::from_str(line).unwrap();
+                    assert_eq!(event["principal"], "anonymous");
+                    assert_eq!(event["frontend"], "http");
+                    event["sql"].as_str().unwrap().to_string()
+                })
+                .collect()
+        };
+        assert_eq!(sql(path.clone()), vec!["SELECT 6"]);
+        assert_eq!(sql(format!("{path}.1")), vec!["SELECT 4", "SELECT 5"]);
+        assert_eq!(sql(format!("{path}.2")), vec!["SELECT 2", "SELECT 3"]);
+        assert!(fs::metadata(format!("{path}.3")).is_err());
+    }
+}
@@ -1,6 +1,12 @@
+use std::sync::Arc;
+
 use crate::{
-    config::schema::{str_to_hex_hash, AccessSettings, HttpFrontend},
+    config::schema::{
+        str_to_hex_hash, AccessSettings, AccessToken, Frontend, Grant, GrantAccess,
+        HttpFrontend, JwtConfig,
+    },
     frontend::http_utils::ApiError,
+    jwt::{JwtIdentity, JwtValidator},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -8,11 +14,31 @@ pub enum Principal {
     Anonymous,
     Writer,
     Reader,
+    // Bearer of one of the named tokens
+    Token(String),
+    // Bearer of a valid JWT
+    Jwt(JwtIdentity),
+}
+
+impl Principal {
+    // The name by which row-level security policies refer to the principal
+    pub fn name(&self) -> &str {
+        match self {
+            Principal::Anonymous => "anonymous",
+            Principal::Writer => "writer",
+            Principal::Reader => "reader",
+            Principal::Token(name) => name,
+            Principal::Jwt(identity) => &identity.principal,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Resource {
-    Database,
+    // Any resource at all, i.e. whether the action can be performed anywhere
+    Any,
+    Database(String),
+    Schema { database: String, schema: String },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,6 +51,8 @@ pub enum Action {
 pub struct AccessPolicy {
     pub read: AccessSettings,
     pub write: AccessSettings,
+    pub tokens: Vec<AccessToken>,
+    pub jwt: Option<Arc<JwtValidator>>,
 }
 
 impl AccessPolicy {
@@ -32,20 +60,30 @@ impl AccessPolicy {
         Self {
             read: config.read_access.clone(),
             write: config.write_access.clone(),
+            tokens: config.tokens.clone(),
+            jwt: None,
         }
     }
 
+    // The HTTP frontend's access settings, for the frontends that have none of their own
+    pub fn from_frontend_config(config: &Frontend) -> Self {
+        Self::from_config(&config.http.clone().unwrap_or_default())
+            .with_jwt_config(config.jwt.as_ref())
+    }
+
     pub fn free_for_all() -> Self {
         Self {
             read: AccessSettings::Any,
             write: AccessSettings::Any,
+            tokens: vec![],
+            jwt: None,
         }
     }
 
     pub fn with_read_disabled(self) -> Self {
         Self {
             read: AccessSettings::Off,
-            write: self.write,
+            ..self
         }
     }
 
@@ -54,31 +92,101 @@ impl AccessPolicy {
             read: AccessSettings::Password {
                 sha256_hash: str_to_hex_hash(password),
             },
-            write: self.write,
+            ..self
         }
     }
 
     pub fn with_write_disabled(self) -> Self {
         Self {
-            read: self.read,
             write: AccessSettings::Off,
+            ..self
         }
     }
 
     pub fn with_write_password(self, password: &str) -> Self {
         Self {
-            read: self.read,
             write: AccessSettings::Password {
                 sha256_hash: str_to_hex_hash(password),
             },
+            ..self
         }
     }
+
+    pub fn with_token(mut self, name: &str, token: &str, grants: Vec<Grant>) -> Self {
+        self.tokens.push(AccessToken {
+            name: name.to_string(),
+            sha256_hash: str_to_hex_hash(token),
+            grants,
+        });
+        self
+    }
+
+    pub fn with_jwt(self, validator: Arc<JwtValidator>) -> Self {
+        Self {
+            jwt: Some(validator),
+            ..self
+        }
+    }
+
+    // The JWKS file is checked when loading the config, so failing to load it here is unexpected
+    pub fn with_jwt_config(self, config: Option<&JwtConfig>) -> Self {
+        match config {
+            None => self,
+            Some(config) => self.with_jwt(Arc::new(
+                JwtValidator::try_new(config).expect("Error loading the JWKS file"),
+            )),
+        }
+    }
+}
+
+impl Grant {
+    fn matches(&self, resource: &Resource) -> bool {
+        let matches_database =
+            |database: &str| self.database == "*" || self.database == database;
+
+        match resource {
+            Resource::Any => true,
+            // Database-wide grants only
+            Resource::Database(database) => {
+                matches_database(database) && self.schema.is_none()
+            }
+            Resource::Schema { database, schema } => {
+                matches_database(database)
+                    && (self.schema.is_none() || self.schema.as_ref() == Some(schema))
+            }
+        }
+    }
+
+    fn allows(&self, action: &Action) -> bool {
+        matches!(
+            (&self.access, action),
+            (GrantAccess::Write, _) | (GrantAccess::Read, Action::Read)
+        )
+    }
 }
 
 pub fn token_to_principal(
     token: Option<String>,
     policy: &AccessPolicy,
 ) -> Result<Principal, ApiError> {
+    // Named tokens take precedence over the global read/write settings
+    if let Some(t) = &token {
+        let hash = str_to_hex_hash(t);
+        if let Some(access_token) = policy.tokens.iter().find(|at| at.sha256_hash == hash)
+        {
+            return Ok(Principal::Token(access_token.name.clone()));
+        }
+
+        if let Some(validator) = &policy.jwt
+            && JwtValidator::is_jwt(t)
+        {
+            return validator
+                .validate(t)
+                .map(Principal::Jwt)
+                .map_err(ApiError::InvalidJwt);
+        }
+    }
+
     match (token, &policy.write, &policy.read) {
         // If both read and write require a password and the user didn't pass a token: error
         (
@@ -112,9 +220,27 @@ pub fn token_to_principal(
 pub fn can_perform_action(
     principal: &Principal,
     action: Action,
-    _: Resource,
+    resource: &Resource,
     policy: &AccessPolicy,
 ) -> bool {
+    // Token bearers can do whatever their grants allow on the resource
+    let grants: Vec<&Grant> = match principal {
+        Principal::Token(name) => policy
+            .tokens
+            .iter()
+            .filter(|at| &at.name == name)
+            .flat_map(|at| &at.grants)
+            .collect(),
+        Principal::Jwt(identity) => identity.grants.iter().collect(),
+        _ => vec![],
+    };
+    if grants
+        .iter()
+        .any(|grant| grant.matches(resource) && grant.allows(&action))
+    {
+        return true;
+    }
+
     matches!(
         (principal, action, &policy.read, &policy.write),
         // Writer can do anything (note we don't issue Writer/Reader if the policy for Write/Read doesn't have a password)
@@ -134,22 +260,38 @@ pub struct UserContext {
 }
 
 impl UserContext {
+    // Whether the action is allowed at all; for token bearers, the resources they actually
+    // access must then be checked with `can_access`.
     pub fn can_perform_action(&self, action: Action) -> bool {
-        can_perform_action(&self.principal, action, Resource::Database, &self.policy)
+        can_perform_action(&self.principal, action, &Resource::Any, &self.policy)
+    }
+
+    pub fn can_access(&self, action: Action, resource: &Resource) -> bool {
+        can_perform_action(&self.principal, action, resource, &self.policy)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
     use crate::{
-        auth::{Action, UserContext},
+        auth::{Action, Resource, UserContext},
+        config::schema::{Grant, GrantAccess},
         frontend::http_utils::ApiError,
+        jwt::{
+            tests::{jwt_config, now, sign},
+            JwtIdentity, JwtValidator,
+        },
     };
 
     use super::{token_to_principal, AccessPolicy, Principal};
 
     const READ_PW: &str = "read_password";
     const WRITE_PW: &str = "write_password";
+    const NAMED_TOKEN: &str = "named_token";
 
     fn free_for_all() -> AccessPolicy {
         AccessPolicy::free_for_all()
@@ -175,6 +317,33 @@ mod tests {
             .with_read_password(READ_PW)
     }
 
+    fn grant(database: &str, schema: Option<&str>, access: GrantAccess) -> Grant {
+        Grant {
+            database: database.to_string(),
+            schema: schema.map(|s| s.to_string()),
+            access,
+        }
+    }
+
+    fn schema(database: &str, schema: &str) -> Resource {
+        Resource::Schema {
+            database: database.to_string(),
+            schema: schema.to_string(),
+        }
+    }
+
+    fn read_pw_write_pw_named_token() -> AccessPolicy {
+        read_pw_write_pw().with_token(
+            "analytics",
+            NAMED_TOKEN,
+            vec![
+                grant("default", Some("public"), GrantAccess::Write),
+                grant("*", Some("shared"), GrantAccess::Read),
+                grant("reporting", None, GrantAccess::Read),
+            ],
+        )
+    }
+
     #[test]
     fn test_all_allowed_disallows_token() {
         assert!(matches!(
@@ -330,4 +499,111 @@ mod tests {
         assert!(context.can_perform_action(Action::Read));
         assert!(context.can_perform_action(Action::Write));
     }
+
+    #[test]
+    fn test_named_token_principal() {
+        assert!(matches!(
+            token_to_principal(
+                Some(NAMED_TOKEN.to_string()),
+                &read_pw_write_pw_named_token()
+            ),
+            Ok(Principal::Token(name)) if name == "analytics"
+        ));
+
+        // Named tokens are still useful even if the reads/writes are open to everyone
+        assert!(matches!(
+            token_to_principal(
+                Some(NAMED_TOKEN.to_string()),
+                &free_for_all().with_token("analytics", NAMED_TOKEN, vec![])
+            ),
+            Ok(Principal::Token(name)) if name == "analytics"
+        ));
+    }
+
+    #[test]
+    fn test_named_token_grants() {
+        let context = UserContext {
+            principal: Principal::Token("analytics".to_string()),
+            policy: read_pw_write_pw_named_token(),
+        };
+
+        assert!(context.can_perform_action(Action::Read));
+        assert!(context.can_perform_action(Action::Write));
+
+        assert!(context.can_access(Action::Read, &schema("default", "public")));
+        assert!(context.can_access(Action::Write, &schema("default", "public")));
+        assert!(!context.can_access(Action::Read, &schema("default", "private")));
+        assert!(context.can_access(Action::Read, &schema("other", "shared")));
+        assert!(!context.can_access(Action::Write, &schema("other", "shared")));
+        assert!(context.can_access(Action::Read, &schema("reporting", "private")));
+
+        // Database-wide actions require database-wide grants
+        assert!(!context
+            .can_access(Action::Write, &Resource::Database("default".to_string())));
+        assert!(context
+            .can_access(Action::Read, &Resource::Database("reporting".to_string())));
+    }
+
+    #[test]
+    fn test_named_token_without_write_grants_cant_write() {
+        let context = UserContext {
+            principal: Principal::Token("viewer".to_string()),
+            policy: read_pw_write_pw().with_token(
+                "viewer",
+                NAMED_TOKEN,
+                vec![grant("default", None, GrantAccess::Read)],
+            ),
+        };
+
+        assert!(context.can_perform_action(Action::Read));
+        assert!(!context.can_perform_action(Action::Write));
+        assert!(!context.can_access(Action::Write, &schema("default", "public")));
+    }
+
+    #[test]
+    fn test_jwt_principal_and_grants() {
+        let (_file, config) = jwt_config();
+        let policy = read_pw_write_pw()
+            .with_jwt(Arc::new(JwtValidator::try_new(&config).unwrap()));
+
+        let token = sign(json!({
+            "sub": "alice",
+            "exp": now() + 3600,
+            "databases": ["analytics"],
+            "access": "write",
+        }));
+        let principal = token_to_principal(Some(token), &policy).unwrap();
+        assert_eq!(
+            principal,
+            Principal::Jwt(JwtIdentity {
+                principal: "alice".to_string(),
+                grants: vec![grant("analytics", None, GrantAccess::Write)],
+            })
+        );
+
+        let context = UserContext { principal, policy };
+        assert!(context.can_access(Action::Write, &schema("analytics", "public")));
+        assert!(context
+            .can_access(Action::Write, &Resource::Database("analytics".to_string())));
+        assert!(!context.can_access(Action::Read, &schema("default", "public")));
+    }
+
+    #[test]
+    fn test_jwt_expired() {
+        let (_file, config) = jwt_config();
+        let policy = read_pw_write_pw()
+            .with_jwt(Arc::new(JwtValidator::try_new(&config).unwrap()));
+
+        let token = sign(json!({"sub": "alice", "exp": now() - 3600}));
+        assert!(matches!(
+            token_to_principal(Some(token), &policy),
+            Err(ApiError::InvalidJwt(_))
+        ));
+
+        // The global passwords still work alongside JWTs
+        assert!(matches!(
+            token_to_principal(Some(WRITE_PW.to_string()), &policy),
+            Ok(Principal::Writer)
+        ));
+    }
 }
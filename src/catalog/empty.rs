@@ -1,9 +1,12 @@
 /// Empty metastore that raises errors if the API caller didn't pass an inline metastore
 /// over the Flight gRPC interface.
 use crate::catalog::{
-    CatalogResult, CatalogStore, FunctionStore, SchemaStore, TableStore,
+    CatalogResult, CatalogStore, FunctionStore, PolicyStore, SchemaStore, TableStore,
+    ViewStore,
+};
+use crate::repository::interface::{
+    AllDatabaseFunctionsResult, AllDatabasePoliciesResult, AllDatabaseViewsResult,
 };
-use crate::repository::interface::AllDatabaseFunctionsResult;
 use async_trait::async_trait;
 use clade::schema::ListSchemaResponse;
 
@@ -34,3 +37,23 @@ impl FunctionStore for EmptyStore {
         Ok(vec![])
     }
 }
+
+#[async_trait]
+impl ViewStore for EmptyStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        Ok(vec![])
+    }
+}
+
+#[async_trait]
+impl PolicyStore for EmptyStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabasePoliciesResult>> {
+        Ok(vec![])
+    }
+}
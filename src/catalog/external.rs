@@ -1,7 +1,10 @@
 use crate::catalog::{
-    CatalogResult, CatalogStore, FunctionStore, SchemaStore, TableStore,
+    CatalogResult, CatalogStore, FunctionStore, PolicyStore, SchemaStore, TableStore,
+    ViewStore,
+};
+use crate::repository::interface::{
+    AllDatabaseFunctionsResult, AllDatabasePoliciesResult, AllDatabaseViewsResult,
 };
-use crate::repository::interface::AllDatabaseFunctionsResult;
 use clade::schema::schema_store_service_client::SchemaStoreServiceClient;
 use clade::schema::{ListSchemaRequest, ListSchemaResponse};
 use tonic::transport::{channel::Channel, Endpoint, Error};
@@ -56,3 +59,23 @@ impl FunctionStore for ExternalStore {
         Ok(vec![])
     }
 }
+
+#[tonic::async_trait]
+impl ViewStore for ExternalStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        Ok(vec![])
+    }
+}
+
+#[tonic::async_trait]
+impl PolicyStore for ExternalStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabasePoliciesResult>> {
+        Ok(vec![])
+    }
+}
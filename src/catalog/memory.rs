@@ -1,7 +1,10 @@
 use crate::catalog::{
-    CatalogResult, CatalogStore, FunctionStore, SchemaStore, TableStore,
+    CatalogResult, CatalogStore, FunctionStore, PolicyStore, SchemaStore, TableStore,
+    ViewStore,
+};
+use crate::repository::interface::{
+    AllDatabaseFunctionsResult, AllDatabasePoliciesResult, AllDatabaseViewsResult,
 };
-use crate::repository::interface::AllDatabaseFunctionsResult;
 use clade::schema::ListSchemaResponse;
 
 #[derive(Clone)]
@@ -31,3 +34,23 @@ impl FunctionStore for MemoryStore {
         Ok(vec![])
     }
 }
+
+#[tonic::async_trait]
+impl ViewStore for MemoryStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        Ok(vec![])
+    }
+}
+
+#[tonic::async_trait]
+impl PolicyStore for MemoryStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabasePoliciesResult>> {
+        Ok(vec![])
+    }
+}
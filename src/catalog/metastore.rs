@@ -2,12 +2,16 @@ use crate::catalog::external::ExternalStore;
 use crate::catalog::repository::RepositoryStore;
 use crate::catalog::{
     CatalogError, CatalogResult, CatalogStore, CreateFunctionError, FunctionStore,
-    SchemaStore, TableStore,
+    PolicyStore, SchemaStore, TableStore, ViewStore,
 };
 
 use crate::object_store::factory::ObjectStoreFactory;
-use crate::provider::{SeafowlDatabase, SeafowlFunction, SeafowlSchema};
-use crate::repository::interface::{AllDatabaseFunctionsResult, Repository};
+use crate::provider::{SeafowlDatabase, SeafowlFunction, SeafowlSchema, TablePolicy};
+use crate::queries::QueryRegistry;
+use crate::repository::interface::{
+    AllDatabaseFunctionsResult, AllDatabasePoliciesResult, AllDatabaseViewsResult,
+    Repository,
+};
 use crate::system_tables::SystemSchemaProvider;
 use crate::wasm_udf::data_types::{
     CreateFunctionDataType, CreateFunctionDetails, CreateFunctionLanguage,
@@ -40,6 +44,8 @@ pub struct Metastore {
     pub schemas: Arc<dyn SchemaStore>,
     pub tables: Arc<dyn TableStore>,
     pub functions: Arc<dyn FunctionStore>,
+    pub views: Arc<dyn ViewStore>,
+    pub policies: Arc<dyn PolicyStore>,
     staging_schema: Arc<MemorySchemaProvider>,
     pub object_stores: Arc<ObjectStoreFactory>,
 }
@@ -56,7 +62,9 @@ impl Metastore {
             catalogs: repository_store.clone(),
             schemas: repository_store.clone(),
             tables: repository_store.clone(),
-            functions: repository_store,
+            functions: repository_store.clone(),
+            views: repository_store.clone(),
+            policies: repository_store,
             staging_schema,
             object_stores,
         }
@@ -71,7 +79,9 @@ impl Metastore {
             catalogs: external_store.clone(),
             schemas: external_store.clone(),
             tables: external_store.clone(),
-            functions: external_store,
+            functions: external_store.clone(),
+            views: external_store.clone(),
+            policies: external_store,
             staging_schema,
             object_stores,
         }
@@ -86,7 +96,9 @@ impl Metastore {
             catalogs: memory_store.clone(),
             schemas: memory_store.clone(),
             tables: memory_store.clone(),
-            functions: memory_store,
+            functions: memory_store.clone(),
+            views: memory_store.clone(),
+            policies: memory_store,
             staging_schema,
             object_stores,
         }
@@ -99,7 +111,9 @@ impl Metastore {
             catalogs: empty_store.clone(),
             schemas: empty_store.clone(),
             tables: empty_store.clone(),
-            functions: empty_store,
+            functions: empty_store.clone(),
+            views: empty_store.clone(),
+            policies: empty_store,
             staging_schema,
             object_stores,
         }
@@ -108,6 +122,8 @@ impl Metastore {
     pub async fn build_catalog(
         &self,
         catalog_name: &str,
+        queries: Arc<QueryRegistry>,
+        pinned_versions: HashMap<String, i64>,
     ) -> CatalogResult<SeafowlDatabase> {
         let catalog_schemas = self.schemas.list(catalog_name).await?;
 
@@ -118,9 +134,37 @@ impl Metastore {
             .map(|store| (store.name, (store.location, store.options)))
             .collect();
 
+        // Group the view definitions by the schema they belong to
+        let mut views: HashMap<String, Vec<AllDatabaseViewsResult>> = HashMap::new();
+        for view in self.views.list(catalog_name).await? {
+            views
+                .entry(view.collection_name.clone())
+                .or_default()
+                .push(view);
+        }
+
+        // Likewise for the row-level security policies
+        let mut policies: HashMap<String, Vec<AllDatabasePoliciesResult>> =
+            HashMap::new();
+        for policy in self.policies.list(catalog_name).await? {
+            policies
+                .entry(policy.collection_name.clone())
+                .or_default()
+                .push(policy);
+        }
+
         // Turn the list of all collections, tables and their columns into a nested map.
+        let pinned_versions = Arc::new(pinned_versions);
         let schemas = stream::iter(catalog_schemas.schemas)
-            .then(|schema| self.build_schema(schema, &store_options))
+            .then(|schema| {
+                self.build_schema(
+                    schema,
+                    &store_options,
+                    &views,
+                    &policies,
+                    &pinned_versions,
+                )
+            })
             .try_collect()
             .await?;
 
@@ -130,7 +174,11 @@ impl Metastore {
             name: name.clone(),
             schemas,
             staging_schema: self.staging_schema.clone(),
-            system_schema: Arc::new(SystemSchemaProvider::new(name, self.tables.clone())),
+            system_schema: Arc::new(SystemSchemaProvider::new(
+                name,
+                self.tables.clone(),
+                queries,
+            )),
         })
     }
 
@@ -138,6 +186,9 @@ impl Metastore {
         &self,
         schema: SchemaObject,
         store_options: &HashMap<String, LocationAndOptions>,
+        views: &HashMap<String, Vec<AllDatabaseViewsResult>>,
+        policies: &HashMap<String, Vec<AllDatabasePoliciesResult>>,
+        pinned_versions: &Arc<HashMap<String, i64>>,
     ) -> CatalogResult<(Arc<str>, Arc<SeafowlSchema>)> {
         let schema_name = schema.name;
 
@@ -146,11 +197,44 @@ impl Metastore {
             .try_collect()
             .await?;
 
+        // Views only get planned once they're referenced in a query, so just keep the definitions
+        let views = views
+            .get(&schema_name)
+            .into_iter()
+            .flatten()
+            .map(|view| {
+                (
+                    Arc::from(view.name.as_str()),
+                    Arc::from(view.definition.as_str()),
+                )
+            })
+            .collect();
+
+        let mut table_policies: HashMap<Arc<str>, Vec<TablePolicy>> = HashMap::new();
+        for policy in policies.get(&schema_name).into_iter().flatten() {
+            table_policies
+                .entry(Arc::from(policy.table_name.as_str()))
+                .or_default()
+                .push(TablePolicy {
+                    name: policy.name.clone(),
+                    column: policy.column_name.clone(),
+                    expression: policy.predicate.clone(),
+                    principals: policy
+                        .principals
+                        .as_deref()
+                        .map(serde_json::from_str)
+                        .transpose()?,
+                });
+        }
+
         Ok((
             Arc::from(schema_name.clone()),
             Arc::new(SeafowlSchema {
                 name: Arc::from(schema_name),
                 tables,
+                views,
+                policies: table_policies,
+                pinned_versions: pinned_versions.clone(),
             }),
         ))
     }
@@ -1,7 +1,7 @@
 use crate::repository::interface::{
-    AllDatabaseFunctionsResult, CollectionRecord, DatabaseRecord,
-    DroppedTableDeletionStatus, DroppedTablesResult, TableId, TableRecord,
-    TableVersionId, TableVersionsResult,
+    AllDatabaseFunctionsResult, AllDatabasePoliciesResult, AllDatabaseViewsResult,
+    CollectionRecord, DatabaseRecord, DroppedTableDeletionStatus, DroppedTablesResult,
+    TableId, TableRecord, TableVersionId, TableVersionsResult,
 };
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 use arrow_schema::Schema;
@@ -16,6 +16,7 @@ pub mod external;
 pub mod memory;
 pub mod metastore;
 mod repository;
+pub mod versions;
 
 pub const DEFAULT_DB: &str = "default";
 pub const DEFAULT_SCHEMA: &str = "public";
@@ -50,6 +51,20 @@ pub enum CatalogError {
     #[error("Table {name:?} already exists")]
     TableAlreadyExists { name: String },
 
+    // View errors
+    #[error("View {name:?} doesn't exist")]
+    ViewDoesNotExist { name: String },
+
+    #[error("View {name:?} already exists")]
+    ViewAlreadyExists { name: String },
+
+    // Policy errors
+    #[error("Policy {name:?} on table {table_name:?} doesn't exist")]
+    PolicyDoesNotExist { name: String, table_name: String },
+
+    #[error("Policy {name:?} on table {table_name:?} already exists")]
+    PolicyAlreadyExists { name: String, table_name: String },
+
     // Function errors
     #[error("Function {name:?} already exists")]
     FunctionAlreadyExists { name: String },
@@ -208,6 +223,20 @@ pub trait TableStore: Sync + Send {
         not_impl()
     }
 
+    async fn get_by_uuid(&self, _uuid: Uuid) -> CatalogResult<TableRecord> {
+        not_impl()
+    }
+
+    async fn update_definition(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _definition: Option<&str>,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
     async fn create_new_version(
         &self,
         _uuid: Uuid,
@@ -302,3 +331,66 @@ pub trait FunctionStore: Sync + Send {
         not_impl()
     }
 }
+
+#[async_trait]
+pub trait ViewStore: Sync + Send {
+    async fn create(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _view_name: &str,
+        _definition: &str,
+        _or_replace: bool,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        not_impl()
+    }
+
+    async fn delete(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _view_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+}
+
+#[async_trait]
+pub trait PolicyStore: Sync + Send {
+    async fn create(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _policy_name: &str,
+        _column_name: Option<&str>,
+        _predicate: &str,
+        _principals: Option<&[String]>,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabasePoliciesResult>> {
+        not_impl()
+    }
+
+    async fn delete(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _policy_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+}
@@ -8,12 +8,13 @@ use uuid::Uuid;
 use clade::schema::{ListSchemaResponse, SchemaObject, TableObject};
 
 use crate::catalog::{
-    CatalogError, CatalogResult, CatalogStore, FunctionStore, SchemaStore, TableStore,
-    STAGING_SCHEMA,
+    CatalogError, CatalogResult, CatalogStore, FunctionStore, PolicyStore, SchemaStore,
+    TableStore, ViewStore, STAGING_SCHEMA,
 };
 use crate::repository::interface::{
-    AllDatabaseFunctionsResult, CollectionRecord, Error as RepositoryError, Repository,
-    TableId, TableVersionId, TableVersionsResult,
+    AllDatabaseFunctionsResult, AllDatabasePoliciesResult, AllDatabaseViewsResult,
+    CollectionRecord, Error as RepositoryError, Repository, TableId, TableVersionId,
+    TableVersionsResult,
 };
 use crate::repository::interface::{
     DatabaseRecord, DroppedTableDeletionStatus, DroppedTablesResult, TableRecord,
@@ -236,6 +237,33 @@ impl TableStore for RepositoryStore {
             })
     }
 
+    async fn get_by_uuid(&self, uuid: Uuid) -> CatalogResult<TableRecord> {
+        self.repository
+            .get_table_by_uuid(uuid)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::TableUuidDoesNotExist { uuid }
+                }
+                e => e.into(),
+            })
+    }
+
+    async fn update_definition(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        definition: Option<&str>,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        Ok(self
+            .repository
+            .update_table_definition(table.id, definition)
+            .await?)
+    }
+
     async fn create_new_version(
         &self,
         uuid: Uuid,
@@ -452,3 +480,149 @@ impl FunctionStore for RepositoryStore {
         }
     }
 }
+
+#[async_trait]
+impl ViewStore for RepositoryStore {
+    async fn create(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        view_name: &str,
+        definition: &str,
+        or_replace: bool,
+    ) -> CatalogResult<()> {
+        let collection = SchemaStore::get(self, catalog_name, schema_name).await?;
+
+        self.repository
+            .create_view(collection.id, view_name, definition, or_replace)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::ViewAlreadyExists {
+                        name: view_name.to_string(),
+                    }
+                }
+                RepositoryError::FKConstraintViolation(_) => {
+                    CatalogError::SchemaDoesNotExist {
+                        name: schema_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        Ok(self
+            .repository
+            .get_all_views_in_database(catalog_name)
+            .await?)
+    }
+
+    async fn delete(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        view_name: &str,
+    ) -> CatalogResult<()> {
+        let not_found = || CatalogError::ViewDoesNotExist {
+            name: view_name.to_string(),
+        };
+
+        let view = self
+            .repository
+            .get_view(catalog_name, schema_name, view_name)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    not_found()
+                }
+                e => e.into(),
+            })?;
+
+        self.repository
+            .delete_view(view.id)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    not_found()
+                }
+                e => e.into(),
+            })
+    }
+}
+
+#[async_trait]
+impl PolicyStore for RepositoryStore {
+    async fn create(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        policy_name: &str,
+        column_name: Option<&str>,
+        predicate: &str,
+        principals: Option<&[String]>,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+        let principals = principals.map(serde_json::to_string).transpose()?;
+
+        self.repository
+            .create_policy(
+                table.id,
+                policy_name,
+                column_name,
+                predicate,
+                principals.as_deref(),
+            )
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::PolicyAlreadyExists {
+                        name: policy_name.to_string(),
+                        table_name: table_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabasePoliciesResult>> {
+        Ok(self
+            .repository
+            .get_all_policies_in_database(catalog_name)
+            .await?)
+    }
+
+    async fn delete(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        policy_name: &str,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        self.repository
+            .delete_policy(table.id, policy_name)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::PolicyDoesNotExist {
+                        name: policy_name.to_string(),
+                        table_name: table_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })
+    }
+}
@@ -0,0 +1,42 @@
+//! Table versions that queries should read instead of the latest one.
+//!
+//! The data sync writer commits to each table separately, so in order to apply transactions that
+//! span multiple tables atomically, it pins each table it's about to commit to at its current
+//! version, and only moves the pins of all affected tables forward (in one go) once the whole
+//! transaction is durable. Queries take a snapshot of the pins when building the catalog, so that
+//! all tables they reference are read as of the same point.
+//!
+//! Note that the pins only apply to queries executed by this Seafowl instance. With the sync
+//! write-ahead log enabled, they are persisted alongside it and restored on startup, so that a
+//! restart in the middle of a flush doesn't expose the tables flushed until then. Without it, the
+//! pins are only kept in memory, so those tables are readable until the origin re-sends the rest
+//! of the transaction.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Default)]
+pub struct PinnedVersions {
+    // Table location URI => version to read
+    versions: Mutex<HashMap<String, i64>>,
+}
+
+impl PinnedVersions {
+    // Pin the table at the provided location to a version, unless it's already pinned
+    pub fn pin(&self, location: &str, version: i64) {
+        self.versions
+            .lock()
+            .unwrap()
+            .entry(location.to_string())
+            .or_insert(version);
+    }
+
+    // Replace all the pins at once; any table not in the new map gets unpinned
+    pub fn replace(&self, versions: HashMap<String, i64>) {
+        *self.versions.lock().unwrap() = versions;
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, i64> {
+        self.versions.lock().unwrap().clone()
+    }
+}
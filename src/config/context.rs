@@ -2,10 +2,12 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use crate::{
+    audit::AuditLog,
     catalog::{DEFAULT_DB, DEFAULT_SCHEMA},
     context::SeafowlContext,
     memory_pool::MemoryPoolMetrics,
     object_store::factory::ObjectStoreFactory,
+    queries::QueryRegistry,
     repository::{interface::Repository, sqlite::SqliteRepository},
 };
 use datafusion::execution::{
@@ -178,6 +180,19 @@ pub async fn build_context(cfg: schema::SeafowlConfig) -> Result<SeafowlContext>
         metastore.schemas.create(DEFAULT_DB, DEFAULT_SCHEMA).await?;
     }
 
+    let queries = Arc::new(QueryRegistry::new(
+        cfg.misc.query_log_size,
+        cfg.runtime.limits.clone(),
+    ));
+
+    let audit_log = cfg
+        .misc
+        .audit_log
+        .as_ref()
+        .map(AuditLog::try_new)
+        .transpose()?
+        .map(Arc::new);
+
     // Convergence doesn't support connecting to different DB names. We are supposed
     // to do one context per query (as we need to load the schema before executing every
     // query) and per database (since the context is supposed to be limited to the database
@@ -189,6 +204,9 @@ pub async fn build_context(cfg: schema::SeafowlConfig) -> Result<SeafowlContext>
         inner: context,
         metastore: Arc::new(metastore),
         internal_object_store: object_stores.get_internal_store(),
+        audit_log,
+        queries,
+        pinned_versions: Default::default(),
         default_catalog: DEFAULT_DB.to_string(),
         default_schema: DEFAULT_SCHEMA.to_string(),
     })
@@ -217,15 +235,20 @@ mod tests {
                 postgres: Some(schema::PostgresFrontend {
                     bind_host: "127.0.0.1".to_string(),
                     bind_port: 6432,
+                    users: vec![],
+                    tls_settings: Default::default(),
                 }),
                 http: Some(schema::HttpFrontend {
                     bind_host: "127.0.0.1".to_string(),
                     bind_port: 80,
                     read_access: schema::AccessSettings::Any,
                     write_access: schema::AccessSettings::Any,
+                    tokens: vec![],
                     upload_data_max_length: 256 * 1024 * 1024,
                     cache_control: "max-age=43200, public".to_string(),
+                    tls_settings: Default::default(),
                 }),
+                jwt: None,
             },
             runtime: schema::Runtime {
                 max_memory: Some(512 * 1024 * 1024),
@@ -238,6 +261,8 @@ mod tests {
                 metrics: None,
                 object_store_cache: None,
                 sync_conf: Default::default(),
+                audit_log: None,
+                query_log_size: 1000,
             },
         };
 
@@ -4,6 +4,9 @@ use std::{
 };
 
 use crate::catalog::DEFAULT_SCHEMA;
+#[cfg(feature = "frontend-postgres")]
+use crate::frontend::postgres::PasswordVerifier;
+use crate::jwt::JwtValidator;
 use crate::object_store::cache::{
     DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_ENTRY_TTL, DEFAULT_MIN_FETCH_SIZE,
 };
@@ -170,6 +173,75 @@ pub struct Frontend {
     #[cfg(feature = "frontend-postgres")]
     pub postgres: Option<PostgresFrontend>,
     pub http: Option<HttpFrontend>,
+    pub jwt: Option<JwtConfig>,
+}
+
+// Validation of JWT bearer tokens, shared by all frontends
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct JwtConfig {
+    // Local JWKS file with the HS256/RS256 keys that the tokens are signed with
+    pub jwks_file: String,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    // Claim holding the principal name
+    #[serde(default = "default_jwt_principal_claim")]
+    pub principal_claim: String,
+    // Claim holding the list of databases the principal can access (`*` matches any)
+    #[serde(default = "default_jwt_databases_claim")]
+    pub databases_claim: String,
+    // Claim holding the access level (`read` or `write`); defaults to read access if absent
+    #[serde(default = "default_jwt_access_claim")]
+    pub access_claim: String,
+}
+
+fn default_jwt_principal_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_jwt_databases_claim() -> String {
+    "databases".to_string()
+}
+
+fn default_jwt_access_claim() -> String {
+    "access".to_string()
+}
+
+// The TLS settings of a frontend, if it serves TLS
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TlsConfig {
+    pub cert_file: String,
+    pub key_file: String,
+    pub client_ca_file: Option<String>,
+}
+
+// The TLS settings in the section of each frontend
+#[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone)]
+#[serde(default)]
+pub struct TlsSettings {
+    // PEM-encoded certificate chain and private key to serve TLS with, instead of plaintext
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    // PEM-encoded CA certificates to verify client certificates against; if set, clients
+    // without a certificate signed by one of them are rejected (mutual TLS)
+    pub tls_client_ca_file: Option<String>,
+}
+
+impl TlsSettings {
+    pub fn tls(&self) -> Result<Option<TlsConfig>, String> {
+        match (&self.tls_cert_file, &self.tls_key_file) {
+            (Some(cert_file), Some(key_file)) => Ok(Some(TlsConfig {
+                cert_file: cert_file.clone(),
+                key_file: key_file.clone(),
+                client_ca_file: self.tls_client_ca_file.clone(),
+            })),
+            (None, None) if self.tls_client_ca_file.is_none() => Ok(None),
+            (None, None) => {
+                Err("tls_client_ca_file requires tls_cert_file and tls_key_file"
+                    .to_string())
+            }
+            _ => Err("tls_cert_file and tls_key_file must be set together".to_string()),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -177,6 +249,8 @@ pub struct Frontend {
 pub struct FlightFrontend {
     pub bind_host: String,
     pub bind_port: u16,
+    #[serde(flatten)]
+    pub tls_settings: TlsSettings,
 }
 
 impl Default for FlightFrontend {
@@ -184,6 +258,7 @@ impl Default for FlightFrontend {
         Self {
             bind_host: "127.0.0.1".to_string(),
             bind_port: 47470,
+            tls_settings: Default::default(),
         }
     }
 }
@@ -193,6 +268,9 @@ impl Default for FlightFrontend {
 pub struct PostgresFrontend {
     pub bind_host: String,
     pub bind_port: u16,
+    pub users: Vec<PostgresUser>,
+    #[serde(flatten)]
+    pub tls_settings: TlsSettings,
 }
 
 impl Default for PostgresFrontend {
@@ -200,10 +278,22 @@ impl Default for PostgresFrontend {
         Self {
             bind_host: "127.0.0.1".to_string(),
             bind_port: 6432,
+            users: vec![],
+            tls_settings: Default::default(),
         }
     }
 }
 
+// PostgreSQL user authenticating with MD5 or SCRAM-SHA-256 instead of a cleartext token. The
+// password is stored the same way PostgreSQL does in `pg_authid`, i.e. as `md5<hash>` or as a
+// `SCRAM-SHA-256$<iterations>:<salt>$<StoredKey>:<ServerKey>` verifier. The user name designates
+// the principal: `writer`, `reader` or the name of one of the HTTP access tokens.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PostgresUser {
+    pub name: String,
+    pub password: String,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum AccessSettings {
     Any,
@@ -259,6 +349,32 @@ pub fn random_password() -> String {
     sha256_hash
 }
 
+// Level of access that a grant confers; write access implies read access
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum GrantAccess {
+    Read,
+    Write,
+}
+
+// Access to a database, or to a single schema in it if specified. The database name `*` matches
+// any database.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Grant {
+    pub database: String,
+    pub schema: Option<String>,
+    pub access: GrantAccess,
+}
+
+// A named access token, alongside the global read/write passwords
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AccessToken {
+    pub name: String,
+    pub sha256_hash: String,
+    #[serde(default)]
+    pub grants: Vec<Grant>,
+}
+
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(default)]
 pub struct HttpFrontend {
@@ -266,8 +382,11 @@ pub struct HttpFrontend {
     pub bind_port: u16,
     pub read_access: AccessSettings,
     pub write_access: AccessSettings,
+    pub tokens: Vec<AccessToken>,
     pub upload_data_max_length: u64,
     pub cache_control: String,
+    #[serde(flatten)]
+    pub tls_settings: TlsSettings,
 }
 
 impl Default for HttpFrontend {
@@ -277,8 +396,10 @@ impl Default for HttpFrontend {
             bind_port: 8080,
             read_access: AccessSettings::Any,
             write_access: AccessSettings::Off,
+            tokens: vec![],
             upload_data_max_length: 256,
             cache_control: "max-age=43200, public".to_string(), // defaults to 12 hours
+            tls_settings: Default::default(),
         }
     }
 }
@@ -293,6 +414,9 @@ pub struct Misc {
     pub metrics: Option<Metrics>,
     pub object_store_cache: Option<ObjectCacheProperties>,
     pub sync_conf: DataSyncConfig,
+    pub audit_log: Option<AuditLogConfig>,
+    // How many finished queries to keep around in `system.query_log`
+    pub query_log_size: usize,
 }
 
 impl Default for Misc {
@@ -304,6 +428,8 @@ impl Default for Misc {
             metrics: None,
             object_store_cache: None,
             sync_conf: Default::default(),
+            audit_log: None,
+            query_log_size: 1000,
         }
     }
 }
@@ -324,6 +450,26 @@ impl Default for Metrics {
     }
 }
 
+// Append-only log of the queries and writes received by the frontends, as JSON lines
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AuditLogConfig {
+    pub path: String,
+    // Size in bytes past which the file is rotated
+    #[serde(default = "default_audit_log_max_file_size")]
+    pub max_file_size: u64,
+    // How many rotated files (`<path>.1`, `<path>.2`, ...) to keep around
+    #[serde(default = "default_audit_log_max_files")]
+    pub max_files: usize,
+}
+
+fn default_audit_log_max_file_size() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_audit_log_max_files() -> usize {
+    10
+}
+
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(default)]
 pub struct ObjectCacheProperties {
@@ -350,6 +496,13 @@ pub struct DataSyncConfig {
     pub max_syncs_per_url: usize,
     pub write_lock_timeout_s: u64,
     pub flush_task_interval_s: u64,
+    // Persist the syncs held in memory to a local write-ahead log, so that they survive a restart
+    pub wal: bool,
+    // Defaults to a `sync_wal` subdirectory of `runtime.temp_dir`
+    pub wal_dir: Option<PathBuf>,
+    // Flush all the tables touched by a transaction together, and only expose their new versions
+    // to queries once the entire transaction is durable (across restarts only with `wal` enabled)
+    pub transactional: bool,
 }
 
 impl Default for DataSyncConfig {
@@ -360,7 +513,25 @@ impl Default for DataSyncConfig {
             max_syncs_per_url: 50,
             write_lock_timeout_s: 3,
             flush_task_interval_s: 900,
+            wal: false,
+            wal_dir: None,
+            transactional: false,
+        }
+    }
+}
+
+impl DataSyncConfig {
+    pub fn wal_dir(&self, runtime: &Runtime) -> Option<PathBuf> {
+        if !self.wal {
+            return None;
         }
+
+        self.wal_dir.clone().or_else(|| {
+            runtime
+                .temp_dir
+                .as_ref()
+                .map(|temp_dir| temp_dir.join("sync_wal"))
+        })
     }
 }
 
@@ -369,6 +540,61 @@ impl Default for DataSyncConfig {
 pub struct Runtime {
     pub max_memory: Option<u64>,
     pub temp_dir: Option<PathBuf>,
+    // Time (in milliseconds, as in PostgreSQL) after which a query gets aborted
+    pub statement_timeout: Option<u64>,
+    // Size of the results past which a query gets aborted; clients can lower these limits for
+    // their own queries, but not raise them
+    pub max_result_rows: Option<u64>,
+    pub max_result_bytes: Option<u64>,
+    pub limits: QueryLimits,
+}
+
+// Quotas that every principal is subject to separately, so that a single client can't starve
+// the others. Note that all anonymous clients share the same quota.
+#[derive(Default, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct QueryLimits {
+    pub max_concurrent_queries: Option<usize>,
+    pub queries_per_minute: Option<usize>,
+    // Memory (in MB) that a single query can reserve in DataFusion's memory pool
+    pub max_query_memory: Option<u64>,
+    // Overrides of the above limits for particular principals
+    pub principals: Vec<PrincipalQueryLimits>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PrincipalQueryLimits {
+    // Either a named token, a JWT principal, or one of `anonymous`, `reader` and `writer`
+    pub principal: String,
+    #[serde(default)]
+    pub max_concurrent_queries: Option<usize>,
+    #[serde(default)]
+    pub queries_per_minute: Option<usize>,
+    #[serde(default)]
+    pub max_query_memory: Option<u64>,
+}
+
+impl QueryLimits {
+    // The limits that the principal is subject to, with its own overrides taking precedence
+    pub fn for_principal(&self, principal: &str) -> PrincipalQueryLimits {
+        let overrides = self
+            .principals
+            .iter()
+            .find(|limits| limits.principal == principal);
+
+        PrincipalQueryLimits {
+            principal: principal.to_string(),
+            max_concurrent_queries: overrides
+                .and_then(|limits| limits.max_concurrent_queries)
+                .or(self.max_concurrent_queries),
+            queries_per_minute: overrides
+                .and_then(|limits| limits.queries_per_minute)
+                .or(self.queries_per_minute),
+            max_query_memory: overrides
+                .and_then(|limits| limits.max_query_memory)
+                .or(self.max_query_memory),
+        }
+    }
 }
 
 pub fn validate_config(config: SeafowlConfig) -> Result<SeafowlConfig, ConfigError> {
@@ -421,6 +647,93 @@ If Seafowl is running on GCP a token should be fetched using the GCP metadata en
         }
     };
 
+    if config.misc.sync_conf.wal
+        && config.misc.sync_conf.wal_dir(&config.runtime).is_none()
+    {
+        return Err(ConfigError::Message(
+            "misc.sync_conf.wal requires either misc.sync_conf.wal_dir or runtime.temp_dir to be set"
+                .to_string(),
+        ));
+    }
+
+    let frontend = &config.frontend;
+    let mut tls_settings = vec![(
+        "HTTP",
+        frontend.http.as_ref().map(|http| &http.tls_settings),
+    )];
+    #[cfg(feature = "frontend-arrow-flight")]
+    tls_settings.push((
+        "Arrow Flight",
+        frontend.flight.as_ref().map(|flight| &flight.tls_settings),
+    ));
+    #[cfg(feature = "frontend-postgres")]
+    tls_settings.push((
+        "PostgreSQL",
+        frontend
+            .postgres
+            .as_ref()
+            .map(|postgres| &postgres.tls_settings),
+    ));
+    for (name, tls_settings) in tls_settings {
+        let Some(tls) = tls_settings
+            .map(TlsSettings::tls)
+            .transpose()
+            .map_err(|e| {
+                ConfigError::Message(format!(
+                    "Invalid TLS settings for the {name} frontend: {e}"
+                ))
+            })?
+            .flatten()
+        else {
+            continue;
+        };
+
+        let files = [
+            Some(&tls.cert_file),
+            Some(&tls.key_file),
+            tls.client_ca_file.as_ref(),
+        ];
+        for file in files.into_iter().flatten() {
+            if !Path::new(file).is_file() {
+                return Err(ConfigError::Message(format!(
+                    "TLS file {file} for the {name} frontend doesn't exist"
+                )));
+            }
+        }
+    }
+
+    if let Some(jwt) = &config.frontend.jwt {
+        JwtValidator::try_new(jwt).map_err(|e| ConfigError::Message(e.to_string()))?;
+    }
+
+    #[cfg(feature = "frontend-postgres")]
+    if let Some(postgres) = &config.frontend.postgres {
+        let tokens = config
+            .frontend
+            .http
+            .as_ref()
+            .map(|http| http.tokens.clone())
+            .unwrap_or_default();
+
+        for user in &postgres.users {
+            if !["writer", "reader"].contains(&user.name.as_str())
+                && !tokens.iter().any(|token| token.name == user.name)
+            {
+                return Err(ConfigError::Message(format!(
+                    "PostgreSQL user {} must be either writer, reader or the name of an access token",
+                    user.name
+                )));
+            }
+
+            PasswordVerifier::parse(&user.password).map_err(|e| {
+                ConfigError::Message(format!(
+                    "Invalid password for PostgreSQL user {}: {e}",
+                    user.name
+                ))
+            })?;
+        }
+    }
+
     Ok(config)
 }
 
@@ -429,7 +742,11 @@ pub fn load_config(path: &Path) -> Result<SeafowlConfig, ConfigError> {
         .add_source(
             File::with_name(path.to_str().expect("Error parsing path")).required(false),
         )
-        .add_source(Environment::with_prefix(ENV_PREFIX).separator(ENV_SEPARATOR));
+        .add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .separator(ENV_SEPARATOR)
+                .try_parsing(true),
+        );
 
     config.build()?.try_deserialize().and_then(validate_config)
 }
@@ -445,6 +762,7 @@ pub fn load_config_from_string(
         .add_source(
             Environment::with_prefix(ENV_PREFIX)
                 .separator(ENV_SEPARATOR)
+                .try_parsing(true)
                 .source(env_override),
         );
 
@@ -458,8 +776,10 @@ pub fn load_config_from_string(
 #[cfg(test)]
 mod tests {
     use super::{
-        build_default_config, load_config_from_string, AccessSettings, Catalog, Frontend,
-        HttpFrontend, ObjectStoreConfig, Postgres, Runtime, S3Config, SeafowlConfig,
+        build_default_config, load_config_from_string, AccessSettings, AccessToken,
+        Catalog, Frontend, Grant, GrantAccess, HttpFrontend, JwtConfig,
+        ObjectStoreConfig, Postgres, PrincipalQueryLimits, Runtime, S3Config,
+        SeafowlConfig, TlsConfig,
     };
     use crate::config::schema::{Misc, ObjectCacheProperties, Sqlite};
     use crate::object_store::cache::DEFAULT_CACHE_CAPACITY;
@@ -543,6 +863,115 @@ read_access = "any"
 write_access = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
 upload_data_max_length = 1
 cache_control = "private, max-age=86400"
+"#;
+
+    const TEST_CONFIG_TOKENS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[[frontend.http.tokens]]
+name = "product_a"
+sha256_hash = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
+grants = [
+    { database = "default", schema = "product_a", access = "write" },
+    { database = "*", schema = "shared", access = "read" },
+]
+
+[[frontend.http.tokens]]
+name = "auditor"
+sha256_hash = "91e51b2ad8c7dd36b8c4a1a0ff1bb7de2c9e6d2f3f6c2ac4f6a1a1c1e0d0a2b3"
+grants = [{ database = "default", access = "read" }]
+"#;
+
+    const TEST_CONFIG_JWT: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.jwt]
+jwks_file = "/etc/seafowl/jwks.json"
+issuer = "https://sso.example.com"
+databases_claim = "seafowl_databases"
+"#;
+
+    const TEST_CONFIG_TLS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.http]
+tls_cert_file = "/etc/seafowl/cert.pem"
+tls_key_file = "/etc/seafowl/key.pem"
+tls_client_ca_file = "/etc/seafowl/ca.pem"
+"#;
+
+    const TEST_CONFIG_LIMITS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[runtime]
+statement_timeout = 30000
+max_result_rows = 1000000
+
+[runtime.limits]
+max_concurrent_queries = 4
+queries_per_minute = 60
+max_query_memory = 256
+
+[[runtime.limits.principals]]
+principal = "Notebook"
+max_concurrent_queries = 1
+"#;
+
+    const TEST_CONFIG_SYNC_WAL: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[runtime]
+temp_dir = "/tmp/seafowl"
+
+[misc.sync_conf]
+wal = true
+"#;
+
+    #[cfg(feature = "frontend-postgres")]
+    const TEST_CONFIG_POSTGRES_USERS: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[[frontend.http.tokens]]
+name = "analytics"
+sha256_hash = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
+
+[[frontend.postgres.users]]
+name = "writer"
+password = "SCRAM-SHA-256$4096:c29tZS1zYWx0$a2V5:a2V5"
+
+[[frontend.postgres.users]]
+name = "analytics"
+password = "md5e8a48653851e28c69d0506508fb27fc5"
 "#;
 
     const TEST_CONFIG_ERROR: &str = r#"
@@ -653,13 +1082,20 @@ cache_control = "private, max-age=86400"
                         bind_port: 80,
                         read_access: AccessSettings::Any,
                         write_access: AccessSettings::Off,
+                        tokens: vec![],
                         upload_data_max_length: 256,
                         cache_control: "max-age=43200, public".to_string(),
-                    })
+                        tls_settings: Default::default(),
+                    }),
+                    jwt: None,
                 },
                 runtime: Runtime {
                     max_memory: Some(512),
                     temp_dir: Some(PathBuf::from("/tmp/seafowl")),
+                    statement_timeout: None,
+                    max_result_rows: None,
+                    max_result_bytes: None,
+                    limits: Default::default(),
                 },
                 misc: Misc {
                     max_partition_size: 1024 * 1024,
@@ -668,6 +1104,8 @@ cache_control = "private, max-age=86400"
                     metrics: None,
                     object_store_cache: None,
                     sync_conf: Default::default(),
+                    audit_log: None,
+                    query_log_size: 1000,
                 },
             }
         )
@@ -688,12 +1126,215 @@ cache_control = "private, max-age=86400"
                         "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
                             .to_string()
                 },
+                tokens: vec![],
                 upload_data_max_length: 1,
                 cache_control: "private, max-age=86400".to_string(),
+                tls_settings: Default::default(),
             }
         );
     }
 
+    #[test]
+    fn test_parse_config_tokens() {
+        let config = load_config_from_string(TEST_CONFIG_TOKENS, false, None).unwrap();
+
+        assert_eq!(
+            config.frontend.http.unwrap().tokens,
+            vec![
+                AccessToken {
+                    name: "product_a".to_string(),
+                    sha256_hash:
+                        "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
+                            .to_string(),
+                    grants: vec![
+                        Grant {
+                            database: "default".to_string(),
+                            schema: Some("product_a".to_string()),
+                            access: GrantAccess::Write,
+                        },
+                        Grant {
+                            database: "*".to_string(),
+                            schema: Some("shared".to_string()),
+                            access: GrantAccess::Read,
+                        },
+                    ],
+                },
+                AccessToken {
+                    name: "auditor".to_string(),
+                    sha256_hash:
+                        "91e51b2ad8c7dd36b8c4a1a0ff1bb7de2c9e6d2f3f6c2ac4f6a1a1c1e0d0a2b3"
+                            .to_string(),
+                    grants: vec![Grant {
+                        database: "default".to_string(),
+                        schema: None,
+                        access: GrantAccess::Read,
+                    }],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_config_jwt() {
+        let config = load_config_from_string(TEST_CONFIG_JWT, true, None).unwrap();
+
+        assert_eq!(
+            config.frontend.jwt,
+            Some(JwtConfig {
+                jwks_file: "/etc/seafowl/jwks.json".to_string(),
+                issuer: Some("https://sso.example.com".to_string()),
+                audience: None,
+                principal_claim: "sub".to_string(),
+                databases_claim: "seafowl_databases".to_string(),
+                access_claim: "access".to_string(),
+            })
+        );
+
+        // The JWKS file must exist
+        let error = load_config_from_string(TEST_CONFIG_JWT, false, None).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Error loading the JWKS file /etc/seafowl/jwks.json"));
+    }
+
+    #[test]
+    fn test_parse_config_limits() {
+        let config = load_config_from_string(TEST_CONFIG_LIMITS, false, None).unwrap();
+        assert_eq!(config.runtime.statement_timeout, Some(30000));
+        assert_eq!(config.runtime.max_result_rows, Some(1000000));
+        assert_eq!(config.runtime.max_result_bytes, None);
+
+        let limits = config.runtime.limits;
+
+        assert_eq!(
+            limits.for_principal("Notebook"),
+            PrincipalQueryLimits {
+                principal: "Notebook".to_string(),
+                max_concurrent_queries: Some(1),
+                queries_per_minute: Some(60),
+                max_query_memory: Some(256),
+            }
+        );
+        assert_eq!(
+            limits.for_principal("anonymous"),
+            PrincipalQueryLimits {
+                principal: "anonymous".to_string(),
+                max_concurrent_queries: Some(4),
+                queries_per_minute: Some(60),
+                max_query_memory: Some(256),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_sync_wal() {
+        let config = load_config_from_string(TEST_CONFIG_SYNC_WAL, false, None).unwrap();
+        assert_eq!(
+            config.misc.sync_conf.wal_dir(&config.runtime),
+            Some(PathBuf::from("/tmp/seafowl/sync_wal"))
+        );
+
+        let config = load_config_from_string(
+            &format!("{TEST_CONFIG_SYNC_WAL}wal_dir = \"/var/lib/seafowl/wal\"\n"),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.misc.sync_conf.wal_dir(&config.runtime),
+            Some(PathBuf::from("/var/lib/seafowl/wal"))
+        );
+
+        // The WAL needs somewhere to live
+        let error = load_config_from_string(
+            &TEST_CONFIG_SYNC_WAL.replace("temp_dir = \"/tmp/seafowl\"\n", ""),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "misc.sync_conf.wal requires either misc.sync_conf.wal_dir or runtime.temp_dir to be set"
+        );
+    }
+
+    #[test]
+    fn test_parse_config_tls() {
+        let config = load_config_from_string(TEST_CONFIG_TLS, true, None).unwrap();
+
+        assert_eq!(
+            config.frontend.http.unwrap().tls_settings.tls(),
+            Ok(Some(TlsConfig {
+                cert_file: "/etc/seafowl/cert.pem".to_string(),
+                key_file: "/etc/seafowl/key.pem".to_string(),
+                client_ca_file: Some("/etc/seafowl/ca.pem".to_string()),
+            }))
+        );
+
+        // The files must exist
+        let error = load_config_from_string(TEST_CONFIG_TLS, false, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "TLS file /etc/seafowl/cert.pem for the HTTP frontend doesn't exist"
+        );
+
+        // The certificate and the key go together
+        let error = load_config_from_string(
+            &TEST_CONFIG_TLS.replace("tls_key_file = \"/etc/seafowl/key.pem\"\n", ""),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid TLS settings for the HTTP frontend: tls_cert_file and tls_key_file must be set together"
+        );
+    }
+
+    #[cfg(feature = "frontend-postgres")]
+    #[test]
+    fn test_parse_config_postgres_users() {
+        let config =
+            load_config_from_string(TEST_CONFIG_POSTGRES_USERS, false, None).unwrap();
+        assert_eq!(
+            config
+                .frontend
+                .postgres
+                .unwrap()
+                .users
+                .iter()
+                .map(|user| user.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["writer", "analytics"]
+        );
+
+        // Users need to map to a principal
+        let error = load_config_from_string(
+            &TEST_CONFIG_POSTGRES_USERS.replace(
+                "name = \"analytics\"\npassword",
+                "name = \"unknown\"\npassword",
+            ),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains(
+            "PostgreSQL user unknown must be either writer, reader or the name of an access token"
+        ));
+
+        // Passwords need to be stored as MD5 hashes or SCRAM verifiers
+        let error = load_config_from_string(
+            &TEST_CONFIG_POSTGRES_USERS
+                .replace("md5e8a48653851e28c69d0506508fb27fc5", "secret"),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid password for PostgreSQL user analytics"));
+    }
+
     #[test]
     fn test_parse_config_env_override() {
         let env_vars = HashMap::from([
@@ -754,13 +1395,20 @@ cache_control = "private, max-age=86400"
                                 "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
                                     .to_string()
                         },
+                        tokens: vec![],
                         upload_data_max_length: 256,
                         cache_control: "max-age=43200, public".to_string(),
-                    })
+                        tls_settings: Default::default(),
+                    }),
+                    jwt: None,
                 },
                 runtime: Runtime {
                     max_memory: Some(512),
                     temp_dir: Some(PathBuf::from("/tmp/seafowl")),
+                    statement_timeout: None,
+                    max_result_rows: None,
+                    max_result_bytes: None,
+                    limits: Default::default(),
                 },
                 misc: Misc {
                     max_partition_size: 1024 * 1024,
@@ -769,6 +1417,8 @@ cache_control = "private, max-age=86400"
                     metrics: None,
                     object_store_cache: None,
                     sync_conf: Default::default(),
+                    audit_log: None,
+                    query_log_size: 1000,
                 },
             }
         )
@@ -4,11 +4,20 @@ use crate::frontend::http::tests::deterministic_uuid;
 use crate::object_store::utils::fast_upload;
 
 use bytes::BytesMut;
+use datafusion::arrow::array::{ArrayRef, UInt32Array};
+use datafusion::arrow::compute::{
+    lexsort_to_indices, partition, take, take_record_batch, SortColumn, SortOptions,
+};
+use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
 use datafusion::parquet::basic::{Compression, ZstdLevel};
+use datafusion::parquet::format::FileMetaData;
+use datafusion::physical_expr::{expressions::Column, PhysicalSortExpr};
+use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+use datafusion::physical_plan::sorts::sort::SortExec;
 use datafusion::{
-    arrow::datatypes::{Schema, SchemaRef},
+    arrow::datatypes::{DataType, Schema, SchemaRef},
     datasource::TableProvider,
     error::DataFusionError,
     execution::context::TaskContext,
@@ -16,19 +25,25 @@ use datafusion::{
     physical_plan::{ExecutionPlan, ExecutionPlanProperties},
     sql::TableReference,
 };
-use deltalake::kernel::{Action, Add, Schema as DeltaSchema};
+use datafusion_common::ScalarValue;
+use deltalake::kernel::{Action, Add, Metadata, Remove, Schema as DeltaSchema};
 use deltalake::operations::{
     convert_to_delta::ConvertToDeltaBuilder, create::CreateBuilder,
     transaction::CommitBuilder,
 };
 use deltalake::protocol::{DeltaOperation, SaveMode};
 use deltalake::writer::create_add;
-use deltalake::DeltaTable;
+use deltalake::{DeltaTable, NULL_PARTITION_VALUE_DATA_PATH};
 use futures::{StreamExt, TryStreamExt};
+use indexmap::map::Entry;
+use indexmap::IndexMap;
 use object_store::path::Path;
 use object_store::ObjectStore;
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
+use std::iter;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tempfile::{NamedTempFile, TempPath};
 
 use tokio::fs::File as AsyncFile;
@@ -54,6 +69,17 @@ const PARTITION_FILE_MIN_PART_SIZE: usize = 5 * 1024 * 1024;
 // meaning that with 2 partition upload tasks x 8 part upload tasks x 5MB we have 80MB of memory usage
 const PARTITION_FILE_UPLOAD_MAX_CONCURRENCY: usize = 2;
 
+// Max number of partitions that can have a temporary file open for writing at a time, since each
+// open file buffers up to a row group in memory. Beyond that, the file of the least recently
+// written partition gets closed, and any further data for that partition goes into a new file.
+const MAX_OPEN_PARTITION_FILES: usize = 32;
+
+// Table property holding the JSON-encoded list of columns by which the data is sorted on write
+const CLUSTER_BY_CONFIG_KEY: &str = "seafowl.clusterBy";
+
+// Values of the partition columns of a given partition, serialized as in the Delta log
+type PartitionValues = Vec<(String, Option<String>)>;
+
 #[cfg(test)]
 fn get_uuid() -> Uuid {
     deterministic_uuid()
@@ -91,21 +117,262 @@ fn temp_partition_file_writer(
     Ok((path, writer))
 }
 
+/// Temporary Parquet files holding the data of a single (Hive-style) table partition.
+struct PartitionFiles {
+    schema: SchemaRef,
+    current_file_size: u32,
+    writer: Option<ArrowWriter<File>>,
+    file_paths: Vec<TempPath>,
+    file_metadata: Vec<FileMetaData>,
+}
+
+impl PartitionFiles {
+    fn try_new(schema: SchemaRef) -> Result<Self> {
+        let mut files = Self {
+            schema,
+            current_file_size: 0,
+            writer: None,
+            file_paths: vec![],
+            file_metadata: vec![],
+        };
+        files.writer()?;
+        Ok(files)
+    }
+
+    /// Get the writer of the current file, opening a new file if there isn't one.
+    fn writer(&mut self) -> Result<&mut ArrowWriter<File>> {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => {
+                let (file_path, writer) =
+                    temp_partition_file_writer(self.schema.clone())?;
+                self.file_paths.push(file_path);
+                self.current_file_size = 0;
+                writer
+            }
+        };
+        Ok(self.writer.insert(writer))
+    }
+
+    /// Write out the batch, rolling over into a new file whenever the current one reaches
+    /// `max_partition_size` rows.
+    fn write(&mut self, mut batch: RecordBatch, max_partition_size: u32) -> Result<()> {
+        // Make sure there's a file open before checking how much room is left in it
+        self.writer()?;
+        let mut leftover_partition_capacity =
+            (max_partition_size - self.current_file_size) as usize;
+
+        while batch.num_rows() > leftover_partition_capacity {
+            if leftover_partition_capacity > 0 {
+                // Fill up the remaining capacity in the slice
+                self.writer()?
+                    .write(&batch.slice(0, leftover_partition_capacity))
+                    .map_err(DataFusionError::from)?;
+                // Trim away the part that made it to the current partition
+                batch = batch.slice(
+                    leftover_partition_capacity,
+                    batch.num_rows() - leftover_partition_capacity,
+                );
+            }
+
+            // Roll-over into the next partition: close the current file and open a new one
+            self.close_file()?;
+            self.writer()?;
+            leftover_partition_capacity = max_partition_size as usize;
+        }
+
+        self.writer()?
+            .write(&batch)
+            .map_err(DataFusionError::from)?;
+        self.current_file_size += batch.num_rows() as u32;
+        Ok(())
+    }
+
+    /// Finish the current file, if any; the next write will start a new one.
+    fn close_file(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let file_metadata = writer.close().map_err(DataFusionError::from)?;
+            self.file_metadata.push(file_metadata);
+        }
+        Ok(())
+    }
+
+    fn close(mut self) -> Result<Vec<(TempPath, FileMetaData)>> {
+        self.close_file()?;
+        Ok(self
+            .file_paths
+            .into_iter()
+            .zip(self.file_metadata)
+            .collect())
+    }
+}
+
+/// Get the columns by which the table data is sorted on write, if any
+pub(crate) fn cluster_columns(metadata: &Metadata) -> Result<Vec<String>> {
+    match metadata.configuration.get(CLUSTER_BY_CONFIG_KEY) {
+        Some(Some(columns)) => serde_json::from_str(columns)
+            .map_err(|e| DataFusionError::External(Box::new(e))),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Sort the plan output by the clustering columns, so that each of the files it gets chunked into
+/// covers a narrow range of the clustering key, making the min/max stats selective when pruning.
+pub(crate) fn cluster_plan(
+    plan: Arc<dyn ExecutionPlan>,
+    cluster_columns: &[String],
+) -> Result<Arc<dyn ExecutionPlan>> {
+    if cluster_columns.is_empty() {
+        return Ok(plan);
+    }
+
+    let schema = plan.schema();
+    let sort_exprs = cluster_columns
+        .iter()
+        .map(|name| {
+            Ok(PhysicalSortExpr {
+                expr: Arc::new(Column::new_with_schema(name, &schema)?),
+                options: SortOptions::default(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // We don't run the physical optimizer here, so ensure the sort gets a single input partition
+    let input: Arc<dyn ExecutionPlan> =
+        if plan.output_partitioning().partition_count() > 1 {
+            Arc::new(CoalescePartitionsExec::new(plan))
+        } else {
+            plan
+        };
+    Ok(Arc::new(SortExec::new(sort_exprs, input)))
+}
+
+/// Ensure the partition columns are present in the schema and have a type whose values can be
+/// losslessly round-tripped through the partition directory names.
+fn validate_partition_columns(
+    schema: &Schema,
+    partition_columns: &[String],
+) -> Result<()> {
+    for name in partition_columns {
+        let field = schema.field_with_name(name).map_err(|_| {
+            DataFusionError::Plan(format!(
+                "Partition column {name} not found in the table"
+            ))
+        })?;
+
+        // String partition columns read from Delta tables are dictionary-encoded
+        let data_type = match field.data_type() {
+            DataType::Dictionary(_, value_type) => value_type.as_ref(),
+            data_type => data_type,
+        };
+
+        match data_type {
+            DataType::Utf8
+            | DataType::Boolean
+            | DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::Date32 => {}
+            data_type => {
+                return Err(DataFusionError::Plan(format!(
+                    "Unsupported type {data_type} for partition column {name}"
+                )))
+            }
+        }
+    }
+
+    if partition_columns.len() == schema.fields().len() {
+        return Err(DataFusionError::Plan(
+            "Can't partition a table by all of its columns".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Split a batch into one batch per distinct combination of partition column values. The
+/// partition columns themselves are left out of the resulting batches, as per the Delta protocol
+/// their values are only recorded in the `Add` actions.
+fn divide_by_partition_values(
+    batch: RecordBatch,
+    partition_columns: &[String],
+) -> Result<Vec<(PartitionValues, RecordBatch)>> {
+    if partition_columns.is_empty() {
+        return Ok(vec![(vec![], batch)]);
+    }
+
+    let schema = batch.schema();
+    let partition_arrays = partition_columns
+        .iter()
+        .map(|name| Ok(batch.column(schema.index_of(name)?).clone()))
+        .collect::<Result<Vec<_>>>()?;
+    let data_columns = (0..schema.fields().len())
+        .filter(|i| !partition_columns.contains(schema.field(*i).name()))
+        .collect::<Vec<_>>();
+    let data = batch.project(&data_columns)?;
+
+    // Sort the rows by the partition values, so that each partition is a contiguous range; the
+    // row number is used as a tie-breaker to keep the original order within each partition
+    let row_numbers: ArrayRef =
+        Arc::new(UInt32Array::from_iter_values(0..batch.num_rows() as u32));
+    let indices = lexsort_to_indices(
+        &partition_arrays
+            .iter()
+            .chain(iter::once(&row_numbers))
+            .map(|values| SortColumn {
+                values: values.clone(),
+                options: None,
+            })
+            .collect::<Vec<_>>(),
+        None,
+    )?;
+    let sorted_partition_arrays = partition_arrays
+        .iter()
+        .map(|values| take(values, &indices, None))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    partition(&sorted_partition_arrays)?
+        .ranges()
+        .into_iter()
+        .map(|range| {
+            let partition_values = partition_columns
+                .iter()
+                .zip(&sorted_partition_arrays)
+                .map(|(name, values)| {
+                    let value = ScalarValue::try_from_array(values, range.start)?;
+                    Ok((name.clone(), (!value.is_null()).then(|| value.to_string())))
+                })
+                .collect::<Result<_>>()?;
+            let batch = take_record_batch(
+                &data,
+                &indices.slice(range.start, range.end - range.start),
+            )?;
+            Ok((partition_values, batch))
+        })
+        .collect()
+}
+
 /// Execute a plan and upload the results to object storage as Parquet files, indexing them.
 /// Partially taken from DataFusion's plan_to_parquet with some additions (file stats, using a DiskManager)
+///
+/// For partitioned tables the rows are split into Hive-style partition directories, with the
+/// partition column values recorded in the `Add` actions.
 pub async fn plan_to_object_store(
     state: &SessionState,
     plan: &Arc<dyn ExecutionPlan>,
     store: Arc<dyn ObjectStore>,
     local_data_dir: Option<String>,
     max_partition_size: u32,
+    partition_columns: &[String],
 ) -> Result<Vec<Add>> {
-    let mut current_partition_size = 0;
-    let (mut current_partition_file_path, mut writer) =
-        temp_partition_file_writer(plan.schema())?;
-    let mut partition_file_paths = vec![current_partition_file_path];
-    let mut partition_metadata = vec![];
-    let mut tasks = vec![];
+    let mut partitions: IndexMap<PartitionValues, PartitionFiles> = IndexMap::new();
+    if partition_columns.is_empty() {
+        // Always produce at least one (possibly empty) file for non-partitioned tables
+        partitions.insert(vec![], PartitionFiles::try_new(plan.schema())?);
+    }
+
+    let mut open_partitions = VecDeque::new();
 
     // Iterate over Datafusion partitions and re-chunk them, since we want to enforce a pre-defined
     // partition size limit, which is not guaranteed by DF.
@@ -115,52 +382,47 @@ pub async fn plan_to_object_store(
         let mut stream = plan.execute(i, task_ctx)?;
 
         while let Some(batch) = stream.next().await {
-            let mut batch = batch?;
-
-            let mut leftover_partition_capacity =
-                (max_partition_size - current_partition_size) as usize;
-
-            while batch.num_rows() > leftover_partition_capacity {
-                if leftover_partition_capacity > 0 {
-                    // Fill up the remaining capacity in the slice
-                    writer
-                        .write(&batch.slice(0, leftover_partition_capacity))
-                        .map_err(DataFusionError::from)?;
-                    // Trim away the part that made it to the current partition
-                    batch = batch.slice(
-                        leftover_partition_capacity,
-                        batch.num_rows() - leftover_partition_capacity,
-                    );
+            for (partition_values, batch) in
+                divide_by_partition_values(batch?, partition_columns)?
+            {
+                // Keep the partitions with an open file in the order they were last written to
+                if let Some(position) =
+                    open_partitions.iter().position(|p| *p == partition_values)
+                {
+                    open_partitions.remove(position);
+                } else if open_partitions.len() >= MAX_OPEN_PARTITION_FILES
+                    && let Some(oldest) = open_partitions.pop_front()
+                    && let Some(files) = partitions.get_mut(&oldest)
+                {
+                    files.close_file()?;
                 }
+                open_partitions.push_back(partition_values.clone());
 
-                // Roll-over into the next partition: close partition writer, reset partition size
-                // counter and open new temp file + writer.
-                let file_metadata = writer.close().map_err(DataFusionError::from)?;
-                partition_metadata.push(file_metadata);
-
-                current_partition_size = 0;
-                leftover_partition_capacity = max_partition_size as usize;
-
-                (current_partition_file_path, writer) =
-                    temp_partition_file_writer(plan.schema())?;
-                partition_file_paths.push(current_partition_file_path);
+                let files = match partitions.entry(partition_values) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(PartitionFiles::try_new(batch.schema())?)
+                    }
+                };
+                files.write(batch, max_partition_size)?;
             }
+        }
+    }
 
-            current_partition_size += batch.num_rows() as u32;
-            writer.write(&batch).map_err(DataFusionError::from)?;
+    let mut partition_files = vec![];
+    for (partition_values, files) in partitions {
+        for (file_path, metadata) in files.close()? {
+            partition_files.push((partition_values.clone(), file_path, metadata));
         }
     }
-    let file_metadata = writer.close().map_err(DataFusionError::from)?;
-    partition_metadata.push(file_metadata);
 
     info!("Starting upload of partition objects");
     let partitions_uuid = get_uuid();
 
     let sem = Arc::new(Semaphore::new(PARTITION_FILE_UPLOAD_MAX_CONCURRENCY));
-    for (part, (partition_file_path, metadata)) in partition_file_paths
-        .into_iter()
-        .zip(partition_metadata)
-        .enumerate()
+    let mut tasks = vec![];
+    for (part, (partition_values, partition_file_path, metadata)) in
+        partition_files.into_iter().enumerate()
     {
         let permit = Arc::clone(&sem).acquire_owned().await.ok();
 
@@ -172,9 +434,21 @@ pub async fn plan_to_object_store(
                 let _permit = permit;
 
                 // This is taken from delta-rs `PartitionWriter::next_data_path`
-                let file_name = Path::from(format!(
-                    "part-{part:0>5}-{partitions_uuid}-c000.snappy.parquet"
-                ));
+                let file_name = Path::from_iter(
+                    partition_values
+                        .iter()
+                        .map(|(name, value)| {
+                            format!(
+                                "{name}={}",
+                                value
+                                    .as_deref()
+                                    .unwrap_or(NULL_PARTITION_VALUE_DATA_PATH)
+                            )
+                        })
+                        .chain(iter::once(format!(
+                            "part-{part:0>5}-{partitions_uuid}-c000.snappy.parquet"
+                        ))),
+                );
 
                 let size = tokio::fs::metadata(
                     partition_file_path
@@ -257,9 +531,9 @@ pub async fn plan_to_object_store(
                     multipart_upload.complete().await?;
                 }
 
-                // Create the corresponding `Add` action; currently we don't support partition columns
-                // which simplifies things.
-                let add = create_add(
+                // Create the corresponding `Add` action; the partition columns aren't present in
+                // the file, so there are no stats to collect for them.
+                let mut add = create_add(
                     &Default::default(),
                     file_name.to_string(),
                     size,
@@ -267,6 +541,7 @@ pub async fn plan_to_object_store(
                     -1, // collect stats for all columns
                     &None::<Vec<String>>,
                 )?;
+                add.partition_values = partition_values.into_iter().collect();
 
                 Ok(add)
             });
@@ -280,8 +555,43 @@ pub async fn plan_to_object_store(
         .collect()
 }
 
+/// Build the actions that remove the given files from a table in the same commit.
+pub(crate) fn remove_actions(
+    files: impl IntoIterator<Item = Add>,
+    data_change: bool,
+) -> impl Iterator<Item = Action> {
+    let deletion_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    files.into_iter().map(move |add| {
+        Action::Remove(Remove {
+            path: add.path,
+            deletion_timestamp: Some(deletion_timestamp),
+            data_change,
+            extended_file_metadata: Some(true),
+            partition_values: Some(add.partition_values),
+            size: Some(add.size),
+            tags: None,
+            deletion_vector: None,
+            base_row_id: None,
+            default_row_commit_version: None,
+        })
+    })
+}
+
+/// Physical layout of the data in a newly created table
+#[derive(Default)]
+pub struct TableLayout {
+    /// Columns by which the data is split into Hive-style partition directories
+    pub partition_columns: Vec<String>,
+    /// Columns by which the data is sorted before being chunked into files
+    pub cluster_columns: Vec<String>,
+}
+
 pub enum CreateDeltaTableDetails {
-    EmptyTable(Schema),
+    EmptyTable(Schema, TableLayout),
     FromPath(Path),
 }
 
@@ -306,7 +616,32 @@ impl SeafowlContext {
         // NB: there's also a uuid generated below for table's `DeltaTableMetaData::id`, so it would
         // be nice if those two could match somehow
         let (table_uuid, table) = match details {
-            CreateDeltaTableDetails::EmptyTable(schema) => {
+            CreateDeltaTableDetails::EmptyTable(
+                schema,
+                TableLayout {
+                    partition_columns,
+                    cluster_columns,
+                },
+            ) => {
+                validate_partition_columns(&schema, &partition_columns)?;
+                let mut configuration = HashMap::new();
+                if !cluster_columns.is_empty() {
+                    for name in &cluster_columns {
+                        schema.field_with_name(name).map_err(|_| {
+                            DataFusionError::Plan(format!(
+                                "Clustering column {name} not found in the table"
+                            ))
+                        })?;
+                    }
+                    configuration.insert(
+                        CLUSTER_BY_CONFIG_KEY.to_string(),
+                        Some(
+                            serde_json::to_string(&cluster_columns)
+                                .map_err(|e| DataFusionError::External(Box::new(e)))?,
+                        ),
+                    );
+                }
+
                 // TODO: we could be doing this inside the DB itself (i.e. `... DEFAULT gen_random_uuid()`
                 // in Postgres and `... DEFAULT (uuid())` in SQLite) however we won't be able to do it until
                 // sqlx 0.7 is released (which has libsqlite3-sys > 0.25, with the SQLite version that has
@@ -323,6 +658,10 @@ impl SeafowlContext {
                     .with_log_store(table_log_store)
                     .with_table_name(&*table_name)
                     .with_columns(delta_schema.fields().cloned())
+                    .with_partition_columns(partition_columns)
+                    .with_configuration(configuration)
+                    // Our own table properties aren't known to delta-rs
+                    .with_raise_if_key_not_exists(false)
                     .with_comment(format!(
                         "Created by Seafowl {}",
                         env!("CARGO_PKG_VERSION")
@@ -393,11 +732,13 @@ impl SeafowlContext {
         Ok(table)
     }
 
-    /// Generate the Delta table builder and execute the write
+    /// Generate the Delta table builder and execute the write; when overwriting all the existing
+    /// table files are removed in the same version
     pub async fn plan_to_delta_table(
         &self,
         name: impl Into<TableReference>,
         plan: &Arc<dyn ExecutionPlan>,
+        mode: SaveMode,
     ) -> Result<DeltaTable> {
         let table_uuid = self.get_table_uuid(name).await?;
         let prefix = table_uuid.to_string();
@@ -405,23 +746,30 @@ impl SeafowlContext {
         let table_log_store = internal_object_store.get_log_store(&prefix);
         let local_table_dir = internal_object_store.local_table_dir(&prefix);
 
+        let mut table = DeltaTable::new(table_log_store.clone(), Default::default());
+        table.load().await?;
+        let metadata = table.metadata()?;
+        let plan = cluster_plan(plan.clone(), &cluster_columns(metadata)?)?;
+
         // Upload partition files to table's root directory
         let adds = plan_to_object_store(
             &self.inner.state(),
-            plan,
+            &plan,
             table_log_store.object_store(),
             local_table_dir,
             self.config.misc.max_partition_size,
+            &metadata.partition_columns,
         )
         .await?;
 
         // Commit the write into a new version
-        let mut table = DeltaTable::new(table_log_store.clone(), Default::default());
-        table.load().await?;
+        let mut actions: Vec<Action> = adds.into_iter().map(Action::Add).collect();
+        if matches!(mode, SaveMode::Overwrite) {
+            actions.extend(remove_actions(table.snapshot()?.file_actions()?, true));
+        }
 
-        let actions: Vec<Action> = adds.into_iter().map(Action::Add).collect();
         let op = DeltaOperation::Write {
-            mode: SaveMode::Append,
+            mode,
             partition_by: None,
             predicate: None,
         };
@@ -476,7 +824,7 @@ impl SeafowlContext {
 #[cfg(test)]
 mod tests {
     use super::super::test_utils::{in_memory_context, in_memory_context_with_test_db};
-    use crate::context::delta::plan_to_object_store;
+    use crate::context::delta::{plan_to_object_store, MAX_OPEN_PARTITION_FILES};
     use crate::object_store::wrapped::InternalObjectStore;
     use crate::testutils::assert_uploaded_objects;
     use arrow::{array::Int32Array, datatypes::DataType, record_batch::RecordBatch};
@@ -555,6 +903,7 @@ mod tests {
             object_store.clone(),
             local_table_dir,
             2,
+            &[],
         )
         .await
         .unwrap();
@@ -708,6 +1057,7 @@ mod tests {
             object_store,
             None,
             max_partition_size,
+            &[],
         )
         .await
         .unwrap();
@@ -734,6 +1084,51 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_plan_to_object_storage_caps_open_partition_files() {
+        let ctx = in_memory_context_with_test_db().await;
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("some_number", DataType::Int32, true),
+            Field::new("some_partition", DataType::Int32, true),
+        ]));
+
+        // Write to one more partition than there can be open files for, twice over
+        let values = Arc::new(Int32Array::from_iter_values(
+            0..=MAX_OPEN_PARTITION_FILES as i32,
+        ));
+        let batch =
+            RecordBatch::try_new(schema.clone(), vec![values.clone(), values]).unwrap();
+        let execution_plan: Arc<dyn ExecutionPlan> = Arc::new(
+            MemoryExec::try_new(&[vec![batch.clone(), batch]], schema, None).unwrap(),
+        );
+
+        let object_store = Arc::new(InternalObjectStore::new(
+            Arc::new(InMemory::new()),
+            ObjectStoreConfig::Memory,
+        ));
+        let adds = plan_to_object_store(
+            &ctx.inner.state(),
+            &execution_plan,
+            object_store,
+            None,
+            100,
+            &["some_partition".to_string()],
+        )
+        .await
+        .unwrap();
+
+        // Each partition had its file closed before the second batch reached it
+        assert_eq!(adds.len(), 2 * (MAX_OPEN_PARTITION_FILES + 1));
+        for add in adds {
+            assert_eq!(
+                serde_json::from_str::<Value>(add.stats.unwrap().as_str()).unwrap()
+                    ["numRecords"],
+                1
+            );
+        }
+    }
+
     #[tokio::test]
     async fn test_create_table_without_columns_fails() {
         let context = Arc::new(in_memory_context().await);
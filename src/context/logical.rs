@@ -1,37 +1,88 @@
+use crate::auth::{Action, Principal, Resource, UserContext};
 use crate::catalog::DEFAULT_SCHEMA;
-use crate::context::SeafowlContext;
+use crate::context::{delta, SeafowlContext};
 use crate::datafusion::parser::{DFParser, Statement as DFStatement, CONVERT_TO_DELTA};
-use crate::datafusion::utils::build_schema;
+use crate::datafusion::utils::{build_schema, normalize_ident};
 use crate::nodes::Truncate;
+use crate::provider::{SeafowlDatabase, SeafowlSchema, TablePolicy};
+use crate::system_tables::{QUERY_LOG, RUNNING_QUERIES, SYSTEM_SCHEMA};
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 use crate::{
     nodes::{
-        ConvertTable, CreateFunction, CreateTable, DropFunction, RenameTable,
-        SeafowlExtensionNode, Vacuum,
+        AlterTable, AlteredColumn, ConvertTable, CreateFunction, CreateMaterializedView,
+        CreatePolicy, CreateTable, DropFunction, DropPolicy, KillQuery, Merge, Optimize,
+        RefreshMaterializedView, RenameTable, SeafowlExtensionNode, Vacuum,
     },
-    version::TableVersionProcessor,
+    version::{TableVersion, TableVersionProcessor, TABLE_CHANGES},
 };
 
+use arrow::compute::can_cast_types;
+use arrow_schema::{DataType, SchemaRef};
 use datafusion::common::DFSchema;
+use datafusion::datasource::{
+    provider_as_source, DefaultTableSource, TableProvider, ViewTable,
+};
 use datafusion::error::{DataFusionError as Error, Result};
 use datafusion::execution::context::SessionState;
+use datafusion::functions_aggregate::expr_fn::sum;
 use datafusion::optimizer::analyzer::Analyzer;
 use datafusion::optimizer::optimizer::Optimizer;
-use datafusion::optimizer::simplify_expressions::SimplifyExpressions;
+use datafusion::optimizer::simplify_expressions::{
+    ExprSimplifier, SimplifyContext, SimplifyExpressions,
+};
 use datafusion::optimizer::{OptimizerContext, OptimizerRule};
+use datafusion::physical_expr::execution_props::ExecutionProps;
 use datafusion::prelude::SessionContext;
 use datafusion::sql::parser::{CopyToSource, CopyToStatement};
-use datafusion_common::TableReference;
-use datafusion_expr::logical_plan::{Extension, LogicalPlan};
+use datafusion::sql::ResolvedTableReference;
+use datafusion_common::tree_node::{
+    Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
+};
+use datafusion_common::{Column, TableReference};
+use datafusion_expr::expr::Alias;
+use datafusion_expr::logical_plan::{
+    CreateMemoryTable, CreateView, DdlStatement, DmlStatement, DropTable, DropView,
+    Extension, LogicalPlan, Projection, TableScan,
+};
+use datafusion_expr::utils::conjunction;
+use datafusion_expr::{
+    cast, col, ident, lit, Expr as LogicalExpr, ExprSchemable, LogicalPlanBuilder,
+    WriteOp,
+};
+use deltalake::delta_datafusion::{DeltaScanConfig, DeltaTableProvider};
+use deltalake::kernel::Add;
 use deltalake::DeltaTable;
+use futures::future::BoxFuture;
 use itertools::Itertools;
 use sqlparser::ast::{
-    AlterTableOperation, CreateFunctionBody, CreateTable as CreateTableSql,
-    Expr as SqlExpr, Expr, Insert, ObjectType, Query, Statement, TableFactor,
-    TableWithJoins, Value, VisitMut,
+    visit_expressions_mut, AlterTableOperation, Assignment, AssignmentTarget,
+    CreateFunctionBody, CreateIndex, CreateTable as CreateTableSql, Expr as SqlExpr,
+    Expr, Function, FunctionArguments, Ident, Insert, MergeAction, MergeClause,
+    MergeClauseKind, MergeInsertExpr, MergeInsertKind, ObjectName, ObjectType,
+    OrderByExpr, Query, Statement, TableAlias, TableFactor, TableWithJoins, Value,
+    Values, VisitMut, WrappedCollection,
 };
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
+
+// Marker columns used to track the provenance of rows when planning a MERGE
+const MERGE_TARGET_COLUMN: &str = "__merge_target";
+const MERGE_SOURCE_COLUMN: &str = "__merge_source";
+const MERGE_KEEP_COLUMN: &str = "__merge_keep";
+const MERGE_FILE_COLUMN: &str = "__merge_file";
+const MERGE_ROW_COLUMN: &str = "__merge_row";
+const MERGE_MATCHED_COLUMN: &str = "__merge_matched";
+const MERGE_TOUCHED_COLUMN: &str = "__merge_touched";
+
+// Schema in which DataFusion exposes the catalog metadata
+const INFORMATION_SCHEMA: &str = "information_schema";
+
+// Column denoting whether a row returned by `table_changes` was inserted or deleted
+const CHANGE_TYPE_COLUMN: &str = "_change_type";
 
 pub fn is_read_only(plan: &LogicalPlan) -> bool {
     !matches!(
@@ -44,6 +95,26 @@ pub fn is_read_only(plan: &LogicalPlan) -> bool {
     )
 }
 
+// The tables that a write plan creates, modifies or drops; empty if the plan isn't scoped to
+// particular tables (e.g. creating a schema or a database)
+pub fn write_targets(plan: &LogicalPlan) -> Vec<TableReference> {
+    match plan {
+        LogicalPlan::Dml(DmlStatement { table_name, .. }) => vec![table_name.clone()],
+        LogicalPlan::Ddl(
+            DdlStatement::CreateMemoryTable(CreateMemoryTable { name, .. })
+            | DdlStatement::CreateView(CreateView { name, .. })
+            | DdlStatement::DropTable(DropTable { name, .. })
+            | DdlStatement::DropView(DropView { name, .. }),
+        ) => vec![name.clone()],
+        LogicalPlan::Extension(Extension { node }) => {
+            SeafowlExtensionNode::from_dynamic(node)
+                .map(|node| node.target_tables())
+                .unwrap_or_default()
+        }
+        _ => vec![],
+    }
+}
+
 pub fn is_statement_read_only(statement: &DFStatement) -> bool {
     if let DFStatement::Statement(s) = statement {
         matches!(
@@ -88,6 +159,20 @@ impl SeafowlContext {
         //    it rather than for SeafowlContext.
         self.reload_schema().await?;
 
+        // Views can't be planned lazily by the schema provider, so plan the referenced ones here.
+        // Unqualified names in a view body are resolved against the view's own schema, both when
+        // creating it and when planning it in subsequent queries.
+        let default_schema = match &statement {
+            DFStatement::Statement(s) => match &**s {
+                Statement::CreateView { name, .. } => {
+                    self.resolve_table_ref(name.to_string()).schema.to_string()
+                }
+                _ => self.default_schema.clone(),
+            },
+            _ => self.default_schema.clone(),
+        };
+        self.plan_views(&statement, &default_schema).await?;
+
         // Create a mutable clone of the statement so that we can rewrite table names if we encounter
         // time travel syntax.
         // Alternatively, this could be done without the `mut`, except then we'd need to construct
@@ -113,8 +198,69 @@ impl SeafowlContext {
                 | Statement::ShowTables { .. }
                 | Statement::ShowColumns { .. }
                 | Statement::CreateSchema { .. }
-                | Statement::CreateView { .. }
                 | Statement::CreateDatabase { .. } => self.inner.state().statement_to_plan(stmt).await,
+                Statement::CreateView { name, materialized, .. } => {
+                    let view_ref = self.resolve_table_ref(name.to_string());
+                    let materialized = *materialized;
+                    let plan = self.state_for_schema(&view_ref.schema).statement_to_plan(stmt).await?;
+
+                    match plan {
+                        LogicalPlan::Ddl(DdlStatement::CreateView(CreateView {
+                            name,
+                            input,
+                            or_replace,
+                            definition: Some(definition),
+                        })) if materialized => {
+                            if or_replace {
+                                return Err(Error::NotImplemented(
+                                    "CREATE OR REPLACE MATERIALIZED VIEW is not supported, use REFRESH MATERIALIZED VIEW instead".to_string(),
+                                ));
+                            }
+
+                            Ok(LogicalPlan::Extension(Extension {
+                                node: Arc::new(SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                                    name: name.to_string(),
+                                    definition,
+                                    input,
+                                    output_schema: Arc::new(DFSchema::empty()),
+                                })),
+                            }))
+                        }
+                        plan => Ok(plan),
+                    }
+                }
+                // REFRESH MATERIALIZED VIEW
+                Statement::Msck { table_name, repair: true, partition_action: None } => {
+                    let view_ref = self.resolve_table_ref(table_name.to_string());
+                    let definition = self
+                        .metastore
+                        .tables
+                        .get(&view_ref.catalog, &view_ref.schema, &view_ref.table)
+                        .await?
+                        .definition
+                        .ok_or_else(|| Error::Plan(format!("{table_name} is not a materialized view")))?;
+
+                    // Re-plan the defining query in the same way as when creating the view
+                    let statement = DFParser::parse_sql(&definition)?.pop_front().ok_or_else(|| {
+                        Error::Internal(format!("Empty definition for materialized view {table_name}"))
+                    })?;
+                    self.plan_views(&statement, &view_ref.schema).await?;
+
+                    match self.state_for_schema(&view_ref.schema).statement_to_plan(statement).await? {
+                        LogicalPlan::Ddl(DdlStatement::CreateView(CreateView { input, .. })) => {
+                            Ok(LogicalPlan::Extension(Extension {
+                                node: Arc::new(SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                                    name: table_name.to_string(),
+                                    input,
+                                    output_schema: Arc::new(DFSchema::empty()),
+                                })),
+                            }))
+                        }
+                        plan => Err(Error::Internal(format!(
+                            "Unexpected plan for materialized view {table_name}: {plan:?}"
+                        ))),
+                    }
+                }
                 Statement::Insert(Insert{ source: Some(ref mut source), .. }) => {
                     let state = self.rewrite_time_travel_query(source).await?;
                     let plan = state.statement_to_plan(stmt).await?;
@@ -159,7 +305,35 @@ impl SeafowlContext {
                     let plan = state.statement_to_plan(stmt).await?;
                     state.optimize(&plan)
                 }
-                Statement::Drop { object_type: ObjectType::Table | ObjectType::Schema, .. } => self.inner.state().statement_to_plan(stmt).await,
+                Statement::Merge {
+                    table: TableFactor::Table { name, alias, args: None, with_hints, version: None, .. },
+                    source,
+                    on,
+                    clauses,
+                    ..
+                } if with_hints.is_empty() => {
+                    let table = self.try_get_delta_table(name.to_string()).await?;
+                    let target_schema = TableProvider::schema(&table);
+                    let query = merge_query(name, alias.as_ref(), source, on, clauses, &target_schema)?;
+                    let statement = DFParser::parse_sql(&query)?.pop_front().ok_or_else(|| {
+                        Error::Internal(format!("Failed to plan MERGE into {name}"))
+                    })?;
+                    let plan = self.inner.state().statement_to_plan(statement).await?;
+
+                    // Track the file each target row comes from, so that only the files with
+                    // affected rows get rewritten
+                    let input = scan_merge_target(plan, merge_target_provider(&table, None)?)?;
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::Merge(Merge {
+                            name: name.to_string(),
+                            merge_predicate: on.to_string(),
+                            input: Arc::new(input),
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
+                Statement::Drop { object_type: ObjectType::Table | ObjectType::View | ObjectType::Schema, .. } => self.inner.state().statement_to_plan(stmt).await,
                 // CREATE TABLE (create empty table with columns)
                 Statement::CreateTable(CreateTableSql {
                     query: None,
@@ -169,6 +343,8 @@ impl SeafowlContext {
                     table_properties,
                     with_options,
                     if_not_exists,
+                    partition_by,
+                    cluster_by,
                     or_replace: _,
                     ..
                 }) if constraints.is_empty()
@@ -181,6 +357,9 @@ impl SeafowlContext {
                                 schema,
                                 name: name.to_string(),
                                 if_not_exists: *if_not_exists,
+                                partition_columns: partition_columns(partition_by.as_deref())?,
+                                cluster_columns: cluster_columns(cluster_by.as_ref()),
+                                input: None,
                                 output_schema: Arc::new(DFSchema::empty())
                             })),
                         }))
@@ -191,9 +370,7 @@ impl SeafowlContext {
                     let old_table_name = name.to_string();
                     let new_table_name = match operations[..] {
                         [AlterTableOperation::RenameTable {ref table_name}] => table_name.to_string(),
-                        _ => return Err(Error::Plan(
-                            "Unsupported ALTER TABLE statement".to_string()
-                        ))
+                        _ => return self.plan_alter_columns(name, operations).await,
                     };
 
                     if self.inner.table_provider(old_table_name.to_owned()).await.is_err() {
@@ -215,6 +392,45 @@ impl SeafowlContext {
                     }))
                 }
 
+                // Partitioned/clustered CREATE TABLE AS: CreateMemoryTable has nowhere to keep the
+                // table layout, so take its input and wrap it in our own node instead.
+                Statement::CreateTable(CreateTableSql {
+                    query: Some(ref mut input),
+                    name,
+                    if_not_exists,
+                    partition_by,
+                    cluster_by,
+                    ..
+                }) if partition_by.is_some() || cluster_by.is_some() => {
+                    let name = name.to_string();
+                    let if_not_exists = *if_not_exists;
+                    let partition_columns = partition_columns(partition_by.as_deref())?;
+                    let cluster_columns = cluster_columns(cluster_by.as_ref());
+
+                    let state = self.rewrite_time_travel_query(input).await?;
+                    let input = match state.statement_to_plan(stmt).await? {
+                        LogicalPlan::Ddl(DdlStatement::CreateMemoryTable(CreateMemoryTable {
+                            input,
+                            ..
+                        })) => input,
+                        plan => return Err(Error::Internal(format!(
+                            "Expected a CREATE TABLE AS plan, got {plan:?}"
+                        ))),
+                    };
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::CreateTable(CreateTable {
+                            schema: input.schema().as_arrow().clone(),
+                            name,
+                            if_not_exists,
+                            partition_columns,
+                            cluster_columns,
+                            input: Some(input),
+                            output_schema: Arc::new(DFSchema::empty())
+                        })),
+                    }))
+                },
+
                 // Other CREATE TABLE: SqlToRel only allows CreateTableAs statements and makes
                 // a CreateMemoryTable node. We're fine with that, but we'll execute it differently.
                 Statement::CreateTable(CreateTableSql { query: Some(ref mut input), .. })
@@ -246,6 +462,33 @@ impl SeafowlContext {
                         })),
                     }))
                 },
+                // OPTIMIZE, see `DFParser::parse_optimize`
+                Statement::Analyze { table_name, partitions, for_columns: false, columns, .. } if columns.is_empty() => {
+                    let table = self.try_get_delta_table(table_name.to_string()).await?;
+                    let schema = DFSchema::try_from(TableProvider::schema(&table).as_ref().clone())?;
+
+                    let state = self.inner.state();
+                    let props = ExecutionProps::new();
+                    let simplifier = ExprSimplifier::new(SimplifyContext::new(&props));
+                    let predicate = conjunction(
+                        partitions
+                            .iter()
+                            .flatten()
+                            .map(|expr| {
+                                let expr = state.create_logical_expr(&expr.to_string(), &schema)?;
+                                simplifier.coerce(expr, &schema)
+                            })
+                            .collect::<Result<Vec<_>>>()?,
+                    );
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::Optimize(Optimize {
+                            table_name: table_name.to_string(),
+                            predicate,
+                            output_schema: Arc::new(DFSchema::empty())
+                        })),
+                    }))
+                }
                 Statement::Truncate { table: false, table_name, partitions, .. } => {
                     let table_name = if partitions.is_none() {
                         Some(table_name.to_string())
@@ -276,6 +519,14 @@ impl SeafowlContext {
                         })),
                     }))
                 }
+                Statement::Kill { id, .. } => {
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::KillQuery(KillQuery {
+                            id: *id,
+                            output_schema: Arc::new(DFSchema::empty())
+                        })),
+                    }))
+                }
                 Statement::DropFunction{
                     if_exists,
                     func_desc,
@@ -291,6 +542,73 @@ impl SeafowlContext {
                         }))
                     }))
                 }
+                // CREATE POLICY, see `DFParser::parse_create_policy`
+                Statement::CreateIndex(CreateIndex {
+                    name: Some(name),
+                    table_name,
+                    columns,
+                    include,
+                    predicate: Some(expression),
+                    ..
+                }) if columns.len() <= 1 && name.0.len() == 1 => {
+                    // Make sure the expression is valid for the table and the policy kind
+                    let table = self.try_get_delta_table(table_name.to_string()).await?;
+                    let schema = DFSchema::try_from(TableProvider::schema(&table).as_ref().clone())?;
+                    let expression = expression.to_string();
+                    let data_type = self.policy_expr(&expression, "", &schema)?.get_type(&schema)?;
+
+                    let column = match columns.first() {
+                        None => {
+                            if data_type != DataType::Boolean {
+                                return Err(Error::Plan(format!(
+                                    "Policy predicate {expression} must be a boolean expression, got {data_type}"
+                                )));
+                            }
+                            None
+                        }
+                        Some(OrderByExpr { expr: SqlExpr::Identifier(column), .. }) => {
+                            let column = normalize_ident(column);
+                            let field = schema.field_with_unqualified_name(&column)?;
+                            if !can_cast_types(&data_type, field.data_type()) {
+                                return Err(Error::Plan(format!(
+                                    "Policy expression {expression} of type {data_type} can't be used to mask column {column} of type {}",
+                                    field.data_type()
+                                )));
+                            }
+                            Some(column)
+                        }
+                        Some(column) => {
+                            return Err(Error::Plan(format!(
+                                "Unsupported policy column {column}"
+                            )))
+                        }
+                    };
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                            name: normalize_ident(&name.0[0]),
+                            table_name: table_name.to_string(),
+                            column,
+                            expression,
+                            // Principal names are matched exactly, so unlike SQL identifiers they
+                            // don't get lowercased
+                            principals: (!include.is_empty())
+                                .then(|| include.iter().map(|ident| ident.value.clone()).collect()),
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
+                // DROP POLICY, see `DFParser::parse_drop_policy`
+                Statement::Drop { object_type: ObjectType::Index, if_exists, names, .. } if names.len() == 2 && names[0].0.len() == 1 => {
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::DropPolicy(DropPolicy {
+                            name: normalize_ident(&names[0].0[0]),
+                            table_name: names[1].to_string(),
+                            if_exists: *if_exists,
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
                 _ => Err(Error::NotImplemented(format!(
                     "Unsupported SQL statement: {s:?}"
                 ))),
@@ -332,19 +650,593 @@ impl SeafowlContext {
         }
     }
 
-    // Determine if some of the tables reference a non-latest version using table function syntax.
+    // Work out the new table columns after adding, dropping and renaming the requested columns
+    async fn plan_alter_columns(
+        &self,
+        name: &ObjectName,
+        operations: &[AlterTableOperation],
+    ) -> Result<LogicalPlan> {
+        let table = self.try_get_delta_table(name.to_string()).await?;
+        let mut columns = TableProvider::schema(&table)
+            .fields()
+            .iter()
+            .map(|f| AlteredColumn {
+                field: f.as_ref().clone(),
+                source: Some(f.name().clone()),
+            })
+            .collect::<Vec<_>>();
+        let position = |columns: &[AlteredColumn], column_name: &str| {
+            columns.iter().position(|c| c.field.name() == column_name)
+        };
+        let not_found = |column_name: &str| {
+            Error::Plan(format!("Column {column_name} not found in table {name}"))
+        };
+        let already_exists = |column_name: &str| {
+            Error::Plan(format!(
+                "Column {column_name} already exists in table {name}"
+            ))
+        };
+
+        for operation in operations {
+            match operation {
+                AlterTableOperation::AddColumn {
+                    if_not_exists,
+                    column_def,
+                    column_position: None,
+                    ..
+                } => {
+                    let field = build_schema(vec![column_def.clone()])?.field(0).clone();
+                    if position(&columns, field.name()).is_some() {
+                        if *if_not_exists {
+                            continue;
+                        }
+                        return Err(already_exists(field.name()));
+                    }
+                    // Existing rows can't be populated with anything other than NULLs
+                    if !field.is_nullable() {
+                        return Err(Error::Plan(format!(
+                            "Can't add a non-nullable column {} to table {name}",
+                            field.name()
+                        )));
+                    }
+
+                    columns.push(AlteredColumn {
+                        field,
+                        source: None,
+                    });
+                }
+                AlterTableOperation::DropColumn {
+                    column_name,
+                    if_exists,
+                    ..
+                } => {
+                    let column_name = normalize_ident(column_name);
+                    match position(&columns, &column_name) {
+                        Some(index) => {
+                            columns.remove(index);
+                        }
+                        None if *if_exists => {}
+                        None => return Err(not_found(&column_name)),
+                    }
+                }
+                AlterTableOperation::RenameColumn {
+                    old_column_name,
+                    new_column_name,
+                } => {
+                    let old_column_name = normalize_ident(old_column_name);
+                    let new_column_name = normalize_ident(new_column_name);
+                    if position(&columns, &new_column_name).is_some() {
+                        return Err(already_exists(&new_column_name));
+                    }
+
+                    let index = position(&columns, &old_column_name)
+                        .ok_or_else(|| not_found(&old_column_name))?;
+                    columns[index].field =
+                        columns[index].field.clone().with_name(new_column_name);
+                }
+                _ => {
+                    return Err(Error::Plan(format!(
+                        "Unsupported ALTER TABLE operation: {operation}"
+                    )))
+                }
+            }
+        }
+
+        if columns.is_empty() {
+            return Err(Error::Plan(format!(
+                "Can't drop all the columns of table {name}"
+            )));
+        }
+
+        // The partition and clustering columns are fixed at creation time
+        let metadata = table.metadata()?;
+        let is_kept = |column: &String| {
+            columns
+                .iter()
+                .any(|c| c.field.name() == column && c.source.as_ref() == Some(column))
+        };
+        if let Some(column) = metadata.partition_columns.iter().find(|c| !is_kept(c)) {
+            return Err(Error::Plan(format!(
+                "Can't drop or rename partition column {column} of table {name}"
+            )));
+        }
+        if let Some(column) = delta::cluster_columns(metadata)?
+            .iter()
+            .find(|c| !is_kept(c))
+        {
+            return Err(Error::Plan(format!(
+                "Can't drop or rename clustering column {column} of table {name}"
+            )));
+        }
+
+        Ok(LogicalPlan::Extension(Extension {
+            node: Arc::new(SeafowlExtensionNode::AlterTable(AlterTable {
+                name: name.to_string(),
+                columns,
+                output_schema: Arc::new(DFSchema::empty()),
+            })),
+        }))
+    }
+
+    // Session state that resolves unqualified table names against the provided schema
+    fn state_for_schema(&self, schema: &str) -> SessionState {
+        let mut state = self.inner.state();
+        state.config_mut().options_mut().catalog.default_schema = schema.to_string();
+        state
+    }
+
+    // Plan all views referenced in the statement (including the ones referenced by other views)
+    // and put the resulting `ViewTable`s in the catalog, so that they can be resolved as any other
+    // table provider.
+    async fn plan_views(
+        &self,
+        statement: &DFStatement,
+        default_schema: &str,
+    ) -> Result<()> {
+        let references = self.inner.state().resolve_table_references(statement)?;
+        let mut planning = vec![];
+
+        for reference in references {
+            let reference = reference.resolve(&self.default_catalog, default_schema);
+
+            if reference.schema.as_ref() == INFORMATION_SCHEMA {
+                // Make all views show up in the information schema, skipping over the ones that
+                // can't be planned anymore (e.g. due to a dropped table)
+                for (schema_name, schema) in self.seafowl_schemas() {
+                    let view_names = schema
+                        .views
+                        .iter()
+                        .map(|view| view.key().clone())
+                        .collect::<Vec<_>>();
+
+                    for view_name in view_names {
+                        let view_ref = ResolvedTableReference {
+                            catalog: Arc::from(self.default_catalog.as_str()),
+                            schema: schema_name.clone(),
+                            table: view_name,
+                        };
+                        if let Err(err) =
+                            self.plan_view(view_ref.clone(), &mut planning).await
+                        {
+                            warn!("Failed planning view {view_ref}: {err}");
+                            planning.clear();
+                        }
+                    }
+                }
+            } else {
+                self.plan_view(reference, &mut planning).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn seafowl_schemas(&self) -> Vec<(Arc<str>, Arc<SeafowlSchema>)> {
+        self.inner
+            .catalog(&self.default_catalog)
+            .and_then(|catalog| {
+                catalog
+                    .as_any()
+                    .downcast_ref::<SeafowlDatabase>()
+                    .map(|db| {
+                        db.schemas
+                            .iter()
+                            .map(|(name, schema)| (name.clone(), schema.clone()))
+                            .collect()
+                    })
+            })
+            .unwrap_or_default()
+    }
+
+    fn plan_view<'a>(
+        &'a self,
+        reference: ResolvedTableReference,
+        planning: &'a mut Vec<ResolvedTableReference>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let Some(schema) = self
+                .seafowl_schemas()
+                .into_iter()
+                .find(|(name, _)| *name == reference.schema)
+                .map(|(_, schema)| schema)
+            else {
+                return Ok(());
+            };
+
+            let definition = match schema.views.get(reference.table.as_ref()) {
+                // Not a view, or a view that has already been planned
+                None => return Ok(()),
+                Some(_) if schema.tables.contains_key(reference.table.as_ref()) => {
+                    return Ok(())
+                }
+                Some(definition) => definition.clone(),
+            };
+
+            if planning.iter().any(|r| {
+                r.catalog == reference.catalog
+                    && r.schema == reference.schema
+                    && r.table == reference.table
+            }) {
+                return Err(Error::Plan(format!(
+                    "View {reference} references itself recursively"
+                )));
+            }
+            planning.push(reference.clone());
+
+            let statement =
+                DFParser::parse_sql(&definition)?
+                    .pop_front()
+                    .ok_or_else(|| {
+                        Error::Internal(format!("Empty definition for view {reference}"))
+                    })?;
+
+            // Plan the views referenced by this view first
+            let state = self.state_for_schema(&reference.schema);
+            for dependency in state.resolve_table_references(&statement)? {
+                let dependency =
+                    dependency.resolve(&reference.catalog, &reference.schema);
+                self.plan_view(dependency, planning).await?;
+            }
+
+            let plan = match state.statement_to_plan(statement).await? {
+                LogicalPlan::Ddl(DdlStatement::CreateView(CreateView {
+                    input, ..
+                })) => input.as_ref().clone(),
+                plan => plan,
+            };
+
+            let view = ViewTable::try_new(plan, Some(definition.to_string()))?;
+            schema
+                .tables
+                .insert(reference.table.clone(), Arc::new(view));
+            planning.pop();
+
+            Ok(())
+        })
+    }
+
+    // Restrict the data that the plan reads from tables with access policies applying to the
+    // user: row-level security policies filter the scanned rows, while column masking policies
+    // replace the values of the masked columns, unless the user can write to the table anyway.
+    // This covers the scans in views and in the queries that populate new tables, but not the
+    // ones through which UPDATE, DELETE and MERGE rewrite their target table.
+    pub fn apply_access_policies(
+        &self,
+        plan: LogicalPlan,
+        user_context: &UserContext,
+    ) -> Result<Transformed<LogicalPlan>> {
+        self.apply_access_policies_for_schema(plan, user_context, &self.default_schema)
+    }
+
+    fn apply_access_policies_for_schema(
+        &self,
+        plan: LogicalPlan,
+        user_context: &UserContext,
+        default_schema: &str,
+    ) -> Result<Transformed<LogicalPlan>> {
+        let restrict = |input: &LogicalPlan| {
+            self.apply_access_policies_for_schema(
+                input.clone(),
+                user_context,
+                default_schema,
+            )
+            .data()
+            .map(Arc::new)
+        };
+
+        // Restrict all the scans in the plan and its subqueries, except for the ones of the
+        // table being rewritten, if any
+        let restrict_scans = |plan: LogicalPlan, target: Option<&TableReference>| {
+            let target = target.map(|target| {
+                target
+                    .clone()
+                    .resolve(&self.default_catalog, default_schema)
+            });
+            plan.transform_up_with_subqueries(|plan| match plan {
+                LogicalPlan::TableScan(scan)
+                    if target.as_ref()
+                        != Some(
+                            &scan
+                                .table_name
+                                .clone()
+                                .resolve(&self.default_catalog, default_schema),
+                        ) =>
+                {
+                    self.restrict_table_scan(scan, user_context, default_schema)
+                }
+                plan => Ok(Transformed::no(plan)),
+            })
+        };
+
+        match &plan {
+            LogicalPlan::Dml(DmlStatement {
+                table_name,
+                op: WriteOp::Update | WriteOp::Delete,
+                ..
+            }) => {
+                let table_name = table_name.clone();
+                restrict_scans(plan, Some(&table_name))
+            }
+            // Our nodes don't expose their data inputs as regular plan inputs
+            LogicalPlan::Extension(Extension { node }) => {
+                let node = match SeafowlExtensionNode::from_dynamic(node) {
+                    Some(SeafowlExtensionNode::CreateTable(
+                        create @ CreateTable {
+                            input: Some(input), ..
+                        },
+                    )) => SeafowlExtensionNode::CreateTable(CreateTable {
+                        input: Some(restrict(input)?),
+                        ..create.clone()
+                    }),
+                    Some(SeafowlExtensionNode::CreateMaterializedView(create)) => {
+                        SeafowlExtensionNode::CreateMaterializedView(
+                            CreateMaterializedView {
+                                input: restrict(&create.input)?,
+                                ..create.clone()
+                            },
+                        )
+                    }
+                    Some(SeafowlExtensionNode::RefreshMaterializedView(refresh)) => {
+                        SeafowlExtensionNode::RefreshMaterializedView(
+                            RefreshMaterializedView {
+                                input: restrict(&refresh.input)?,
+                                ..refresh.clone()
+                            },
+                        )
+                    }
+                    // This restricts the source of the merge and any subqueries in its
+                    // conditions and values
+                    Some(SeafowlExtensionNode::Merge(merge)) => {
+                        SeafowlExtensionNode::Merge(Merge {
+                            input: restrict_scans(
+                                merge.input.as_ref().clone(),
+                                Some(&TableReference::from(merge.name.as_str())),
+                            )
+                            .data()
+                            .map(Arc::new)?,
+                            ..merge.clone()
+                        })
+                    }
+                    _ => return Ok(Transformed::no(plan)),
+                };
+                Ok(Transformed::yes(LogicalPlan::Extension(Extension {
+                    node: Arc::new(node),
+                })))
+            }
+            _ => restrict_scans(plan, None),
+        }
+    }
+
+    fn restrict_table_scan(
+        &self,
+        scan: TableScan,
+        user_context: &UserContext,
+        default_schema: &str,
+    ) -> Result<Transformed<LogicalPlan>> {
+        let reference = scan
+            .table_name
+            .clone()
+            .resolve(&self.default_catalog, default_schema);
+        if reference.schema.as_ref() == SYSTEM_SCHEMA {
+            return self.restrict_system_table_scan(scan, &reference, user_context);
+        }
+        let Some(schema) = self
+            .seafowl_schemas()
+            .into_iter()
+            .find(|(name, _)| *name == reference.schema)
+            .map(|(_, schema)| schema)
+        else {
+            return Ok(Transformed::no(LogicalPlan::TableScan(scan)));
+        };
+
+        // Scans of past table versions and table changes use the table name with a suffix
+        let table_name = reference.table.split(':').next().unwrap_or_default();
+        let principal = user_context.principal.name();
+        let can_write = user_context.can_access(
+            Action::Write,
+            &Resource::Schema {
+                database: reference.catalog.to_string(),
+                schema: reference.schema.to_string(),
+            },
+        );
+        let (masks, row_policies): (Vec<&TablePolicy>, Vec<&TablePolicy>) = schema
+            .policies
+            .get(table_name)
+            .into_iter()
+            .flatten()
+            .filter(|policy| policy.applies_to(principal))
+            .filter(|policy| policy.column.is_none() || !can_write)
+            .partition(|policy| policy.column.is_some());
+
+        if masks.is_empty() && row_policies.is_empty() {
+            // Views only get inlined during optimization, so restrict the plan they wrap
+            if schema.is_view(&reference.table)
+                && let Some(source) =
+                    scan.source.as_any().downcast_ref::<DefaultTableSource>()
+                && let Some(view) =
+                    source.table_provider.as_any().downcast_ref::<ViewTable>()
+            {
+                let view_plan = self.apply_access_policies_for_schema(
+                    view.logical_plan().clone(),
+                    user_context,
+                    &reference.schema,
+                )?;
+                if view_plan.transformed {
+                    let view =
+                        ViewTable::try_new(view_plan.data, view.definition().cloned())?;
+                    return Ok(Transformed::yes(LogicalPlan::TableScan(
+                        TableScan::try_new(
+                            scan.table_name,
+                            provider_as_source(Arc::new(view)),
+                            scan.projection,
+                            scan.filters,
+                            scan.fetch,
+                        )?,
+                    )));
+                }
+            }
+            return Ok(Transformed::no(LogicalPlan::TableScan(scan)));
+        }
+
+        let table_schema = DFSchema::try_from_qualified_schema(
+            scan.table_name.clone(),
+            scan.source.schema().as_ref(),
+        )?;
+
+        // Filter the rows and mask the columns before applying the projection and the limit of
+        // the original scan
+        let mut builder = LogicalPlanBuilder::scan_with_filters(
+            scan.table_name,
+            scan.source,
+            None,
+            scan.filters,
+        )?;
+        if let Some(predicate) = conjunction(
+            row_policies
+                .iter()
+                .map(|policy| {
+                    self.policy_expr(&policy.expression, principal, &table_schema)
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ) {
+            builder = builder.filter(predicate)?;
+        }
+        if !masks.is_empty() {
+            // If multiple masks apply to a column, the first one (by name) wins
+            let columns = table_schema
+                .iter()
+                .map(|(qualifier, field)| {
+                    match masks.iter().find(|mask| {
+                        mask.column.as_deref() == Some(field.name().as_str())
+                    }) {
+                        Some(mask) => Ok(cast(
+                            self.policy_expr(&mask.expression, principal, &table_schema)?,
+                            field.data_type().clone(),
+                        )
+                        .alias_qualified(qualifier.cloned(), field.name())),
+                        None => Ok(LogicalExpr::from((qualifier, field))),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+            builder = builder.project(columns)?;
+        }
+        restricted_scan(builder, scan.projection, scan.fetch)
+    }
+
+    // The SQL text of the queries can carry sensitive values (e.g. the filters of the tenants
+    // isolated by policies, or the credentials of external tables), so only the principals that
+    // can write to the database, and can thus cancel any query, get to see the queries of others
+    fn restrict_system_table_scan(
+        &self,
+        scan: TableScan,
+        reference: &ResolvedTableReference,
+        user_context: &UserContext,
+    ) -> Result<Transformed<LogicalPlan>> {
+        if !matches!(reference.table.as_ref(), RUNNING_QUERIES | QUERY_LOG)
+            || user_context.can_access(
+                Action::Write,
+                &Resource::Database(reference.catalog.to_string()),
+            )
+        {
+            return Ok(Transformed::no(LogicalPlan::TableScan(scan)));
+        }
+
+        // As with cancelling queries, only named principals own the queries they run
+        let principal = &user_context.principal;
+        let predicate = if matches!(principal, Principal::Token(_) | Principal::Jwt(_)) {
+            LogicalExpr::Column(Column::new(Some(scan.table_name.clone()), "principal"))
+                .eq(lit(principal.name()))
+        } else {
+            lit(false)
+        };
+
+        let builder = LogicalPlanBuilder::scan_with_filters(
+            scan.table_name,
+            scan.source,
+            None,
+            scan.filters,
+        )?
+        .filter(predicate)?;
+        restricted_scan(builder, scan.projection, scan.fetch)
+    }
+
+    // Plan the expression of an access policy, replacing the session attributes with their
+    // values: `current_user` (or `session_user` and `user`) with the principal name and
+    // `current_catalog` with the database name.
+    fn policy_expr(
+        &self,
+        expression: &str,
+        principal: &str,
+        schema: &DFSchema,
+    ) -> Result<LogicalExpr> {
+        let mut expr = Parser::new(&GenericDialect {})
+            .try_with_sql(expression)?
+            .parse_expr()?;
+
+        let _ = visit_expressions_mut(&mut expr, |expr| {
+            if let SqlExpr::Function(Function {
+                name,
+                args: FunctionArguments::None,
+                ..
+            }) = expr
+                && let [attribute] = name.0.as_slice()
+                && attribute.quote_style.is_none()
+            {
+                let value = match attribute.value.to_ascii_lowercase().as_str() {
+                    "current_user" | "session_user" | "user" => Some(principal),
+                    "current_catalog" => Some(self.default_catalog.as_str()),
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    *expr = SqlExpr::Value(Value::SingleQuotedString(value.to_string()));
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+
+        self.inner
+            .state()
+            .create_logical_expr(&expr.to_string(), schema)
+    }
+
+    // Determine if some of the tables reference a non-latest version using table function syntax,
+    // or if the query asks for the changes between two table versions using `table_changes`.
     // If so, rename the tables in the query by appending the explicit version to the name, and add
     // it to the schema provider's map inside a new session state.
-    // Should become obsolete once `sqlparser-rs` introduces support for some form of the `AS OF`
-    // clause: https://en.wikipedia.org/wiki/SQL:2011.
+    // The `FOR SYSTEM_TIME AS OF` and `VERSION AS OF` clauses get rewritten to the table function
+    // syntax by our parser.
     async fn rewrite_time_travel_query(&self, q: &mut Query) -> Result<SessionState> {
         let mut version_processor = TableVersionProcessor::new(
             self.default_catalog.clone(),
             DEFAULT_SCHEMA.to_string(),
         );
-        q.visit(&mut version_processor);
+        if let ControlFlow::Break(e) = q.visit(&mut version_processor) {
+            return Err(e);
+        }
 
-        if version_processor.table_versions.is_empty() {
+        if version_processor.table_versions.is_empty()
+            && version_processor.table_changes.is_empty()
+        {
             // No time-travel syntax detected, just return the regular session state
             return Ok(self.inner.state());
         }
@@ -365,15 +1257,20 @@ impl SeafowlContext {
             let mut resolved_ref = TableReference::from(full_table_name.as_str())
                 .resolve(&self.default_catalog, &self.default_schema);
 
-            // We only support datetime DeltaTable version specification for start
             let table_uuid = self.get_table_uuid(resolved_ref.clone()).await?;
             let table_log_store = self
                 .get_internal_object_store()?
                 .get_log_store(&table_uuid.to_string());
-            let datetime = TableVersionProcessor::version_to_datetime(version)?;
 
             let mut delta_table = DeltaTable::new(table_log_store, Default::default());
-            delta_table.load_with_datetime(datetime).await?;
+            match TableVersionProcessor::parse_version(version)? {
+                TableVersion::Version(version) => {
+                    delta_table.load_version(version).await?
+                }
+                TableVersion::Timestamp(datetime) => {
+                    delta_table.load_with_datetime(datetime).await?
+                }
+            }
             let table_provider_for_version = Arc::from(delta_table);
 
             resolved_ref.table = Arc::from(name_with_version.as_str());
@@ -383,8 +1280,465 @@ impl SeafowlContext {
             }
         }
 
+        for (table, from_version, to_version) in &version_processor.table_changes {
+            let name_with_changes = TableVersionProcessor::table_with_changes(
+                table,
+                *from_version,
+                *to_version,
+            );
+
+            let full_table_name = table.to_string();
+            let mut resolved_ref = TableReference::from(full_table_name.as_str())
+                .resolve(&self.default_catalog, &self.default_schema);
+
+            let table_changes = self
+                .plan_table_changes(resolved_ref.clone(), *from_version, *to_version)
+                .await?;
+
+            resolved_ref.table = Arc::from(name_with_changes.as_str());
+
+            if !session_ctx.table_exist(resolved_ref.clone())? {
+                session_ctx.register_table(resolved_ref, table_changes)?;
+            }
+        }
+
         Ok(session_ctx.state())
     }
+
+    // Build a view over the rows inserted and deleted between two versions of a table.
+    // The changes are derived from the data files that were added and removed in the Delta log
+    // in the meantime; rows that were merely re-written (e.g. by an UPDATE touching other rows in
+    // the same file) cancel out.
+    async fn plan_table_changes(
+        &self,
+        resolved_ref: ResolvedTableReference,
+        from_version: i64,
+        to_version: i64,
+    ) -> Result<Arc<dyn TableProvider>> {
+        if from_version > to_version {
+            return Err(Error::Plan(format!(
+                "Starting version {from_version} is greater than the ending version {to_version}"
+            )));
+        }
+
+        let table_uuid = self.get_table_uuid(resolved_ref.clone()).await?;
+        let log_store = self
+            .get_internal_object_store()?
+            .get_log_store(&table_uuid.to_string());
+
+        let mut from_table = DeltaTable::new(log_store.clone(), Default::default());
+        from_table.load_version(from_version).await?;
+        let mut to_table = DeltaTable::new(log_store.clone(), Default::default());
+        to_table.load_version(to_version).await?;
+
+        let from_files = from_table.snapshot()?.file_actions()?;
+        let to_files = to_table.snapshot()?.file_actions()?;
+        let from_paths = from_files
+            .iter()
+            .map(|add| &add.path)
+            .collect::<HashSet<_>>();
+        let to_paths = to_files.iter().map(|add| &add.path).collect::<HashSet<_>>();
+
+        let added_files = to_files
+            .iter()
+            .filter(|add| !from_paths.contains(&add.path))
+            .cloned()
+            .collect();
+        let removed_files = from_files
+            .iter()
+            .filter(|add| !to_paths.contains(&add.path))
+            .cloned()
+            .collect();
+
+        let added = DeltaTableProvider::try_new(
+            to_table.snapshot()?.clone(),
+            log_store.clone(),
+            Default::default(),
+        )?
+        .with_files(added_files);
+        let removed = DeltaTableProvider::try_new(
+            from_table.snapshot()?.clone(),
+            log_store,
+            Default::default(),
+        )?
+        .with_files(removed_files);
+
+        if added.schema() != removed.schema() {
+            return Err(Error::Plan(format!(
+                "Schema of table {resolved_ref} changed between versions {from_version} and {to_version}"
+            )));
+        }
+
+        let added =
+            LogicalPlanBuilder::scan("added", provider_as_source(Arc::new(added)), None)?
+                .build()?;
+        let removed = LogicalPlanBuilder::scan(
+            "removed",
+            provider_as_source(Arc::new(removed)),
+            None,
+        )?
+        .build()?;
+
+        // Tag the rows from each side of the difference with the kind of change
+        let with_change_type = |plan: LogicalPlan, change_type: &str| {
+            let exprs = plan
+                .schema()
+                .columns()
+                .into_iter()
+                .map(col)
+                .chain([lit(change_type).alias(CHANGE_TYPE_COLUMN)])
+                .collect::<Vec<_>>();
+            LogicalPlanBuilder::from(plan).project(exprs)?.build()
+        };
+
+        let inserted = with_change_type(
+            LogicalPlanBuilder::except(added.clone(), removed.clone(), true)?,
+            "insert",
+        )?;
+        let deleted = with_change_type(
+            LogicalPlanBuilder::except(removed, added, true)?,
+            "delete",
+        )?;
+        let plan = LogicalPlanBuilder::from(inserted).union(deleted)?.build()?;
+
+        let definition = format!(
+            "SELECT * FROM {TABLE_CHANGES}('{}', {from_version}, {to_version})",
+            to_table.table_uri()
+        );
+        Ok(Arc::new(ViewTable::try_new(plan, Some(definition))?))
+    }
+}
+
+// Extract the names of the partition columns from a `PARTITIONED BY (col, ...)` clause
+// Apply the projection and the limit of the original scan on top of the restricted one
+fn restricted_scan(
+    mut builder: LogicalPlanBuilder,
+    projection: Option<Vec<usize>>,
+    fetch: Option<usize>,
+) -> Result<Transformed<LogicalPlan>> {
+    if let Some(projection) = projection {
+        let columns = builder.schema().columns();
+        builder = builder.project(projection.iter().map(|i| col(columns[*i].clone())))?;
+    }
+    if let Some(fetch) = fetch {
+        builder = builder.limit(0, Some(fetch))?;
+    }
+
+    Ok(Transformed::yes(builder.build()?))
+}
+
+fn partition_columns(partition_by: Option<&SqlExpr>) -> Result<Vec<String>> {
+    match partition_by {
+        None => Ok(vec![]),
+        Some(SqlExpr::Identifier(ident)) => Ok(vec![normalize_ident(ident)]),
+        Some(SqlExpr::Tuple(exprs)) => exprs
+            .iter()
+            .map(|expr| match expr {
+                SqlExpr::Identifier(ident) => Ok(normalize_ident(ident)),
+                _ => Err(Error::NotImplemented(format!(
+                    "Unsupported partitioning expression {expr}"
+                ))),
+            })
+            .collect(),
+        Some(expr) => Err(Error::NotImplemented(format!(
+            "Unsupported partitioning expression {expr}"
+        ))),
+    }
+}
+
+// Extract the names of the clustering columns from a `CLUSTER BY (col, ...)` clause
+fn cluster_columns(cluster_by: Option<&WrappedCollection<Vec<Ident>>>) -> Vec<String> {
+    match cluster_by {
+        None => vec![],
+        Some(WrappedCollection::NoWrapping(idents))
+        | Some(WrappedCollection::Parentheses(idents)) => {
+            idents.iter().map(normalize_ident).collect()
+        }
+    }
+}
+
+// Translate a MERGE statement into a query over the full outer join of the target and source
+// relations. For each row the query computes the new values of all target columns, as well as
+// whether the row should be present in the new table version at all. As per the standard, the
+// first clause whose condition holds for a given row decides what happens to it; target rows
+// that no clause applies to are left as is.
+//
+// In addition, each row carries the file and the number of the target row it comes from, whether
+// it pairs a target row with a source row, and whether the target row is affected by the merge
+// at all, i.e. has a match or has a clause applying to it.
+fn merge_query(
+    name: &ObjectName,
+    alias: Option<&TableAlias>,
+    source: &TableFactor,
+    on: &SqlExpr,
+    clauses: &[MergeClause],
+    target_schema: &SchemaRef,
+) -> Result<String> {
+    if clauses.is_empty() {
+        return Err(Error::Plan(
+            "MERGE requires at least one WHEN clause".to_string(),
+        ));
+    }
+
+    let last_ident = |name: &ObjectName| {
+        name.0
+            .last()
+            .cloned()
+            .ok_or_else(|| Error::Plan(format!("Invalid table name {name}")))
+    };
+
+    let target_alias = match alias {
+        Some(TableAlias { name, columns }) if columns.is_empty() => name.clone(),
+        Some(alias) => {
+            return Err(Error::NotImplemented(format!(
+                "Column aliases for the MERGE target are not supported: {alias}"
+            )))
+        }
+        None => last_ident(name)?,
+    };
+    let source_alias = match source {
+        TableFactor::Table {
+            alias: Some(alias), ..
+        }
+        | TableFactor::Derived {
+            alias: Some(alias), ..
+        } => alias.name.clone(),
+        TableFactor::Table {
+            name, alias: None, ..
+        } => last_ident(name)?,
+        _ => {
+            return Err(Error::NotImplemented(format!(
+                "MERGE source must be a table or an aliased subquery, got {source}"
+            )))
+        }
+    };
+
+    let columns = target_schema
+        .fields()
+        .iter()
+        .map(|f| Ident::with_quote('"', f.name()))
+        .collect::<Vec<_>>();
+    let column_index = |column: &Ident| {
+        let column_name = normalize_ident(column);
+        columns
+            .iter()
+            .position(|c| c.value == column_name)
+            .ok_or_else(|| {
+                Error::Plan(format!("Column {column} not found in table {name}"))
+            })
+    };
+
+    let target_marker = format!("{target_alias}.{MERGE_TARGET_COLUMN}");
+    let source_marker = format!("{source_alias}.{MERGE_SOURCE_COLUMN}");
+    let mut conditions = vec![];
+    let mut clause_values = vec![];
+
+    for MergeClause {
+        clause_kind,
+        predicate,
+        action,
+    } in clauses
+    {
+        let condition = match clause_kind {
+            MergeClauseKind::Matched => {
+                format!("{target_marker} IS NOT NULL AND {source_marker} IS NOT NULL")
+            }
+            MergeClauseKind::NotMatched | MergeClauseKind::NotMatchedByTarget => {
+                format!("{target_marker} IS NULL")
+            }
+            MergeClauseKind::NotMatchedBySource => format!("{source_marker} IS NULL"),
+        };
+        conditions.push(match predicate {
+            Some(predicate) => format!("{condition} AND ({predicate})"),
+            None => condition,
+        });
+
+        let values = match action {
+            MergeAction::Update { assignments } => {
+                let mut values = columns
+                    .iter()
+                    .map(|c| format!("{target_alias}.{c}"))
+                    .collect::<Vec<_>>();
+                for Assignment { target, value } in assignments {
+                    let AssignmentTarget::ColumnName(column) = target else {
+                        return Err(Error::NotImplemented(format!(
+                            "Unsupported assignment in MERGE: {target}"
+                        )));
+                    };
+                    values[column_index(&last_ident(column)?)?] = format!("({value})");
+                }
+                values
+            }
+            MergeAction::Delete => vec!["NULL".to_string(); columns.len()],
+            MergeAction::Insert(MergeInsertExpr {
+                columns: insert_columns,
+                kind: MergeInsertKind::Values(Values { rows, .. }),
+            }) if rows.len() == 1 => {
+                let insert_columns = if insert_columns.is_empty() {
+                    &columns
+                } else {
+                    insert_columns
+                };
+                if insert_columns.len() != rows[0].len() {
+                    return Err(Error::Plan(format!(
+                        "MERGE INSERT has {} target columns but {} values",
+                        insert_columns.len(),
+                        rows[0].len()
+                    )));
+                }
+
+                let mut values = vec!["NULL".to_string(); columns.len()];
+                for (column, value) in insert_columns.iter().zip(&rows[0]) {
+                    values[column_index(column)?] = format!("({value})");
+                }
+                values
+            }
+            MergeAction::Insert(insert) => {
+                return Err(Error::NotImplemented(format!(
+                    "Unsupported insert in MERGE: {insert}"
+                )))
+            }
+        };
+        clause_values.push(values);
+    }
+
+    let projections = columns.iter().enumerate().map(|(i, column)| {
+        let whens = conditions
+            .iter()
+            .zip(&clause_values)
+            .map(|(condition, values)| format!("WHEN {condition} THEN {}", values[i]))
+            .join(" ");
+        format!("CASE {whens} ELSE {target_alias}.{column} END AS {column}")
+    });
+    let keep = conditions
+        .iter()
+        .zip(clauses)
+        .map(|(condition, clause)| {
+            let keep = !matches!(clause.action, MergeAction::Delete);
+            format!("WHEN {condition} THEN {keep}")
+        })
+        .join(" ");
+
+    let touched = conditions.iter().map(|c| format!("({c})")).join(" OR ");
+
+    Ok(format!(
+        "SELECT {}, CASE {keep} WHEN {target_marker} IS NULL THEN FALSE ELSE TRUE END AS {MERGE_KEEP_COLUMN}, \
+        {target_alias}.{MERGE_FILE_COLUMN} AS {MERGE_FILE_COLUMN}, \
+        {target_alias}.{MERGE_ROW_COLUMN} AS {MERGE_ROW_COLUMN}, \
+        CASE WHEN {target_marker} IS NOT NULL AND {source_marker} IS NOT NULL THEN 1 ELSE 0 END AS {MERGE_MATCHED_COLUMN}, \
+        {target_marker} IS NOT NULL AND ({source_marker} IS NOT NULL OR {touched}) AS {MERGE_TOUCHED_COLUMN} \
+        FROM (SELECT *, TRUE AS {MERGE_TARGET_COLUMN}, CAST(NULL AS VARCHAR) AS {MERGE_FILE_COLUMN}, \
+        ROW_NUMBER() OVER () AS {MERGE_ROW_COLUMN} FROM {name}) AS {target_alias} \
+        FULL JOIN (SELECT *, TRUE AS {MERGE_SOURCE_COLUMN} FROM {source}) AS {source_alias} \
+        ON {on}",
+        projections.collect::<Vec<_>>().join(", ")
+    ))
+}
+
+/// Build a provider for scanning the MERGE target table that also outputs the path of the file
+/// each row comes from, optionally restricted to the given files.
+pub(super) fn merge_target_provider(
+    table: &DeltaTable,
+    files: Option<Vec<Add>>,
+) -> Result<Arc<dyn TableProvider>> {
+    let config = DeltaScanConfig {
+        file_column_name: Some(MERGE_FILE_COLUMN.to_string()),
+        ..Default::default()
+    };
+    let provider = DeltaTableProvider::try_new(
+        table.snapshot()?.clone(),
+        table.log_store(),
+        config,
+    )?;
+    Ok(Arc::new(match files {
+        Some(files) => provider.with_files(files),
+        None => provider,
+    }))
+}
+
+/// Point the scan of the target table in a MERGE plan at the given provider, and take the file
+/// column of the target relation from it.
+pub(super) fn scan_merge_target(
+    plan: LogicalPlan,
+    provider: Arc<dyn TableProvider>,
+) -> Result<LogicalPlan> {
+    let is_alias_of = |expr: &LogicalExpr, column: &str| matches!(expr, LogicalExpr::Alias(Alias { name, .. }) if name == column);
+
+    plan.transform_down(|plan| match plan {
+        LogicalPlan::Projection(Projection { expr, input, .. })
+            if expr.iter().any(|e| is_alias_of(e, MERGE_TARGET_COLUMN)) =>
+        {
+            let mut table_name = None;
+            let input = Arc::unwrap_or_clone(input)
+                .transform_up(|plan| match plan {
+                    LogicalPlan::TableScan(scan) => {
+                        table_name = Some(scan.table_name.clone());
+                        Ok(Transformed::yes(LogicalPlan::TableScan(
+                            TableScan::try_new(
+                                scan.table_name,
+                                provider_as_source(provider.clone()),
+                                scan.projection,
+                                scan.filters,
+                                scan.fetch,
+                            )?,
+                        )))
+                    }
+                    plan => plan.recompute_schema().map(Transformed::yes),
+                })
+                .data()?;
+
+            let expr = expr
+                .into_iter()
+                .map(|e| {
+                    if is_alias_of(&e, MERGE_FILE_COLUMN) {
+                        LogicalExpr::Column(Column::new(
+                            table_name.clone(),
+                            MERGE_FILE_COLUMN,
+                        ))
+                        .alias(MERGE_FILE_COLUMN)
+                    } else {
+                        e
+                    }
+                })
+                .collect();
+            Ok(Transformed::new(
+                LogicalPlan::Projection(Projection::try_new(expr, Arc::new(input))?),
+                true,
+                TreeNodeRecursion::Jump,
+            ))
+        }
+        plan => Ok(Transformed::no(plan)),
+    })
+    .data()
+}
+
+/// Find the target rows affected by a MERGE, along with the file they come from and the number
+/// of source rows they are matched with.
+pub(super) fn merge_touched_rows(input: LogicalPlan) -> Result<LogicalPlan> {
+    LogicalPlanBuilder::from(input)
+        .filter(ident(MERGE_TOUCHED_COLUMN))?
+        .aggregate(
+            vec![ident(MERGE_FILE_COLUMN), ident(MERGE_ROW_COLUMN)],
+            vec![sum(ident(MERGE_MATCHED_COLUMN)).alias(MERGE_MATCHED_COLUMN)],
+        )?
+        .build()
+}
+
+/// Drop the rows of a MERGE plan that were deleted or not inserted, and coerce the rest back into
+/// the target table schema.
+pub(super) fn merge_output(
+    input: LogicalPlan,
+    target_schema: &SchemaRef,
+) -> Result<LogicalPlan> {
+    LogicalPlanBuilder::from(input)
+        .filter(ident(MERGE_KEEP_COLUMN))?
+        .project(
+            target_schema
+                .fields()
+                .iter()
+                .map(|f| cast(ident(f.name()), f.data_type().clone()).alias(f.name())),
+        )?
+        .build()
 }
 
 #[cfg(test)]
@@ -2,14 +2,19 @@ pub mod delta;
 pub mod logical;
 pub mod physical;
 
+use crate::audit::{AuditEvent, AuditLog, AuditTableVersion};
+use crate::auth::Principal;
 use crate::catalog::metastore::Metastore;
+use crate::catalog::versions::PinnedVersions;
 use crate::catalog::{DEFAULT_SCHEMA, STAGING_SCHEMA};
 use crate::config::context::build_state_with_table_factories;
 use crate::object_store::wrapped::InternalObjectStore;
+use crate::queries::{ExecutionLimits, QueryGuard, QueryLimitError, QueryRegistry};
 use crate::wasm_udf::data_types::{get_volatility, CreateFunctionDetails};
 use crate::wasm_udf::wasm::create_udf_from_wasm;
 
 use crate::config::schema::SeafowlConfig;
+use crate::context::logical::{is_read_only, write_targets};
 use base64::{engine::general_purpose::STANDARD, Engine};
 pub use datafusion::error::{DataFusionError as Error, Result};
 use datafusion::{
@@ -17,9 +22,12 @@ use datafusion::{
     prelude::SessionContext,
     sql::{ResolvedTableReference, TableReference},
 };
+use datafusion_expr::LogicalPlan;
 use deltalake::DeltaTable;
 use object_store::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
 use uuid::Uuid;
 
 // The core Seafowl object, responsible for parsing, logical and physical planning, as well as
@@ -29,6 +37,9 @@ pub struct SeafowlContext {
     pub inner: SessionContext,
     pub metastore: Arc<Metastore>,
     pub internal_object_store: Option<Arc<InternalObjectStore>>,
+    pub audit_log: Option<Arc<AuditLog>>,
+    pub queries: Arc<QueryRegistry>,
+    pub pinned_versions: Arc<PinnedVersions>,
     pub default_catalog: String,
     pub default_schema: String,
 }
@@ -50,6 +61,9 @@ impl SeafowlContext {
             inner: SessionContext::new_with_state(state),
             metastore: self.metastore.clone(),
             internal_object_store: self.internal_object_store.clone(),
+            audit_log: self.audit_log.clone(),
+            queries: self.queries.clone(),
+            pinned_versions: self.pinned_versions.clone(),
             default_catalog: catalog,
             default_schema: schema,
         })
@@ -69,6 +83,9 @@ impl SeafowlContext {
             inner: self.inner.clone(),
             metastore,
             internal_object_store: self.internal_object_store.clone(),
+            audit_log: self.audit_log.clone(),
+            queries: self.queries.clone(),
+            pinned_versions: self.pinned_versions.clone(),
             default_catalog: self.default_catalog.clone(),
             default_schema: self.default_schema.clone(),
         })
@@ -93,7 +110,15 @@ impl SeafowlContext {
 
         self.inner.register_catalog(
             &self.default_catalog,
-            Arc::new(self.metastore.build_catalog(&self.default_catalog).await?),
+            Arc::new(
+                self.metastore
+                    .build_catalog(
+                        &self.default_catalog,
+                        self.queries.clone(),
+                        self.pinned_versions.snapshot(),
+                    )
+                    .await?,
+            ),
         );
 
         // Register all functions in the database
@@ -190,6 +215,74 @@ impl SeafowlContext {
         }
     }
 
+    // Append the event to the audit log, if one is configured
+    pub fn audit(&self, event: AuditEvent) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.log(&event);
+        }
+    }
+
+    // The configured execution limits, tightened by the ones requested by the client
+    pub fn execution_limits(&self, requested: ExecutionLimits) -> ExecutionLimits {
+        let runtime = &self.config.runtime;
+        ExecutionLimits {
+            statement_timeout: runtime.statement_timeout.map(Duration::from_millis),
+            max_result_rows: runtime.max_result_rows,
+            max_result_bytes: runtime.max_result_bytes,
+        }
+        .restrict(requested)
+    }
+
+    // Register a query with the list of running ones, so that it can be inspected and cancelled,
+    // unless the principal has hit one of its query limits
+    pub fn start_query(
+        &self,
+        principal: &Principal,
+        sql: &str,
+        limits: ExecutionLimits,
+    ) -> Result<QueryGuard, QueryLimitError> {
+        Ok(self
+            .queries
+            .start(
+                principal.name(),
+                &self.default_catalog,
+                sql,
+                self.inner.runtime_env().memory_pool.clone(),
+            )?
+            .with_limits(limits))
+    }
+
+    // Look up the current versions of the tables an executed write plan has written to, for the
+    // audit log. Tables that are gone by now (e.g. dropped ones) or aren't versioned are skipped.
+    pub async fn written_table_versions(
+        &self,
+        plan: &LogicalPlan,
+    ) -> Vec<AuditTableVersion> {
+        if self.audit_log.is_none() || is_read_only(plan) {
+            return vec![];
+        }
+
+        // Pick up any tables created by the plan
+        if let Err(err) = self.reload_schema().await {
+            warn!("Error reloading the schema for the audit log: {err}");
+            return vec![];
+        }
+
+        let mut versions = vec![];
+        for table in write_targets(plan) {
+            let table = self.resolve_table_ref(table);
+            if let Ok(mut delta_table) = self.try_get_delta_table(table.clone()).await
+                && delta_table.load().await.is_ok()
+            {
+                versions.push(AuditTableVersion {
+                    table: table.to_string(),
+                    version: delta_table.version(),
+                });
+            }
+        }
+        versions
+    }
+
     fn register_function(
         &self,
         name: &str,
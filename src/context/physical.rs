@@ -1,19 +1,27 @@
-use super::delta::CreateDeltaTableDetails;
-use crate::catalog::{DEFAULT_SCHEMA, STAGING_SCHEMA};
-use crate::context::delta::plan_to_object_store;
+use super::delta::{CreateDeltaTableDetails, TableLayout};
+use super::logical::{
+    merge_output, merge_target_provider, merge_touched_rows, scan_merge_target,
+};
+use crate::catalog::{CatalogError, DEFAULT_SCHEMA, STAGING_SCHEMA};
+use crate::context::delta::{
+    cluster_columns, cluster_plan, plan_to_object_store, remove_actions,
+};
 use crate::context::SeafowlContext;
 use crate::nodes::{
-    ConvertTable, CreateFunction, CreateTable, DropFunction, RenameTable,
-    SeafowlExtensionNode, Truncate, Vacuum,
+    AlterTable, ConvertTable, CreateFunction, CreateMaterializedView, CreatePolicy,
+    CreateTable, DropFunction, DropPolicy, KillQuery, Merge, Optimize,
+    RefreshMaterializedView, RenameTable, SeafowlExtensionNode, Truncate, Vacuum,
 };
 use crate::object_store::factory::build_object_store;
 use crate::object_store::http::try_prepare_http_url;
 use crate::object_store::wrapped::InternalObjectStore;
-use crate::provider::project_expressions;
+use crate::provider::{project_expressions, SeafowlSchema};
+use crate::queries::QueryGuard;
 use crate::utils::gc_databases;
 
 use arrow_schema::{DataType, Schema, TimeUnit};
 use chrono::TimeDelta;
+use datafusion::common::cast::{as_int64_array, as_string_array};
 use datafusion::common::DFSchema;
 use datafusion::datasource::file_format::csv::CsvFormat;
 use datafusion::datasource::listing::{
@@ -22,7 +30,7 @@ use datafusion::datasource::listing::{
 use datafusion::datasource::TableProvider;
 use datafusion::error::{DataFusionError as Error, Result};
 use datafusion::physical_expr::execution_props::ExecutionProps;
-use datafusion::physical_expr::expressions::{cast, Column};
+use datafusion::physical_expr::expressions::{cast, Column, Literal};
 use datafusion::physical_expr::{create_physical_expr, PhysicalExpr};
 use datafusion::physical_optimizer::pruning::PruningPredicate;
 use datafusion::physical_plan::empty::EmptyExec;
@@ -33,28 +41,32 @@ use datafusion::{
     arrow::{datatypes::SchemaRef, record_batch::RecordBatch},
     datasource::file_format::{parquet::ParquetFormat, FileFormat},
     error::DataFusionError,
-    execution::context::TaskContext,
+    execution::{context::TaskContext, runtime_env::RuntimeEnv},
     physical_plan::{ExecutionPlan, SendableRecordBatchStream},
     sql::TableReference,
 };
 use datafusion_common::tree_node::{Transformed, TransformedResult, TreeNode};
-use datafusion_common::{Column as ColumnExpr, ResolvedTableReference, SchemaReference};
+use datafusion_common::{
+    Column as ColumnExpr, ResolvedTableReference, ScalarValue, SchemaReference,
+};
 use datafusion_expr::logical_plan::{
     CreateCatalog, CreateCatalogSchema, CreateExternalTable, CreateMemoryTable,
-    DropTable, Extension, LogicalPlan, Projection,
+    CreateView, DropTable, DropView, Extension, LogicalPlan, Projection,
 };
 use datafusion_expr::{
     DdlStatement, DmlStatement, DropCatalogSchema, Expr, Filter, WriteOp,
 };
-use deltalake::kernel::{Action, Add, Remove};
+use deltalake::delta_datafusion::DeltaTableProvider;
+use deltalake::kernel::{Action, Add, Schema as DeltaSchema};
 use deltalake::operations::vacuum::VacuumBuilder;
 use deltalake::protocol::{DeltaOperation, SaveMode};
 use deltalake::DeltaTable;
+use itertools::Itertools;
 use object_store::path::Path;
+use std::collections::{BTreeMap, HashSet};
 use std::ops::Deref;
 use std::ops::Not;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::info;
 use url::Url;
 
@@ -166,27 +178,13 @@ impl SeafowlContext {
                 ..
             })) => {
                 // This is actually CREATE TABLE AS
-                if *if_not_exists && self.try_get_delta_table(name.clone()).await.is_ok()
-                {
-                    // Table already exists
-                    return Ok(make_dummy_exec());
-                }
-
-                let plan = self.inner.state().create_physical_plan(input).await?;
-                let plan = self.coerce_plan(plan).await?;
-
-                // First create the table and then insert the data from the subquery
-                // TODO: this means we'll have 2 table versions at the end, 1st from the create
-                // and 2nd from the insert, while it seems more reasonable that in this case we have
-                // only one
-                self.create_delta_table(
+                self.create_table_as(
                     name.clone(),
-                    CreateDeltaTableDetails::EmptyTable(plan.schema().as_ref().clone()),
+                    input,
+                    *if_not_exists,
+                    TableLayout::default(),
                 )
-                .await?;
-                self.plan_to_delta_table(name.clone(), &plan).await?;
-
-                Ok(make_dummy_exec())
+                .await
             }
             LogicalPlan::Dml(DmlStatement {
                 table_name,
@@ -196,7 +194,7 @@ impl SeafowlContext {
             }) => {
                 let physical = self.inner.state().create_physical_plan(input).await?;
 
-                self.plan_to_delta_table(table_name.clone(), &physical)
+                self.plan_to_delta_table(table_name.clone(), &physical, SaveMode::Append)
                     .await?;
 
                 Ok(make_dummy_exec())
@@ -315,29 +313,12 @@ impl SeafowlContext {
                         object_store,
                         local_table_dir,
                         self.config.misc.max_partition_size,
+                        &table.metadata()?.partition_columns,
                     )
                     .await?;
 
-                    let deletion_timestamp = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as i64;
-
                     actions = adds.into_iter().map(Action::Add).collect();
-                    for remove in removes {
-                        actions.push(Action::Remove(Remove {
-                            path: remove.path,
-                            deletion_timestamp: Some(deletion_timestamp),
-                            data_change: true,
-                            extended_file_metadata: Some(true),
-                            partition_values: Some(remove.partition_values),
-                            size: Some(remove.size),
-                            tags: None,
-                            deletion_vector: None,
-                            base_row_id: None,
-                            default_row_commit_version: None,
-                        }))
-                    }
+                    actions.extend(remove_actions(removes, true));
                 }
 
                 let op = DeltaOperation::Write {
@@ -427,6 +408,7 @@ impl SeafowlContext {
                                 object_store,
                                 local_table_dir,
                                 self.config.misc.max_partition_size,
+                                &table.metadata()?.partition_columns,
                             )
                             .await?;
 
@@ -438,27 +420,9 @@ impl SeafowlContext {
                         (vec![], snapshot.file_actions()?)
                     };
 
-                let deletion_timestamp = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as i64;
-
                 let mut actions: Vec<Action> =
                     adds.into_iter().map(Action::Add).collect();
-                for remove in removes {
-                    actions.push(Action::Remove(Remove {
-                        path: remove.path,
-                        deletion_timestamp: Some(deletion_timestamp),
-                        data_change: true,
-                        extended_file_metadata: Some(true),
-                        partition_values: Some(remove.partition_values),
-                        size: Some(remove.size),
-                        tags: None,
-                        deletion_vector: None,
-                        base_row_id: None,
-                        default_row_commit_version: None,
-                    }))
-                }
+                actions.extend(remove_actions(removes, true));
 
                 let op = DeltaOperation::Delete { predicate: None };
 
@@ -536,8 +500,15 @@ impl SeafowlContext {
                     Some(schema) => schema,
                 };
 
-                // Delete each table sequentially
+                // Delete each table sequentially; the views get deleted along with the schema
                 for table_name in schema.table_names() {
+                    if let Some(seafowl_schema) =
+                        schema.as_any().downcast_ref::<SeafowlSchema>()
+                        && seafowl_schema.is_view(&table_name)
+                    {
+                        continue;
+                    }
+
                     let table_ref = ResolvedTableReference {
                         catalog: Arc::from(self.default_catalog.as_str()),
                         schema: Arc::from(schema_name),
@@ -558,9 +529,60 @@ impl SeafowlContext {
 
                 Ok(make_dummy_exec())
             }
-            LogicalPlan::Ddl(DdlStatement::CreateView(_)) => Err(Error::Plan(
-                "Creating views is currently unsupported!".to_string(),
-            )),
+            LogicalPlan::Ddl(DdlStatement::CreateView(CreateView {
+                name,
+                or_replace,
+                definition,
+                ..
+            })) => {
+                let resolved_ref = self.resolve_table_ref(name.clone());
+
+                if self.try_get_delta_table(name.clone()).await.is_ok() {
+                    return Err(Error::Plan(format!(
+                        "Table {name} already exists, can't create a view with the same name"
+                    )));
+                }
+
+                let definition = definition.as_ref().ok_or_else(|| {
+                    Error::Plan(format!("Missing definition for view {name}"))
+                })?;
+
+                self.metastore
+                    .views
+                    .create(
+                        &resolved_ref.catalog,
+                        &resolved_ref.schema,
+                        &resolved_ref.table,
+                        definition,
+                        *or_replace,
+                    )
+                    .await?;
+
+                Ok(make_dummy_exec())
+            }
+            LogicalPlan::Ddl(DdlStatement::DropView(DropView {
+                name,
+                if_exists,
+                ..
+            })) => {
+                let resolved_ref = self.resolve_table_ref(name.clone());
+
+                match self
+                    .metastore
+                    .views
+                    .delete(
+                        &resolved_ref.catalog,
+                        &resolved_ref.schema,
+                        &resolved_ref.table,
+                    )
+                    .await
+                {
+                    Err(CatalogError::ViewDoesNotExist { .. }) if *if_exists => {}
+                    result => result?,
+                };
+
+                Ok(make_dummy_exec())
+            }
             LogicalPlan::Extension(Extension { ref node }) => {
                 // Other custom nodes we made like CREATE TABLE/INSERT/ALTER
                 match SeafowlExtensionNode::from_dynamic(node) {
@@ -580,17 +602,353 @@ impl SeafowlContext {
 
                             Ok(make_dummy_exec())
                         }
+                        SeafowlExtensionNode::CreateTable(CreateTable {
+                            name,
+                            if_not_exists,
+                            partition_columns,
+                            cluster_columns,
+                            input: Some(input),
+                            ..
+                        }) => {
+                            self.create_table_as(
+                                name.as_str(),
+                                input,
+                                *if_not_exists,
+                                TableLayout {
+                                    partition_columns: partition_columns.clone(),
+                                    cluster_columns: cluster_columns.clone(),
+                                },
+                            )
+                            .await
+                        }
                         SeafowlExtensionNode::CreateTable(CreateTable {
                             schema,
                             name,
+                            partition_columns,
+                            cluster_columns,
+                            input: None,
                             ..
                         }) => {
                             self.create_delta_table(
                                 name.as_str(),
-                                CreateDeltaTableDetails::EmptyTable(schema.clone()),
+                                CreateDeltaTableDetails::EmptyTable(
+                                    schema.clone(),
+                                    TableLayout {
+                                        partition_columns: partition_columns.clone(),
+                                        cluster_columns: cluster_columns.clone(),
+                                    },
+                                ),
+                            )
+                            .await?;
+
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::CreateMaterializedView(
+                            CreateMaterializedView {
+                                name,
+                                definition,
+                                input,
+                                ..
+                            },
+                        ) => {
+                            let resolved_ref = self.resolve_table_ref(name.as_str());
+                            if self.inner.table_exist(resolved_ref.clone())? {
+                                return Err(Error::Plan(format!(
+                                    "Table {name:?} already exists"
+                                )));
+                            }
+
+                            let plan =
+                                self.inner.state().create_physical_plan(input).await?;
+                            let plan = self.coerce_plan(plan).await?;
+
+                            // The contents are stored as a regular table, with the catalog
+                            // keeping track of the defining query for refreshes
+                            self.create_delta_table(
+                                name.as_str(),
+                                CreateDeltaTableDetails::EmptyTable(
+                                    plan.schema().as_ref().clone(),
+                                    TableLayout::default(),
+                                ),
+                            )
+                            .await?;
+                            let populated = async {
+                                self.metastore
+                                    .tables
+                                    .update_definition(
+                                        &resolved_ref.catalog,
+                                        &resolved_ref.schema,
+                                        &resolved_ref.table,
+                                        Some(definition),
+                                    )
+                                    .await?;
+                                self.plan_to_delta_table(
+                                    name.as_str(),
+                                    &plan,
+                                    SaveMode::Append,
+                                )
+                                .await
+                            }
+                            .await;
+
+                            // Don't leave an empty or definition-less view behind
+                            if let Err(err) = populated {
+                                self.delete_delta_table(name.as_str())
+                                    .await
+                                    .unwrap_or_else(|e| {
+                                        info!("Failed to cleanup table {name}: {e}")
+                                    });
+                                self.metastore
+                                    .tables
+                                    .delete(
+                                        &resolved_ref.catalog,
+                                        &resolved_ref.schema,
+                                        &resolved_ref.table,
+                                    )
+                                    .await?;
+                                self.inner.deregister_table(resolved_ref)?;
+                                return Err(err);
+                            }
+
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::RefreshMaterializedView(
+                            RefreshMaterializedView { name, input, .. },
+                        ) => {
+                            let table = self.try_get_delta_table(name.as_str()).await?;
+
+                            let plan =
+                                self.inner.state().create_physical_plan(input).await?;
+                            let plan = self.coerce_plan(plan).await?;
+
+                            // Changes to the underlying tables may result in a different schema,
+                            // which we can't write into the existing table
+                            let new_schema = Schema::try_from(&DeltaSchema::try_from(
+                                plan.schema().as_ref(),
+                            )?)?;
+                            if !new_schema
+                                .fields()
+                                .iter()
+                                .map(|f| (f.name(), f.data_type()))
+                                .eq(TableProvider::schema(&table)
+                                    .fields()
+                                    .iter()
+                                    .map(|f| (f.name(), f.data_type())))
+                            {
+                                return Err(Error::Plan(format!(
+                                    "The schema of materialized view {name} has changed, it needs to be re-created"
+                                )));
+                            }
+
+                            // Replace the contents in a single new table version
+                            self.plan_to_delta_table(
+                                name.as_str(),
+                                &plan,
+                                SaveMode::Overwrite,
+                            )
+                            .await?;
+
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::Merge(Merge {
+                            name,
+                            merge_predicate,
+                            input,
+                            ..
+                        }) => {
+                            // This is the same table version that the merge plan scans
+                            let table = self.try_get_delta_table(name.as_str()).await?;
+                            let uuid = self.get_table_uuid(name.as_str()).await?;
+                            let state = self.inner.state();
+                            let input = scan_merge_target(
+                                input.as_ref().clone(),
+                                merge_target_provider(&table, None)?,
+                            )?;
+
+                            // Find the files holding the target rows the merge affects, and
+                            // make sure none of them is matched by more than one source row
+                            let touched = state
+                                .create_physical_plan(&merge_touched_rows(input.clone())?)
+                                .await?;
+                            let mut touched_files = HashSet::new();
+                            for batch in self.collect(touched).await? {
+                                let matches = as_int64_array(batch.column(2))?;
+                                if matches.iter().any(|m| m.unwrap_or_default() > 1) {
+                                    return Err(DataFusionError::Execution(
+                                        "MERGE matched a target row with more than one source row"
+                                            .to_string(),
+                                    ));
+                                }
+                                touched_files.extend(
+                                    as_string_array(batch.column(0))?
+                                        .iter()
+                                        .flatten()
+                                        .map(String::from),
+                                );
+                            }
+                            let removes = table
+                                .snapshot()?
+                                .file_actions()?
+                                .into_iter()
+                                .filter(|add| touched_files.contains(&add.path))
+                                .collect::<Vec<_>>();
+
+                            // Rewrite only those files, together with any inserted rows, and
+                            // replace them in a single table version
+                            let input = scan_merge_target(
+                                input,
+                                merge_target_provider(&table, Some(removes.clone()))?,
+                            )?;
+                            let plan = state
+                                .create_physical_plan(&merge_output(
+                                    input,
+                                    &TableProvider::schema(&table),
+                                )?)
+                                .await?;
+                            let internal_object_store =
+                                self.get_internal_object_store()?;
+                            let adds = plan_to_object_store(
+                                &state,
+                                &plan,
+                                internal_object_store
+                                    .get_log_store(&uuid.to_string())
+                                    .object_store(),
+                                internal_object_store.local_table_dir(&uuid.to_string()),
+                                self.config.misc.max_partition_size,
+                                &table.metadata()?.partition_columns,
                             )
                             .await?;
 
+                            let mut actions: Vec<Action> =
+                                adds.into_iter().map(Action::Add).collect();
+                            actions.extend(remove_actions(removes, true));
+
+                            let op = DeltaOperation::Merge {
+                                predicate: None,
+                                merge_predicate: Some(merge_predicate.clone()),
+                                matched_predicates: vec![],
+                                not_matched_predicates: vec![],
+                                not_matched_by_source_predicates: vec![],
+                            };
+
+                            let version = self.commit(actions, &table, op).await?;
+
+                            self.metastore
+                                .tables
+                                .create_new_version(uuid, version)
+                                .await?;
+
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::AlterTable(AlterTable {
+                            name,
+                            columns,
+                            ..
+                        }) => {
+                            let table = self.try_get_delta_table(name.as_str()).await?;
+                            let uuid = self.get_table_uuid(name.as_str()).await?;
+                            let existing_schema = TableProvider::schema(&table);
+
+                            let schema = Schema::new(
+                                columns
+                                    .iter()
+                                    .map(|c| c.field.clone())
+                                    .collect::<Vec<_>>(),
+                            );
+                            let delta_schema = DeltaSchema::try_from(&schema)?;
+
+                            // Keep the table identity and configuration, only the schema changes
+                            let snapshot = table.snapshot()?;
+                            let mut metadata = snapshot.metadata().clone();
+                            metadata.schema_string = serde_json::to_string(&delta_schema)
+                                .map_err(|e| DataFusionError::External(Box::new(e)))?;
+                            let mut actions = vec![Action::Metadata(metadata)];
+
+                            let unchanged_columns = columns
+                                .iter()
+                                .filter(|c| c.source.as_ref() == Some(c.field.name()))
+                                .count();
+                            let op = if unchanged_columns
+                                == existing_schema.fields().len()
+                            {
+                                // Only new columns have been appended; these get resolved as
+                                // NULLs when reading the existing data files.
+                                DeltaOperation::AddColumn {
+                                    fields: delta_schema
+                                        .fields()
+                                        .skip(unchanged_columns)
+                                        .cloned()
+                                        .collect(),
+                                }
+                            } else {
+                                // Dropped and renamed columns can't be resolved by name from the
+                                // existing data files anymore, so rewrite them with the new schema.
+                                // Leaving a dropped column in the files isn't enough either, since
+                                // a column re-added under the same name would resurface the old
+                                // values. Making this metadata-only requires Delta column mapping
+                                // (`delta.columnMapping.mode = name`), but delta-rs can't yet commit
+                                // to or scan tables with that feature enabled.
+                                let state = self.inner.state();
+                                let scan = table.scan(&state, None, &[], None).await?;
+                                let scan_schema = scan.schema();
+                                let projections = columns
+                                    .iter()
+                                    .map(|c| {
+                                        let expr: Arc<dyn PhysicalExpr> = match &c.source
+                                        {
+                                            Some(source) => {
+                                                Arc::new(Column::new_with_schema(
+                                                    source,
+                                                    &scan_schema,
+                                                )?)
+                                            }
+                                            None => Arc::new(Literal::new(
+                                                ScalarValue::try_from(
+                                                    c.field.data_type(),
+                                                )?,
+                                            )),
+                                        };
+                                        Ok((expr, c.field.name().to_string()))
+                                    })
+                                    .collect::<Result<Vec<_>>>()?;
+                                let plan: Arc<dyn ExecutionPlan> =
+                                    Arc::new(ProjectionExec::try_new(projections, scan)?);
+
+                                let internal_object_store =
+                                    self.get_internal_object_store()?;
+                                let adds = plan_to_object_store(
+                                    &state,
+                                    &plan,
+                                    internal_object_store
+                                        .get_log_store(&uuid.to_string())
+                                        .object_store(),
+                                    internal_object_store
+                                        .local_table_dir(&uuid.to_string()),
+                                    self.config.misc.max_partition_size,
+                                    &table.metadata()?.partition_columns,
+                                )
+                                .await?;
+
+                                actions.extend(adds.into_iter().map(Action::Add));
+                                actions.extend(remove_actions(
+                                    snapshot.file_actions()?,
+                                    true,
+                                ));
+
+                                DeltaOperation::Write {
+                                    mode: SaveMode::Overwrite,
+                                    partition_by: None,
+                                    predicate: None,
+                                }
+                            };
+
+                            let version = self.commit(actions, &table, op).await?;
+
+                            self.metastore
+                                .tables
+                                .create_new_version(uuid, version)
+                                .await?;
+
                             Ok(make_dummy_exec())
                         }
                         SeafowlExtensionNode::CreateFunction(CreateFunction {
@@ -620,6 +978,65 @@ impl SeafowlContext {
                                 .await?;
                             Ok(make_dummy_exec())
                         }
+                        SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                            name,
+                            table_name,
+                            column,
+                            expression,
+                            principals,
+                            output_schema: _,
+                        }) => {
+                            let resolved_ref = self.resolve_table_ref(table_name);
+
+                            self.metastore
+                                .policies
+                                .create(
+                                    &resolved_ref.catalog,
+                                    &resolved_ref.schema,
+                                    &resolved_ref.table,
+                                    name,
+                                    column.as_deref(),
+                                    expression,
+                                    principals.as_deref(),
+                                )
+                                .await?;
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::DropPolicy(DropPolicy {
+                            name,
+                            table_name,
+                            if_exists,
+                            output_schema: _,
+                        }) => {
+                            let resolved_ref = self.resolve_table_ref(table_name);
+
+                            match self
+                                .metastore
+                                .policies
+                                .delete(
+                                    &resolved_ref.catalog,
+                                    &resolved_ref.schema,
+                                    &resolved_ref.table,
+                                    name,
+                                )
+                                .await
+                            {
+                                Err(CatalogError::PolicyDoesNotExist { .. })
+                                    if *if_exists => {}
+                                result => result?,
+                            };
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::KillQuery(KillQuery { id, .. }) => {
+                            // Only queries against the current database are visible
+                            match self.queries.get(*id) {
+                                Some(query) if query.database == self.default_catalog => {
+                                    query.cancel();
+                                    Ok(make_dummy_exec())
+                                }
+                                _ => Err(Error::Plan(format!("Query {id} not found"))),
+                            }
+                        }
                         SeafowlExtensionNode::RenameTable(RenameTable {
                             old_name,
                             new_name,
@@ -657,31 +1074,8 @@ impl SeafowlContext {
                             let mut table = self.try_get_delta_table(table_name).await?;
                             table.load().await?;
                             let snapshot = table.snapshot()?;
-                            let deletion_timestamp = SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .unwrap()
-                                .as_millis()
-                                as i64;
-                            let actions: Vec<Action> = snapshot
-                                .file_actions()?
-                                .into_iter()
-                                .map(|add_action| {
-                                    Action::Remove(Remove {
-                                        path: add_action.path,
-                                        deletion_timestamp: Some(deletion_timestamp),
-                                        data_change: true,
-                                        extended_file_metadata: Some(true),
-                                        partition_values: Some(
-                                            add_action.partition_values,
-                                        ),
-                                        size: Some(add_action.size),
-                                        tags: None,
-                                        deletion_vector: None,
-                                        base_row_id: None,
-                                        default_row_commit_version: None,
-                                    })
-                                })
-                                .collect();
+                            let actions: Vec<Action> =
+                                remove_actions(snapshot.file_actions()?, true).collect();
 
                             let op = DeltaOperation::Delete { predicate: None };
                             self.commit(actions, &table, op).await?;
@@ -750,6 +1144,136 @@ impl SeafowlContext {
                                 }
                             }
 
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::Optimize(Optimize {
+                            table_name,
+                            predicate,
+                            ..
+                        }) => {
+                            let uuid = self.get_table_uuid(table_name.as_str()).await?;
+                            let table =
+                                self.try_get_delta_table(table_name.as_str()).await?;
+                            let snapshot = table.snapshot()?;
+                            let max_partition_size = self.config.misc.max_partition_size;
+
+                            let mut files = snapshot.file_actions()?;
+                            if let Some(predicate) = predicate {
+                                // Only consider the files that may contain matching rows
+                                let schema = TableProvider::schema(&table);
+                                let prune_expr = create_physical_expr(
+                                    predicate,
+                                    &DFSchema::try_from(schema.as_ref().clone())?,
+                                    &ExecutionProps::new(),
+                                )?;
+                                let prune_map =
+                                    PruningPredicate::try_new(prune_expr, schema)?
+                                        .prune(snapshot)?;
+                                files = files
+                                    .into_iter()
+                                    .zip(prune_map)
+                                    .filter_map(|(add, keep)| keep.then_some(add))
+                                    .collect();
+                            }
+
+                            // Group the files that are smaller than the target size by table
+                            // partition; only partitions with more than one such file can be
+                            // compacted.
+                            let mut small_files: BTreeMap<Vec<_>, Vec<Add>> =
+                                BTreeMap::new();
+                            for add in files {
+                                let num_records = add
+                                    .stats
+                                    .as_deref()
+                                    .and_then(|stats| {
+                                        serde_json::from_str::<serde_json::Value>(stats)
+                                            .ok()
+                                    })
+                                    .and_then(|stats| stats["numRecords"].as_u64());
+                                if num_records
+                                    .is_some_and(|rows| rows >= max_partition_size as u64)
+                                {
+                                    continue;
+                                }
+
+                                let partition_values = add
+                                    .partition_values
+                                    .clone()
+                                    .into_iter()
+                                    .sorted()
+                                    .collect();
+                                small_files
+                                    .entry(partition_values)
+                                    .or_default()
+                                    .push(add);
+                            }
+                            let files_to_compact = small_files
+                                .into_values()
+                                .filter(|files| files.len() > 1)
+                                .flatten()
+                                .collect::<Vec<_>>();
+
+                            if files_to_compact.is_empty() {
+                                info!("No files to compact in table {table_name}");
+                                return Ok(make_dummy_exec());
+                            }
+
+                            // Scan only the selected files and re-chunk their rows
+                            let state = self.inner.state();
+                            let plan = DeltaTableProvider::try_new(
+                                snapshot.clone(),
+                                table.log_store(),
+                                Default::default(),
+                            )?
+                            .with_files(files_to_compact.clone())
+                            .scan(&state, None, &[], None)
+                            .await?;
+                            let plan =
+                                cluster_plan(plan, &cluster_columns(table.metadata()?)?)?;
+
+                            let internal_object_store =
+                                self.get_internal_object_store()?;
+                            let adds = plan_to_object_store(
+                                &state,
+                                &plan,
+                                internal_object_store
+                                    .get_log_store(&uuid.to_string())
+                                    .object_store(),
+                                internal_object_store.local_table_dir(&uuid.to_string()),
+                                max_partition_size,
+                                &table.metadata()?.partition_columns,
+                            )
+                            .await?;
+                            info!(
+                                "Compacting {} files into {} in table {table_name}",
+                                files_to_compact.len(),
+                                adds.len()
+                            );
+
+                            // Compaction doesn't change the table data, only its layout
+                            let mut actions: Vec<Action> = adds
+                                .into_iter()
+                                .map(|add| {
+                                    Action::Add(Add {
+                                        data_change: false,
+                                        ..add
+                                    })
+                                })
+                                .collect();
+                            actions.extend(remove_actions(files_to_compact, false));
+
+                            // NB: our target file size is expressed in rows, not bytes
+                            let op = DeltaOperation::Optimize {
+                                predicate: predicate.as_ref().map(|p| p.to_string()),
+                                target_size: max_partition_size as i64,
+                            };
+                            let version = self.commit(actions, &table, op).await?;
+
+                            self.metastore
+                                .tables
+                                .create_new_version(uuid, version)
+                                .await?;
+
                             Ok(make_dummy_exec())
                         }
                     },
@@ -890,7 +1414,27 @@ impl SeafowlContext {
         execute_stream(physical_plan, task_context)
     }
 
-    /// Append data from the provided file, creating a new schema/table if absent
+    // Execute the plan on behalf of a registered query, so that its memory usage gets tracked and
+    // the returned stream stops once the query is cancelled
+    pub async fn execute_query_stream(
+        &self,
+        physical_plan: Arc<dyn ExecutionPlan>,
+        query: QueryGuard,
+    ) -> Result<SendableRecordBatchStream> {
+        let runtime_env = self.inner.runtime_env();
+        let task_context =
+            TaskContext::from(self.inner()).with_runtime(Arc::new(RuntimeEnv {
+                memory_pool: query.memory_pool(),
+                disk_manager: runtime_env.disk_manager.clone(),
+                cache_manager: runtime_env.cache_manager.clone(),
+                object_store_registry: runtime_env.object_store_registry.clone(),
+            }));
+        let stream = execute_stream(physical_plan, Arc::new(task_context))?;
+        Ok(query.track(stream))
+    }
+
+    /// Append data from the provided file, creating a new schema/table if absent. Also returns
+    /// the number of appended rows, if the file scan reported it.
     pub async fn file_to_table(
         &self,
         file_path: String,
@@ -899,7 +1443,7 @@ impl SeafowlContext {
         has_header: bool,
         schema_name: String,
         table_name: String,
-    ) -> Result<DeltaTable> {
+    ) -> Result<(DeltaTable, Option<usize>)> {
         // Reload the schema since `try_get_delta_table` relies on using DataFusion's
         // TableProvider interface (which we need to pre-populate with up to date
         // information on our tables)
@@ -979,12 +1523,52 @@ impl SeafowlContext {
         if !table_exists {
             self.create_delta_table(
                 table_ref.clone(),
-                CreateDeltaTableDetails::EmptyTable(plan.schema().as_ref().clone()),
+                CreateDeltaTableDetails::EmptyTable(
+                    plan.schema().as_ref().clone(),
+                    TableLayout::default(),
+                ),
             )
             .await?;
         }
 
-        self.plan_to_delta_table(table_ref, &plan).await
+        let table = self
+            .plan_to_delta_table(table_ref, &plan, SaveMode::Append)
+            .await?;
+
+        // The scan has been fully executed by now, so its metrics hold the total row count
+        let rows = plan.metrics().and_then(|metrics| metrics.output_rows());
+        Ok((table, rows))
+    }
+
+    async fn create_table_as(
+        &self,
+        name: impl Into<TableReference>,
+        input: &LogicalPlan,
+        if_not_exists: bool,
+        layout: TableLayout,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let name = name.into();
+        if if_not_exists && self.try_get_delta_table(name.clone()).await.is_ok() {
+            // Table already exists
+            return Ok(make_dummy_exec());
+        }
+
+        let plan = self.inner.state().create_physical_plan(input).await?;
+        let plan = self.coerce_plan(plan).await?;
+
+        // First create the table and then insert the data from the subquery
+        // TODO: this means we'll have 2 table versions at the end, 1st from the create
+        // and 2nd from the insert, while it seems more reasonable that in this case we have
+        // only one
+        self.create_delta_table(
+            name.clone(),
+            CreateDeltaTableDetails::EmptyTable(plan.schema().as_ref().clone(), layout),
+        )
+        .await?;
+        self.plan_to_delta_table(name, &plan, SaveMode::Append)
+            .await?;
+
+        Ok(make_dummy_exec())
     }
 }
 
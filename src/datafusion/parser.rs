@@ -28,12 +28,16 @@
 pub use datafusion::sql::parser::Statement;
 use datafusion::sql::parser::{CopyToSource, CopyToStatement, CreateExternalTable};
 use lazy_static::lazy_static;
-use sqlparser::ast::{CreateFunctionBody, Expr, ObjectName, OrderByExpr, Value};
+use sqlparser::ast::helpers::stmt_create_table::CreateTableBuilder;
+use sqlparser::ast::{
+    CreateFunctionBody, CreateIndex, Expr, KillType, ObjectName, ObjectType, OrderByExpr,
+    Value, WrappedCollection,
+};
 use sqlparser::tokenizer::{TokenWithLocation, Word};
 use sqlparser::{
     ast::{ColumnDef, ColumnOptionDef, Statement as SQLStatement, TableConstraint},
     dialect::{keywords::Keyword, Dialect, GenericDialect},
-    parser::{Parser, ParserError},
+    parser::{IsOptional, Parser, ParserError},
     tokenizer::{Token, Tokenizer},
 };
 use std::collections::VecDeque;
@@ -58,6 +62,66 @@ fn ensure_not_set<T>(field: &Option<T>, name: &str) -> Result<(), ParserError> {
     Ok(())
 }
 
+// XXX SEAFOWL: rewrite the `FOR SYSTEM_TIME AS OF <version>` and `VERSION AS OF <version>`
+// clauses following a table name into our time travel table function syntax, i.e.
+// `table(<version>)`, since sqlparser only parses the former for some dialects.
+fn rewrite_as_of_clauses(tokens: Vec<Token>) -> Vec<Token> {
+    // Indices of the tokens that aren't whitespace
+    let positions = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| !matches!(token, Token::Whitespace(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let keyword_at = |n: usize, keyword: Keyword| {
+        positions.get(n).is_some_and(|&i| {
+            matches!(
+                &tokens[i],
+                Token::Word(w) if w.keyword == keyword && w.quote_style.is_none()
+            )
+        })
+    };
+
+    let mut rewritten = Vec::with_capacity(tokens.len());
+    let mut copied = 0;
+    let mut n = 1;
+    while n < positions.len() {
+        let clause_len = if keyword_at(n, Keyword::FOR)
+            && keyword_at(n + 1, Keyword::SYSTEM_TIME)
+            && keyword_at(n + 2, Keyword::AS)
+            && keyword_at(n + 3, Keyword::OF)
+        {
+            4
+        } else if keyword_at(n, Keyword::VERSION)
+            && keyword_at(n + 1, Keyword::AS)
+            && keyword_at(n + 2, Keyword::OF)
+        {
+            3
+        } else {
+            0
+        };
+
+        if clause_len > 0
+            && matches!(tokens[positions[n - 1]], Token::Word(_))
+            && let Some(&i) = positions.get(n + clause_len)
+            && matches!(
+                tokens[i],
+                Token::SingleQuotedString(_) | Token::Number(_, _)
+            )
+        {
+            rewritten.extend_from_slice(&tokens[copied..positions[n]]);
+            rewritten.extend([Token::LParen, tokens[i].clone(), Token::RParen]);
+            copied = i + 1;
+            n += clause_len + 1;
+        } else {
+            n += 1;
+        }
+    }
+    rewritten.extend_from_slice(&tokens[copied..]);
+    rewritten
+}
+// XXX SEAFOWL: change ends here
+
 // XXX SEAFOWL: removed the struct definitions here because we want to use
 // the original datafusion::sql::parser structs in order to pass them back
 // to its logical planner
@@ -89,6 +153,9 @@ impl<'a> DFParser<'a> {
     ) -> Result<Self, ParserError> {
         let mut tokenizer = Tokenizer::new(dialect, sql);
         let tokens = tokenizer.tokenize()?;
+        // XXX SEAFOWL: time travel clause support
+        let tokens = rewrite_as_of_clauses(tokens);
+        // XXX SEAFOWL: change ends here
 
         Ok(DFParser {
             parser: Parser::new(dialect).with_tokens(tokens),
@@ -163,6 +230,38 @@ impl<'a> DFParser<'a> {
                         self.parser.next_token();
                         self.parse_truncate()
                     }
+                    Keyword::OPTIMIZE => {
+                        self.parser.next_token();
+                        self.parse_optimize()
+                    }
+                    Keyword::DROP => match self.parser.peek_nth_token(1).token {
+                        Token::Word(w) if w.keyword == Keyword::POLICY => {
+                            self.parser.next_token();
+                            self.parser.next_token();
+                            self.parse_drop_policy()
+                        }
+                        // DROP POLICY is conveyed as DROP INDEX, so don't accept the original
+                        Token::Word(w) if w.keyword == Keyword::INDEX => {
+                            parser_err!("Indexes are not supported")
+                        }
+                        _ => Ok(Statement::Statement(Box::from(
+                            self.parser.parse_statement()?,
+                        ))),
+                    },
+                    Keyword::KILL => {
+                        self.parser.next_token();
+                        self.parse_kill()
+                    }
+                    // REFRESH is not a keyword in sqlparser
+                    Keyword::NoKeyword if w.value.eq_ignore_ascii_case("REFRESH") => {
+                        self.parser.next_token();
+                        self.parse_refresh()
+                    }
+                    // REFRESH MATERIALIZED VIEW is conveyed as MSCK REPAIR TABLE, so don't
+                    // accept the original
+                    Keyword::MSCK => parser_err!("MSCK is not supported"),
+                    // Likewise for OPTIMIZE, which is conveyed as ANALYZE
+                    Keyword::ANALYZE => parser_err!("ANALYZE is not supported"),
                     _ => {
                         // use the native parser
                         Ok(Statement::Statement(Box::from(
@@ -223,6 +322,27 @@ impl<'a> DFParser<'a> {
         })))
     }
 
+    pub fn parse_optimize(&mut self) -> Result<Statement, ParserError> {
+        let table_name = self.parser.parse_object_name(true)?;
+        let predicate = if self.parser.parse_keyword(Keyword::WHERE) {
+            Some(vec![self.parser.parse_expr()?])
+        } else {
+            None
+        };
+
+        // There's no OPTIMIZE statement in sqlparser either, so we use the Hive `ANALYZE TABLE`,
+        // which is another table maintenance command, and pass the filter in its partition spec.
+        Ok(Statement::Statement(Box::new(SQLStatement::Analyze {
+            table_name,
+            partitions: predicate,
+            for_columns: false,
+            columns: vec![],
+            cache_metadata: false,
+            noscan: false,
+            compute_statistics: false,
+        })))
+    }
+
     pub fn parse_truncate(&mut self) -> Result<Statement, ParserError> {
         if !self.parser.parse_keyword(Keyword::TABLE) {
             return self.expected("TABLE as a TRUNCATE target", self.parser.peek_token());
@@ -237,6 +357,126 @@ impl<'a> DFParser<'a> {
         })))
     }
 
+    pub fn parse_refresh(&mut self) -> Result<Statement, ParserError> {
+        self.parser
+            .expect_keywords(&[Keyword::MATERIALIZED, Keyword::VIEW])?;
+        let table_name = self.parser.parse_object_name(true)?;
+
+        // There's no REFRESH statement in sqlparser, so we use the Hive `MSCK REPAIR TABLE`, which
+        // is a (loosely) related command for syncing the table with its source.
+        Ok(Statement::Statement(Box::new(SQLStatement::Msck {
+            table_name,
+            repair: true,
+            partition_action: None,
+        })))
+    }
+
+    /// Parse `KILL [QUERY] <id>`, where the id can also be quoted, i.e. `KILL QUERY '42'`
+    pub fn parse_kill(&mut self) -> Result<Statement, ParserError> {
+        if let Token::Word(w) = self.parser.peek_token().token
+            && matches!(w.keyword, Keyword::CONNECTION | Keyword::MUTATION)
+        {
+            return self.expected("QUERY as a KILL target", self.parser.peek_token());
+        }
+        // The QUERY keyword is optional
+        let _ = self.parser.parse_keyword(Keyword::QUERY);
+
+        let id = match self.parser.peek_token().token {
+            Token::SingleQuotedString(_) => {
+                let id = self.parser.parse_literal_string()?;
+                id.parse::<u64>().map_err(|_| {
+                    ParserError::ParserError(format!("Invalid query id: {id}"))
+                })?
+            }
+            _ => self.parser.parse_literal_uint()?,
+        };
+
+        Ok(Statement::Statement(Box::new(SQLStatement::Kill {
+            modifier: Some(KillType::Query),
+            id,
+        })))
+    }
+
+    // XXX SEAFOWL: row-level security policies
+    /// Parse `CREATE POLICY <name> ON <table> [FOR COLUMN <column>] [TO <principal>, ...]
+    /// USING (<expression>)`
+    ///
+    /// Without a column, the expression is the predicate that the visible rows must satisfy.
+    /// Otherwise, the policy masks the column, replacing its values with the expression.
+    ///
+    /// There's no CREATE POLICY in sqlparser, so we use CREATE INDEX instead, with the expression
+    /// as the partial index predicate, the masked column as the indexed column and the principals
+    /// as the INCLUDE columns.
+    pub fn parse_create_policy(&mut self) -> Result<Statement, ParserError> {
+        let name = self.parser.parse_identifier(false)?;
+        self.parser.expect_keyword(Keyword::ON)?;
+        let table_name = self.parser.parse_object_name(true)?;
+
+        let mut columns = vec![];
+        if self.parser.parse_keywords(&[Keyword::FOR, Keyword::COLUMN]) {
+            columns.push(OrderByExpr {
+                expr: Expr::Identifier(self.parser.parse_identifier(false)?),
+                asc: None,
+                nulls_first: None,
+                with_fill: None,
+            });
+        }
+
+        let mut principals = vec![];
+        if self.parser.parse_keyword(Keyword::TO) {
+            principals = self
+                .parser
+                .parse_comma_separated(|p| p.parse_identifier(false))?;
+        }
+        // PUBLIC stands for all principals, same as omitting the TO clause
+        if principals
+            .iter()
+            .any(|p| p.quote_style.is_none() && p.value.eq_ignore_ascii_case("PUBLIC"))
+        {
+            principals = vec![];
+        }
+
+        self.parser.expect_keyword(Keyword::USING)?;
+        self.parser.expect_token(&Token::LParen)?;
+        let predicate = self.parser.parse_expr()?;
+        self.parser.expect_token(&Token::RParen)?;
+
+        Ok(Statement::Statement(Box::new(SQLStatement::CreateIndex(
+            CreateIndex {
+                name: Some(ObjectName(vec![name])),
+                table_name,
+                using: None,
+                columns,
+                unique: false,
+                concurrently: false,
+                if_not_exists: false,
+                include: principals,
+                nulls_distinct: None,
+                predicate: Some(predicate),
+            },
+        ))))
+    }
+
+    /// Parse `DROP POLICY [IF EXISTS] <name> ON <table>`, conveyed as a DROP INDEX with the policy
+    /// and table names
+    pub fn parse_drop_policy(&mut self) -> Result<Statement, ParserError> {
+        let if_exists = self.parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+        let name = self.parser.parse_identifier(false)?;
+        self.parser.expect_keyword(Keyword::ON)?;
+        let table_name = self.parser.parse_object_name(true)?;
+
+        Ok(Statement::Statement(Box::new(SQLStatement::Drop {
+            object_type: ObjectType::Index,
+            if_exists,
+            names: vec![ObjectName(vec![name]), table_name],
+            cascade: false,
+            restrict: false,
+            purge: false,
+            temporary: false,
+        })))
+    }
+    // XXX SEAFOWL: change ends here
+
     /// Parse a SQL `COPY TO` statement
     pub fn parse_copy(&mut self) -> Result<Statement, ParserError> {
         // parse as a query
@@ -340,11 +580,124 @@ impl<'a> DFParser<'a> {
             // assume we don't have CREATE TEMPORARY FUNCTION (since we don't care about TEMPORARY)
             self.parse_create_function(or_replace, false)
         // XXX SEAFOWL: change ends here
+        }
+        // XXX SEAFOWL: row-level security policies
+        else if !or_replace && self.parser.parse_keyword(Keyword::POLICY) {
+            self.parse_create_policy()
+        }
+        // CREATE POLICY is conveyed as CREATE INDEX, so don't accept the original
+        else if self.parser.parse_keyword(Keyword::INDEX)
+            || self
+                .parser
+                .parse_keywords(&[Keyword::UNIQUE, Keyword::INDEX])
+        {
+            parser_err!("Indexes are not supported")
+        // XXX SEAFOWL: change ends here
+        }
+        // XXX SEAFOWL: sqlparser only supports Hive-style PARTITIONED BY with column definitions,
+        // and CLUSTER BY without parentheses
+        else if self.is_create_table_with_layout() {
+            self.parse_create_table_with_layout(or_replace)
+        // XXX SEAFOWL: change ends here
         } else {
+            // XXX SEAFOWL: hand the OR REPLACE back to sqlparser (e.g. for CREATE OR REPLACE VIEW)
+            if or_replace {
+                self.parser.prev_token();
+                self.parser.prev_token();
+            }
+            // XXX SEAFOWL: change ends here
             Ok(Statement::Statement(Box::from(self.parser.parse_create()?)))
         }
     }
 
+    // XXX SEAFOWL: partitioned and clustered table support
+    /// Look ahead (without consuming any tokens) for a top-level `PARTITIONED` or `CLUSTER` keyword
+    /// in a `CREATE TABLE` statement, before any `AS` query.
+    fn is_create_table_with_layout(&self) -> bool {
+        match self.parser.peek_token().token {
+            Token::Word(w) if w.keyword == Keyword::TABLE => {}
+            _ => return false,
+        }
+
+        let mut depth = 0;
+        let mut n = 1;
+        loop {
+            match self.parser.peek_nth_token(n).token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                Token::EOF | Token::SemiColon => return false,
+                Token::Word(w) if depth == 0 => match w.keyword {
+                    Keyword::PARTITIONED | Keyword::CLUSTER => {
+                        if let Token::Word(w) = self.parser.peek_nth_token(n + 1).token
+                            && w.keyword == Keyword::BY
+                        {
+                            return true;
+                        }
+                    }
+                    Keyword::AS => return false,
+                    _ => {}
+                },
+                _ => {}
+            }
+            n += 1;
+        }
+    }
+
+    /// Parse `CREATE TABLE <name> [(<columns>)] [PARTITIONED BY (<column>, ...)]
+    /// [CLUSTER BY (<column>, ...)] [AS <query>]`
+    fn parse_create_table_with_layout(
+        &mut self,
+        or_replace: bool,
+    ) -> Result<Statement, ParserError> {
+        self.parser.expect_keyword(Keyword::TABLE)?;
+        let if_not_exists =
+            self.parser
+                .parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+        let name = self.parser.parse_object_name(false)?;
+        let (columns, constraints) = self.parse_columns()?;
+
+        let partition_by = if self
+            .parser
+            .parse_keywords(&[Keyword::PARTITIONED, Keyword::BY])
+        {
+            let columns = self
+                .parser
+                .parse_parenthesized_column_list(IsOptional::Mandatory, false)?;
+            Some(Box::new(Expr::Tuple(
+                columns.into_iter().map(Expr::Identifier).collect(),
+            )))
+        } else {
+            None
+        };
+        let cluster_by = if self.parser.parse_keywords(&[Keyword::CLUSTER, Keyword::BY]) {
+            Some(WrappedCollection::Parentheses(
+                self.parser
+                    .parse_parenthesized_column_list(IsOptional::Mandatory, false)?,
+            ))
+        } else {
+            None
+        };
+
+        let query = if self.parser.parse_keyword(Keyword::AS) {
+            Some(self.parser.parse_boxed_query()?)
+        } else {
+            None
+        };
+
+        let create_table = CreateTableBuilder::new(name)
+            .or_replace(or_replace)
+            .if_not_exists(if_not_exists)
+            .columns(columns)
+            .constraints(constraints)
+            .partition_by(partition_by)
+            .cluster_by(cluster_by)
+            .query(query)
+            .build();
+
+        Ok(Statement::Statement(Box::from(create_table)))
+    }
+    // XXX SEAFOWL: change ends here
+
     /// Parse CREATE FUNCTION AS in the Hive dialect
     pub fn parse_create_function(
         &mut self,
@@ -1,32 +1,47 @@
+use crate::audit::{AuditEvent, AuditFrontend, AuditOperation};
+use crate::auth::{token_to_principal, AccessPolicy, Action, Resource, UserContext};
 use crate::catalog::memory::MemoryStore;
 use crate::catalog::metastore::Metastore;
+use arrow::ipc::{root_as_message, MessageHeader};
 use arrow::record_batch::RecordBatch;
 use arrow_flight::sql::metadata::{SqlInfoData, SqlInfoDataBuilder};
 use arrow_flight::sql::{ProstMessageExt, SqlInfo, TicketStatementQuery};
-use arrow_flight::{FlightDescriptor, FlightEndpoint, FlightInfo, Ticket};
-use clade::sync::{DataSyncCommand, DataSyncResponse};
+use arrow_flight::utils::flight_data_to_arrow_batch;
+use arrow_flight::{FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, Ticket};
+use arrow_schema::{Schema, SchemaRef};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clade::sync::{DataSyncCommand, DataSyncResponse, SchemaMismatch};
 use dashmap::DashMap;
-use datafusion::common::Result;
 use datafusion::execution::SendableRecordBatchStream;
 use datafusion_common::DataFusionError;
+use futures::StreamExt;
 use lazy_static::lazy_static;
 use prost::Message;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{Mutex, RwLock};
-use tonic::{Request, Status};
-use tracing::{debug, error, info};
-use url::Url;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tonic::{Request, Status, Streaming};
+use tracing::{debug, error, info, warn};
+use warp::hyper::StatusCode;
 
+use crate::context::logical::is_read_only;
 use crate::context::SeafowlContext;
 use crate::frontend::flight::sync::schema::SyncSchema;
 use crate::frontend::flight::sync::writer::SeafowlDataSyncWriter;
-use crate::frontend::flight::sync::SyncResult;
+use crate::frontend::flight::sync::{
+    sync_log_store, sync_operation, Origin, SyncError, SyncResult,
+};
+use crate::frontend::http::authorize_plan;
+use crate::frontend::http_utils::ApiError;
+use crate::queries::{ExecutionLimits, QueryGuard};
 
 pub const SYNC_COMMIT_INFO: &str = "sync_commit_info";
 // Denoted the last sequence number that was fully committed
 pub const SEAFOWL_SYNC_CALL_MAX_ROWS: usize = 65536;
+// Placeholder bearer token handed out on handshakes without credentials
+pub const EMPTY_BEARER_TOKEN: &str = "empty";
 
 lazy_static! {
     pub static ref SEAFOWL_SQL_DATA: SqlInfoData = {
@@ -44,9 +59,11 @@ lazy_static! {
 // by interacting with the context, and keeping track of the relevant state.
 // Note that the `Mutex` below is needed solely because `FlightSqlService`
 // has a `Sync` trait bound, so we need to employ a synchronisation mechanism.
+#[derive(Clone)]
 pub(super) struct SeafowlFlightHandler {
     pub context: Arc<SeafowlContext>,
     pub results: Arc<DashMap<String, Mutex<SendableRecordBatchStream>>>,
+    access_policy: AccessPolicy,
     sync_writer: Arc<RwLock<SeafowlDataSyncWriter>>,
     // Denotes whether we're past the first sync response, thus indicating Seafowl (re)starts
     first_sync: Arc<AtomicBool>,
@@ -57,7 +74,14 @@ impl SeafowlFlightHandler {
         context: Arc<SeafowlContext>,
         sync_writer: Arc<RwLock<SeafowlDataSyncWriter>>,
     ) -> Self {
+        // Arrow Flight is open to everyone, unless JWT validation is configured
+        let access_policy = match context.config.frontend.jwt {
+            None => AccessPolicy::free_for_all(),
+            Some(_) => AccessPolicy::from_frontend_config(&context.config.frontend),
+        };
+
         Self {
+            access_policy,
             context: context.clone(),
             results: Arc::new(Default::default()),
             sync_writer,
@@ -65,6 +89,85 @@ impl SeafowlFlightHandler {
         }
     }
 
+    // Extract the execution limits requested by the client from the request metadata
+    pub fn request_limits<T>(
+        request: &Request<T>,
+    ) -> core::result::Result<ExecutionLimits, Status> {
+        let limit = |key: &str| {
+            request
+                .metadata()
+                .get(key)
+                .map(|value| {
+                    value
+                        .to_str()
+                        .ok()
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .ok_or_else(|| {
+                            Status::invalid_argument(format!(
+                                "Couldn't parse {key} from header value {value:?}"
+                            ))
+                        })
+                })
+                .transpose()
+        };
+
+        Ok(ExecutionLimits {
+            statement_timeout: limit("statement-timeout")?.map(Duration::from_millis),
+            max_result_rows: limit("max-result-rows")?,
+            max_result_bytes: limit("max-result-bytes")?,
+        })
+    }
+
+    // Extract the bearer token (or the basic auth password) from the request metadata, if any
+    pub fn request_token<T>(
+        request: &Request<T>,
+    ) -> core::result::Result<Option<String>, Status> {
+        let Some(header) = request.metadata().get("authorization") else {
+            return Ok(None);
+        };
+        let header = header.to_str().map_err(|_| {
+            Status::unauthenticated("Couldn't parse the authorization header")
+        })?;
+
+        if let Some(token) = header.strip_prefix("Bearer ") {
+            Ok((token != EMPTY_BEARER_TOKEN).then(|| token.to_string()))
+        } else if let Some(credentials) = header.strip_prefix("Basic ") {
+            // The password holds the token, the user name is ignored
+            STANDARD
+                .decode(credentials)
+                .ok()
+                .and_then(|c| String::from_utf8(c).ok())
+                .and_then(|c| c.split_once(':').map(|(_, token)| token.to_string()))
+                .map(Some)
+                .ok_or_else(|| {
+                    Status::unauthenticated("Couldn't decode the basic credentials")
+                })
+        } else {
+            Err(Status::unauthenticated(
+                "Unsupported authorization header, expected a bearer token",
+            ))
+        }
+    }
+
+    pub fn authenticate(
+        &self,
+        token: Option<String>,
+    ) -> core::result::Result<UserContext, Status> {
+        let principal = token_to_principal(token, &self.access_policy)
+            .map_err(api_error_to_status)?;
+        Ok(UserContext {
+            principal,
+            policy: self.access_policy.clone(),
+        })
+    }
+
+    pub fn user_context<T>(
+        &self,
+        request: &Request<T>,
+    ) -> core::result::Result<UserContext, Status> {
+        self.authenticate(Self::request_token(request)?)
+    }
+
     // Plan and execute the query, persisting the resulting stream handle in memory
     pub async fn query_to_stream(
         &self,
@@ -72,12 +175,14 @@ impl SeafowlFlightHandler {
         query_id: String,
         request: Request<FlightDescriptor>,
         memory_store: Option<MemoryStore>,
-    ) -> Result<FlightInfo> {
+    ) -> core::result::Result<FlightInfo, Status> {
+        let user_context = self.user_context(&request)?;
+
         let mut ctx = if let Some(search_path) = request.metadata().get("search-path") {
             self.context.scope_to_schema(
                 search_path
                     .to_str()
-                    .map_err(|e| DataFusionError::Execution(format!(
+                    .map_err(|e| Status::internal(format!(
                         "Couldn't parse search path from header value {search_path:?}: {e}"
                     )))?
                     .to_string(),
@@ -94,14 +199,34 @@ impl SeafowlFlightHandler {
             )));
         }
 
-        let plan = ctx
-            .plan_query(query)
+        let mut event = AuditEvent::query(
+            AuditFrontend::Flight,
+            &user_context.principal,
+            &ctx.default_catalog,
+            query,
+        );
+        let limits = ctx.execution_limits(Self::request_limits(&request)?);
+        let mut running_query = None;
+        let result = limits
+            .with_timeout(Self::execute_query(
+                &ctx,
+                &user_context,
+                query,
+                &query_id,
+                limits,
+                &mut event,
+                &mut running_query,
+            ))
             .await
-            .inspect_err(|err| info!("Error planning query id {query_id}: {err}"))?;
-        let batch_stream = ctx
-            .execute_stream(plan)
-            .await
-            .inspect_err(|err| info!("Error executing query id {query_id}: {err}"))?;
+            .unwrap_or_else(|e| Err(api_error_to_status(e.into())));
+        let error = result.as_ref().err().map(|e| e.message().to_string());
+        if let Some(running_query) = running_query.as_mut()
+            && let Some(error) = &error
+        {
+            running_query.fail(error);
+        }
+        ctx.audit(event.with_error(error));
+        let batch_stream = result?;
         let schema = batch_stream.schema();
 
         self.results
@@ -116,13 +241,73 @@ impl SeafowlFlightHandler {
             .with_ticket(Ticket::new(ticket.as_any().encode_to_vec()));
 
         let flight_info = FlightInfo::new()
-            .try_with_schema(&schema)?
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(e.to_string()))?
             .with_endpoint(endpoint)
             .with_descriptor(request.into_inner());
 
         Ok(flight_info)
     }
 
+    async fn execute_query(
+        ctx: &SeafowlContext,
+        user_context: &UserContext,
+        query: &str,
+        query_id: &str,
+        limits: ExecutionLimits,
+        event: &mut AuditEvent,
+        running_query: &mut Option<QueryGuard>,
+    ) -> core::result::Result<SendableRecordBatchStream, Status> {
+        // Writes get executed while planning, so they can run into the query limits as well
+        let internal = |e: DataFusionError| match ApiError::from(e) {
+            ApiError::DataFusionError(e) => Status::internal(e.to_string()),
+            e => api_error_to_status(e),
+        };
+
+        *running_query = Some(
+            ctx.start_query(&user_context.principal, query, limits)
+                .map_err(|e| api_error_to_status(e.into()))?,
+        );
+        let plan = ctx
+            .create_logical_plan(query)
+            .await
+            .inspect_err(|err| info!("Error planning query id {query_id}: {err}"))
+            .map_err(internal)?;
+        event.read_only = is_read_only(&plan);
+        let plan = ctx
+            .apply_access_policies(plan, user_context)
+            .map_err(internal)?
+            .data;
+        authorize_plan(ctx, user_context, &plan).map_err(api_error_to_status)?;
+        let physical = ctx
+            .create_physical_plan(&plan)
+            .await
+            .inspect_err(|err| info!("Error planning query id {query_id}: {err}"))
+            .map_err(internal)?;
+        event.table_versions = ctx.written_table_versions(&plan).await;
+        let running_query = running_query.take().expect("query is only executed once");
+        ctx.execute_query_stream(physical, running_query)
+            .await
+            .inspect_err(|err| info!("Error executing query id {query_id}: {err}"))
+            .map_err(internal)
+    }
+
+    // Syncing requires write access to the whole database
+    pub fn authorize_sync<T>(
+        &self,
+        request: &Request<T>,
+    ) -> core::result::Result<UserContext, Status> {
+        let user_context = self.user_context(request)?;
+        if user_context.can_access(
+            Action::Write,
+            &Resource::Database(self.context.default_catalog.clone()),
+        ) {
+            Ok(user_context)
+        } else {
+            Err(api_error_to_status(ApiError::WriteForbidden))
+        }
+    }
+
     // Get a specific stream from the map
     pub async fn fetch_stream(
         &self,
@@ -136,6 +321,207 @@ impl SeafowlFlightHandler {
         Ok(batch_stream_mutex.into_inner())
     }
 
+    // Validate and apply a single sync message, recording it in the audit log
+    pub async fn process_sync_message(
+        &self,
+        user_context: &UserContext,
+        cmd: DataSyncCommand,
+        batches: Vec<RecordBatch>,
+    ) -> core::result::Result<DataSyncResponse, Status> {
+        let sync_schema = match batches.first() {
+            Some(batch) => {
+                SyncSchema::try_new(cmd.column_descriptors.clone(), batch.schema())
+                    .map_err(|err| {
+                        warn!("{err}");
+                        Status::invalid_argument(err.to_string())
+                    })?
+            }
+            None => SyncSchema::empty(),
+        };
+
+        let mut event = AuditEvent::new(
+            AuditFrontend::Flight,
+            AuditOperation::Sync,
+            &user_context.principal,
+            &self.context.default_catalog,
+        );
+        event.target = Some(cmd.path.clone());
+        event.rows_affected = Some(
+            batches
+                .iter()
+                .fold(0, |rows, batch| rows + batch.num_rows() as u64),
+        );
+
+        let path = cmd.path.clone();
+        let result = self.process_sync_cmd(cmd, sync_schema, batches).await;
+        let error = match &result {
+            Ok(response) => response.schema_mismatch.as_ref().map(|mismatch| {
+                SyncError::SchemaMismatch {
+                    column: mismatch.column.clone(),
+                    reason: mismatch.reason.clone(),
+                }
+                .to_string()
+            }),
+            Err(e) => Some(e.to_string()),
+        };
+        self.context.audit(event.with_error(error));
+
+        result.map_err(|e| {
+            let err = format!("Failed processing sync for {path}: {e}");
+            warn!(err);
+            match e {
+                SyncError::InvalidMessage { reason } => Status::invalid_argument(reason),
+                _ => Status::internal(err),
+            }
+        })
+    }
+
+    // Apply the sync messages streamed by the client over a single `do_exchange` call. Each
+    // message is either a record batch, or a message without any data (e.g. to end a transaction
+    // without further changes), carrying its `DataSyncCommand` in the app metadata. A
+    // `DataSyncResponse` is pushed back whenever a message is rejected or the memory or durable
+    // sequence number of the client's origin advances. Once the client is done sending, updates
+    // keep coming until all of its complete transactions are durable.
+    pub async fn process_sync_exchange(
+        &self,
+        user_context: UserContext,
+        mut input: Streaming<FlightData>,
+        output: mpsc::Sender<core::result::Result<FlightData, Status>>,
+    ) {
+        let mut durable_updates = self.sync_writer.read().await.subscribe_durable();
+        let mut schema = None;
+        let mut origin = None;
+        let mut last_sequences = (None, None);
+        let mut input_done = false;
+
+        loop {
+            let response = tokio::select! {
+                data = input.next(), if !input_done => match data {
+                    Some(data) => {
+                        match self.process_sync_data(&user_context, &mut schema, data).await {
+                            Ok(Some((data_origin, response))) => {
+                                origin = Some(data_origin);
+                                Some(response)
+                            }
+                            Ok(None) => None,
+                            Err(status) => {
+                                let _ = output.send(Err(status)).await;
+                                return;
+                            }
+                        }
+                    }
+                    None => {
+                        input_done = true;
+                        None
+                    }
+                },
+                changed = durable_updates.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+
+                    match &origin {
+                        Some(origin) => {
+                            let (mem_seq, dur_seq) =
+                                self.sync_writer.read().await.stored_sequences(origin);
+                            Some(DataSyncResponse {
+                                accepted: true,
+                                memory_sequence_number: mem_seq,
+                                durable_sequence_number: dur_seq,
+                                first: false,
+                                schema_mismatch: None,
+                            })
+                        }
+                        None => None,
+                    }
+                },
+                _ = output.closed() => return,
+            };
+
+            if let Some(response) = response {
+                let sequences = (
+                    response.memory_sequence_number,
+                    response.durable_sequence_number,
+                );
+                // Skip updates that the client already knows about
+                if !response.accepted || response.first || sequences != last_sequences {
+                    last_sequences = sequences;
+                    let data = FlightData {
+                        app_metadata: response.encode_to_vec().into(),
+                        ..Default::default()
+                    };
+                    if output.send(Ok(data)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if input_done && last_sequences.0 == last_sequences.1 {
+                return;
+            }
+        }
+    }
+
+    // Decode a single message of a streaming sync, and apply it unless it only carries the schema
+    // for the following record batches
+    async fn process_sync_data(
+        &self,
+        user_context: &UserContext,
+        schema: &mut Option<SchemaRef>,
+        data: core::result::Result<FlightData, Status>,
+    ) -> core::result::Result<Option<(Origin, DataSyncResponse)>, Status> {
+        let data = data?;
+        let invalid = |err: String| {
+            warn!(err);
+            Status::invalid_argument(err)
+        };
+
+        let batches = if data.data_header.is_empty() {
+            vec![]
+        } else {
+            let message = root_as_message(&data.data_header[..])
+                .map_err(|e| invalid(format!("Couldn't decode message: {e}")))?;
+            match message.header_type() {
+                MessageHeader::Schema => {
+                    *schema =
+                        Some(Arc::new(Schema::try_from(&data).map_err(|e| {
+                            invalid(format!("Couldn't decode schema: {e}"))
+                        })?));
+                    return Ok(None);
+                }
+                MessageHeader::RecordBatch => {
+                    let schema = schema.clone().ok_or_else(|| {
+                        invalid("Received a record batch before its schema".to_string())
+                    })?;
+                    let batch =
+                        flight_data_to_arrow_batch(&data, schema, &HashMap::new())
+                            .map_err(|e| {
+                                invalid(format!("Couldn't decode record batch: {e}"))
+                            })?;
+                    if batch.num_rows() > 0 {
+                        vec![batch]
+                    } else {
+                        vec![]
+                    }
+                }
+                other => {
+                    return Err(invalid(format!(
+                        "Unsupported message type in sync: {}",
+                        other.variant_name().unwrap_or("UNKNOWN")
+                    )))
+                }
+            }
+        };
+
+        let cmd = DataSyncCommand::decode(data.app_metadata)
+            .map_err(|err| invalid(format!("Couldn't decode command: {err}")))?;
+        let origin = cmd.origin.clone();
+        let response = self
+            .process_sync_message(user_context, cmd, batches)
+            .await?;
+        Ok(Some((origin, response)))
+    }
+
     pub async fn process_sync_cmd(
         &self,
         cmd: DataSyncCommand,
@@ -153,7 +539,7 @@ impl SeafowlFlightHandler {
             .fold(0, |rows, batch| rows + batch.num_rows());
 
         // Short-circuit the "probing" request
-        if num_rows == 0 && cmd.sequence_number.is_none() {
+        if num_rows == 0 && cmd.sequence_number.is_none() && cmd.operation.is_none() {
             // Get the current volatile and durable sequence numbers
             debug!("Received empty batches with no sequence number, returning current sequence numbers");
             let (mem_seq, dur_seq) =
@@ -163,30 +549,12 @@ impl SeafowlFlightHandler {
                 memory_sequence_number: mem_seq,
                 durable_sequence_number: dur_seq,
                 first,
+                schema_mismatch: None,
             });
         }
 
-        let log_store = match cmd.store {
-            None => self
-                .context
-                .get_internal_object_store()?
-                .get_log_store(&cmd.path),
-            Some(store_loc) => {
-                self.context
-                    .metastore
-                    .object_stores
-                    .get_log_store_for_table(
-                        Url::parse(&store_loc.location).map_err(|e| {
-                            DataFusionError::Execution(format!(
-                                "Couldn't parse sync location: {e}"
-                            ))
-                        })?,
-                        store_loc.options,
-                        cmd.path,
-                    )
-                    .await?
-            }
-        };
+        let log_store = sync_log_store(&self.context, &cmd).await?;
+        let operation = sync_operation(&self.context, &cmd).await?;
         let url = log_store.root_uri();
 
         debug!("Processing data change with {num_rows} rows for url {url} from origin {:?} at position {:?}",
@@ -199,12 +567,28 @@ impl SeafowlFlightHandler {
         .await
         {
             Ok(mut sync_writer) => {
+                match sync_writer.check_schema(&log_store, &sync_schema).await {
+                    Err(SyncError::SchemaMismatch { column, reason }) => {
+                        debug!("Rejecting sync for url {url} due to a schema mismatch in column {column}: {reason}");
+                        let (mem_seq, dur_seq) =
+                            sync_writer.stored_sequences(&cmd.origin);
+                        return Ok(DataSyncResponse {
+                            accepted: false,
+                            memory_sequence_number: mem_seq,
+                            durable_sequence_number: dur_seq,
+                            first,
+                            schema_mismatch: Some(SchemaMismatch { column, reason }),
+                        });
+                    }
+                    result => result?,
+                }
+
                 sync_writer.enqueue_sync(
                     log_store,
-                    cmd.sequence_number,
-                    cmd.origin.clone(),
+                    &cmd,
                     sync_schema,
                     batches,
+                    operation,
                 )?;
 
                 sync_writer.flush().await?;
@@ -215,6 +599,7 @@ impl SeafowlFlightHandler {
                     memory_sequence_number: mem_seq,
                     durable_sequence_number: dur_seq,
                     first,
+                    schema_mismatch: None,
                 })
             }
             Err(_) => {
@@ -224,8 +609,24 @@ impl SeafowlFlightHandler {
                     memory_sequence_number: None,
                     durable_sequence_number: None,
                     first,
+                    schema_mismatch: None,
                 })
             }
         }
     }
 }
+
+// Map the authentication/authorization and query limit errors shared with the HTTP frontend to
+// gRPC statuses
+pub(super) fn api_error_to_status(err: ApiError) -> Status {
+    let (status, message) = err.status_code_body();
+    match status {
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::REQUEST_TIMEOUT => Status::deadline_exceeded(message),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::PAYLOAD_TOO_LARGE => {
+            Status::resource_exhausted(message)
+        }
+        _ => Status::invalid_argument(message),
+    }
+}
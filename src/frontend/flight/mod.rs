@@ -17,7 +17,7 @@ use tokio::sync::RwLock;
 
 use crate::frontend::flight::sync::flush_task;
 use crate::frontend::flight::sync::writer::SeafowlDataSyncWriter;
-use tonic::transport::Server;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 
 pub async fn run_flight_server(
     context: Arc<SeafowlContext>,
@@ -32,14 +32,44 @@ pub async fn run_flight_server(
         Duration::from_secs(context.config.misc.sync_conf.flush_task_interval_s);
     let lock_timeout =
         Duration::from_secs(context.config.misc.sync_conf.write_lock_timeout_s);
-    let sync_writer = Arc::new(RwLock::new(SeafowlDataSyncWriter::new(context.clone())));
+    let mut sync_writer = SeafowlDataSyncWriter::new(context.clone());
+    if let Some(wal_dir) = context
+        .config
+        .misc
+        .sync_conf
+        .wal_dir(&context.config.runtime)
+    {
+        sync_writer = sync_writer
+            .with_wal(wal_dir)
+            .await
+            .expect("Error replaying the sync write-ahead log");
+    }
+    let sync_writer = Arc::new(RwLock::new(sync_writer));
     let handler = SeafowlFlightHandler::new(context, sync_writer.clone());
     tokio::spawn(flush_task(flush_interval, lock_timeout, sync_writer));
 
     let svc =
         FlightServiceServer::new(handler).max_decoding_message_size(16 * 1024 * 1024);
 
-    let server = Server::builder();
+    let mut server = Server::builder();
+    if let Some(tls) = config.tls_settings.tls().expect("Invalid TLS settings") {
+        let read = |path: &str| {
+            std::fs::read(path)
+                .unwrap_or_else(|e| panic!("Error reading the TLS file {path}: {e}"))
+        };
+
+        let mut tls_config = ServerTlsConfig::new().identity(Identity::from_pem(
+            read(&tls.cert_file),
+            read(&tls.key_file),
+        ));
+        if let Some(client_ca_file) = &tls.client_ca_file {
+            tls_config =
+                tls_config.client_ca_root(Certificate::from_pem(read(client_ca_file)));
+        }
+        server = server
+            .tls_config(tls_config)
+            .expect("Error configuring TLS for the Arrow Flight server");
+    }
     let mut server = server.layer(MetricsLayer {});
 
     server
@@ -1,9 +1,9 @@
 use crate::catalog::memory::MemoryStore;
 use crate::frontend::flight::handler::{
-    SeafowlFlightHandler, SEAFOWL_SQL_DATA, SEAFOWL_SYNC_CALL_MAX_ROWS,
+    api_error_to_status, SeafowlFlightHandler, EMPTY_BEARER_TOKEN, SEAFOWL_SQL_DATA,
+    SEAFOWL_SYNC_CALL_MAX_ROWS,
 };
-use crate::frontend::flight::sync::schema::SyncSchema;
-use crate::frontend::flight::sync::SyncError;
+use crate::frontend::http_utils::ApiError;
 use arrow::record_batch::RecordBatch;
 use arrow_flight::decode::FlightRecordBatchStream;
 use arrow_flight::encode::FlightDataEncoderBuilder;
@@ -14,8 +14,8 @@ use arrow_flight::sql::{
     Any, Command, CommandGetSqlInfo, CommandStatementQuery, SqlInfo, TicketStatementQuery,
 };
 use arrow_flight::{
-    FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest, HandshakeResponse,
-    Ticket,
+    FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, Ticket,
 };
 use async_trait::async_trait;
 use clade::{schema::InlineMetastoreCommandStatementQuery, sync::DataSyncCommand};
@@ -24,16 +24,21 @@ use futures::StreamExt;
 use futures::TryStreamExt;
 use prost::Message;
 use std::pin::Pin;
+use tokio::sync::mpsc;
 use tonic::metadata::MetadataValue;
 use tonic::{Request, Response, Status, Streaming};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
+// How many sync responses can be queued up for a slow streaming sync client
+const SYNC_EXCHANGE_CHANNEL_SIZE: usize = 16;
+
 #[async_trait]
 impl FlightSqlService for SeafowlFlightHandler {
     type FlightService = Self;
 
-    // Perform authentication; for now just pass-through everything
+    // Authenticate the client using the token from the authorization header (either as a bearer
+    // token, or as the basic auth password), and hand it back to be used in subsequent requests
     async fn do_handshake(
         &self,
         request: Request<Streaming<HandshakeRequest>>,
@@ -42,6 +47,9 @@ impl FlightSqlService for SeafowlFlightHandler {
         Status,
     > {
         debug!("Handshake request: {:?}", request.metadata());
+        let token = Self::request_token(&request)?;
+        self.authenticate(token.clone())?;
+
         let result = HandshakeResponse {
             protocol_version: 0,
             payload: vec![].into(),
@@ -51,10 +59,13 @@ impl FlightSqlService for SeafowlFlightHandler {
         let mut resp: Response<Pin<Box<dyn Stream<Item = Result<_, _>> + Send>>> =
             Response::new(Box::pin(output));
 
-        // Include a dummy auth header, so that clients that expect it don't error out (e.g. Python's
-        // adbc_driver_flightsql)
-        let md = MetadataValue::try_from("Bearer empty")
-            .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
+        // Hand back the token for use in subsequent requests. Include a dummy one if there's no
+        // token, so that clients that expect it don't error out (e.g. Python's adbc_driver_flightsql)
+        let md = MetadataValue::try_from(format!(
+            "Bearer {}",
+            token.as_deref().unwrap_or(EMPTY_BEARER_TOKEN)
+        ))
+        .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
         resp.metadata_mut().insert("authorization", md);
         Ok(resp)
     }
@@ -99,8 +110,7 @@ impl FlightSqlService for SeafowlFlightHandler {
         );
         let info = self
             .query_to_stream(&query.query, query_id.clone(), request, None)
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .await?;
 
         let resp = Response::new(info);
         debug!("Results for query id {query_id} ready for streaming");
@@ -141,8 +151,7 @@ impl FlightSqlService for SeafowlFlightHandler {
                 request,
                 Some(memory_store),
             )
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .await?;
 
         let resp = Response::new(info);
         debug!("Results for inlined query id {query_id} ready for streaming");
@@ -165,9 +174,15 @@ impl FlightSqlService for SeafowlFlightHandler {
         let schema = batch_stream.schema();
 
         // The Flight encoder below expects a stream where the error type on the item is a
-        // `FlightError`, hence we need to map the DF batch stream here
+        // `FlightError`, hence we need to map the DF batch stream here. Streams aborted due to
+        // the execution limits get a proper status though.
         let mapped_stream = batch_stream.map(|batch| {
-            batch.map_err(|e| FlightError::from_external_error(Box::new(e)))
+            batch.map_err(|e| match ApiError::from(e) {
+                ApiError::DataFusionError(e) => {
+                    FlightError::from_external_error(Box::new(e))
+                }
+                e => FlightError::Tonic(api_error_to_status(e)),
+            })
         });
 
         let stream = FlightDataEncoderBuilder::new()
@@ -186,6 +201,8 @@ impl FlightSqlService for SeafowlFlightHandler {
         request: Request<PeekableFlightDataStream>,
         message: Any,
     ) -> Result<Response<<Self as FlightService>::DoPutStream>, Status> {
+        let user_context = self.authorize_sync(&request)?;
+
         // Extract the command
         let cmd: DataSyncCommand = Message::decode(&*message.value).map_err(|err| {
             let err = format!("Couldn't decode command: {err}");
@@ -200,43 +217,22 @@ impl FlightSqlService for SeafowlFlightHandler {
         .try_collect()
         .await?;
 
-        let sync_schema = if !batches.is_empty() {
-            // Validate row count under prescribed limit
-            if batches
-                .iter()
-                .fold(0, |rows, batch| rows + batch.num_rows())
-                > SEAFOWL_SYNC_CALL_MAX_ROWS
-            {
-                let err = format!("Change contains more than max allowed {SEAFOWL_SYNC_CALL_MAX_ROWS} rows");
-                warn!(err);
-                return Err(Status::invalid_argument(err));
-            }
-
-            SyncSchema::try_new(
-                cmd.column_descriptors.clone(),
-                batches.first().unwrap().schema(),
-            )
-            .map_err(|err| {
-                warn!("{err}");
-                Status::invalid_argument(err.to_string())
-            })?
-        } else {
-            SyncSchema::empty()
-        };
+        // Validate row count under prescribed limit
+        if batches
+            .iter()
+            .fold(0, |rows, batch| rows + batch.num_rows())
+            > SEAFOWL_SYNC_CALL_MAX_ROWS
+        {
+            let err = format!(
+                "Change contains more than max allowed {SEAFOWL_SYNC_CALL_MAX_ROWS} rows"
+            );
+            warn!(err);
+            return Err(Status::invalid_argument(err));
+        }
 
         let put_result = self
-            .process_sync_cmd(cmd.clone(), sync_schema, batches)
-            .await
-            .map_err(|e| {
-                let err = format!("Failed processing DoPut for {}: {e}", cmd.path);
-                warn!(err);
-                match e {
-                    SyncError::InvalidMessage { reason } => {
-                        Status::invalid_argument(reason)
-                    }
-                    _ => Status::internal(err),
-                }
-            })?;
+            .process_sync_message(&user_context, cmd, batches)
+            .await?;
 
         Ok(Response::new(Box::pin(futures::stream::iter(vec![Ok(
             arrow_flight::PutResult {
@@ -244,4 +240,27 @@ impl FlightSqlService for SeafowlFlightHandler {
             },
         )]))))
     }
+
+    // Streaming sync: the client sends a sequence of sync messages over a single call and gets
+    // back a `DataSyncResponse` every time its changes advance in memory or become durable. Since
+    // the changes aren't buffered per call, there's no cap on the total number of rows.
+    async fn do_exchange_fallback(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<<Self as FlightService>::DoExchangeStream>, Status> {
+        let user_context = self.authorize_sync(&request)?;
+
+        let (tx, rx) = mpsc::channel(SYNC_EXCHANGE_CHANNEL_SIZE);
+        let handler = self.clone();
+        tokio::spawn(async move {
+            handler
+                .process_sync_exchange(user_context, request.into_inner(), tx)
+                .await
+        });
+
+        Ok(Response::new(Box::pin(futures::stream::unfold(
+            rx,
+            |mut rx| async move { rx.recv().await.map(|item| (item, rx)) },
+        ))))
+    }
 }
@@ -1,13 +1,24 @@
+use crate::catalog::CatalogError;
+use crate::context::SeafowlContext;
 use crate::frontend::flight::sync::writer::SeafowlDataSyncWriter;
+use clade::sync::data_sync_command::Operation;
+use clade::sync::{DataSyncCommand, RenameTable};
+use datafusion::error::DataFusionError;
+use deltalake::logstore::LogStore;
+use object_store::path::Path;
+use object_store::ObjectStore;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::warn;
+use url::Url;
+use uuid::Uuid;
 
 mod metrics;
 pub mod schema;
 mod utils;
+mod wal;
 pub(crate) mod writer;
 
 pub(super) type Origin = String;
@@ -21,6 +32,9 @@ pub enum SyncError {
     #[error("Invalid sync message: {reason}")]
     InvalidMessage { reason: String },
 
+    #[error("Incompatible sync schema for column {column}: {reason}")]
+    SchemaMismatch { column: String, reason: String },
+
     #[error(transparent)]
     ArrowError(#[from] arrow_schema::ArrowError),
 
@@ -35,6 +49,12 @@ pub enum SyncError {
 
     #[error(transparent)]
     ObjectStoreError(#[from] object_store::Error),
+
+    #[error("Invalid sync WAL segment {path}: {reason}")]
+    WalError { path: String, reason: String },
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
 }
 
 pub type SyncResult<T, E = SyncError> = Result<T, E>;
@@ -71,6 +91,172 @@ impl SyncCommitInfo {
     }
 }
 
+// Resolve the log store of the table that the sync command targets
+pub(super) async fn sync_log_store(
+    context: &SeafowlContext,
+    cmd: &DataSyncCommand,
+) -> SyncResult<Arc<dyn LogStore>> {
+    Ok(match &cmd.store {
+        None => context
+            .get_internal_object_store()?
+            .get_log_store(&cmd.path),
+        Some(store_loc) => {
+            context
+                .metastore
+                .object_stores
+                .get_log_store_for_table(
+                    Url::parse(&store_loc.location).map_err(|e| {
+                        DataFusionError::Execution(format!(
+                            "Couldn't parse sync location: {e}"
+                        ))
+                    })?,
+                    store_loc.options.clone(),
+                    cmd.path.clone(),
+                )
+                .await?
+        }
+    })
+}
+
+// A table-level operation pending in memory, applied in order with the row changes to the table
+#[derive(Clone, Debug)]
+pub(super) enum TableOperation {
+    Truncate,
+    Drop,
+    // Move the table to the location of the provided log store
+    Rename(Arc<dyn LogStore>),
+}
+
+impl PartialEq for TableOperation {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TableOperation::Truncate, TableOperation::Truncate)
+            | (TableOperation::Drop, TableOperation::Drop) => true,
+            (TableOperation::Rename(target), TableOperation::Rename(other_target)) => {
+                target.root_uri() == other_target.root_uri()
+            }
+            _ => false,
+        }
+    }
+}
+
+// Object kept at the target of a rename while the table gets copied over, so that a rename
+// interrupted by a restart can be resumed instead of being mistaken for an existing table
+const RENAME_MARKER: &str = "_seafowl_rename";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(super) struct RenameMarker {
+    // Root URI of the table being renamed
+    pub(super) source: String,
+    // Whether all the objects have been copied, so that only the source is left to delete
+    pub(super) copied: bool,
+}
+
+impl RenameMarker {
+    pub(super) fn path() -> Path {
+        Path::from(RENAME_MARKER)
+    }
+
+    pub(super) async fn get(store: &dyn ObjectStore) -> SyncResult<Option<Self>> {
+        match store.get(&Self::path()).await {
+            Ok(result) => Ok(Some(serde_json::from_slice(&result.bytes().await?)?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(super) async fn put(&self, store: &dyn ObjectStore) -> SyncResult<()> {
+        store
+            .put(&Self::path(), serde_json::to_vec(self)?.into())
+            .await?;
+        Ok(())
+    }
+}
+
+// Make sure the sync command doesn't target the location of a table registered in the catalog,
+// whose entry would be left dangling by a table-level operation
+async fn check_unregistered(
+    context: &SeafowlContext,
+    cmd: &DataSyncCommand,
+) -> SyncResult<()> {
+    if cmd.store.is_none()
+        && let Ok(uuid) = Uuid::parse_str(&cmd.path)
+    {
+        match context.metastore.tables.get_by_uuid(uuid).await {
+            Ok(table) => {
+                return Err(SyncError::InvalidMessage {
+                    reason: format!(
+                        "The table at {} is registered in the catalog as {}",
+                        cmd.path, table.name
+                    ),
+                })
+            }
+            Err(CatalogError::TableUuidDoesNotExist { .. })
+            | Err(CatalogError::NotImplemented { .. }) => {}
+            Err(err) => return Err(DataFusionError::from(err).into()),
+        }
+    }
+
+    Ok(())
+}
+
+// Resolve the table-level operation that the sync command carries, if any
+pub(super) async fn sync_operation(
+    context: &SeafowlContext,
+    cmd: &DataSyncCommand,
+) -> SyncResult<Option<TableOperation>> {
+    // An empty path resolves to the root of the store, rather than to any single table
+    if cmd.operation.is_some() && cmd.path.is_empty() {
+        return Err(SyncError::InvalidMessage {
+            reason: "Table operations require a non-empty table path".to_string(),
+        });
+    }
+
+    Ok(match &cmd.operation {
+        None => None,
+        Some(Operation::Truncate(_)) => Some(TableOperation::Truncate),
+        Some(Operation::Drop(_)) => {
+            check_unregistered(context, cmd).await?;
+            Some(TableOperation::Drop)
+        }
+        Some(Operation::Rename(RenameTable { new_path })) => {
+            if new_path.is_empty() || new_path == &cmd.path {
+                return Err(SyncError::InvalidMessage {
+                    reason: format!(
+                        "Invalid new path for the table at {}: \"{new_path}\"",
+                        cmd.path
+                    ),
+                });
+            }
+
+            let target = DataSyncCommand {
+                path: new_path.clone(),
+                ..cmd.clone()
+            };
+            check_unregistered(context, cmd).await?;
+            check_unregistered(context, &target).await?;
+
+            // A table at the target is only expected if it's the partial copy of an interrupted
+            // rename from the same source
+            let source = sync_log_store(context, cmd).await?;
+            let target = sync_log_store(context, &target).await?;
+            let resumed = RenameMarker::get(target.object_store().as_ref())
+                .await?
+                .is_some_and(|marker| marker.source == source.root_uri());
+            if !resumed && target.is_delta_table_location().await? {
+                return Err(SyncError::InvalidMessage {
+                    reason: format!(
+                        "Can't rename the table at {} to {new_path}, since a table already exists there",
+                        cmd.path
+                    ),
+                });
+            }
+
+            Some(TableOperation::Rename(target))
+        }
+    })
+}
+
 pub async fn flush_task(
     interval: Duration,
     write_timeout: Duration,
@@ -1,7 +1,8 @@
 use crate::frontend::flight::sync::SyncError;
-use arrow_schema::{DataType, FieldRef, SchemaRef};
+use arrow_schema::{DataType, Field, FieldRef, Schema, SchemaBuilder, SchemaRef};
 use clade::sync::{ColumnDescriptor, ColumnRole};
 use std::collections::HashSet;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct SyncSchema {
@@ -107,6 +108,86 @@ impl SyncSchema {
         &self.columns
     }
 
+    // Schema of a table created from this sync, i.e. without the old PK and changed columns
+    pub fn table_schema(&self) -> Schema {
+        let mut builder = SchemaBuilder::new();
+        self.columns.iter().for_each(|col| {
+            if matches!(col.role, ColumnRole::NewPk | ColumnRole::Value) {
+                builder.push(col.field.as_ref().clone().with_name(col.name()));
+            }
+        });
+        builder.finish()
+    }
+
+    // Work out the schema that the table needs to have in order to accommodate this sync, by
+    // adding any new columns and widening the types of existing value columns. Values narrower
+    // than the type of their column get cast to it, while any other type changes are rejected.
+    pub fn evolve_schema(
+        &self,
+        table_schema: &SchemaRef,
+    ) -> Result<SchemaRef, SyncError> {
+        let mut fields = table_schema.fields().to_vec();
+        let mismatch = |col: &SyncColumn, reason: String| SyncError::SchemaMismatch {
+            column: col.name.clone(),
+            reason,
+        };
+
+        for col in &self.columns {
+            if !matches!(col.role, ColumnRole::NewPk | ColumnRole::Value) {
+                continue;
+            }
+
+            let sync_type = col.field.data_type();
+            let Some(ind) = fields.iter().position(|f| f.name() == col.name()) else {
+                if col.role == ColumnRole::NewPk {
+                    return Err(mismatch(
+                        col,
+                        "new primary key columns can't be added".to_string(),
+                    ));
+                }
+
+                // Existing rows don't have a value for the new column
+                fields.push(Arc::new(Field::new(col.name(), sync_type.clone(), true)));
+                continue;
+            };
+
+            let table_type = fields[ind].data_type();
+            if sync_type == table_type
+                || (col.role == ColumnRole::Value && can_widen(sync_type, table_type))
+            {
+                // The values can be stored as they are, or cast to the column type
+                continue;
+            }
+
+            if col.role == ColumnRole::Value && can_widen(table_type, sync_type) {
+                fields[ind] = Arc::new(
+                    fields[ind]
+                        .as_ref()
+                        .clone()
+                        .with_data_type(sync_type.clone()),
+                );
+            } else {
+                let reason = if col.role == ColumnRole::NewPk {
+                    format!(
+                        "primary key type can't change from {table_type} to {sync_type}"
+                    )
+                } else {
+                    format!("type can't change from {table_type} to {sync_type}")
+                };
+                return Err(mismatch(col, reason));
+            }
+        }
+
+        if fields[..] == table_schema.fields()[..] {
+            Ok(table_schema.clone())
+        } else {
+            Ok(Arc::new(Schema::new_with_metadata(
+                fields,
+                table_schema.metadata().clone(),
+            )))
+        }
+    }
+
     // Map over all columns with a specific role
     pub fn map_columns<F, T>(&self, role: ColumnRole, f: F) -> Vec<T>
     where
@@ -121,6 +202,30 @@ impl SyncSchema {
     }
 }
 
+// Whether all values of one type can be represented by another one, so that they can be cast to it
+// losslessly
+fn can_widen(from: &DataType, to: &DataType) -> bool {
+    match (from, to) {
+        (
+            DataType::Int8,
+            DataType::Int16 | DataType::Int32 | DataType::Int64 | DataType::Float64,
+        )
+        | (DataType::Int16, DataType::Int32 | DataType::Int64 | DataType::Float64)
+        | (DataType::Int32, DataType::Int64 | DataType::Float64)
+        | (DataType::Float32, DataType::Float64) => true,
+        (
+            DataType::Decimal128(from_precision, from_scale),
+            DataType::Decimal128(to_precision, to_scale),
+        ) => {
+            // Neither the integral nor the fractional part may lose digits
+            to_scale >= from_scale
+                && *to_precision as i16 - *to_scale as i16
+                    >= *from_precision as i16 - *from_scale as i16
+        }
+        _ => false,
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct SyncColumn {
     role: ColumnRole,
@@ -148,3 +253,76 @@ impl SyncColumn {
         &self.field
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::flight::sync::schema::SyncSchema;
+    use arrow_schema::{DataType, Field, Schema};
+    use clade::sync::{ColumnDescriptor, ColumnRole};
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    #[rstest]
+    #[case::unchanged(DataType::Int32, Ok(DataType::Int32))]
+    #[case::narrower(DataType::Int16, Ok(DataType::Int32))]
+    #[case::wider(DataType::Int64, Ok(DataType::Int64))]
+    #[case::int_to_double(DataType::Float64, Ok(DataType::Float64))]
+    #[case::int_to_float(
+        DataType::Float32,
+        Err("Incompatible sync schema for column c2: type can't change from Int32 to Float32")
+    )]
+    #[case::decimal(
+        DataType::Decimal128(10, 0),
+        Err("Incompatible sync schema for column c2: type can't change from Int32 to Decimal128(10, 0)")
+    )]
+    fn test_evolve_schema_value_type(
+        #[case] sync_type: DataType,
+        #[case] expected: Result<DataType, &str>,
+    ) {
+        let table_schema = Arc::new(Schema::new(vec![
+            Field::new("c1", DataType::Int32, false),
+            Field::new("c2", DataType::Int32, true),
+        ]));
+        let sync_schema = SyncSchema::try_new(
+            vec![
+                ColumnDescriptor {
+                    role: ColumnRole::OldPk as i32,
+                    name: "c1".to_string(),
+                },
+                ColumnDescriptor {
+                    role: ColumnRole::NewPk as i32,
+                    name: "c1".to_string(),
+                },
+                ColumnDescriptor {
+                    role: ColumnRole::Value as i32,
+                    name: "c2".to_string(),
+                },
+                ColumnDescriptor {
+                    role: ColumnRole::Value as i32,
+                    name: "c3".to_string(),
+                },
+            ],
+            Arc::new(Schema::new(vec![
+                Field::new("old_c1", DataType::Int32, true),
+                Field::new("new_c1", DataType::Int32, true),
+                Field::new("value_c2", sync_type, true),
+                Field::new("value_c3", DataType::Utf8, false),
+            ])),
+        )
+        .unwrap();
+
+        match (sync_schema.evolve_schema(&table_schema), expected) {
+            (Ok(schema), Ok(c2_type)) => assert_eq!(
+                schema.as_ref(),
+                &Schema::new(vec![
+                    Field::new("c1", DataType::Int32, false),
+                    Field::new("c2", c2_type, true),
+                    // New columns are always nullable, since the existing rows lack them
+                    Field::new("c3", DataType::Utf8, true),
+                ])
+            ),
+            (Err(err), Err(message)) => assert_eq!(err.to_string(), message),
+            (result, expected) => panic!("Expected {expected:?}, got {result:?}"),
+        }
+    }
+}
@@ -765,11 +765,13 @@ mod tests {
                 tx_id: Uuid::new_v4(),
                 sync_schema: sync_schema.clone(),
                 batch: batch_1,
+                operation: None,
             },
             DataSyncItem {
                 tx_id: Uuid::new_v4(),
                 sync_schema,
                 batch: batch_2,
+                operation: None,
             },
         ];
 
@@ -907,11 +909,13 @@ mod tests {
                 tx_id: Uuid::new_v4(),
                 sync_schema: sync_schema.clone(),
                 batch: batch_1,
+                operation: None,
             },
             DataSyncItem {
                 tx_id: Uuid::new_v4(),
                 sync_schema,
                 batch: batch_2,
+                operation: None,
             },
         ];
 
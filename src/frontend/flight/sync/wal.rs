@@ -0,0 +1,268 @@
+//! Local write-ahead log of the syncs held in memory by `SeafowlDataSyncWriter`.
+//!
+//! Every accepted sync message is persisted as a separate Arrow IPC stream segment before it is
+//! acknowledged, with the command it came with (minus the data) stored in the schema metadata.
+//! Segments are deleted once the transaction they belong to becomes durable, and replayed into
+//! memory on startup otherwise.
+//!
+//! In the transactional mode, the table versions pinned by the writer are persisted to the same
+//! directory as well, so that they can be restored on startup.
+//!
+//! Note that the command includes the connection options for syncs to external stores, so the log
+//! directory should be treated as sensitive.
+
+use arrow::array::RecordBatch;
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
+use arrow_schema::Schema;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clade::sync::{ColumnDescriptor, DataSyncCommand};
+use prost::Message;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::frontend::flight::sync::schema::SyncSchema;
+use crate::frontend::flight::sync::{SyncError, SyncResult};
+
+const SEGMENT_EXTENSION: &str = "arrows";
+const SEGMENT_TMP_EXTENSION: &str = "arrows.tmp";
+const SYNC_COMMAND_KEY: &str = "seafowl_sync_command";
+const PINNED_VERSIONS_FILE: &str = "pinned_versions.json";
+const PINNED_VERSIONS_TMP_FILE: &str = "pinned_versions.json.tmp";
+
+pub(super) type SegmentId = u64;
+
+// A sync message read back from the log
+pub(super) struct WalEntry {
+    pub(super) id: SegmentId,
+    pub(super) cmd: DataSyncCommand,
+    pub(super) sync_schema: SyncSchema,
+    pub(super) batch: Option<RecordBatch>,
+}
+
+#[derive(Debug)]
+pub(super) struct SyncWal {
+    dir: PathBuf,
+    next_id: SegmentId,
+}
+
+impl SyncWal {
+    pub(super) fn try_new(dir: PathBuf) -> SyncResult<Self> {
+        fs::create_dir_all(&dir)?;
+        sync_dir(&dir)?;
+
+        let mut wal = Self { dir, next_id: 0 };
+        wal.next_id = wal.segments()?.last().map(|id| id + 1).unwrap_or_default();
+        Ok(wal)
+    }
+
+    fn segment_path(&self, id: SegmentId, extension: &str) -> PathBuf {
+        self.dir.join(format!("{id:020}.{extension}"))
+    }
+
+    // List the ids of the complete segments in the log in order, cleaning up any segments that
+    // were only partially written
+    fn segments(&self) -> SyncResult<Vec<SegmentId>> {
+        let mut ids = vec![];
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            if file_name.ends_with(SEGMENT_TMP_EXTENSION) {
+                warn!("Removing incomplete sync WAL segment {}", path.display());
+                fs::remove_file(&path)?;
+            } else if let Some(id) = file_name
+                .strip_suffix(SEGMENT_EXTENSION)
+                .and_then(|stem| stem.strip_suffix('.'))
+                .and_then(|stem| stem.parse::<SegmentId>().ok())
+            {
+                ids.push(id);
+            }
+        }
+
+        ids.sort();
+        Ok(ids)
+    }
+
+    // Persist a sync message, returning the id of its segment. The segment is written out to a
+    // temporary file first and renamed once synced to disk, so that a crash mid-way doesn't leave
+    // a truncated segment behind. The rename itself is only durable once the directory is synced.
+    pub(super) fn append(
+        &mut self,
+        cmd: &DataSyncCommand,
+        sync_schema: &SyncSchema,
+        batch: Option<&RecordBatch>,
+    ) -> SyncResult<SegmentId> {
+        let id = self.next_id;
+
+        // Record the descriptors of the (squashed) batch, rather than the ones of the original
+        // message
+        let cmd = DataSyncCommand {
+            column_descriptors: sync_schema
+                .columns()
+                .iter()
+                .map(|col| ColumnDescriptor {
+                    role: col.role() as i32,
+                    name: col.name().clone(),
+                })
+                .collect(),
+            ..cmd.clone()
+        };
+        let metadata = HashMap::from([(
+            SYNC_COMMAND_KEY.to_string(),
+            STANDARD.encode(cmd.encode_to_vec()),
+        )]);
+        let schema = match batch {
+            Some(batch) => batch.schema().as_ref().clone(),
+            None => Schema::empty(),
+        }
+        .with_metadata(metadata);
+
+        let tmp_path = self.segment_path(id, SEGMENT_TMP_EXTENSION);
+        let mut writer =
+            StreamWriter::try_new(BufWriter::new(File::create(&tmp_path)?), &schema)?;
+        if let Some(batch) = batch {
+            writer.write(&batch.clone().with_schema(Arc::new(schema))?)?;
+        }
+        writer
+            .into_inner()?
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(&tmp_path, self.segment_path(id, SEGMENT_EXTENSION))?;
+        sync_dir(&self.dir)?;
+
+        self.next_id += 1;
+        Ok(id)
+    }
+
+    // Read back the sync messages in the log, in the order they were appended. The messages of an
+    // origin following its last one with a sequence number belong to a transaction that was never
+    // completed, and that the origin will re-send in full, so their segments get deleted instead.
+    pub(super) fn entries(&self) -> SyncResult<Vec<WalEntry>> {
+        let ids = self.segments()?;
+        if !ids.is_empty() {
+            info!(
+                "Replaying {} sync WAL segments from {}",
+                ids.len(),
+                self.dir.display()
+            );
+        }
+
+        let entries = ids
+            .into_iter()
+            .map(|id| self.read(id))
+            .collect::<SyncResult<Vec<_>>>()?;
+
+        // Segments are read in order, so this ends up with the last complete segment per origin
+        let last_complete = entries
+            .iter()
+            .filter(|entry| entry.cmd.sequence_number.is_some())
+            .map(|entry| (entry.cmd.origin.clone(), entry.id))
+            .collect::<HashMap<_, _>>();
+        let (entries, incomplete): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|entry| {
+                last_complete
+                    .get(&entry.cmd.origin)
+                    .is_some_and(|last| entry.id <= *last)
+            });
+
+        if !incomplete.is_empty() {
+            warn!(
+                "Discarding {} sync WAL segments of incomplete transactions",
+                incomplete.len()
+            );
+            self.truncate(incomplete.iter().map(|entry| entry.id));
+        }
+
+        Ok(entries)
+    }
+
+    fn read(&self, id: SegmentId) -> SyncResult<WalEntry> {
+        let path = self.segment_path(id, SEGMENT_EXTENSION);
+        let invalid = |reason: String| SyncError::WalError {
+            path: path.display().to_string(),
+            reason,
+        };
+
+        let mut reader = StreamReader::try_new(File::open(&path)?, None)?;
+        let schema = reader.schema();
+        let cmd = schema
+            .metadata()
+            .get(SYNC_COMMAND_KEY)
+            .ok_or_else(|| invalid("missing sync command".to_string()))
+            .and_then(|encoded| {
+                STANDARD.decode(encoded).map_err(|e| invalid(e.to_string()))
+            })
+            .and_then(|bytes| {
+                DataSyncCommand::decode(bytes.as_slice())
+                    .map_err(|e| invalid(e.to_string()))
+            })?;
+
+        // Drop the metadata, to get back the batch as it was appended
+        let batch_schema = Arc::new(Schema::new(schema.fields().clone()));
+        let (sync_schema, batch) = match reader.next().transpose()? {
+            Some(batch) => (
+                SyncSchema::try_new(
+                    cmd.column_descriptors.clone(),
+                    batch_schema.clone(),
+                )?,
+                Some(RecordBatch::try_new(
+                    batch_schema,
+                    batch.columns().to_vec(),
+                )?),
+            ),
+            None => (SyncSchema::empty(), None),
+        };
+
+        Ok(WalEntry {
+            id,
+            cmd,
+            sync_schema,
+            batch,
+        })
+    }
+
+    // Persist the pinned table versions (location URI => version), replacing the previous ones
+    pub(super) fn save_pins(&self, pins: &HashMap<String, i64>) -> SyncResult<()> {
+        let tmp_path = self.dir.join(PINNED_VERSIONS_TMP_FILE);
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(pins)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(PINNED_VERSIONS_FILE))?;
+        sync_dir(&self.dir)
+    }
+
+    // Read back the last persisted pinned table versions
+    pub(super) fn pins(&self) -> SyncResult<HashMap<String, i64>> {
+        match fs::read(self.dir.join(PINNED_VERSIONS_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // Delete the segments of transactions that are now durably stored. Failing to do so only means
+    // the syncs get replayed on the next restart, so don't fail the flush over it.
+    pub(super) fn truncate(&self, ids: impl IntoIterator<Item = SegmentId>) {
+        for id in ids {
+            let path = self.segment_path(id, SEGMENT_EXTENSION);
+            if let Err(err) = fs::remove_file(&path) {
+                warn!("Error removing sync WAL segment {}: {err}", path.display());
+            }
+        }
+    }
+}
+
+// Flush the entries of a directory to disk, so that the files created or renamed in it survive a
+// power loss
+fn sync_dir(dir: &Path) -> SyncResult<()> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
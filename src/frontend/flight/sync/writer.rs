@@ -1,6 +1,6 @@
 use arrow::array::RecordBatch;
-use arrow_schema::{SchemaBuilder, SchemaRef};
-use clade::sync::ColumnRole;
+use arrow_schema::{Schema as ArrowSchema, SchemaRef};
+use clade::sync::{ColumnRole, DataSyncCommand};
 use datafusion::datasource::{provider_as_source, TableProvider};
 use datafusion::execution::session_state::SessionStateBuilder;
 use datafusion::physical_expr::create_physical_expr;
@@ -9,24 +9,29 @@ use datafusion::prelude::DataFrame;
 use datafusion_common::{JoinType, Result, ScalarValue, ToDFSchema};
 use datafusion_expr::execution_props::ExecutionProps;
 use datafusion_expr::{
-    col, is_null, lit, when, LogicalPlan, LogicalPlanBuilder, Projection,
+    cast, col, is_null, lit, when, LogicalPlan, LogicalPlanBuilder, Projection,
 };
 use datafusion_expr::{is_true, Expr};
 use deltalake::delta_datafusion::DeltaTableProvider;
-use deltalake::kernel::{Action, Add, Remove, Schema};
+use deltalake::kernel::{Action, Add, Schema};
 use deltalake::logstore::LogStore;
 use deltalake::operations::create::CreateBuilder;
 use deltalake::protocol::{DeltaOperation, SaveMode};
 use deltalake::DeltaTable;
+use futures::{future, StreamExt, TryStreamExt};
 use indexmap::IndexMap;
+use object_store::path::Path;
+use object_store::ObjectStore;
 use std::collections::{HashMap, HashSet};
 use std::ops::Not;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use crate::context::delta::plan_to_object_store;
+use crate::context::delta::{plan_to_object_store, remove_actions};
 
 use crate::context::SeafowlContext;
 use crate::frontend::flight::handler::SYNC_COMMIT_INFO;
@@ -35,8 +40,10 @@ use crate::frontend::flight::sync::schema::SyncSchema;
 use crate::frontend::flight::sync::utils::{
     construct_qualifier, get_prune_map, squash_batches,
 };
+use crate::frontend::flight::sync::wal::{SegmentId, SyncWal};
 use crate::frontend::flight::sync::{
-    Origin, SequenceNumber, SyncCommitInfo, SyncError, SyncResult,
+    sync_log_store, sync_operation, Origin, RenameMarker, SequenceNumber, SyncCommitInfo,
+    SyncError, SyncResult, TableOperation,
 };
 
 const SYNC_REF: &str = "sync_data";
@@ -81,6 +88,11 @@ const FINE_GRAINED_PRUNING_ROW_CRITERIA: i64 = 3_000_000;
 //
 // Finally, once table_3 is flushed `SeafowlDataSyncWriter` will advance the
 // durable sequence up to 3, since both it and 2 have now been completely persisted.
+//
+// In the transactional mode, flushing a table also flushes all other tables that share pending
+// transactions with it (transitively), e.g. all three tables above get flushed together. In
+// addition, each table is pinned to its last version preceding commits from transactions that
+// aren't yet durable, so that queries don't see transactions that are only partially applied.
 pub(crate) struct SeafowlDataSyncWriter {
     context: Arc<SeafowlContext>,
     // An indexed-queue of transactions sorted by insertion order
@@ -96,6 +108,20 @@ pub(crate) struct SeafowlDataSyncWriter {
     origin_durable: HashMap<Origin, SequenceNumber>,
     // Keep track of various metrics for observability
     metrics: SyncMetrics,
+    // Optional on-disk log of the pending syncs, so that they can be recovered after a restart
+    wal: Option<SyncWal>,
+    // Last known schemas of the tables at the sync locations (`None` if the table doesn't exist
+    // yet), used to reject incompatible syncs before they are accepted
+    table_schemas: HashMap<String, Option<SchemaRef>>,
+    // Notifies streaming sync clients whenever some durable sequence numbers advance
+    durable_updates: watch::Sender<()>,
+    // Table versions committed in the transactional mode that aren't exposed to queries yet,
+    // alongside the transactions they contain, per location
+    unpublished: HashMap<String, Vec<(i64, HashSet<Uuid>)>>,
+    // Table versions pinned before the last restart, alongside the transactions replayed from the
+    // write-ahead log; the pins are kept until all of these transactions are durable
+    restored_pins: HashMap<String, i64>,
+    restored_txs: HashSet<Uuid>,
 }
 
 // Besides the two conditions mentioned below, another implicit condition for marking a transaction
@@ -110,6 +136,8 @@ pub(super) struct Transaction {
     // Locations that have pending flushes for this transaction; having flushed all locations for a
     // transaction is condition #2 for marking it as durable.
     locations: HashSet<String>,
+    // Write-ahead log segments of the syncs in this transaction, to be removed once it is durable
+    segments: Vec<SegmentId>,
 }
 
 // An entry storing all pending in-memory data to replicate to a single table location,
@@ -136,8 +164,10 @@ pub(super) struct DataSyncItem {
     pub(super) tx_id: Uuid,
     // Old and new primary keys, changed and value columns
     pub(super) sync_schema: SyncSchema,
-    // Record batch to replicate
+    // Record batch to replicate; empty for table operations
     pub(super) batch: RecordBatch,
+    // Table-level operation to apply instead of the row changes
+    pub(super) operation: Option<TableOperation>,
 }
 
 impl SeafowlDataSyncWriter {
@@ -150,9 +180,20 @@ impl SeafowlDataSyncWriter {
             origin_memory: Default::default(),
             origin_durable: Default::default(),
             metrics: Default::default(),
+            wal: None,
+            table_schemas: Default::default(),
+            durable_updates: watch::channel(()).0,
+            unpublished: Default::default(),
+            restored_pins: Default::default(),
+            restored_txs: Default::default(),
         }
     }
 
+    // Get notified whenever the durable sequence numbers advance
+    pub fn subscribe_durable(&self) -> watch::Receiver<()> {
+        self.durable_updates.subscribe()
+    }
+
     // Extract the latest memory sequence number for a given table location.
     pub fn stored_sequences(
         &self,
@@ -164,17 +205,51 @@ impl SeafowlDataSyncWriter {
         )
     }
 
+    // Replay the syncs persisted in the write-ahead log at the provided directory, and keep logging
+    // all subsequent ones to it. Any table versions pinned before the restart are restored first,
+    // so that transactions that were only partially flushed stay hidden until they get completed.
+    pub async fn with_wal(mut self, dir: PathBuf) -> SyncResult<Self> {
+        let wal = SyncWal::try_new(dir)?;
+
+        let pins = wal.pins()?;
+        for (url, version) in &pins {
+            self.context.pinned_versions.pin(url, *version);
+        }
+
+        for entry in wal.entries()? {
+            let log_store = sync_log_store(&self.context, &entry.cmd).await?;
+            let operation = sync_operation(&self.context, &entry.cmd).await?;
+            let num_rows = entry
+                .batch
+                .as_ref()
+                .map(|b| b.num_rows())
+                .unwrap_or_default();
+            self.validate_sync(&entry.cmd, num_rows)?;
+            self.insert_sync(
+                log_store,
+                &entry.cmd,
+                entry.sync_schema,
+                entry.batch,
+                operation,
+                Some(entry.id),
+            );
+        }
+
+        self.restored_pins = pins;
+        self.restored_txs = self.txs.keys().cloned().collect();
+        self.wal = Some(wal);
+        Ok(self)
+    }
+
     // Store the pending data in memory and flush if the required criteria are met.
     pub fn enqueue_sync(
         &mut self,
         log_store: Arc<dyn LogStore>,
-        sequence_number: Option<SequenceNumber>,
-        origin: Origin,
+        cmd: &DataSyncCommand,
         sync_schema: SyncSchema,
         batches: Vec<RecordBatch>,
+        operation: Option<TableOperation>,
     ) -> SyncResult<()> {
-        let url = log_store.root_uri();
-
         let (sync_size, sync_rows) =
             batches.iter().fold((0, 0), |(size, rows), batch| {
                 (
@@ -183,11 +258,107 @@ impl SeafowlDataSyncWriter {
                 )
             });
 
-        // Upsert a sequence entry for this origin and sequence number
-        let tx_id = if let Some((tx_id, tx)) = self.txs.last_mut()
+        self.validate_sync(cmd, sync_rows)?;
+
+        let batch = if sync_rows > 0 {
+            // Squash the batches and measure the time it took and the reduction in rows/size
+            self.metrics.request_bytes.increment(sync_size as u64);
+            self.metrics.request_rows.increment(sync_rows as u64);
+            let start = Instant::now();
+            let batch = squash_batches(&sync_schema, batches)?;
+            let duration = start.elapsed().as_secs();
+
+            self.metrics.squash_time.record(duration as f64);
+            self.metrics
+                .squashed_bytes
+                .increment((sync_size - batch.get_array_memory_size()) as u64);
+            self.metrics
+                .squashed_rows
+                .increment((sync_rows - batch.num_rows()) as u64);
+            Some(batch)
+        } else {
+            None
+        };
+
+        // Persist the sync before acknowledging it, if the write-ahead log is enabled
+        let segment = self
+            .wal
+            .as_mut()
+            .map(|wal| wal.append(cmd, &sync_schema, batch.as_ref()))
+            .transpose()?;
+
+        self.insert_sync(log_store, cmd, sync_schema, batch, operation, segment);
+        Ok(())
+    }
+
+    // Make sure the schema of the sync is compatible with the table it targets, taking into account
+    // any schema changes from the syncs still pending in memory.
+    pub async fn check_schema(
+        &mut self,
+        log_store: &Arc<dyn LogStore>,
+        sync_schema: &SyncSchema,
+    ) -> SyncResult<()> {
+        if sync_schema.columns().is_empty() {
+            return Ok(());
+        }
+
+        let url = log_store.root_uri();
+        let table_schema = match self.table_schemas.get(&url) {
+            Some(table_schema) => table_schema.clone(),
+            None => {
+                let table_schema = if log_store.is_delta_table_location().await? {
+                    let mut table =
+                        DeltaTable::new(log_store.clone(), Default::default());
+                    table.load().await?;
+                    Some(TableProvider::schema(&table))
+                } else {
+                    None
+                };
+                self.table_schemas.insert(url.clone(), table_schema.clone());
+                table_schema
+            }
+        };
+
+        let mut pending = self
+            .syncs
+            .get(&url)
+            .map(|entry| entry.syncs.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|sync| (&sync.sync_schema, sync.operation.as_ref()))
+            .chain([(sync_schema, None)]);
+        pending.try_fold(table_schema, |table_schema, (sync_schema, operation)| {
+            Ok::<_, SyncError>(match (operation, table_schema) {
+                (Some(TableOperation::Truncate), table_schema) => table_schema,
+                // The next sync with row changes creates a new table
+                (Some(_), _) => None,
+                (None, Some(table_schema)) => {
+                    Some(sync_schema.evolve_schema(&table_schema)?)
+                }
+                // A new table gets created with the schema of the first sync
+                (None, None) => Some(Arc::new(sync_schema.table_schema())),
+            })
+        })?;
+
+        Ok(())
+    }
+
+    // Make sure the sync can be added to the pending transactions
+    fn validate_sync(&self, cmd: &DataSyncCommand, sync_rows: usize) -> SyncResult<()> {
+        let origin = &cmd.origin;
+        if cmd.operation.is_some() && sync_rows > 0 {
+            return Err(SyncError::InvalidMessage {
+                reason: format!(
+                    "Table operation for {} can't be combined with row changes",
+                    cmd.path
+                ),
+            });
+        }
+
+        if let Some((_, tx)) = self.txs.last()
             && tx.sequence.is_none()
         {
-            if origin != tx.origin {
+            if origin != &tx.origin {
                 return Err(SyncError::InvalidMessage {
                     reason: format!(
                         "Transaction from a new origin ({origin}) started without finishing the transaction from the previous one ({})",
@@ -195,7 +366,48 @@ impl SeafowlDataSyncWriter {
                     )
                 });
             }
+        } else if sync_rows == 0
+            && cmd.operation.is_none()
+            && let Some(seq) = cmd.sequence_number
+        {
+            // The message would start a new transaction, but it is empty, which isn't supported
+            return Err(SyncError::InvalidMessage {
+                reason: format!(
+                    "Received empty transaction for origin {origin} with sequence number {seq}",
+                )
+            });
+        }
+
+        Ok(())
+    }
+
+    // Add an already validated (and squashed) sync to the pending transactions and syncs
+    fn insert_sync(
+        &mut self,
+        log_store: Arc<dyn LogStore>,
+        cmd: &DataSyncCommand,
+        sync_schema: SyncSchema,
+        batch: Option<RecordBatch>,
+        operation: Option<TableOperation>,
+        segment: Option<SegmentId>,
+    ) {
+        let url = log_store.root_uri();
+        let sequence_number = cmd.sequence_number;
+        let origin = cmd.origin.clone();
 
+        // Table operations are kept in line with the row changes, so that they get applied in order
+        let (sync_schema, batch) = match (&operation, batch) {
+            (Some(_), _) => (
+                SyncSchema::empty(),
+                Some(RecordBatch::new_empty(Arc::new(ArrowSchema::empty()))),
+            ),
+            (None, batch) => (sync_schema, batch),
+        };
+
+        // Upsert a sequence entry for this origin and sequence number
+        let tx_id = if let Some((tx_id, tx)) = self.txs.last_mut()
+            && tx.sequence.is_none()
+        {
             // Merge the information for the pending transaction
             if let Some(seq) = sequence_number {
                 // A sequence number was provided, denoting the end of the transaction
@@ -203,26 +415,16 @@ impl SeafowlDataSyncWriter {
                 tx.sequence = sequence_number;
             }
 
-            if sync_rows > 0 {
+            if batch.is_some() {
                 debug!("Adding {url} as sync destination for {origin} in the pending tx ({tx_id})");
                 tx.locations.insert(url.clone());
             }
 
+            tx.segments.extend(segment);
             *tx_id
         } else {
             // The previous message contains a definite sequence number (or there are no entries at
             // all), so this means this message belongs to a new transaction
-            if sync_rows == 0
-                && let Some(seq) = sequence_number
-            {
-                // We received an empty transaction, this isn't supported
-                return Err(SyncError::InvalidMessage {
-                    reason: format!(
-                        "Received empty transaction for origin {origin} with sequence number {seq}",
-                    )
-                });
-            }
-
             let tx_id = Uuid::new_v4();
             debug!("Adding {url} as sync destination for {origin} in a new tx {tx_id}");
             self.txs.insert(
@@ -231,19 +433,13 @@ impl SeafowlDataSyncWriter {
                     origin: origin.clone(),
                     sequence: sequence_number,
                     locations: HashSet::from([url.clone()]),
+                    segments: segment.into_iter().collect(),
                 },
             );
             tx_id
         };
 
-        if sync_rows > 0 {
-            // Squash the batches and measure the time it took and the reduction in rows/size
-            self.metrics.request_bytes.increment(sync_size as u64);
-            self.metrics.request_rows.increment(sync_rows as u64);
-            let start = Instant::now();
-            let batch = squash_batches(&sync_schema, batches)?;
-            let duration = start.elapsed().as_secs();
-
+        if let Some(batch) = batch {
             // Get new size and row count
             let size = batch.get_array_memory_size();
             let rows = batch.num_rows();
@@ -252,6 +448,7 @@ impl SeafowlDataSyncWriter {
                 tx_id,
                 sync_schema,
                 batch,
+                operation,
             };
             self.syncs
                 .entry(url)
@@ -272,13 +469,6 @@ impl SeafowlDataSyncWriter {
             self.size += size;
             self.metrics.in_memory_bytes.increment(size as f64);
             self.metrics.in_memory_rows.increment(rows as f64);
-            self.metrics.squash_time.record(duration as f64);
-            self.metrics
-                .squashed_bytes
-                .increment((sync_size - size) as u64);
-            self.metrics
-                .squashed_rows
-                .increment((sync_rows - rows) as u64);
             self.metrics.in_memory_oldest.set(
                 self.syncs
                     .first()
@@ -293,8 +483,6 @@ impl SeafowlDataSyncWriter {
             self.metrics.sequence_memory(&origin, seq);
             self.origin_memory.insert(origin, seq);
         }
-
-        Ok(())
     }
 
     async fn create_table(
@@ -302,16 +490,7 @@ impl SeafowlDataSyncWriter {
         log_store: Arc<dyn LogStore>,
         sync_schema: &SyncSchema,
     ) -> SyncResult<DeltaTable> {
-        // Get the actual table schema by removing the OldPk and Changed column roles from the schema.
-        let mut builder = SchemaBuilder::new();
-        sync_schema.columns().iter().for_each(|col| {
-            if matches!(col.role(), ColumnRole::NewPk | ColumnRole::Value) {
-                let field = col.field().as_ref().clone().with_name(col.name());
-                builder.push(field);
-            }
-        });
-
-        let delta_schema = Schema::try_from(&builder.finish())?;
+        let delta_schema = Schema::try_from(&sync_schema.table_schema())?;
 
         Ok(CreateBuilder::new()
             .with_log_store(log_store)
@@ -322,7 +501,13 @@ impl SeafowlDataSyncWriter {
 
     pub async fn flush(&mut self) -> SyncResult<()> {
         while let Some(url) = self.flush_ready() {
-            self.flush_syncs(url).await?;
+            if self.context.config.misc.sync_conf.transactional {
+                for url in self.tx_group(&url) {
+                    self.flush_syncs(url).await?;
+                }
+            } else {
+                self.flush_syncs(url).await?;
+            }
         }
 
         self.metrics.in_memory_oldest.set(
@@ -374,23 +559,57 @@ impl SeafowlDataSyncWriter {
         }
     }
 
-    // Flush the table containing the oldest sync in memory
+    // Flush all pending syncs for the provided location
     async fn flush_syncs(&mut self, url: String) -> SyncResult<()> {
-        let entry = match self.syncs.get(&url) {
-            Some(table_syncs) => table_syncs,
-            None => {
-                info!("No pending syncs to flush");
-                return Ok(());
+        // Any pending renames into this location need to be applied first
+        for source in self.rename_sources(&url) {
+            self.flush_location(source).await?;
+        }
+
+        self.flush_location(url).await
+    }
+
+    // Apply the pending syncs for the location in order, one table operation or run of row changes
+    // at a time
+    async fn flush_location(&mut self, url: String) -> SyncResult<()> {
+        if !self.syncs.contains_key(&url) {
+            info!("No pending syncs to flush");
+            return Ok(());
+        }
+
+        while let Some(entry) = self.syncs.get(&url) {
+            let end = entry
+                .syncs
+                .iter()
+                .position(|sync| sync.operation.is_some())
+                .unwrap_or(entry.syncs.len());
+            if end == 0 {
+                self.flush_operation(url.clone()).await?;
+            } else {
+                self.flush_rows(url.clone(), end).await?;
             }
-        };
+        }
+
+        Ok(())
+    }
 
-        info!("Flushing {} syncs for url {url}", entry.syncs.len());
+    // Flush the first `end` syncs with row changes for the provided location
+    async fn flush_rows(&mut self, url: String, end: usize) -> SyncResult<()> {
+        let entry = &self.syncs[&url];
+
+        info!("Flushing {end} syncs for url {url}");
 
         let start = Instant::now();
         let insertion_time = entry.insertion_time;
-        let rows = entry.rows;
-        let size = entry.size;
-        let url = url.clone();
+        let (size, rows) =
+            entry.syncs[..end]
+                .iter()
+                .fold((0, 0), |(size, rows), sync| {
+                    (
+                        size + sync.batch.get_array_memory_size(),
+                        rows + sync.batch.num_rows(),
+                    )
+                });
         let log_store = entry.log_store.clone();
 
         // If there's no delta table at this location yet create one first.
@@ -407,52 +626,57 @@ impl SeafowlDataSyncWriter {
         table.load().await?;
 
         let last_sync_commit = self.table_sequence(&table).await?;
-        let (syncs, new_sync_commit) = self.skip_syncs(&last_sync_commit, &entry.syncs);
+        let (syncs, new_sync_commit) =
+            self.skip_syncs(&last_sync_commit, &entry.syncs[..end]);
 
         info!(
             "Location at {url} already durable up to {:?}, skipping {} messages",
             last_sync_commit,
-            entry.syncs.len() - syncs.len(),
+            end - syncs.len(),
         );
 
         if syncs.is_empty() {
             // TODO: Update metrics
-            self.remove_sync(&url);
+            self.remove_syncs(&url, end);
             return Ok(());
         }
 
         // Use the schema from the object store as a source of truth, since it's not guaranteed
-        // that any of the entries has the full column list.
-        let full_schema = TableProvider::schema(&table);
-
-        let prune_start = Instant::now();
-        // Gather previous Add files that (might) need to be re-written.
-        let files = self.prune_partitions(syncs, full_schema.clone(), &table)?;
-        let prune_time = prune_start.elapsed().as_millis();
-        info!(
-            "Partition pruning found {} files in {prune_time} ms",
-            files.len(),
-        );
-        self.metrics.pruning_time.record(prune_time as f64);
-        self.metrics.pruning_files.record(files.len() as f64);
-        // Create removes to prune away files that are refuted by the qualifier
-        let removes = files
+        // that any of the entries has the full column list, and extend it with any new columns or
+        // wider types from the syncs.
+        let table_schema = TableProvider::schema(&table);
+        let full_schema = syncs
             .iter()
-            .map(|add| {
-                Action::Remove(Remove {
-                    path: add.path.clone(),
-                    deletion_timestamp: Some(now() as i64),
-                    data_change: true,
-                    extended_file_metadata: Some(true),
-                    partition_values: Some(add.partition_values.clone()),
-                    size: Some(add.size),
-                    tags: None,
-                    deletion_vector: None,
-                    base_row_id: None,
-                    default_row_commit_version: None,
-                })
-            })
-            .collect::<Vec<_>>();
+            .try_fold(table_schema.clone(), |schema, sync| {
+                sync.sync_schema.evolve_schema(&schema)
+            })?;
+
+        // Widening a column type means rewriting all of the table data with the new type in the
+        // same commit; otherwise the files that the syncs don't touch would keep the old type,
+        // which Delta readers only support with the type widening table feature.
+        let widened = full_schema.fields().iter().any(|f| {
+            table_schema
+                .field_with_name(f.name())
+                .is_ok_and(|table_field| table_field.data_type() != f.data_type())
+        });
+        let files = if widened {
+            info!("Rewriting all files of the table at {url} due to widened columns");
+            table.snapshot()?.file_actions()?
+        } else {
+            let prune_start = Instant::now();
+            // Gather previous Add files that (might) need to be re-written.
+            let files = self.prune_partitions(syncs, table_schema.clone(), &table)?;
+            let prune_time = prune_start.elapsed().as_millis();
+            info!(
+                "Partition pruning found {} files in {prune_time} ms",
+                files.len(),
+            );
+            self.metrics.pruning_time.record(prune_time as f64);
+            self.metrics.pruning_files.record(files.len() as f64);
+            files
+        };
+        // Create removes to prune away files that are refuted by the qualifier
+        let removes = remove_actions(files.clone(), true).collect::<Vec<_>>();
 
         // Create a special Delta table provider that will only hit the above partition files
         let base_scan = Arc::new(
@@ -477,6 +701,39 @@ impl SeafowlDataSyncWriter {
             .build();
         let mut sync_df = DataFrame::new(state, base_plan);
 
+        let mut metadata_action = None;
+        if full_schema != table_schema {
+            info!("Evolving the schema of the table at {url} to {full_schema:?}");
+
+            // Bring the existing data in line with the new schema, so that the syncs can be
+            // applied on top of it
+            let projection = full_schema
+                .fields()
+                .iter()
+                .map(|f| {
+                    let name = f.name();
+                    let expr = match table_schema.field_with_name(name) {
+                        Ok(table_field) if table_field.data_type() == f.data_type() => {
+                            col(name)
+                        }
+                        Ok(_) => cast(col(name), f.data_type().clone()),
+                        Err(_) => lit(ScalarValue::Null.cast_to(f.data_type())?),
+                    };
+                    Ok(expr.alias(name))
+                })
+                .collect::<Result<_>>()?;
+            let (session_state, plan) = sync_df.into_parts();
+            let plan = Projection::try_new(projection, Arc::new(plan))
+                .map(LogicalPlan::Projection)?;
+            sync_df = DataFrame::new(session_state, plan);
+
+            // Keep the table identity and configuration, only the schema changes
+            let mut metadata = table.metadata()?.clone();
+            metadata.schema_string =
+                serde_json::to_string(&Schema::try_from(full_schema.as_ref())?)?;
+            metadata_action = Some(Action::Metadata(metadata));
+        }
+
         // Iterate through all syncs for this table and construct a full plan by applying each
         // individual sync
         for sync in syncs {
@@ -505,6 +762,7 @@ impl SeafowlDataSyncWriter {
             log_store.object_store(),
             local_data_dir,
             self.context.config.misc.max_partition_size,
+            &table.metadata()?.partition_columns,
         )
         .await?;
 
@@ -516,6 +774,7 @@ impl SeafowlDataSyncWriter {
             actions.len(),
         );
         actions.extend(removes);
+        actions.extend(metadata_action);
         debug!("Actions to commit:\n{actions:?}");
 
         // Append a special `CommitInfo` action to record latest durable sequence number
@@ -534,15 +793,17 @@ impl SeafowlDataSyncWriter {
             partition_by: None,
             predicate: None,
         };
-        self.context.commit(actions, &table, op).await?;
+        let tx_ids: Vec<Uuid> =
+            entry.syncs[..end].iter().map(|sync| sync.tx_id).collect();
+        self.commit(&url, &table, actions, op, &tx_ids).await?;
         debug!("Committed data sync up to {new_sync_commit:?} for location {url}");
 
-        // We've flushed all the presently accumulated batches for this location.
-        // Modify our syncs and sequences maps to reflect this.
-        let tx_ids = entry.syncs.iter().map(|sync| sync.tx_id).collect();
+        // We've flushed the presently accumulated batches up to the next table operation for this
+        // location. Modify our syncs and sequences maps to reflect this.
+        self.remove_syncs(&url, end);
         self.remove_tx_locations(url.clone(), tx_ids);
-        self.remove_sync(&url);
         self.advance_durable();
+        self.table_schemas.insert(url.clone(), Some(full_schema));
 
         // Record flush metrics
         let flush_duration = start.elapsed().as_millis();
@@ -560,6 +821,230 @@ impl SeafowlDataSyncWriter {
         Ok(())
     }
 
+    // Commit the actions to the table at the provided location. In the transactional mode, the new
+    // version stays hidden from queries until all the transactions it contains are durable, and
+    // the pin is persisted before the commit, so that it survives a restart.
+    async fn commit(
+        &mut self,
+        url: &String,
+        table: &DeltaTable,
+        actions: Vec<Action>,
+        op: DeltaOperation,
+        tx_ids: &[Uuid],
+    ) -> SyncResult<()> {
+        let transactional = self.context.config.misc.sync_conf.transactional;
+        if transactional {
+            self.context.pinned_versions.pin(url, table.version());
+            if let Some(wal) = &self.wal {
+                wal.save_pins(&self.context.pinned_versions.snapshot())?;
+            }
+        }
+
+        let version = self.context.commit(actions, table, op).await?;
+        if transactional {
+            self.unpublished
+                .entry(url.clone())
+                .or_default()
+                .push((version, tx_ids.iter().cloned().collect()));
+        }
+        Ok(())
+    }
+
+    // Apply the table operation at the front of the pending syncs for the provided location
+    async fn flush_operation(&mut self, url: String) -> SyncResult<()> {
+        let entry = &self.syncs[&url];
+        let log_store = entry.log_store.clone();
+        let tx_id = entry.syncs[0].tx_id;
+
+        match entry.syncs[0].operation.clone() {
+            Some(TableOperation::Truncate) => {
+                if log_store.is_delta_table_location().await? {
+                    let mut table =
+                        DeltaTable::new(log_store.clone(), Default::default());
+                    table.load().await?;
+
+                    let last_sync_commit = self.table_sequence(&table).await?;
+                    let (syncs, new_sync_commit) =
+                        self.skip_syncs(&last_sync_commit, &entry.syncs[..1]);
+
+                    if !syncs.is_empty() {
+                        info!("Truncating the table at {url}");
+                        let mut actions: Vec<Action> =
+                            remove_actions(table.snapshot()?.file_actions()?, true)
+                                .collect();
+                        if let Some(ref sync_commit) = new_sync_commit {
+                            let info = HashMap::from([(
+                                SYNC_COMMIT_INFO.to_string(),
+                                serde_json::to_value(sync_commit)?,
+                            )]);
+                            actions.push(Action::commit_info(info));
+                        }
+
+                        let op = DeltaOperation::Delete { predicate: None };
+                        self.commit(&url, &table, actions, op, &[tx_id]).await?;
+                    }
+                }
+            }
+            Some(TableOperation::Drop) => {
+                info!("Dropping the table at {url}");
+                delete_objects(log_store.object_store()).await?;
+
+                self.table_schemas.insert(url.clone(), None);
+                self.unpublished.remove(&url);
+                self.restored_pins.remove(&url);
+            }
+            Some(TableOperation::Rename(target)) => {
+                let target_url = target.root_uri();
+                info!("Renaming the table at {url} to {target_url}");
+
+                let source_store = log_store.object_store();
+                let target_store = target.object_store();
+
+                // The marker at the target records the progress of the rename, so that re-applying
+                // it after a restart picks up where it left off
+                let target_exists = || {
+                    SyncError::InvalidMessage {
+                    reason: format!(
+                        "Can't rename the table at {url} to {target_url}, since a table already exists there"
+                    ),
+                }
+                };
+                let copied = match RenameMarker::get(target_store.as_ref()).await? {
+                    Some(marker) if marker.source == url => Some(marker.copied),
+                    Some(_) => return Err(target_exists()),
+                    // Without a marker, the rename has either completed already or not started yet
+                    None if log_store.is_delta_table_location().await? => {
+                        if target.is_delta_table_location().await? {
+                            return Err(target_exists());
+                        }
+                        RenameMarker {
+                            source: url.clone(),
+                            copied: false,
+                        }
+                        .put(target_store.as_ref())
+                        .await?;
+                        Some(false)
+                    }
+                    None => None,
+                };
+
+                if copied == Some(false) {
+                    // Table files are referenced relative to the table root, so a plain copy of
+                    // all objects results in a valid table. The log goes last, so that the target
+                    // doesn't turn into a table before all of its files are in place, and objects
+                    // copied before a restart are skipped.
+                    let (log_paths, file_paths): (Vec<_>, Vec<_>) = source_store
+                        .list(None)
+                        .map_ok(|meta| meta.location)
+                        .try_filter(|path| future::ready(*path != RenameMarker::path()))
+                        .try_collect::<Vec<_>>()
+                        .await?
+                        .into_iter()
+                        .partition(|path| path.prefix_matches(&Path::from("_delta_log")));
+                    for path in file_paths.into_iter().chain(log_paths) {
+                        match target_store.head(&path).await {
+                            Ok(_) => continue,
+                            Err(object_store::Error::NotFound { .. }) => {}
+                            Err(err) => return Err(err.into()),
+                        }
+                        let data = source_store.get(&path).await?.bytes().await?;
+                        target_store.put(&path, data.into()).await?;
+                    }
+
+                    RenameMarker {
+                        source: url.clone(),
+                        copied: true,
+                    }
+                    .put(target_store.as_ref())
+                    .await?;
+                }
+
+                // Only delete the source once the target is complete
+                if copied.is_some() {
+                    delete_objects(source_store).await?;
+                    target_store.delete(&RenameMarker::path()).await?;
+                }
+
+                match self.table_schemas.insert(url.clone(), None) {
+                    Some(table_schema) => {
+                        self.table_schemas.insert(target_url.clone(), table_schema)
+                    }
+                    None => self.table_schemas.remove(&target_url),
+                };
+                if let Some(commits) = self.unpublished.remove(&url) {
+                    self.unpublished.insert(target_url.clone(), commits);
+                }
+                if let Some(version) = self.restored_pins.remove(&url) {
+                    self.restored_pins.insert(target_url, version);
+                }
+            }
+            None => unreachable!("Pending sync at {url} is not a table operation"),
+        }
+
+        self.remove_syncs(&url, 1);
+        self.remove_tx_locations(url, vec![tx_id]);
+        self.advance_durable();
+        Ok(())
+    }
+
+    // Find the locations with pending renames into the provided location, either directly or
+    // through a chain of renames, in the order in which they need to be flushed
+    fn rename_sources(&self, url: &String) -> Vec<String> {
+        let mut sources = vec![];
+        let mut target = url.clone();
+
+        while let Some(source) = self.syncs.iter().find_map(|(source, entry)| {
+            entry
+                .syncs
+                .iter()
+                .any(|sync| {
+                    matches!(
+                        &sync.operation,
+                        Some(TableOperation::Rename(log_store)) if log_store.root_uri() == target
+                    )
+                })
+                .then(|| source.clone())
+        }) && source != *url
+            && !sources.contains(&source)
+        {
+            sources.push(source.clone());
+            target = source;
+        }
+
+        sources.reverse();
+        sources
+    }
+
+    // Find all the locations with pending syncs that share a transaction with the provided one,
+    // either directly or through other locations, in the order of their oldest sync
+    fn tx_group(&self, url: &String) -> Vec<String> {
+        let mut group = HashSet::from([url.clone()]);
+        let mut pending = vec![url.clone()];
+
+        while let Some(url) = pending.pop() {
+            let Some(entry) = self.syncs.get(&url) else {
+                continue;
+            };
+
+            for sync in &entry.syncs {
+                let Some(tx) = self.txs.get(&sync.tx_id) else {
+                    continue;
+                };
+                for location in &tx.locations {
+                    if group.insert(location.clone()) {
+                        pending.push(location.clone());
+                    }
+                }
+            }
+        }
+
+        self.syncs
+            .keys()
+            .filter(|url| group.contains(*url))
+            .cloned()
+            .collect()
+    }
+
     // Inspect the table logs to find out what is the latest origin/sequence number committed.
     // Note that the origin/sequence denote only the last _fully_ flushed, and in general there
     // may be further commits from subsequent origin/sequences, as denoted by the
@@ -690,6 +1175,13 @@ impl SeafowlDataSyncWriter {
                     .column(name, ColumnRole::Value)
                     .or(sync_schema.column(name, ColumnRole::NewPk))
                 {
+                    // Values narrower than the column type need to be cast to it
+                    let sync_value = if sync_col.field().data_type() == f.data_type() {
+                        col(sync_col.field().name())
+                    } else {
+                        cast(col(sync_col.field().name()), f.data_type().clone())
+                    };
+
                     // The column is present in the sync schema...
                     when(
                         old_pk_nulls.clone().and(new_pk_nulls.clone()),
@@ -704,7 +1196,7 @@ impl SeafowlDataSyncWriter {
                             when(
                                 is_true(col(changed_sync_col.field().name())),
                                 // If it's true take the new value
-                                col(sync_col.field().name()),
+                                sync_value,
                             )
                             .otherwise(
                                 // If it's false take the old value
@@ -712,7 +1204,7 @@ impl SeafowlDataSyncWriter {
                             )?
                         } else {
                             // ... and the sync has a new corresponding value without a `Changed` flag
-                            col(sync_col.field().name())
+                            sync_value
                         },
                     )?
                 } else {
@@ -835,9 +1327,16 @@ impl SeafowlDataSyncWriter {
             .collect::<Vec<Add>>())
     }
 
-    // Remove the pending location from a sequence for all syncs in the collection
+    // Remove the pending location from a sequence for all flushed syncs, unless the location still
+    // has pending syncs from the same transaction
     fn remove_tx_locations(&mut self, url: String, tx_ids: Vec<Uuid>) {
-        for tx_id in tx_ids {
+        let pending: HashSet<Uuid> = self
+            .syncs
+            .get(&url)
+            .map(|entry| entry.syncs.iter().map(|sync| sync.tx_id).collect())
+            .unwrap_or_default();
+
+        for tx_id in tx_ids.into_iter().filter(|tx_id| !pending.contains(tx_id)) {
             // Remove the pending location for this origin/sequence
             if let Some(tx) = self.txs.get_mut(&tx_id) {
                 tx.locations.remove(&url);
@@ -845,20 +1344,44 @@ impl SeafowlDataSyncWriter {
         }
     }
 
-    // Remove the in-memory sync collection for the provided location, and update the size
-    fn remove_sync(&mut self, url: &String) {
-        if let Some(sync) = self.syncs.shift_remove(url) {
-            self.size -= sync.size;
-            self.metrics.in_memory_bytes.decrement(sync.size as f64);
-            self.metrics.in_memory_rows.decrement(sync.rows as f64);
-        }
+    // Remove the first `count` in-memory syncs for the provided location, and update the size
+    fn remove_syncs(&mut self, url: &String, count: usize) {
+        let Some(entry) = self.syncs.get_mut(url) else {
+            return;
+        };
+
+        let (size, rows) = if count >= entry.syncs.len() {
+            let (size, rows) = (entry.size, entry.rows);
+            self.syncs.shift_remove(url);
+            (size, rows)
+        } else {
+            let (size, rows) =
+                entry
+                    .syncs
+                    .drain(..count)
+                    .fold((0, 0), |(size, rows), sync| {
+                        (
+                            size + sync.batch.get_array_memory_size(),
+                            rows + sync.batch.num_rows(),
+                        )
+                    });
+            entry.size -= size;
+            entry.rows -= rows;
+            (size, rows)
+        };
+
+        self.size -= size;
+        self.metrics.in_memory_bytes.decrement(size as f64);
+        self.metrics.in_memory_rows.decrement(rows as f64);
     }
 
     // Iterate through all origin-sequences in the insertion order and:
     //    - mark as durable all flushed and final sequences up to the first one that is not
     //    - remove the durable sequences from the map
+    //    - remove the write-ahead log segments of the durable sequences
     fn advance_durable(&mut self) {
         let mut durable_txs = HashSet::new();
+        let mut durable_segments = vec![];
 
         // Iterate through all origins in order of insertion
         for (tx_id, tx) in &mut self.txs {
@@ -869,6 +1392,7 @@ impl SeafowlDataSyncWriter {
                 // with pending flushes; it's safe to mark the sequence as durable for this origin.
                 self.origin_durable.insert(tx.origin.clone(), seq);
                 durable_txs.insert(*tx_id);
+                durable_segments.append(&mut tx.segments);
                 debug!("Set new durable sequence {seq} for {}", tx.origin);
             } else {
                 // We either haven't seen the end of the transaction or some locations still have
@@ -878,9 +1402,63 @@ impl SeafowlDataSyncWriter {
         }
 
         self.txs.retain(|tx_id, _| !durable_txs.contains(tx_id));
+        if !durable_txs.is_empty() {
+            self.durable_updates.send_replace(());
+        }
+        if let Some(wal) = &self.wal {
+            wal.truncate(durable_segments);
+        }
+        if self.context.config.misc.sync_conf.transactional {
+            self.publish_versions();
+        }
+    }
+
+    // Expose the versions whose transactions are all durable now to queries, keeping each table
+    // with commits from non-durable transactions pinned to the version preceding the first one.
+    // All pins get updated at once, so that queries see either all or none of the tables
+    // affected by a transaction in their new state.
+    fn publish_versions(&mut self) {
+        for commits in self.unpublished.values_mut() {
+            commits.retain(|(_, tx_ids)| {
+                tx_ids.iter().any(|tx_id| self.txs.contains_key(tx_id))
+            });
+        }
+        self.unpublished.retain(|_, commits| !commits.is_empty());
+
+        self.restored_txs
+            .retain(|tx_id| self.txs.contains_key(tx_id));
+        if self.restored_txs.is_empty() {
+            self.restored_pins.clear();
+        }
+
+        // Pins restored from before the restart precede any commits made since
+        let mut pins: HashMap<String, i64> = self
+            .unpublished
+            .iter()
+            .map(|(url, commits)| (url.clone(), commits[0].0 - 1))
+            .collect();
+        pins.extend(self.restored_pins.clone());
+        if pins == self.context.pinned_versions.snapshot() {
+            return;
+        }
+
+        if let Some(wal) = &self.wal
+            && let Err(err) = wal.save_pins(&pins)
+        {
+            // At worst, the previous pins hide some more table versions after a restart
+            warn!("Error persisting the pinned table versions: {err}");
+        }
+        self.context.pinned_versions.replace(pins);
     }
 }
 
+// Delete all objects in the (table-scoped) store
+async fn delete_objects(store: Arc<dyn ObjectStore>) -> SyncResult<()> {
+    let paths = store.list(None).map_ok(|meta| meta.location).boxed();
+    store.delete_stream(paths).try_collect::<Vec<_>>().await?;
+    Ok(())
+}
+
 fn now() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -895,16 +1473,26 @@ mod tests {
     use crate::frontend::flight::sync::writer::{SeafowlDataSyncWriter, SequenceNumber};
     use arrow::{array::RecordBatch, util::data_gen::create_random_batch};
     use arrow_schema::{DataType, Field, Schema, SchemaRef};
-    use clade::sync::{ColumnDescriptor, ColumnRole};
+    use clade::sync::data_sync_command::Operation;
+    use clade::sync::{
+        ColumnDescriptor, ColumnRole, DataSyncCommand, DropTable, RenameTable,
+        TruncateTable,
+    };
     use rand::Rng;
     use rstest::rstest;
     use std::collections::HashMap;
 
-    use crate::frontend::flight::sync::SyncCommitInfo;
+    use crate::frontend::flight::sync::{
+        sync_operation, RenameMarker, SyncCommitInfo, TableOperation,
+    };
     use arrow::array::{Float32Array, Int32Array};
     use datafusion_common::assert_batches_eq;
+    use futures::TryStreamExt;
     use itertools::Itertools;
+    use object_store::path::Path;
+    use std::fs;
     use std::sync::Arc;
+    use tempfile::TempDir;
     use uuid::Uuid;
 
     fn sync_schema() -> (SchemaRef, SyncSchema) {
@@ -935,6 +1523,21 @@ mod tests {
         )
     }
 
+    fn sync_cmd(
+        path: &str,
+        origin: &str,
+        sequence_number: Option<SequenceNumber>,
+    ) -> DataSyncCommand {
+        DataSyncCommand {
+            path: path.to_string(),
+            store: None,
+            column_descriptors: vec![],
+            origin: origin.to_string(),
+            sequence_number,
+            operation: None,
+        }
+    }
+
     // Create a randomly sized vector of random record batches with
     // a pre-defined schema
     fn random_batches(schema: SchemaRef) -> Vec<RecordBatch> {
@@ -1061,10 +1664,14 @@ mod tests {
             sync_mgr
                 .enqueue_sync(
                     log_store,
-                    sequence.map(|seq| seq as SequenceNumber),
-                    origin.clone(),
+                    &sync_cmd(
+                        table_name,
+                        &origin,
+                        sequence.map(|seq| seq as SequenceNumber),
+                    ),
                     sync_schema.clone(),
                     random_batches(arrow_schema.clone()),
+                    None,
                 )
                 .unwrap();
 
@@ -1167,10 +1774,14 @@ mod tests {
             sync_mgr
                 .enqueue_sync(
                     log_store,
-                    sequence.map(|seq| seq as SequenceNumber),
-                    origin.to_string(),
+                    &sync_cmd(
+                        table_name,
+                        origin,
+                        sequence.map(|seq| seq as SequenceNumber),
+                    ),
                     sync_schema.clone(),
                     random_batches(arrow_schema.clone()),
+                    None,
                 )
                 .unwrap();
         }
@@ -1200,10 +1811,10 @@ mod tests {
         sync_mgr
             .enqueue_sync(
                 log_store.clone(),
-                None,
-                A.to_string(),
+                &sync_cmd("test_table", A, None),
                 sync_schema.clone(),
                 random_batches(arrow_schema.clone()),
+                None,
             )
             .unwrap();
 
@@ -1211,10 +1822,10 @@ mod tests {
         sync_mgr
             .enqueue_sync(
                 log_store.clone(),
-                Some(100),
-                A.to_string(),
+                &sync_cmd("test_table", A, Some(100)),
                 SyncSchema::empty(),
                 vec![],
+                None,
             )
             .unwrap();
 
@@ -1223,10 +1834,10 @@ mod tests {
         let err = sync_mgr
             .enqueue_sync(
                 log_store,
-                Some(200),
-                A.to_string(),
+                &sync_cmd("test_table", A, Some(200)),
                 SyncSchema::empty(),
                 vec![],
+                None,
             )
             .unwrap_err();
         assert!(err.to_string().contains(
@@ -1242,6 +1853,654 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_sync_wal_replay() {
+        let ctx = Arc::new(in_memory_context().await);
+        let dir = TempDir::new().unwrap();
+        let wal_dir = dir.path().join("sync_wal");
+        let wal_segments = || fs::read_dir(&wal_dir).unwrap().count();
+        let (arrow_schema, sync_schema) = sync_schema();
+
+        let enqueue = |sync_mgr: &mut SeafowlDataSyncWriter,
+                       table_name: &str,
+                       sequence: Option<SequenceNumber>,
+                       empty: bool| {
+            let log_store = ctx
+                .get_internal_object_store()
+                .unwrap()
+                .get_log_store(table_name);
+            let (sync_schema, batches) = if empty {
+                (SyncSchema::empty(), vec![])
+            } else {
+                (sync_schema.clone(), random_batches(arrow_schema.clone()))
+            };
+            sync_mgr
+                .enqueue_sync(
+                    log_store,
+                    &sync_cmd(table_name, A, sequence),
+                    sync_schema,
+                    batches,
+                    None,
+                )
+                .unwrap();
+        };
+        let pending = |sync_mgr: &SeafowlDataSyncWriter| {
+            sync_mgr
+                .syncs
+                .iter()
+                .map(|(url, collection)| {
+                    let syncs = collection
+                        .syncs
+                        .iter()
+                        .map(|sync| (sync.sync_schema.clone(), sync.batch.clone()))
+                        .collect::<Vec<_>>();
+                    (url.clone(), syncs)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut sync_mgr = SeafowlDataSyncWriter::new(ctx.clone())
+            .with_wal(wal_dir.clone())
+            .await
+            .unwrap();
+        enqueue(&mut sync_mgr, T1, None, false);
+        enqueue(&mut sync_mgr, T2, Some(1), false);
+        enqueue(&mut sync_mgr, T1, None, false);
+        enqueue(&mut sync_mgr, T1, Some(2), true);
+        assert_eq!(wal_segments(), 4);
+        assert_eq!(sync_mgr.stored_sequences(&A.to_string()), (Some(2), None));
+
+        // Simulate a restart; all acknowledged syncs should be back in memory
+        let expected = pending(&sync_mgr);
+        drop(sync_mgr);
+        let mut sync_mgr = SeafowlDataSyncWriter::new(ctx.clone())
+            .with_wal(wal_dir.clone())
+            .await
+            .unwrap();
+        assert_eq!(sync_mgr.stored_sequences(&A.to_string()), (Some(2), None));
+        assert_eq!(sync_mgr.txs.len(), 2);
+        assert_eq!(pending(&sync_mgr), expected);
+
+        enqueue(&mut sync_mgr, T3, Some(3), false);
+        assert_eq!(wal_segments(), 5);
+
+        // Segments are only removed once their transaction is durable
+        let url = sync_mgr.syncs.first().unwrap().0.clone();
+        sync_mgr.flush_syncs(url).await.unwrap();
+        assert_eq!(sync_mgr.stored_sequences(&A.to_string()), (Some(3), None));
+        assert_eq!(wal_segments(), 5);
+
+        let url = sync_mgr.syncs.first().unwrap().0.clone();
+        sync_mgr.flush_syncs(url).await.unwrap();
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(3), Some(2))
+        );
+        assert_eq!(wal_segments(), 1);
+
+        let url = sync_mgr.syncs.first().unwrap().0.clone();
+        sync_mgr.flush_syncs(url).await.unwrap();
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(3), Some(3))
+        );
+        assert_eq!(wal_segments(), 0);
+
+        // The trailing messages of a transaction that wasn't completed before the restart are
+        // discarded, as the origin re-sends the whole transaction
+        enqueue(&mut sync_mgr, T1, Some(4), false);
+        enqueue(&mut sync_mgr, T2, None, false);
+        enqueue(&mut sync_mgr, T1, None, false);
+        assert_eq!(wal_segments(), 3);
+
+        drop(sync_mgr);
+        let sync_mgr = SeafowlDataSyncWriter::new(ctx.clone())
+            .with_wal(wal_dir.clone())
+            .await
+            .unwrap();
+        assert_eq!(wal_segments(), 1);
+        assert_eq!(sync_mgr.stored_sequences(&A.to_string()), (Some(4), None));
+        assert_eq!(sync_mgr.syncs.len(), 1);
+        assert_eq!(sync_mgr.syncs.first().unwrap().1.syncs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_transactional() {
+        let mut ctx = in_memory_context().await;
+        ctx.config.misc.sync_conf.transactional = true;
+        let ctx = Arc::new(ctx);
+        let mut sync_mgr = SeafowlDataSyncWriter::new(ctx.clone());
+        let (arrow_schema, sync_schema) = sync_schema();
+
+        let table_uuids = HashMap::from([
+            (T1, Uuid::new_v4()),
+            (T2, Uuid::new_v4()),
+            (T3, Uuid::new_v4()),
+        ]);
+        let log_store = |table_name: &str| {
+            ctx.get_internal_object_store()
+                .unwrap()
+                .get_log_store(&table_uuids[table_name].to_string())
+        };
+        let url = |table_name: &str| log_store(table_name).root_uri();
+
+        // The sync mechanism doesn't register the table, so for the sake of testing do it here
+        ctx.metastore
+            .tables
+            .create(
+                &ctx.default_catalog,
+                &ctx.default_schema,
+                T1,
+                &Schema::new(vec![
+                    Field::new("c1", DataType::Int32, true),
+                    Field::new("c2", DataType::Float32, true),
+                ]),
+                table_uuids[T1],
+            )
+            .await
+            .unwrap();
+        let t1_rows = || {
+            let ctx = ctx.clone();
+            async move {
+                let results = ctx
+                    .collect(
+                        ctx.plan_query(&format!("SELECT * FROM {T1}"))
+                            .await
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                results.iter().map(|batch| batch.num_rows()).sum::<usize>()
+            }
+        };
+
+        // Transaction 1 touches T1 and T2, transaction 2 T3 and the (incomplete) transaction 3
+        // touches T1 and T3
+        for (table_name, sequence, new_pk) in [
+            (T1, None, 1),
+            (T2, Some(1), 1),
+            (T3, Some(2), 1),
+            (T1, None, 2),
+            (T3, None, 2),
+        ] {
+            let batch = RecordBatch::try_new(
+                arrow_schema.clone(),
+                vec![
+                    Arc::new(Int32Array::new_null(1)),
+                    Arc::new(Int32Array::from(vec![new_pk])),
+                    Arc::new(Float32Array::new_null(1)),
+                ],
+            )
+            .unwrap();
+            sync_mgr
+                .enqueue_sync(
+                    log_store(table_name),
+                    &sync_cmd(&table_uuids[table_name].to_string(), A, sequence),
+                    sync_schema.clone(),
+                    vec![batch],
+                    None,
+                )
+                .unwrap();
+        }
+
+        // All tables are linked through the transactions
+        assert_eq!(sync_mgr.tx_group(&url(T2)), vec![url(T1), url(T2), url(T3)]);
+
+        // T1 has been committed to, but transaction 1 isn't durable until T2 is flushed as well
+        sync_mgr.flush_syncs(url(T1)).await.unwrap();
+        assert_eq!(
+            ctx.pinned_versions.snapshot(),
+            HashMap::from([(url(T1), 0)])
+        );
+        assert_eq!(t1_rows().await, 0);
+
+        // Transaction 1 is now durable, but transaction 3 (also in the new T1 version) isn't
+        sync_mgr.flush_syncs(url(T2)).await.unwrap();
+        sync_mgr.flush_syncs(url(T3)).await.unwrap();
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(2), Some(2))
+        );
+        assert_eq!(
+            ctx.pinned_versions.snapshot(),
+            HashMap::from([(url(T1), 0), (url(T3), 0)])
+        );
+        assert_eq!(t1_rows().await, 0);
+
+        // Once transaction 3 is complete and durable all of its changes become visible
+        let batch = RecordBatch::try_new(
+            arrow_schema.clone(),
+            vec![
+                Arc::new(Int32Array::new_null(1)),
+                Arc::new(Int32Array::from(vec![2])),
+                Arc::new(Float32Array::new_null(1)),
+            ],
+        )
+        .unwrap();
+        sync_mgr
+            .enqueue_sync(
+                log_store(T2),
+                &sync_cmd(&table_uuids[T2].to_string(), A, Some(3)),
+                sync_schema.clone(),
+                vec![batch],
+                None,
+            )
+            .unwrap();
+        sync_mgr.flush_syncs(url(T2)).await.unwrap();
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(3), Some(3))
+        );
+        assert!(ctx.pinned_versions.snapshot().is_empty());
+        assert_eq!(t1_rows().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_sync_transactional_restart() {
+        let mut ctx = in_memory_context().await;
+        ctx.config.misc.sync_conf.transactional = true;
+        let ctx = Arc::new(ctx);
+        let dir = TempDir::new().unwrap();
+        let wal_dir = dir.path().join("sync_wal");
+        let mut sync_mgr = SeafowlDataSyncWriter::new(ctx.clone())
+            .with_wal(wal_dir.clone())
+            .await
+            .unwrap();
+        let (arrow_schema, sync_schema) = sync_schema();
+
+        let table_uuids = HashMap::from([(T1, Uuid::new_v4()), (T2, Uuid::new_v4())]);
+        let log_store = |table_name: &str| {
+            ctx.get_internal_object_store()
+                .unwrap()
+                .get_log_store(&table_uuids[table_name].to_string())
+        };
+        let url = |table_name: &str| log_store(table_name).root_uri();
+
+        ctx.metastore
+            .tables
+            .create(
+                &ctx.default_catalog,
+                &ctx.default_schema,
+                T1,
+                &Schema::new(vec![
+                    Field::new("c1", DataType::Int32, true),
+                    Field::new("c2", DataType::Float32, true),
+                ]),
+                table_uuids[T1],
+            )
+            .await
+            .unwrap();
+        let t1_rows = || {
+            let ctx = ctx.clone();
+            async move {
+                let results = ctx
+                    .collect(
+                        ctx.plan_query(&format!("SELECT * FROM {T1}"))
+                            .await
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                results.iter().map(|batch| batch.num_rows()).sum::<usize>()
+            }
+        };
+
+        // A transaction touching T1 and T2
+        for (table_name, sequence) in [(T1, None), (T2, Some(1))] {
+            let batch = RecordBatch::try_new(
+                arrow_schema.clone(),
+                vec![
+                    Arc::new(Int32Array::new_null(1)),
+                    Arc::new(Int32Array::from(vec![1])),
+                    Arc::new(Float32Array::new_null(1)),
+                ],
+            )
+            .unwrap();
+            sync_mgr
+                .enqueue_sync(
+                    log_store(table_name),
+                    &sync_cmd(&table_uuids[table_name].to_string(), A, sequence),
+                    sync_schema.clone(),
+                    vec![batch],
+                    None,
+                )
+                .unwrap();
+        }
+
+        sync_mgr.flush_syncs(url(T1)).await.unwrap();
+        assert_eq!(
+            ctx.pinned_versions.snapshot(),
+            HashMap::from([(url(T1), 0)])
+        );
+
+        // Simulate a restart before T2 gets flushed; T1 stays pinned until the transaction is
+        // durable
+        drop(sync_mgr);
+        ctx.pinned_versions.replace(HashMap::new());
+        let mut sync_mgr = SeafowlDataSyncWriter::new(ctx.clone())
+            .with_wal(wal_dir.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            ctx.pinned_versions.snapshot(),
+            HashMap::from([(url(T1), 0)])
+        );
+        assert_eq!(t1_rows().await, 0);
+
+        while let Some(url) = sync_mgr.syncs.keys().next().cloned() {
+            sync_mgr.flush_syncs(url).await.unwrap();
+        }
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(1), Some(1))
+        );
+        assert!(ctx.pinned_versions.snapshot().is_empty());
+        assert_eq!(t1_rows().await, 1);
+
+        // The released pins are persisted as well
+        drop(sync_mgr);
+        SeafowlDataSyncWriter::new(ctx.clone())
+            .with_wal(wal_dir)
+            .await
+            .unwrap();
+        assert!(ctx.pinned_versions.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_table_operations() {
+        let ctx = Arc::new(in_memory_context().await);
+        let mut sync_mgr = SeafowlDataSyncWriter::new(ctx.clone());
+        let (arrow_schema, sync_schema) = sync_schema();
+
+        let table_uuids = HashMap::from([
+            (T1, Uuid::new_v4()),
+            (T2, Uuid::new_v4()),
+            (T3, Uuid::new_v4()),
+        ]);
+        let log_store = |table_name: &str| {
+            ctx.get_internal_object_store()
+                .unwrap()
+                .get_log_store(&table_uuids[table_name].to_string())
+        };
+        for table_name in [T1, T2, T3] {
+            ctx.metastore
+                .tables
+                .create(
+                    &ctx.default_catalog,
+                    &ctx.default_schema,
+                    table_name,
+                    &Schema::new(vec![
+                        Field::new("c1", DataType::Int32, true),
+                        Field::new("c2", DataType::Float32, true),
+                    ]),
+                    table_uuids[table_name],
+                )
+                .await
+                .unwrap();
+        }
+        let pks = |table_name: &str| {
+            let ctx = ctx.clone();
+            let query = format!("SELECT c1 FROM {table_name} ORDER BY c1");
+            async move {
+                let results = ctx
+                    .collect(ctx.plan_query(&query).await.unwrap())
+                    .await
+                    .unwrap();
+                results
+                    .iter()
+                    .flat_map(|batch| {
+                        batch
+                            .column(0)
+                            .as_any()
+                            .downcast_ref::<Int32Array>()
+                            .unwrap()
+                            .values()
+                            .to_vec()
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let mut enqueue =
+            |table_name: &str,
+             sequence: Option<SequenceNumber>,
+             change: Result<Vec<i32>, (Operation, TableOperation)>| {
+                let mut cmd = sync_cmd(&table_uuids[table_name].to_string(), A, sequence);
+                let (sync_schema, batches, operation) = match change {
+                    Ok(new_pks) => {
+                        let rows = new_pks.len();
+                        let batch = RecordBatch::try_new(
+                            arrow_schema.clone(),
+                            vec![
+                                Arc::new(Int32Array::new_null(rows)),
+                                Arc::new(Int32Array::from(new_pks)),
+                                Arc::new(Float32Array::new_null(rows)),
+                            ],
+                        )
+                        .unwrap();
+                        (sync_schema.clone(), vec![batch], None)
+                    }
+                    Err((operation, table_operation)) => {
+                        cmd.operation = Some(operation);
+                        (SyncSchema::empty(), vec![], Some(table_operation))
+                    }
+                };
+                sync_mgr
+                    .enqueue_sync(
+                        log_store(table_name),
+                        &cmd,
+                        sync_schema,
+                        batches,
+                        operation,
+                    )
+                    .unwrap();
+            };
+
+        // The truncate only wipes the rows preceding it
+        enqueue(T1, None, Ok(vec![1, 2]));
+        enqueue(
+            T1,
+            None,
+            Err((
+                Operation::Truncate(TruncateTable {}),
+                TableOperation::Truncate,
+            )),
+        );
+        enqueue(T1, Some(1), Ok(vec![3]));
+
+        // The rename gets applied before the changes to the new location
+        enqueue(
+            T1,
+            Some(2),
+            Err((
+                Operation::Rename(RenameTable {
+                    new_path: table_uuids[T2].to_string(),
+                }),
+                TableOperation::Rename(log_store(T2)),
+            )),
+        );
+        enqueue(T2, Some(3), Ok(vec![4]));
+        drop(enqueue);
+
+        // Flushing the target of the rename flushes all pending changes of the source first
+        sync_mgr
+            .flush_syncs(log_store(T2).root_uri())
+            .await
+            .unwrap();
+        assert!(sync_mgr.syncs.is_empty());
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(3), Some(3))
+        );
+        assert!(!log_store(T1).is_delta_table_location().await.unwrap());
+        assert_eq!(pks(T2).await, vec![3, 4]);
+
+        // Finally drop the table
+        let cmd = DataSyncCommand {
+            operation: Some(Operation::Drop(DropTable {})),
+            ..sync_cmd(&table_uuids[T2].to_string(), A, Some(4))
+        };
+        sync_mgr
+            .enqueue_sync(
+                log_store(T2),
+                &cmd,
+                SyncSchema::empty(),
+                vec![],
+                Some(TableOperation::Drop),
+            )
+            .unwrap();
+        sync_mgr
+            .flush_syncs(log_store(T2).root_uri())
+            .await
+            .unwrap();
+        assert_eq!(
+            sync_mgr.stored_sequences(&A.to_string()),
+            (Some(4), Some(4))
+        );
+        assert!(!log_store(T2).is_delta_table_location().await.unwrap());
+
+        // Row changes can't be combined with table operations
+        let err = sync_mgr
+            .enqueue_sync(
+                log_store(T1),
+                &cmd,
+                sync_schema.clone(),
+                random_batches(arrow_schema.clone()),
+                Some(TableOperation::Drop),
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can't be combined with row changes"));
+
+        // Table operations can't target the root of the store or a table in the catalog
+        let drop_err = |path: String| {
+            let ctx = ctx.clone();
+            async move {
+                let cmd = DataSyncCommand {
+                    operation: Some(Operation::Drop(DropTable {})),
+                    ..sync_cmd(&path, A, None)
+                };
+                sync_operation(&ctx, &cmd).await.unwrap_err().to_string()
+            }
+        };
+        assert!(drop_err(String::new())
+            .await
+            .contains("require a non-empty table path"));
+        assert!(drop_err(table_uuids[T1].to_string())
+            .await
+            .contains("registered in the catalog as table_1"));
+
+        let rows = |new_pks: Vec<i32>| {
+            RecordBatch::try_new(
+                arrow_schema.clone(),
+                vec![
+                    Arc::new(Int32Array::new_null(new_pks.len())),
+                    Arc::new(Int32Array::from(new_pks.clone())),
+                    Arc::new(Float32Array::new_null(new_pks.len())),
+                ],
+            )
+            .unwrap()
+        };
+        let rename = |sequence| DataSyncCommand {
+            operation: Some(Operation::Rename(RenameTable {
+                new_path: table_uuids[T3].to_string(),
+            })),
+            ..sync_cmd(&table_uuids[T1].to_string(), A, Some(sequence))
+        };
+        sync_mgr
+            .enqueue_sync(
+                log_store(T1),
+                &sync_cmd(&table_uuids[T1].to_string(), A, Some(5)),
+                sync_schema.clone(),
+                vec![rows(vec![5, 6])],
+                None,
+            )
+            .unwrap();
+        sync_mgr
+            .flush_syncs(log_store(T1).root_uri())
+            .await
+            .unwrap();
+
+        // Simulate a rename interrupted after copying the table, while deleting the source files
+        let source_store = log_store(T1).object_store();
+        let target_store = log_store(T3).object_store();
+        let paths = source_store
+            .list(None)
+            .map_ok(|meta| meta.location)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        for path in &paths {
+            let data = source_store.get(path).await.unwrap().bytes().await.unwrap();
+            target_store.put(path, data.into()).await.unwrap();
+        }
+        RenameMarker {
+            source: log_store(T1).root_uri(),
+            copied: true,
+        }
+        .put(target_store.as_ref())
+        .await
+        .unwrap();
+        for path in paths
+            .iter()
+            .filter(|path| !path.prefix_matches(&Path::from("_delta_log")))
+        {
+            source_store.delete(path).await.unwrap();
+        }
+        assert!(log_store(T1).is_delta_table_location().await.unwrap());
+
+        // Re-applying the rename completes it, without copying the partial source over
+        sync_mgr
+            .enqueue_sync(
+                log_store(T1),
+                &rename(6),
+                SyncSchema::empty(),
+                vec![],
+                Some(TableOperation::Rename(log_store(T3))),
+            )
+            .unwrap();
+        sync_mgr
+            .flush_syncs(log_store(T1).root_uri())
+            .await
+            .unwrap();
+        assert_eq!(pks(T3).await, vec![5, 6]);
+        assert!(!log_store(T1).is_delta_table_location().await.unwrap());
+        assert!(RenameMarker::get(target_store.as_ref())
+            .await
+            .unwrap()
+            .is_none());
+
+        // A rename onto an existing table is rejected instead of overwriting it
+        sync_mgr
+            .enqueue_sync(
+                log_store(T1),
+                &sync_cmd(&table_uuids[T1].to_string(), A, Some(7)),
+                sync_schema.clone(),
+                vec![rows(vec![7])],
+                None,
+            )
+            .unwrap();
+        sync_mgr
+            .enqueue_sync(
+                log_store(T1),
+                &rename(8),
+                SyncSchema::empty(),
+                vec![],
+                Some(TableOperation::Rename(log_store(T3))),
+            )
+            .unwrap();
+        let err = sync_mgr
+            .flush_syncs(log_store(T1).root_uri())
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("since a table already exists there"));
+        assert_eq!(pks(T1).await, vec![7]);
+        assert_eq!(pks(T3).await, vec![5, 6]);
+    }
+
     #[rstest]
     #[case(100, 50)]
     #[case(50, 100)]
@@ -1290,10 +2549,10 @@ mod tests {
             sync_mgr
                 .enqueue_sync(
                     log_store.clone(),
-                    Some(seq as SequenceNumber),
-                    A.to_string(),
+                    &sync_cmd(&table_uuid.to_string(), A, Some(seq as SequenceNumber)),
                     sync_schema.clone(),
                     vec![batch],
+                    None,
                 )
                 .unwrap();
 
@@ -1344,10 +2603,14 @@ mod tests {
             sync_mgr
                 .enqueue_sync(
                     log_store.clone(),
-                    Some(start_seq + seq as SequenceNumber),
-                    A.to_string(),
+                    &sync_cmd(
+                        &table_uuid.to_string(),
+                        A,
+                        Some(start_seq + seq as SequenceNumber),
+                    ),
                     sync_schema.clone(),
                     vec![batch],
+                    None,
                 )
                 .unwrap();
 
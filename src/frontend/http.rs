@@ -2,10 +2,11 @@ use arrow::error::ArrowError;
 use datafusion::error::DataFusionError;
 use std::fmt::Debug;
 use std::io::Write;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{net::SocketAddr, sync::Arc};
 use warp::{hyper, Rejection};
 
+use arrow::ipc::writer::StreamWriter;
 use arrow::json::writer::{LineDelimited, WriterBuilder};
 use arrow::record_batch::RecordBatch;
 #[cfg(feature = "frontend-arrow-flight")]
@@ -14,14 +15,18 @@ use arrow_flight::flight_service_client::FlightServiceClient;
 use arrow_schema::SchemaRef;
 use bytes::Buf;
 
-use datafusion::datasource::DefaultTableSource;
+use datafusion::datasource::{DefaultTableSource, ViewTable};
+use datafusion::parquet::arrow::ArrowWriter;
 
 use datafusion::physical_plan::ExecutionPlan;
-use datafusion_common::tree_node::{TreeNode, TreeNodeRecursion, TreeNodeVisitor};
-use datafusion_expr::logical_plan::{LogicalPlan, TableScan};
+use datafusion_common::tree_node::{
+    Transformed, TreeNode, TreeNodeRecursion, TreeNodeVisitor,
+};
+use datafusion_common::TableReference;
+use datafusion_expr::logical_plan::{Extension, LogicalPlan, Projection, TableScan};
 use deltalake::parquet::data_type::AsBytes;
 use deltalake::DeltaTable;
-use futures::{future, Future, StreamExt};
+use futures::{future, stream, Future, StreamExt};
 use hex::encode;
 use metrics::counter;
 use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
@@ -37,14 +42,19 @@ use warp::reply::{with_header, Response};
 use warp::{hyper::header, hyper::StatusCode, Filter, Reply};
 
 use super::http_utils::{handle_rejection, into_response, ApiError};
-use crate::auth::{token_to_principal, AccessPolicy, Action, UserContext};
+use crate::audit::{AuditEvent, AuditFrontend, AuditOperation, AuditTableVersion};
+use crate::auth::{
+    token_to_principal, AccessPolicy, Action, Principal, Resource, UserContext,
+};
 use crate::catalog::DEFAULT_DB;
 use crate::config::context::HTTP_REQUESTS;
 use crate::config::schema::{AccessSettings, HttpFrontend, MEBIBYTES};
 use crate::{
     config::schema::str_to_hex_hash,
-    context::logical::{is_read_only, is_statement_read_only},
+    context::logical::{is_read_only, is_statement_read_only, write_targets},
     context::SeafowlContext,
+    nodes::SeafowlExtensionNode,
+    queries::{ExecutionLimits, QueryGuard},
 };
 
 const QUERY_HEADER: &str = "X-Seafowl-Query";
@@ -53,16 +63,29 @@ const BEARER_PREFIX: &str = "Bearer ";
 // caching it for as long as possible.
 const CORS_MAXAGE: u32 = 86400;
 const QUERY_TIME_HEADER: &str = "X-Seafowl-Query-Time";
-
+// Per-request execution limits, which can only be stricter than the configured ones
+const STATEMENT_TIMEOUT_HEADER: &str = "X-Seafowl-Statement-Timeout";
+const MAX_RESULT_ROWS_HEADER: &str = "X-Seafowl-Max-Result-Rows";
+const MAX_RESULT_BYTES_HEADER: &str = "X-Seafowl-Max-Result-Bytes";
+/// Reserved key of the last JSON Lines record, denoting that the query failed mid-stream
+pub const ERROR_RECORD_KEY: &str = "__seafowl_error";
+
+// Vary on Accept, since the output format is negotiated via the Accept header.
 // Vary on Origin, as warp's CORS responds with Access-Control-Allow-Origin: [origin],
 // so we can't cache the response in the browser if the origin changes.
 // NB: Cloudflare doesn't take the vary values into account in caching decisions:
 // https://developers.cloudflare.com/cache/about/cache-control/#other
-const VARY: &str = "Authorization, Content-Type, Origin, X-Seafowl-Query";
+const VARY: &str = "Accept, Authorization, Content-Type, Origin, X-Seafowl-Query";
 
 #[derive(Default)]
 struct ETagBuilderVisitor {
     table_versions: Vec<u8>,
+    // All the tables (and views) that the plan scans, regardless of their provider, so that
+    // reading them can be authorized
+    tables: Vec<TableReference>,
+    // Whether access policies restricted the plan, in which case the rows and values returned
+    // depend on the user and on the policy definitions, not just on the table versions
+    restricted: bool,
 }
 
 impl TreeNodeVisitor<'_> for ETagBuilderVisitor {
@@ -72,7 +95,12 @@ impl TreeNodeVisitor<'_> for ETagBuilderVisitor {
         &mut self,
         plan: &LogicalPlan,
     ) -> Result<TreeNodeRecursion, DataFusionError> {
-        if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
+        if let LogicalPlan::TableScan(TableScan {
+            table_name, source, ..
+        }) = plan
+        {
+            self.tables.push(table_name.clone());
+
             // TODO handle external Parquet tables too
             if let Some(default_table_source) =
                 source.as_any().downcast_ref::<DefaultTableSource>()
@@ -86,24 +114,112 @@ impl TreeNodeVisitor<'_> for ETagBuilderVisitor {
                         .extend(table.table_uri().as_bytes().to_vec());
                     self.table_versions
                         .extend(table.version().as_bytes().to_vec());
+                } else if let Some(view) = default_table_source
+                    .table_provider
+                    .as_any()
+                    .downcast_ref::<ViewTable>()
+                {
+                    // Views are inlined only during optimization, so we need to visit the
+                    // underlying plan ourselves
+                    if let Some(definition) = view.definition() {
+                        self.table_versions.extend(definition.as_bytes().to_vec());
+                    }
+                    view.logical_plan().visit_with_subqueries(self)?;
                 }
             }
+        } else if self.restricted {
+            // Account for the row filters and column masks that the policies injected
+            match plan {
+                LogicalPlan::Filter(filter) => self
+                    .table_versions
+                    .extend(filter.predicate.to_string().as_bytes().to_vec()),
+                LogicalPlan::Projection(Projection { expr, .. }) => {
+                    for expr in expr {
+                        self.table_versions
+                            .extend(expr.to_string().as_bytes().to_vec());
+                    }
+                }
+                _ => {}
+            }
         }
         Ok(TreeNodeRecursion::Continue)
     }
 }
 
-fn plan_to_etag(plan: &LogicalPlan) -> String {
-    let mut visitor = ETagBuilderVisitor::default();
-    plan.visit(&mut visitor).unwrap();
+fn plan_to_etag(plan: &LogicalPlan, restricted: bool, format: ResultFormat) -> String {
+    let mut visitor = ETagBuilderVisitor {
+        restricted,
+        ..Default::default()
+    };
+    plan.visit_with_subqueries(&mut visitor).unwrap();
 
     debug!("Extracted table versions: {:?}", visitor.table_versions);
 
     let mut hasher = Sha256::new();
     hasher.update(json!(visitor.table_versions).to_string());
+    // The same results get encoded differently depending on the format, so each one needs its
+    // own ETag
+    if let Some(etag_key) = format.etag_key() {
+        hasher.update(etag_key);
+    }
     encode(hasher.finalize())
 }
 
+// Check that the user is allowed to read all the tables that the plan scans, and to write to the
+// tables that it modifies (or the whole database, if the plan isn't scoped to particular tables).
+// This only makes a difference for named tokens, as their grants are limited to some resources.
+pub(crate) fn authorize_plan(
+    context: &SeafowlContext,
+    user_context: &UserContext,
+    plan: &LogicalPlan,
+) -> Result<(), ApiError> {
+    let to_resource = |table: &TableReference| {
+        let resolved = table
+            .clone()
+            .resolve(&context.default_catalog, &context.default_schema);
+        Resource::Schema {
+            database: resolved.catalog.to_string(),
+            schema: resolved.schema.to_string(),
+        }
+    };
+
+    // Subqueries (e.g. `EXISTS (...)` or `IN (SELECT ...)`) can read tables too
+    let mut visitor = ETagBuilderVisitor::default();
+    plan.visit_with_subqueries(&mut visitor)?;
+    if let LogicalPlan::Extension(Extension { node }) = plan
+        && let Some(input) =
+            SeafowlExtensionNode::from_dynamic(node).and_then(|node| node.data_input())
+    {
+        input.visit_with_subqueries(&mut visitor)?;
+    }
+    if !visitor
+        .tables
+        .iter()
+        .all(|table| user_context.can_access(Action::Read, &to_resource(table)))
+    {
+        return Err(ApiError::ReadForbidden);
+    }
+
+    if !is_read_only(plan) {
+        let targets = write_targets(plan);
+        let can_write = if targets.is_empty() {
+            user_context.can_access(
+                Action::Write,
+                &Resource::Database(context.default_catalog.clone()),
+            )
+        } else {
+            targets
+                .iter()
+                .all(|table| user_context.can_access(Action::Write, &to_resource(table)))
+        };
+        if !can_write {
+            return Err(ApiError::WriteForbidden);
+        }
+    }
+
+    Ok(())
+}
+
 // Construct a content-type header value that also includes schema information.
 fn content_type_with_schema(schema: SchemaRef) -> HeaderValue {
     let schema_string = serde_json::to_string(&schema).unwrap();
@@ -119,36 +235,253 @@ fn content_type_with_schema(schema: SchemaRef) -> HeaderValue {
         })
 }
 
+/// Output format of the query results, negotiated either through the extension in the path of
+/// the cached GET endpoint, or through the `Accept` header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// JSON Lines, with the schema passed in the `arrow-schema` content-type parameter
+    #[default]
+    Json,
+    /// Arrow IPC streaming format; the schema is the first message of the stream
+    ArrowStream,
+    /// A Parquet file; the schema is stored in the file footer
+    Parquet,
+    /// CSV with a header row
+    Csv,
+}
+
+impl ResultFormat {
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "json" | "jsonl" => Some(Self::Json),
+            "arrow" | "arrows" => Some(Self::ArrowStream),
+            "parquet" => Some(Self::Parquet),
+            "csv" => Some(Self::Csv),
+            // Other extensions (e.g. `.bin`) are only there to coax CDNs into caching the response
+            _ => None,
+        }
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "*/*"
+            | "application/*"
+            | "application/json"
+            | "application/jsonl"
+            | "application/x-ndjson" => Some(Self::Json),
+            "application/vnd.apache.arrow.stream" => Some(Self::ArrowStream),
+            "application/vnd.apache.parquet" | "application/x-parquet" => {
+                Some(Self::Parquet)
+            }
+            "text/csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+
+    /// Pick the supported format with the highest quality value from an `Accept` header. Ties
+    /// are resolved in favour of the media range listed first, and we fall back to JSON if none of
+    /// the listed media ranges are supported.
+    fn from_accept_header(accept: &str) -> Self {
+        accept
+            .split(',')
+            .filter_map(|media_range| {
+                let mut params = media_range.split(';');
+                let media_type = params.next()?.trim().to_ascii_lowercase();
+                let quality = params
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .next()
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                Self::from_media_type(&media_type)
+                    .filter(|_| quality > 0.0)
+                    .map(|format| (format, quality))
+            })
+            .fold(
+                None,
+                |best: Option<(Self, f32)>, (format, quality)| match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((format, quality)),
+                },
+            )
+            .map(|(format, _)| format)
+            .unwrap_or_default()
+    }
+
+    /// Negotiate the format, with the path extension taking precedence over the `Accept` header
+    /// (CDNs typically ignore `Vary` and use the URL as the cache key).
+    fn negotiate(extension: Option<&str>, accept: Option<&str>) -> Self {
+        extension
+            .and_then(Self::from_extension)
+            .or_else(|| accept.map(Self::from_accept_header))
+            .unwrap_or_default()
+    }
+
+    /// Stable identifier of the format that goes into the ETags of its results, so that these
+    /// don't change across releases. JSON results keep the ETags they had before the other
+    /// formats were supported.
+    fn etag_key(&self) -> Option<&'static str> {
+        match self {
+            Self::Json => None,
+            Self::ArrowStream => Some("application/vnd.apache.arrow.stream"),
+            Self::Parquet => Some("application/vnd.apache.parquet"),
+            Self::Csv => Some("text/csv"),
+        }
+    }
+
+    fn content_type(&self, schema: SchemaRef) -> HeaderValue {
+        match self {
+            Self::Json => content_type_with_schema(schema),
+            Self::ArrowStream => {
+                HeaderValue::from_static("application/vnd.apache.arrow.stream")
+            }
+            Self::Parquet => HeaderValue::from_static("application/vnd.apache.parquet"),
+            Self::Csv => HeaderValue::from_static("text/csv; header=present"),
+        }
+    }
+
+    /// Encode an error that occurred after the response started streaming. For JSON Lines we emit
+    /// a final record with the reserved [`ERROR_RECORD_KEY`] key. Other formats can't represent
+    /// errors in-band, so we pass the error through, which makes hyper abort the response without
+    /// sending the terminating chunk, so that clients see an incomplete body instead of a valid
+    /// (but truncated) one.
+    fn encode_error(&self, error: ArrowError) -> Result<Vec<u8>, ArrowError> {
+        match self {
+            Self::Json => {
+                let mut buf =
+                    json!({ ERROR_RECORD_KEY: { "message": error.to_string() } })
+                        .to_string()
+                        .into_bytes();
+                buf.push(b'\n');
+                Ok(buf)
+            }
+            _ => Err(error),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct QueryBody {
     query: String,
 }
 
 /// Convert rows from a `RecordBatch` to their JSON Lines byte representation, with newlines at the end
-fn batch_to_json(
-    maybe_batch: Result<RecordBatch, DataFusionError>,
-) -> Result<Vec<u8>, ArrowError> {
-    let batch = maybe_batch?;
+fn batch_to_json(batch: &RecordBatch) -> Result<Vec<u8>, ArrowError> {
     let mut buf = Vec::new();
     let mut writer = WriterBuilder::new()
         .with_explicit_nulls(true)
         .build::<_, LineDelimited>(&mut buf);
-    writer.write(&batch)?;
+    writer.write(batch)?;
+    Ok(buf)
+}
+
+/// Convert rows from a `RecordBatch` to CSV, optionally prefixed with the header row
+fn batch_to_csv(batch: &RecordBatch, header: bool) -> Result<Vec<u8>, ArrowError> {
+    let mut buf = Vec::new();
+    let mut writer = arrow_csv::WriterBuilder::new()
+        .with_header(header)
+        .build(&mut buf);
+    writer.write(batch)?;
+    drop(writer);
     Ok(buf)
 }
 
-// Execute the plan and stream the results
+/// Stateful encoder of the result stream into the negotiated output format.
+///
+/// The binary formats buffer their output in an in-memory `Vec`, which gets drained after each
+/// batch so that the response can be streamed out.
+enum ResultEncoder {
+    Json,
+    ArrowStream(StreamWriter<Vec<u8>>),
+    Parquet(ArrowWriter<Vec<u8>>),
+    Csv { schema: SchemaRef, header: bool },
+}
+
+impl ResultEncoder {
+    fn try_new(format: ResultFormat, schema: SchemaRef) -> Result<Self, ArrowError> {
+        Ok(match format {
+            ResultFormat::Json => Self::Json,
+            ResultFormat::ArrowStream => {
+                Self::ArrowStream(StreamWriter::try_new(Vec::new(), &schema)?)
+            }
+            ResultFormat::Parquet => {
+                Self::Parquet(ArrowWriter::try_new(Vec::new(), schema, None)?)
+            }
+            ResultFormat::Csv => Self::Csv {
+                schema,
+                header: true,
+            },
+        })
+    }
+
+    /// Encode the batch, returning any output bytes that are ready to be sent
+    fn write(&mut self, batch: &RecordBatch) -> Result<Vec<u8>, ArrowError> {
+        match self {
+            Self::Json => batch_to_json(batch),
+            Self::ArrowStream(writer) => {
+                writer.write(batch)?;
+                Ok(std::mem::take(writer.get_mut()))
+            }
+            Self::Parquet(writer) => {
+                // Row groups get flushed out once they reach the max row group size
+                writer.write(batch)?;
+                Ok(std::mem::take(writer.inner_mut()))
+            }
+            Self::Csv { header, .. } => {
+                let buf = batch_to_csv(batch, *header)?;
+                *header = false;
+                Ok(buf)
+            }
+        }
+    }
+
+    /// Return the remaining output bytes, including any format footers
+    fn finish(self) -> Result<Vec<u8>, ArrowError> {
+        match self {
+            Self::Json => Ok(vec![]),
+            Self::ArrowStream(writer) => writer.into_inner(),
+            Self::Parquet(writer) => Ok(writer.into_inner()?),
+            // Make sure we output the header even if there were no rows
+            Self::Csv {
+                schema,
+                header: true,
+            } => batch_to_csv(&RecordBatch::new_empty(schema), true),
+            Self::Csv { .. } => Ok(vec![]),
+        }
+    }
+}
+
+// Execute the plan and stream the results in the requested format
 async fn plan_to_response(
     context: Arc<SeafowlContext>,
     plan: Arc<dyn ExecutionPlan>,
+    format: ResultFormat,
+    query: QueryGuard,
 ) -> Result<Response, DataFusionError> {
-    let stream = context.execute_stream(plan).await?.map(|maybe_batch| {
-        batch_to_json(maybe_batch)
-            // Seems like at this point wrap/hyper don't really handle the stream error well,
-            // i.e. the status code returned is 200 even when stream fails.
-            // To at least make this more transparent convert the error message to payload,
-            // otherwise the client just gets an opaque empty reply.
-            .or_else(|e| Ok::<Vec<u8>, ArrowError>(e.to_string().into_bytes()))
+    let encoder = ResultEncoder::try_new(format, plan.schema())?;
+    let batches = context.execute_query_stream(plan, query).await?;
+
+    let stream = stream::unfold(Some((encoder, batches)), move |state| async move {
+        let (mut encoder, mut batches) = state?;
+        let (chunk, next_state) = match batches.next().await {
+            Some(maybe_batch) => {
+                let chunk = maybe_batch
+                    .map_err(ArrowError::from)
+                    .and_then(|batch| encoder.write(&batch));
+                (chunk, Some((encoder, batches)))
+            }
+            None => (encoder.finish(), None),
+        };
+
+        match chunk {
+            Ok(chunk) => Some((Ok(chunk), next_state)),
+            Err(e) => {
+                // At this point the status code and headers have already been sent, so
+                // signal the error in the body itself and stop streaming the results.
+                warn!("Error while streaming the query results: {e}");
+                Some((format.encode_error(e), None))
+            }
+        }
     });
     let body = hyper::Body::wrap_stream(stream);
     Ok(Response::new(body))
@@ -159,17 +492,63 @@ pub async fn uncached_read_write_query(
     database_name: String,
     user_context: UserContext,
     query: String,
+    accept: Option<String>,
+    requested_limits: ExecutionLimits,
     mut context: Arc<SeafowlContext>,
 ) -> Result<Response, ApiError> {
-    let timer = Instant::now();
-
     // If a specific DB name was used as a parameter in the route, scope the context to it,
     // effectively making it the default DB for the duration of the session.
     if database_name != context.default_catalog {
         context = context.scope_to_catalog(database_name);
     }
 
-    let statements = context.parse_query(&query).await?;
+    let mut event = AuditEvent::query(
+        AuditFrontend::Http,
+        &user_context.principal,
+        &context.default_catalog,
+        &query,
+    );
+    let limits = context.execution_limits(requested_limits);
+    let mut running_query = None;
+    let result = limits
+        .with_timeout(async {
+            running_query =
+                Some(context.start_query(&user_context.principal, &query, limits)?);
+            execute_read_write_query(
+                &user_context,
+                &query,
+                accept,
+                context.clone(),
+                &mut event,
+                &mut running_query,
+            )
+            .await
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.into()));
+    let error = result.as_ref().err().map(|e| e.status_code_body().1);
+    if let Some(running_query) = running_query.as_mut()
+        && let Some(error) = &error
+    {
+        running_query.fail(error);
+    }
+    context.audit(event.with_error(error));
+    result
+}
+
+// The running query is only taken over once the results start streaming, so that the caller can
+// still mark it as failed if we bail out before that
+async fn execute_read_write_query(
+    user_context: &UserContext,
+    query: &str,
+    accept: Option<String>,
+    context: Arc<SeafowlContext>,
+    event: &mut AuditEvent,
+    running_query: &mut Option<QueryGuard>,
+) -> Result<Response, ApiError> {
+    let timer = Instant::now();
+
+    let statements = context.parse_query(query).await?;
 
     // We assume that there's at least one statement throughout the rest of this function
     if statements.is_empty() {
@@ -180,6 +559,7 @@ pub async fn uncached_read_write_query(
         .iter()
         .filter(|s| is_statement_read_only(s))
         .count();
+    event.read_only = reads == statements.len();
 
     // Check for authorization
     if !user_context.can_perform_action(if reads == statements.len() {
@@ -210,18 +590,31 @@ pub async fn uncached_read_write_query(
         let logical = context
             .create_logical_plan_from_statement(statement)
             .await?;
+        let logical = context.apply_access_policies(logical, user_context)?.data;
+        authorize_plan(&context, user_context, &logical)?;
         plan_to_output = Some(context.create_physical_plan(&logical).await?);
+        event
+            .table_versions
+            .extend(context.written_table_versions(&logical).await);
     }
 
     // Stream output for the last statement
     let plan = plan_to_output.expect("at least one statement in the list");
     let schema = plan.schema();
-    let mut response = plan_to_response(context, plan).await?;
+
+    // Only results of read statements get encoded in the requested format
+    let format = if reads > 0 {
+        ResultFormat::negotiate(None, accept.as_deref())
+    } else {
+        ResultFormat::Json
+    };
+    let running_query = running_query.take().expect("query is only executed once");
+    let mut response = plan_to_response(context, plan, format, running_query).await?;
 
     if reads > 0 {
         response
             .headers_mut()
-            .insert(header::CONTENT_TYPE, content_type_with_schema(schema));
+            .insert(header::CONTENT_TYPE, format.content_type(schema));
     }
 
     let elapsed = timer.elapsed().as_millis().to_string();
@@ -264,26 +657,42 @@ pub fn with_auth(
     )
 }
 
+// Extract the execution limits requested by the client
+pub fn with_execution_limits(
+) -> impl Filter<Extract = (ExecutionLimits,), Error = Rejection> + Clone {
+    warp::header::optional::<u64>(STATEMENT_TIMEOUT_HEADER)
+        .and(warp::header::optional::<u64>(MAX_RESULT_ROWS_HEADER))
+        .and(warp::header::optional::<u64>(MAX_RESULT_BYTES_HEADER))
+        .map(
+            |statement_timeout: Option<u64>, max_result_rows, max_result_bytes| {
+                ExecutionLimits {
+                    statement_timeout: statement_timeout.map(Duration::from_millis),
+                    max_result_rows,
+                    max_result_bytes,
+                }
+            },
+        )
+}
+
 // Disable the cached GET endpoint if the reads are disabled. Otherwise extract the principal and
 // check whether it is allowed to perform reads.
 pub fn cached_read_query_authz(
     policy: AccessPolicy,
-) -> impl Filter<Extract = (), Error = Rejection> + Clone {
-    warp::any()
-        .and(with_auth(policy.clone()))
-        .and_then(move |user_context: UserContext| match policy.read {
+) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+    warp::any().and(with_auth(policy.clone())).and_then(
+        move |user_context: UserContext| match policy.read {
             AccessSettings::Off => {
                 future::err(warp::reject::custom(ApiError::ReadOnlyEndpointDisabled))
             }
             _ => {
                 if user_context.can_perform_action(Action::Read) {
-                    future::ok(())
+                    future::ok(user_context)
                 } else {
                     future::err(warp::reject::custom(ApiError::ReadForbidden))
                 }
             }
-        })
-        .untuple_one()
+        },
+    )
 }
 
 /// Supports either one of:
@@ -293,18 +702,26 @@ pub fn cached_read_query_authz(
 ///    3. GET -H "X-Seafowl-Query: [query]" /q/[query hash]
 ///
 /// In all cases the path can have an optional prefix parameter in order do specify a non-default
-/// database as target, e.g. /[database_name]/q/[query]
+/// database as target, e.g. /[database_name]/q/[query], as well as an optional extension
+/// determining the output format, e.g. /q/[query hash].parquet
 pub async fn cached_read_query(
     database_name: String,
     query_or_hash: String,
+    user_context: UserContext,
     maybe_raw_query: Option<String>,
     if_none_match: Option<String>,
+    accept: Option<String>,
+    requested_limits: ExecutionLimits,
     mut context: Arc<SeafowlContext>,
 ) -> Result<Response, ApiError> {
     let timer = Instant::now();
 
-    // Ignore dots at the end
-    let query_or_hash = query_or_hash.split('.').next().unwrap();
+    // Split off the extension; anything after the first dot is not part of the query/hash
+    let (query_or_hash, extension) = match query_or_hash.split_once('.') {
+        Some((query_or_hash, extension)) => (query_or_hash, Some(extension)),
+        None => (query_or_hash.as_str(), None),
+    };
+    let format = ResultFormat::negotiate(extension, accept.as_deref());
 
     let decoded_query = if let Some(raw_query) = maybe_raw_query {
         // If we managed to extract the query from the body or the header, the string from the path
@@ -336,8 +753,61 @@ pub async fn cached_read_query(
         context = context.scope_to_catalog(database_name);
     }
 
+    let mut event = AuditEvent::query(
+        AuditFrontend::Http,
+        &user_context.principal,
+        &context.default_catalog,
+        &decoded_query,
+    );
+    event.read_only = true;
+    let limits = context.execution_limits(requested_limits);
+    let mut running_query = None;
+    let result = limits
+        .with_timeout(async {
+            running_query = Some(context.start_query(
+                &user_context.principal,
+                &decoded_query,
+                limits,
+            )?);
+            execute_cached_read_query(
+                &decoded_query,
+                &user_context,
+                if_none_match,
+                format,
+                context.clone(),
+                timer,
+                &mut running_query,
+            )
+            .await
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.into()));
+    let error = result.as_ref().err().map(|e| e.status_code_body().1);
+    if let Some(running_query) = running_query.as_mut()
+        && let Some(error) = &error
+    {
+        running_query.fail(error);
+    }
+    context.audit(event.with_error(error));
+    result
+}
+
+async fn execute_cached_read_query(
+    query: &str,
+    user_context: &UserContext,
+    if_none_match: Option<String>,
+    format: ResultFormat,
+    context: Arc<SeafowlContext>,
+    timer: Instant,
+    running_query: &mut Option<QueryGuard>,
+) -> Result<Response, ApiError> {
     // Plan the query
-    let plan = context.create_logical_plan(&decoded_query).await?;
+    let plan = context.create_logical_plan(query).await?;
+    let Transformed {
+        data: plan,
+        transformed: restricted,
+        ..
+    } = context.apply_access_policies(plan, user_context)?;
     debug!("Query plan: {:?}", plan);
 
     // Write queries should come in as POST requests
@@ -345,24 +815,37 @@ pub async fn cached_read_query(
         return Err(ApiError::NotReadOnlyQuery);
     };
 
+    authorize_plan(&context, user_context, &plan)?;
+
+    // Results restricted by access policies depend on the user, so shared caches mustn't
+    // store them
+    let with_cache_control = |mut response: Response| {
+        if restricted {
+            response
+                .headers_mut()
+                .insert(header::CACHE_CONTROL, "private".parse().unwrap());
+        }
+        response
+    };
+
     // Pre-execution check: if ETags match, we don't need to re-execute the query
-    let etag = plan_to_etag(&plan);
+    let etag = plan_to_etag(&plan, restricted, format);
     debug!("ETag: {}, if-none-match header: {:?}", etag, if_none_match);
 
     if let Some(if_none_match) = if_none_match {
         if etag == if_none_match {
-            return Ok(warp::reply::with_status(
-                "NOT_MODIFIED",
-                StatusCode::NOT_MODIFIED,
-            )
-            .into_response());
+            return Ok(with_cache_control(
+                warp::reply::with_status("NOT_MODIFIED", StatusCode::NOT_MODIFIED)
+                    .into_response(),
+            ));
         }
     }
 
     // Guess we'll have to actually run the query
     let physical = context.create_physical_plan(&plan).await?;
     let schema = physical.schema().clone();
-    let mut response = plan_to_response(context, physical).await?;
+    let running_query = running_query.take().expect("query is only executed once");
+    let mut response = plan_to_response(context, physical, format, running_query).await?;
 
     let elapsed = timer.elapsed().as_millis().to_string();
     response
@@ -373,8 +856,39 @@ pub async fn cached_read_query(
         .insert(header::ETAG, etag.parse().unwrap());
     response
         .headers_mut()
-        .insert(header::CONTENT_TYPE, content_type_with_schema(schema));
-    Ok(response)
+        .insert(header::CONTENT_TYPE, format.content_type(schema));
+    Ok(with_cache_control(response))
+}
+
+/// DELETE /q/[query id] or /[database_name]/q/[query id]
+pub async fn cancel_query(
+    database_name: String,
+    query_id: u64,
+    user_context: UserContext,
+    context: Arc<SeafowlContext>,
+) -> Result<Response, ApiError> {
+    let query = context
+        .queries
+        .get(query_id)
+        .filter(|query| query.database == database_name)
+        .ok_or(ApiError::QueryNotFound(query_id))?;
+
+    // Named principals can always cancel their own queries, otherwise write access is required
+    let is_owner = matches!(
+        user_context.principal,
+        Principal::Token(_) | Principal::Jwt(_)
+    ) && user_context.principal.name() == query.principal;
+    if !is_owner
+        && !user_context.can_access(Action::Write, &Resource::Database(database_name))
+    {
+        return Err(ApiError::WriteForbidden);
+    }
+
+    query.cancel();
+    Ok(
+        warp::reply::with_status(format!("Query {query_id} cancelled\n"), StatusCode::OK)
+            .into_response(),
+    )
 }
 
 /// POST /upload/[schema]/[table] or /[database]/upload/[schema]/[table]
@@ -383,10 +897,47 @@ pub async fn upload(
     schema_name: String,
     table_name: String,
     user_context: UserContext,
+    form: FormData,
+    context: Arc<SeafowlContext>,
+) -> Result<Response, ApiError> {
+    let mut event = AuditEvent::new(
+        AuditFrontend::Http,
+        AuditOperation::Upload,
+        &user_context.principal,
+        &database_name,
+    );
+    event.target = Some(format!("{schema_name}.{table_name}"));
+    let result = execute_upload(
+        database_name,
+        schema_name,
+        table_name,
+        &user_context,
+        form,
+        context.clone(),
+        &mut event,
+    )
+    .await;
+    context
+        .audit(event.with_error(result.as_ref().err().map(|e| e.status_code_body().1)));
+    result
+}
+
+async fn execute_upload(
+    database_name: String,
+    schema_name: String,
+    table_name: String,
+    user_context: &UserContext,
     mut form: FormData,
     mut context: Arc<SeafowlContext>,
+    event: &mut AuditEvent,
 ) -> Result<Response, ApiError> {
-    if !user_context.can_perform_action(Action::Write) {
+    if !user_context.can_access(
+        Action::Write,
+        &Resource::Schema {
+            database: database_name.clone(),
+            schema: schema_name.clone(),
+        },
+    ) {
         return Err(ApiError::WriteForbidden);
     };
 
@@ -450,7 +1001,7 @@ pub async fn upload(
 
     // Execute the plan and persist objects as well as table/partition metadata
     let temp_path = ref_temp_file.path();
-    let table = context
+    let (table, rows) = context
         .file_to_table(
             temp_path.display().to_string(),
             file_type,
@@ -460,6 +1011,11 @@ pub async fn upload(
             table_name.clone(),
         )
         .await?;
+    event.rows_affected = rows.map(|rows| rows as u64);
+    event.table_versions.push(AuditTableVersion {
+        table: format!("{}.{schema_name}.{table_name}", context.default_catalog),
+        version: table.version(),
+    });
 
     Ok(warp::reply::with_status(
         Ok::<String, ApiError>(format!(
@@ -506,16 +1062,21 @@ pub fn filters(
     context: Arc<SeafowlContext>,
     config: HttpFrontend,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    let access_policy = AccessPolicy::from_config(&config);
+    let access_policy = AccessPolicy::from_config(&config)
+        .with_jwt_config(context.config.frontend.jwt.as_ref());
 
     let cors = warp::cors()
         .allow_any_origin()
         .allow_headers(vec![
             "X-Seafowl-Query",
+            header::ACCEPT.as_str(),
             header::AUTHORIZATION.as_str(),
             header::CONTENT_TYPE.as_str(),
+            STATEMENT_TIMEOUT_HEADER,
+            MAX_RESULT_ROWS_HEADER,
+            MAX_RESULT_BYTES_HEADER,
         ])
-        .allow_methods(vec!["GET", "POST"])
+        .allow_methods(vec!["GET", "POST", "DELETE"])
         .max_age(CORS_MAXAGE);
 
     let log = warp::log::custom(|info: Info<'_>| {
@@ -574,16 +1135,23 @@ pub fn filters(
         .and(warp::header::optional::<String>(
             header::IF_NONE_MATCH.as_str(),
         ))
+        .and(warp::header::optional::<String>(header::ACCEPT.as_str()))
+        .and(with_execution_limits())
         .and(warp::any().map(move || ctx.clone()))
         .then(cached_read_query)
         .map(move |r: Result<Response, ApiError>| {
             if let Ok(response) = r {
-                with_header(
-                    response,
-                    header::CACHE_CONTROL,
-                    config.cache_control.clone(),
-                )
-                .into_response()
+                if response.headers().contains_key(header::CACHE_CONTROL) {
+                    // Keep the private cache control of responses restricted by policies
+                    response
+                } else {
+                    with_header(
+                        response,
+                        header::CACHE_CONTROL,
+                        config.cache_control.clone(),
+                    )
+                    .into_response()
+                }
             } else {
                 into_response(r)
             }
@@ -605,10 +1173,26 @@ pub fn filters(
                 future::err(warp::reject::custom(ApiError::QueryParsingError(r)))
             }),
         )
+        .and(warp::header::optional::<String>(header::ACCEPT.as_str()))
+        .and(with_execution_limits())
         .and(warp::any().map(move || ctx.clone()))
         .then(uncached_read_write_query)
         .map(into_response);
 
+    // Query cancellation
+    let ctx = context.clone();
+    let cancel_query_route = warp::path!(String / "q" / u64)
+        .or(warp::any()
+            .map(move || DEFAULT_DB.to_string())
+            .and(warp::path!("q" / u64)))
+        .and(warp::path::end())
+        .and(warp::delete())
+        .unify()
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || ctx.clone()))
+        .then(cancel_query)
+        .map(into_response);
+
     // Upload endpoint
     let ctx = context.clone();
     let upload_route = warp::path!(String / "upload" / String / String)
@@ -638,6 +1222,7 @@ pub fn filters(
 
     cached_read_query_route
         .or(uncached_read_write_query_route)
+        .or(cancel_query_route)
         .or(upload_route)
         .or(health_route)
         .with(cors)
@@ -656,14 +1241,35 @@ pub async fn run_server(
     let socket_addr: SocketAddr = format!("{}:{}", config.bind_host, config.bind_port)
         .parse()
         .expect("Error parsing the listen address");
-    let (_, future) =
-        warp::serve(filters).bind_with_graceful_shutdown(socket_addr, shutdown);
-    future.await
+
+    match config.tls_settings.tls().expect("Invalid TLS settings") {
+        None => {
+            let (_, future) =
+                warp::serve(filters).bind_with_graceful_shutdown(socket_addr, shutdown);
+            future.await
+        }
+        Some(tls) => {
+            let mut server = warp::serve(filters)
+                .tls()
+                .cert_path(tls.cert_file)
+                .key_path(tls.key_file);
+            if let Some(client_ca_file) = tls.client_ca_file {
+                server = server.client_auth_required_path(client_ca_file);
+            }
+            let (_, future) = server.bind_with_graceful_shutdown(socket_addr, shutdown);
+            future.await
+        }
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use bytes::Bytes;
+    use arrow::ipc::reader::StreamReader;
+    use arrow::record_batch::RecordBatch;
+    use bytes::{Buf, Bytes};
+    use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use datafusion_common::assert_batches_eq;
+    use futures::StreamExt;
 
     use itertools::Itertools;
 
@@ -678,20 +1284,25 @@ pub mod tests {
     use warp::{Filter, Rejection, Reply};
 
     use warp::http::Response;
-    use warp::hyper::header;
+    use warp::hyper::service::Service;
+    use warp::hyper::{self, header};
     use warp::{
         hyper::{header::IF_NONE_MATCH, StatusCode},
         test::request,
     };
 
-    use crate::auth::AccessPolicy;
+    use crate::auth::{AccessPolicy, Principal};
 
     use crate::catalog::DEFAULT_DB;
-    use crate::config::schema::{str_to_hex_hash, HttpFrontend};
+    use crate::config::schema::{str_to_hex_hash, Grant, GrantAccess, HttpFrontend};
+    use crate::queries::ExecutionLimits;
     use crate::testutils::{assert_header_is_float, schema_from_header};
     use crate::{
         context::{test_utils::in_memory_context, SeafowlContext},
-        frontend::http::{filters, QUERY_HEADER, QUERY_TIME_HEADER},
+        frontend::http::{
+            filters, ResultFormat, MAX_RESULT_ROWS_HEADER, QUERY_HEADER,
+            QUERY_TIME_HEADER, STATEMENT_TIMEOUT_HEADER,
+        },
     };
 
     fn http_config_from_access_policy_and_cache_control(
@@ -704,6 +1315,7 @@ pub mod tests {
         HttpFrontend {
             read_access: access_policy.read,
             write_access: access_policy.write,
+            tokens: access_policy.tokens,
             cache_control: cache_control.unwrap().to_string(),
             ..HttpFrontend::default()
         }
@@ -713,6 +1325,7 @@ pub mod tests {
         HttpFrontend {
             read_access: access_policy.read,
             write_access: access_policy.write,
+            tokens: access_policy.tokens,
             ..HttpFrontend::default()
         }
     }
@@ -986,6 +1599,145 @@ pub mod tests {
         );
     }
 
+    #[rstest]
+    #[case::arrow_extension(".arrow", None, ResultFormat::ArrowStream)]
+    #[case::arrow_accept(
+        "",
+        Some("application/vnd.apache.arrow.stream"),
+        ResultFormat::ArrowStream
+    )]
+    #[case::parquet_extension(".parquet", None, ResultFormat::Parquet)]
+    #[case::parquet_accept(
+        ".bin",
+        Some("application/vnd.apache.parquet"),
+        ResultFormat::Parquet
+    )]
+    #[case::csv_accept("", Some("text/csv"), ResultFormat::Csv)]
+    #[case::extension_overrides_accept(
+        ".csv",
+        Some("application/vnd.apache.arrow.stream"),
+        ResultFormat::Csv
+    )]
+    #[case::unsupported_accept("", Some("image/png"), ResultFormat::Json)]
+    #[tokio::test]
+    async fn test_get_cached_output_format(
+        #[case] extension: &str,
+        #[case] accept: Option<&str>,
+        #[case] expected_format: ResultFormat,
+    ) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_cached_endpoint(
+            &handler,
+            format!("/q/{SELECT_QUERY_HASH}{extension}").as_str(),
+            None,
+            Some(
+                [(QUERY_HEADER, SELECT_QUERY)]
+                    .into_iter()
+                    .chain(accept.map(|a| (header::ACCEPT.as_str(), a)))
+                    .collect(),
+            ),
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap();
+        if expected_format == ResultFormat::Json {
+            assert_eq!(etag, V1_ETAG);
+        } else {
+            assert_ne!(etag, V1_ETAG);
+        }
+        assert_output_format(resp, expected_format);
+    }
+
+    #[rstest]
+    #[case::arrow("application/vnd.apache.arrow.stream", ResultFormat::ArrowStream)]
+    #[case::parquet("application/vnd.apache.parquet", ResultFormat::Parquet)]
+    #[case::csv("text/csv", ResultFormat::Csv)]
+    #[case::quality_values(
+        "application/json;q=0.5, text/csv;q=0.8, application/vnd.apache.arrow.stream;q=0.1",
+        ResultFormat::Csv
+    )]
+    #[tokio::test]
+    async fn test_get_uncached_output_format(
+        #[case] accept: &str,
+        #[case] expected_format: ResultFormat,
+    ) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method("POST")
+            .path("/q")
+            .header(header::ACCEPT, accept)
+            .json(&HashMap::from([("query", SELECT_QUERY)]))
+            .reply(&handler)
+            .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_output_format(resp, expected_format);
+    }
+
+    #[tokio::test]
+    async fn test_get_uncached_write_query_ignores_accept() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method("POST")
+            .path("/q")
+            .header(header::ACCEPT, "application/vnd.apache.arrow.stream")
+            .json(&HashMap::from([("query", INSERT_QUERY)]))
+            .reply(&handler)
+            .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "");
+        assert!(!resp.headers().contains_key(header::CONTENT_TYPE));
+    }
+
+    fn assert_output_format(resp: Response<Bytes>, expected_format: ResultFormat) {
+        let content_type = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let expected = ["+---+", "| c |", "+---+", "| 1 |", "+---+"];
+
+        match expected_format {
+            ResultFormat::Json => {
+                assert!(content_type.starts_with("application/json; arrow-schema="));
+                assert_eq!(resp.body(), "{\"c\":1}\n");
+            }
+            ResultFormat::ArrowStream => {
+                assert_eq!(content_type, "application/vnd.apache.arrow.stream");
+                let batches = StreamReader::try_new(resp.body().clone().reader(), None)
+                    .unwrap()
+                    .collect::<Result<Vec<RecordBatch>, _>>()
+                    .unwrap();
+                assert_batches_eq!(expected, &batches);
+            }
+            ResultFormat::Parquet => {
+                assert_eq!(content_type, "application/vnd.apache.parquet");
+                let batches =
+                    ParquetRecordBatchReaderBuilder::try_new(resp.body().clone())
+                        .unwrap()
+                        .build()
+                        .unwrap()
+                        .collect::<Result<Vec<RecordBatch>, _>>()
+                        .unwrap();
+                assert_batches_eq!(expected, &batches);
+            }
+            ResultFormat::Csv => {
+                assert_eq!(content_type, "text/csv; header=present");
+                assert_eq!(resp.body(), "c\n1\n");
+            }
+        }
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_get_cached_hash_no_query(
@@ -1029,37 +1781,75 @@ pub mod tests {
         assert_eq!(resp.body(), "NOT_MODIFIED");
     }
 
-    #[rstest]
     #[tokio::test]
-    async fn test_query_bad_encoding(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
+    async fn test_get_cached_etag_other_format() {
+        // The ETag of the JSON results doesn't match the results in another format
+        let context = in_memory_context_with_single_table(None).await;
         let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
         let resp = request()
             .method("GET")
-            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}.bin"), new_db).as_str())
-            .header(QUERY_HEADER, BAD_PERCENT_ENCODED_SELECT_QUERY)
+            .path(format!("/q/{SELECT_QUERY_HASH}.arrow").as_str())
+            .header(QUERY_HEADER, SELECT_QUERY)
+            .header(IF_NONE_MATCH, V1_ETAG)
             .reply(&handler)
             .await;
-        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
-        assert_eq!(resp.body(), "QUERY_DECODE_ERROR");
-    }
-
-    #[rstest]
-    #[tokio::test]
-    async fn test_get_cached_etag_new_version(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        // Pass the same ETag as If-None-Match, but the table version changed -> reruns the query
-
-        let context = in_memory_context_with_modified_table(new_db).await;
-        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+        assert_eq!(resp.status(), StatusCode::OK);
+        let arrow_etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_output_format(resp, ResultFormat::ArrowStream);
 
+        // Each format has an ETag of its own
         let resp = request()
             .method("GET")
-            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}"), new_db).as_str())
+            .path(format!("/q/{SELECT_QUERY_HASH}.parquet").as_str())
+            .header(QUERY_HEADER, SELECT_QUERY)
+            .header(IF_NONE_MATCH, arrow_etag.clone())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_ne!(resp.headers()[header::ETAG], arrow_etag);
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{SELECT_QUERY_HASH}.arrow").as_str())
+            .header(QUERY_HEADER, SELECT_QUERY)
+            .header(IF_NONE_MATCH, arrow_etag)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_query_bad_encoding(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method("GET")
+            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}.bin"), new_db).as_str())
+            .header(QUERY_HEADER, BAD_PERCENT_ENCODED_SELECT_QUERY)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.body(), "QUERY_DECODE_ERROR");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_cached_etag_new_version(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        // Pass the same ETag as If-None-Match, but the table version changed -> reruns the query
+
+        let context = in_memory_context_with_modified_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method("GET")
+            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}"), new_db).as_str())
             .header(QUERY_HEADER, SELECT_QUERY)
             .header(header::ETAG, V1_ETAG)
             .reply(&handler)
@@ -1072,6 +1862,46 @@ pub mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_get_cached_view_etag_new_version() {
+        // The ETag of a query on a view changes when the underlying table changes
+        let context = in_memory_context_with_single_table(None).await;
+        context
+            .plan_query("CREATE VIEW test_view AS SELECT * FROM test_table")
+            .await
+            .unwrap();
+        let handler = filters(
+            context.clone(),
+            http_config_from_access_policy(free_for_all()),
+        );
+
+        let query = "SELECT COUNT(*) AS c FROM test_view";
+        let path = format!("/q/{}", str_to_hex_hash(query));
+
+        let resp = request()
+            .method("GET")
+            .path(&path)
+            .header(QUERY_HEADER, query)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+        let v1_etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+        context.plan_query(INSERT_QUERY).await.unwrap();
+
+        let resp = request()
+            .method("GET")
+            .path(&path)
+            .header(QUERY_HEADER, query)
+            .header(IF_NONE_MATCH, v1_etag.clone())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+        assert_ne!(resp.headers().get(header::ETAG).unwrap(), &v1_etag);
+    }
+
     #[tokio::test]
     async fn test_get_uncached_read_nonexistent_db() {
         let context = in_memory_context_with_single_table(None).await;
@@ -1201,10 +2031,84 @@ pub mod tests {
         let error_msg = String::from_utf8_lossy(resp.body());
         assert_eq!(
             error_msg,
-            "Cast error: Cannot cast string 'notanint' to value of Int32 type"
+            "{\"__seafowl_error\":{\"message\":\"Cast error: Cannot cast string 'notanint' to value of Int32 type\"}}\n"
         );
     }
 
+    #[tokio::test]
+    async fn test_execution_limits() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        // The results are already streaming by the time they outgrow the limit
+        let resp = request()
+            .method("POST")
+            .path("/q")
+            .header(MAX_RESULT_ROWS_HEADER, "1")
+            .json(&HashMap::from([(
+                "query",
+                "SELECT * FROM (VALUES (1), (2))",
+            )]))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            String::from_utf8_lossy(resp.body()),
+            "{\"__seafowl_error\":{\"message\":\"External error: Query result exceeded the limit of 1 rows\"}}\n"
+        );
+
+        let resp = request()
+            .method("POST")
+            .path("/q")
+            .header(MAX_RESULT_ROWS_HEADER, "2")
+            .json(&HashMap::from([(
+                "query",
+                "SELECT * FROM (VALUES (1), (2))",
+            )]))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"column1\":1}\n{\"column1\":2}\n");
+
+        let resp = request()
+            .method("POST")
+            .path("/q")
+            .header(STATEMENT_TIMEOUT_HEADER, "soon")
+            .json(&HashMap::from([("query", "SELECT 1")]))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_error_execution_aborts_binary_output(
+        #[values(
+            "application/vnd.apache.arrow.stream",
+            "application/vnd.apache.parquet",
+            "text/csv"
+        )]
+        accept: &str,
+    ) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        // Go through the service instead of `request().reply()`, since the latter
+        // collects the whole body and panics on the error
+        let req = hyper::Request::builder()
+            .method("POST")
+            .uri("/q")
+            .header(header::ACCEPT, accept)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(r#"{"query": "SELECT 'notanint'::int"}"#))
+            .unwrap();
+        let resp = warp::service(handler).call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // The body stream gets aborted instead of being terminated properly
+        assert!(hyper::body::to_bytes(resp.into_body()).await.is_err());
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_error_json_conversion(
@@ -1220,7 +2124,7 @@ pub mod tests {
         let error_msg = String::from_utf8_lossy(resp.body());
         assert_eq!(
             error_msg,
-            "Invalid argument error: JSON Writer does not support data type: Decimal128(38, 10)"
+            "{\"__seafowl_error\":{\"message\":\"Invalid argument error: JSON Writer does not support data type: Decimal128(38, 10)\"}}\n"
         );
     }
 
@@ -1515,6 +2419,479 @@ pub mod tests {
         assert_eq!(resp.body(), "INVALID_AUTHORIZATION_HEADER");
     }
 
+    #[tokio::test]
+    async fn test_named_token_grants() {
+        let context = in_memory_context_with_single_table(None).await;
+        context.plan_query("CREATE SCHEMA private").await.unwrap();
+        context
+            .plan_query("CREATE TABLE private.secret_table(col_1 INT)")
+            .await
+            .unwrap();
+
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all()
+                    .with_write_password("somepw")
+                    .with_read_password("somereadpw")
+                    .with_token(
+                        "product",
+                        "sometoken",
+                        vec![Grant {
+                            database: DEFAULT_DB.to_string(),
+                            schema: Some("public".to_string()),
+                            access: GrantAccess::Write,
+                        }],
+                    ),
+            ),
+        );
+
+        // Reads and writes within the granted schema are allowed
+        let resp =
+            query_uncached_endpoint(&handler, SELECT_QUERY, None, Some("sometoken"))
+                .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+
+        let resp =
+            query_uncached_endpoint(&handler, INSERT_QUERY, None, Some("sometoken"))
+                .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{SELECT_QUERY_HASH}").as_str())
+            .header(QUERY_HEADER, SELECT_QUERY)
+            .header(header::AUTHORIZATION, "Bearer sometoken")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+
+        // Tables outside of it can't be read or written to
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT * FROM private.secret_table",
+            None,
+            Some("sometoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "READ_FORBIDDEN");
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "INSERT INTO test_table SELECT * FROM private.secret_table",
+            None,
+            Some("sometoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "READ_FORBIDDEN");
+
+        // Neither can they be read in a subquery
+        for query in [
+            "SELECT 1 WHERE EXISTS (SELECT 1 FROM private.secret_table)",
+            "SELECT * FROM test_table WHERE col_1 IN (SELECT col_1 FROM private.secret_table)",
+            "SELECT (SELECT MAX(col_1) FROM private.secret_table) AS c",
+        ] {
+            let resp =
+                query_uncached_endpoint(&handler, query, None, Some("sometoken")).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+            assert_eq!(resp.body(), "READ_FORBIDDEN");
+        }
+
+        // Same goes for tables that aren't Delta tables, such as the system ones
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT * FROM system.table_versions",
+            None,
+            Some("sometoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "READ_FORBIDDEN");
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "DROP TABLE private.secret_table",
+            None,
+            Some("sometoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        // Statements that aren't scoped to a table need a database-wide grant
+        let resp = query_uncached_endpoint(
+            &handler,
+            "CREATE SCHEMA other",
+            None,
+            Some("sometoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_query() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(
+            context.clone(),
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all()
+                    .with_write_password("somepw")
+                    .with_token(
+                        "product",
+                        "sometoken",
+                        vec![Grant {
+                            database: DEFAULT_DB.to_string(),
+                            schema: None,
+                            access: GrantAccess::Read,
+                        }],
+                    )
+                    .with_token(
+                        "other",
+                        "othertoken",
+                        vec![Grant {
+                            database: DEFAULT_DB.to_string(),
+                            schema: None,
+                            access: GrantAccess::Read,
+                        }],
+                    ),
+            ),
+        );
+
+        // Start a query on behalf of the named token, without consuming its results yet
+        let plan = context.plan_query(SELECT_QUERY).await.unwrap();
+        let running_query = context
+            .start_query(
+                &Principal::Token("product".to_string()),
+                SELECT_QUERY,
+                ExecutionLimits::default(),
+            )
+            .unwrap();
+        let id = running_query.id();
+        let mut results = context
+            .execute_query_stream(plan, running_query)
+            .await
+            .unwrap();
+
+        // The query listing itself is running as well
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT principal, query FROM system.running_queries ORDER BY query_id",
+            None,
+            Some("somepw"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body(),
+            "{\"principal\":\"product\",\"query\":\"SELECT COUNT(*) AS c FROM test_table\"}\n\
+            {\"principal\":\"writer\",\"query\":\"SELECT principal, query FROM system.running_queries ORDER BY query_id\"}\n"
+        );
+
+        // Principals without write access only see their own queries
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT principal, query FROM system.running_queries ORDER BY query_id",
+            None,
+            Some("othertoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body(),
+            "{\"principal\":\"other\",\"query\":\"SELECT principal, query FROM system.running_queries ORDER BY query_id\"}\n"
+        );
+
+        // Only the owner or writers can cancel the query
+        let resp = request()
+            .method("DELETE")
+            .path(format!("/q/{id}").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        let resp = request()
+            .method("DELETE")
+            .path("/q/12345")
+            .header(header::AUTHORIZATION, "Bearer somepw")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.body(), "Query 12345 not found");
+
+        let resp = request()
+            .method("DELETE")
+            .path(format!("/q/{id}").as_str())
+            .header(header::AUTHORIZATION, "Bearer sometoken")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        assert_eq!(
+            results.next().await.unwrap().unwrap_err().to_string(),
+            format!("Execution error: Query {id} was cancelled")
+        );
+        drop(results);
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT principal, status FROM system.query_log ORDER BY query_id",
+            None,
+            Some("somepw"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body(),
+            "{\"principal\":\"product\",\"status\":\"cancelled\"}\n\
+            {\"principal\":\"writer\",\"status\":\"finished\"}\n\
+            {\"principal\":\"other\",\"status\":\"finished\"}\n"
+        );
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT principal, status FROM system.query_log ORDER BY query_id",
+            None,
+            Some("sometoken"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.body(),
+            "{\"principal\":\"product\",\"status\":\"cancelled\"}\n"
+        );
+
+        // Queries can also be cancelled with a KILL statement, which requires write access
+        let running_query = context
+            .start_query(
+                &Principal::Anonymous,
+                SELECT_QUERY,
+                ExecutionLimits::default(),
+            )
+            .unwrap();
+        let kill = format!("KILL QUERY '{}'", running_query.id());
+
+        let resp = query_uncached_endpoint(&handler, &kill, None, None).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(!context
+            .queries
+            .get(running_query.id())
+            .unwrap()
+            .is_cancelled());
+
+        let resp = query_uncached_endpoint(&handler, &kill, None, Some("somepw")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(context
+            .queries
+            .get(running_query.id())
+            .unwrap()
+            .is_cancelled());
+
+        let resp =
+            query_uncached_endpoint(&handler, "KILL 12345", None, Some("somepw")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.body(), "Error during planning: Query 12345 not found");
+    }
+
+    #[tokio::test]
+    async fn test_row_policies() {
+        let context = in_memory_context_with_single_table(None).await;
+        context
+            .plan_query(
+                "CREATE TABLE orders AS VALUES ('alice', 1), ('bob', 2), ('bob', 3)",
+            )
+            .await
+            .unwrap();
+        context
+            .plan_query("CREATE VIEW orders_view AS SELECT * FROM orders")
+            .await
+            .unwrap();
+        context
+            .plan_query(
+                "CREATE POLICY tenant_isolation ON orders TO alice, bob \
+                USING (column1 = current_user)",
+            )
+            .await
+            .unwrap();
+
+        let read_grant = vec![Grant {
+            database: DEFAULT_DB.to_string(),
+            schema: None,
+            access: GrantAccess::Read,
+        }];
+        let handler = filters(
+            context.clone(),
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all()
+                    .with_token("alice", "alicetoken", read_grant.clone())
+                    .with_token("bob", "bobtoken", read_grant),
+            ),
+        );
+
+        let query = "SELECT SUM(column2) AS s FROM orders";
+        let path = format!("/q/{}", str_to_hex_hash(query));
+
+        // Each tenant only sees its own rows, and gets a distinct ETag
+        let alice = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![
+                (QUERY_HEADER, query),
+                ("Authorization", "Bearer alicetoken"),
+            ]),
+        )
+        .await;
+        assert_eq!(alice.status(), StatusCode::OK);
+        assert_eq!(alice.body(), "{\"s\":1}\n");
+
+        let bob = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![
+                (QUERY_HEADER, query),
+                ("Authorization", "Bearer bobtoken"),
+            ]),
+        )
+        .await;
+        assert_eq!(bob.status(), StatusCode::OK);
+        assert_eq!(bob.body(), "{\"s\":5}\n");
+        assert_ne!(
+            alice.headers().get(header::ETAG).unwrap(),
+            bob.headers().get(header::ETAG).unwrap()
+        );
+
+        // Shared caches mustn't serve one tenant's results to another
+        for resp in [&alice, &bob] {
+            assert_eq!(
+                resp.headers().get(header::CACHE_CONTROL).unwrap(),
+                "private"
+            );
+        }
+        let not_modified = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![
+                (QUERY_HEADER, query),
+                ("Authorization", "Bearer alicetoken"),
+                (
+                    header::IF_NONE_MATCH.as_str(),
+                    alice.headers()[header::ETAG].to_str().unwrap(),
+                ),
+            ]),
+        )
+        .await;
+        assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            not_modified.headers().get(header::CACHE_CONTROL).unwrap(),
+            "private"
+        );
+
+        // Reading through a view doesn't bypass the policy
+        let resp = query_uncached_endpoint(
+            &handler,
+            "SELECT SUM(column2) AS s FROM orders_view",
+            None,
+            Some("bobtoken"),
+        )
+        .await;
+        assert_eq!(resp.body(), "{\"s\":5}\n");
+
+        // The policy doesn't apply to other principals
+        let resp = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![(QUERY_HEADER, query)]),
+        )
+        .await;
+        assert_eq!(resp.body(), "{\"s\":6}\n");
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "max-age=43200, public"
+        );
+
+        context
+            .plan_query("DROP POLICY tenant_isolation ON orders")
+            .await
+            .unwrap();
+        let resp = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![
+                (QUERY_HEADER, query),
+                ("Authorization", "Bearer alicetoken"),
+            ]),
+        )
+        .await;
+        assert_eq!(resp.body(), "{\"s\":6}\n");
+    }
+
+    #[tokio::test]
+    async fn test_column_masking_policies() {
+        let context = in_memory_context_with_single_table(None).await;
+        context
+            .plan_query(
+                "CREATE TABLE orders AS VALUES ('alice', 1), ('bob', 2), ('bob', 3)",
+            )
+            .await
+            .unwrap();
+        context
+            .plan_query(
+                "CREATE POLICY hide_tenant ON orders FOR COLUMN column1 USING (NULL)",
+            )
+            .await
+            .unwrap();
+
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_password("somepw"),
+            ),
+        );
+
+        let query = "SELECT COUNT(column1) AS c FROM orders";
+        let path = format!("/q/{}", str_to_hex_hash(query));
+
+        // Readers get the masked values
+        let reader = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![(QUERY_HEADER, query)]),
+        )
+        .await;
+        assert_eq!(reader.status(), StatusCode::OK);
+        assert_eq!(reader.body(), "{\"c\":0}\n");
+
+        // Writers get the actual values, and a different ETag
+        let writer = query_cached_endpoint(
+            &handler,
+            &path,
+            None,
+            Some(vec![
+                (QUERY_HEADER, query),
+                ("Authorization", "Bearer somepw"),
+            ]),
+        )
+        .await;
+        assert_eq!(writer.status(), StatusCode::OK);
+        assert_eq!(writer.body(), "{\"c\":3}\n");
+        assert_ne!(
+            reader.headers().get(header::ETAG).unwrap(),
+            writer.headers().get(header::ETAG).unwrap()
+        );
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_multi_statement_no_reads(
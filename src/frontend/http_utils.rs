@@ -47,6 +47,9 @@ use warp::hyper::{Body, Response, StatusCode};
 use warp::reject::Reject;
 use warp::{Rejection, Reply};
 
+use crate::jwt::JwtError;
+use crate::queries::{ExecutionLimitError, QueryLimitError};
+
 #[derive(Debug)]
 pub enum ApiError {
     DataFusionError(DataFusionError),
@@ -59,6 +62,7 @@ pub enum ApiError {
     NeedAccessToken,
     UselessAccessToken,
     WrongAccessToken,
+    InvalidJwt(JwtError),
     InvalidAuthorizationHeader,
     InvalidMultiStatement,
     EmptyMultiStatement,
@@ -73,16 +77,41 @@ pub enum ApiError {
     UploadUnsupportedFileFormat(String),
     QueryDecodeError,
     QueryParsingError(Rejection),
+    QueryNotFound(u64),
+    QueryLimitExceeded(QueryLimitError),
+    ExecutionLimitExceeded(ExecutionLimitError),
 }
 
 // Wrap DataFusion errors so that we can automagically return an
 // `ApiError(DataFusionError)` by using the `?` operator
 impl From<DataFusionError> for ApiError {
     fn from(err: DataFusionError) -> Self {
+        // Aborted result streams and the per-query memory pool surface the limits through
+        // DataFusion, possibly wrapped by the operators the error passed through
+        if let DataFusionError::External(root) = err.find_root() {
+            if let Some(err) = root.downcast_ref::<ExecutionLimitError>() {
+                return ApiError::ExecutionLimitExceeded(err.clone());
+            }
+            if let Some(err) = root.downcast_ref::<QueryLimitError>() {
+                return ApiError::QueryLimitExceeded(err.clone());
+            }
+        }
         ApiError::DataFusionError(err)
     }
 }
 
+impl From<ExecutionLimitError> for ApiError {
+    fn from(err: ExecutionLimitError) -> Self {
+        ApiError::ExecutionLimitExceeded(err)
+    }
+}
+
+impl From<QueryLimitError> for ApiError {
+    fn from(err: QueryLimitError) -> Self {
+        ApiError::QueryLimitExceeded(err)
+    }
+}
+
 impl From<std::io::Error> for ApiError {
     fn from(err: std::io::Error) -> Self {
         ApiError::IoError(err)
@@ -97,7 +126,7 @@ impl From<std::str::Utf8Error> for ApiError {
 }
 
 impl ApiError {
-    fn status_code_body(self: &ApiError) -> (StatusCode, String) {
+    pub(crate) fn status_code_body(self: &ApiError) -> (StatusCode, String) {
         match self {
             // TODO: figure out which DF errors to propagate, we have ones that are the server's fault
             // here too (e.g. ResourcesExhausted) and potentially some that leak internal
@@ -112,6 +141,7 @@ impl ApiError {
             ApiError::NeedAccessToken => (StatusCode::UNAUTHORIZED, "NEED_ACCESS_TOKEN".to_string()),
             ApiError::UselessAccessToken => (StatusCode::BAD_REQUEST, "USELESS_ACCESS_TOKEN".to_string()),
             ApiError::WrongAccessToken => (StatusCode::UNAUTHORIZED, "INVALID_ACCESS_TOKEN".to_string()),
+            ApiError::InvalidJwt(e) => (StatusCode::UNAUTHORIZED, e.to_string()),
             ApiError::InvalidAuthorizationHeader => (StatusCode::UNAUTHORIZED, "INVALID_AUTHORIZATION_HEADER".to_string()),
             ApiError::InvalidMultiStatement => (StatusCode::BAD_REQUEST, "Only one read statement is allowed and it must be at the end of a multi-statement query".to_string()),
             ApiError::EmptyMultiStatement => (StatusCode::BAD_REQUEST, "Empty query received".to_string()),
@@ -126,6 +156,10 @@ impl ApiError {
             ApiError::UploadUnsupportedFileFormat(filename) => (StatusCode::BAD_REQUEST, format!("File {filename} not supported")),
             ApiError::QueryDecodeError => (StatusCode::BAD_REQUEST, "QUERY_DECODE_ERROR".to_string()),
             ApiError::QueryParsingError(r) => (StatusCode::BAD_REQUEST, format!("No query found in the request: {r:?}")),
+            ApiError::QueryNotFound(id) => (StatusCode::NOT_FOUND, format!("Query {id} not found")),
+            ApiError::QueryLimitExceeded(e) => (StatusCode::TOO_MANY_REQUESTS, e.to_string()),
+            ApiError::ExecutionLimitExceeded(e @ ExecutionLimitError::Timeout { .. }) => (StatusCode::REQUEST_TIMEOUT, e.to_string()),
+            ApiError::ExecutionLimitExceeded(e) => (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()),
         }
     }
 
@@ -0,0 +1,707 @@
+// Authentication of PostgreSQL clients. Convergence doesn't support authentication itself, so we
+// run the startup and authentication part of the protocol before handing the connection over,
+// and then replay the startup message to it.
+//
+// Users configured with an MD5 or SCRAM-SHA-256 password authenticate with that method, while
+// everyone else sends one of the access tokens (the HTTP passwords, named tokens or JWTs) as a
+// cleartext password, or an empty password to connect anonymously.
+use std::collections::HashMap;
+use std::io::{self, Cursor};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::debug;
+
+use crate::auth::{token_to_principal, AccessPolicy, Principal, UserContext};
+use crate::config::schema::PostgresUser;
+
+// Special protocol version codes, sent by clients instead of a regular startup message
+pub(super) const SSL_REQUEST_CODE: i32 = 80877103;
+pub(super) const GSSENC_REQUEST_CODE: i32 = 80877104;
+const MAX_MESSAGE_LENGTH: i32 = 10000;
+
+const AUTHENTICATION_CLEARTEXT_PASSWORD: i32 = 3;
+const AUTHENTICATION_MD5_PASSWORD: i32 = 5;
+const AUTHENTICATION_SASL: i32 = 10;
+const AUTHENTICATION_SASL_CONTINUE: i32 = 11;
+const AUTHENTICATION_SASL_FINAL: i32 = 12;
+const INVALID_PASSWORD_SQL_STATE: &str = "28P01";
+
+const MD5_PREFIX: &str = "md5";
+const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+// We don't support channel binding, so the client must send one of the GS2 headers without it
+const SCRAM_GS2_HEADERS: [&str; 2] = ["n,,", "y,,"];
+
+type HmacSha256 = Hmac<Sha256>;
+
+// A password as stored by PostgreSQL in `pg_authid`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordVerifier {
+    // Hex-encoded MD5 of the password concatenated with the user name
+    Md5(String),
+    ScramSha256 {
+        iterations: u32,
+        salt: Vec<u8>,
+        stored_key: Vec<u8>,
+        server_key: Vec<u8>,
+    },
+}
+
+impl PasswordVerifier {
+    pub fn parse(password: &str) -> Result<Self, String> {
+        if let Some(hash) = password.strip_prefix(MD5_PREFIX) {
+            if hash.len() != 32 || hex::decode(hash).is_err() {
+                return Err(
+                    "MD5 passwords must be of the form md5<32 hex digits>".to_string()
+                );
+            }
+            return Ok(Self::Md5(hash.to_string()));
+        }
+
+        let invalid = || {
+            format!(
+                "Expected md5<hash> or {SCRAM_SHA_256}$<iterations>:<salt>$<StoredKey>:<ServerKey>"
+            )
+        };
+        let decode = |value: &str| STANDARD.decode(value).map_err(|_| invalid());
+
+        let Some(verifier) = password.strip_prefix(&format!("{SCRAM_SHA_256}$")) else {
+            return Err(invalid());
+        };
+        let Some((iterations_salt, keys)) = verifier.split_once('$') else {
+            return Err(invalid());
+        };
+        let (Some((iterations, salt)), Some((stored_key, server_key))) =
+            (iterations_salt.split_once(':'), keys.split_once(':'))
+        else {
+            return Err(invalid());
+        };
+
+        Ok(Self::ScramSha256 {
+            iterations: iterations.parse().map_err(|_| invalid())?,
+            salt: decode(salt)?,
+            stored_key: decode(stored_key)?,
+            server_key: decode(server_key)?,
+        })
+    }
+}
+
+// The principal that a configured PostgreSQL user authenticates as
+fn user_principal(user: &str) -> Principal {
+    match user {
+        "writer" => Principal::Writer,
+        "reader" => Principal::Reader,
+        name => Principal::Token(name.to_string()),
+    }
+}
+
+// Read the startup message (declining any encryption requests), returning it as is so that it
+// can be replayed to the connection
+async fn read_startup_message<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+) -> io::Result<Vec<u8>> {
+    loop {
+        let length = stream.read_i32().await?;
+        if !(8..=MAX_MESSAGE_LENGTH).contains(&length) {
+            return Err(invalid_data(format!(
+                "Invalid startup message length {length}"
+            )));
+        }
+
+        let mut body = vec![0; length as usize - 4];
+        stream.read_exact(&mut body).await?;
+
+        let code = i32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+        if code == SSL_REQUEST_CODE || code == GSSENC_REQUEST_CODE {
+            stream.write_all(b"N").await?;
+            continue;
+        }
+
+        let mut message = length.to_be_bytes().to_vec();
+        message.extend(body);
+        return Ok(message);
+    }
+}
+
+// Parse the null-terminated key/value pairs following the length and the protocol version
+fn startup_parameters(message: &[u8]) -> HashMap<String, String> {
+    let fields: Vec<String> = message[8..]
+        .split(|b| *b == 0)
+        .map(|field| String::from_utf8_lossy(field).to_string())
+        .collect();
+
+    fields
+        .chunks_exact(2)
+        .take_while(|pair| !pair[0].is_empty())
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect()
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// Read a password message (which also carries the SASL responses), returning its body
+async fn read_password_message<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> io::Result<Vec<u8>> {
+    let tag = stream.read_u8().await?;
+    let length = stream.read_i32().await?;
+    if tag != b'p' || !(4..=MAX_MESSAGE_LENGTH).contains(&length) {
+        return Err(invalid_data("Expected a password message"));
+    }
+
+    let mut body = vec![0; length as usize - 4];
+    stream.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+async fn read_password<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<String> {
+    let body = read_password_message(stream).await?;
+    let password = body.strip_suffix(&[0]).unwrap_or(&body);
+    Ok(String::from_utf8_lossy(password).to_string())
+}
+
+async fn write_message<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    tag: u8,
+    body: &[u8],
+) -> io::Result<()> {
+    let mut message = vec![tag];
+    message.extend((body.len() as i32 + 4).to_be_bytes());
+    message.extend(body);
+    stream.write_all(&message).await
+}
+
+async fn write_authentication_request<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    code: i32,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut body = code.to_be_bytes().to_vec();
+    body.extend(data);
+    write_message(stream, b'R', &body).await
+}
+
+async fn write_auth_error<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    message: &str,
+) -> io::Result<()> {
+    write_fatal_error(stream, INVALID_PASSWORD_SQL_STATE, message).await
+}
+
+pub(super) async fn write_fatal_error<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    sql_state: &str,
+    message: &str,
+) -> io::Result<()> {
+    let mut body = vec![];
+    for (field, value) in [
+        (b'S', "FATAL"),
+        (b'V', "FATAL"),
+        (b'C', sql_state),
+        (b'M', message),
+    ] {
+        body.push(field);
+        body.extend(value.as_bytes());
+        body.push(0);
+    }
+    body.push(0);
+
+    write_message(stream, b'E', &body).await
+}
+
+async fn authenticate_md5<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    hash: &str,
+) -> io::Result<bool> {
+    let salt: [u8; 4] = rand::random();
+    write_authentication_request(stream, AUTHENTICATION_MD5_PASSWORD, &salt).await?;
+
+    // The client responds with md5(md5(password + user) + salt)
+    let response = read_password(stream).await?;
+    let mut hasher = Md5::new();
+    hasher.update(hash.as_bytes());
+    hasher.update(salt);
+    let expected = format!("{MD5_PREFIX}{}", hex::encode(hasher.finalize()));
+
+    Ok(response == expected)
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac =
+        HmacSha256::new_from_slice(key).expect("HMAC can take a key of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn scram_attribute(message: &str, name: char) -> Option<&str> {
+    message.split(',').find_map(|attribute| {
+        attribute
+            .strip_prefix(name)
+            .and_then(|value| value.strip_prefix('='))
+    })
+}
+
+// Perform the SCRAM-SHA-256 exchange as per RFC 5802 and RFC 7677, without channel binding
+async fn authenticate_scram<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    iterations: u32,
+    salt: &[u8],
+    stored_key: &[u8],
+    server_key: &[u8],
+) -> io::Result<bool> {
+    let mut mechanisms = SCRAM_SHA_256.as_bytes().to_vec();
+    mechanisms.extend([0, 0]);
+    write_authentication_request(stream, AUTHENTICATION_SASL, &mechanisms).await?;
+
+    // The initial response holds the chosen mechanism, followed by the client-first-message
+    let initial_response = read_password_message(stream).await?;
+    let Some((mechanism, rest)) = initial_response
+        .iter()
+        .position(|b| *b == 0)
+        .map(|position| initial_response.split_at(position))
+    else {
+        return Err(invalid_data("Invalid SASL initial response"));
+    };
+    if mechanism != SCRAM_SHA_256.as_bytes() || rest.len() < 5 {
+        return Ok(false);
+    }
+    let client_first = String::from_utf8_lossy(&rest[5..]).to_string();
+
+    let Some((gs2_header, client_first_bare)) = SCRAM_GS2_HEADERS.iter().find_map(|h| {
+        client_first
+            .strip_prefix(h)
+            .map(|client_first_bare| (*h, client_first_bare))
+    }) else {
+        return Ok(false);
+    };
+    let Some(client_nonce) = scram_attribute(client_first_bare, 'r') else {
+        return Ok(false);
+    };
+
+    let nonce = format!(
+        "{client_nonce}{}",
+        Alphanumeric.sample_string(&mut rand::thread_rng(), 24)
+    );
+    let server_first = format!("r={nonce},s={},i={iterations}", STANDARD.encode(salt));
+    write_authentication_request(
+        stream,
+        AUTHENTICATION_SASL_CONTINUE,
+        server_first.as_bytes(),
+    )
+    .await?;
+
+    let client_final =
+        String::from_utf8_lossy(&read_password_message(stream).await?).to_string();
+    let Some((client_final_without_proof, proof)) = client_final.rsplit_once(",p=")
+    else {
+        return Ok(false);
+    };
+    if scram_attribute(client_final_without_proof, 'c')
+        != Some(STANDARD.encode(gs2_header).as_str())
+        || scram_attribute(client_final_without_proof, 'r') != Some(nonce.as_str())
+    {
+        return Ok(false);
+    }
+
+    // Recover the client key from the proof, and check that it hashes to the stored key
+    let auth_message =
+        format!("{client_first_bare},{server_first},{client_final_without_proof}");
+    let client_signature = hmac_sha256(stored_key, auth_message.as_bytes());
+    let proof = STANDARD.decode(proof).unwrap_or_default();
+    if proof.len() != client_signature.len() {
+        return Ok(false);
+    }
+    let client_key: Vec<u8> = proof
+        .iter()
+        .zip(client_signature)
+        .map(|(p, s)| p ^ s)
+        .collect();
+    if Sha256::digest(client_key).as_slice() != stored_key {
+        return Ok(false);
+    }
+
+    let server_final = format!(
+        "v={}",
+        STANDARD.encode(hmac_sha256(server_key, auth_message.as_bytes()))
+    );
+    write_authentication_request(
+        stream,
+        AUTHENTICATION_SASL_FINAL,
+        server_final.as_bytes(),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+// Authenticate the client, returning the stream with the startup message put back in front of
+// it, or `None` if the authentication failed (in which case the client has been notified).
+pub(super) async fn authenticate<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    policy: &AccessPolicy,
+    users: &[PostgresUser],
+) -> io::Result<Option<(impl AsyncRead + AsyncWrite + Unpin, UserContext)>> {
+    let startup_message = read_startup_message(&mut stream).await?;
+    let user = startup_parameters(&startup_message)
+        .remove("user")
+        .unwrap_or_default();
+    let failed = format!("password authentication failed for user \"{user}\"");
+
+    let principal = if let Some(pg_user) = users.iter().find(|u| u.name == user) {
+        let verified = match PasswordVerifier::parse(&pg_user.password)
+            .map_err(invalid_data)?
+        {
+            PasswordVerifier::Md5(hash) => authenticate_md5(&mut stream, &hash).await?,
+            PasswordVerifier::ScramSha256 {
+                iterations,
+                salt,
+                stored_key,
+                server_key,
+            } => {
+                authenticate_scram(
+                    &mut stream,
+                    iterations,
+                    &salt,
+                    &stored_key,
+                    &server_key,
+                )
+                .await?
+            }
+        };
+        verified.then(|| user_principal(&user)).ok_or(failed)
+    } else if *policy == AccessPolicy::free_for_all() {
+        Ok(Principal::Anonymous)
+    } else {
+        write_authentication_request(&mut stream, AUTHENTICATION_CLEARTEXT_PASSWORD, &[])
+            .await?;
+        let password = read_password(&mut stream).await?;
+        let token = (!password.is_empty()).then_some(password);
+
+        token_to_principal(token, policy)
+            .map_err(|err| format!("{failed}: {}", err.status_code_body().1))
+    };
+
+    let principal = match principal {
+        Ok(principal) => principal,
+        Err(message) => {
+            debug!("Failed PostgreSQL authentication: {message}");
+            write_auth_error(&mut stream, &message).await?;
+            return Ok(None);
+        }
+    };
+
+    let (reader, writer) = tokio::io::split(stream);
+    Ok(Some((
+        tokio::io::join(Cursor::new(startup_message).chain(reader), writer),
+        UserContext {
+            principal,
+            policy: policy.clone(),
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use md5::Md5;
+    use rstest::rstest;
+    use sha2::{Digest, Sha256};
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    use crate::auth::{AccessPolicy, Principal};
+    use crate::config::schema::PostgresUser;
+
+    use super::{
+        authenticate, hmac_sha256, scram_attribute, write_message, PasswordVerifier,
+        AUTHENTICATION_CLEARTEXT_PASSWORD, AUTHENTICATION_MD5_PASSWORD,
+        AUTHENTICATION_SASL, AUTHENTICATION_SASL_CONTINUE, AUTHENTICATION_SASL_FINAL,
+        SCRAM_SHA_256, SSL_REQUEST_CODE,
+    };
+
+    const WRITE_PW: &str = "write_password";
+    const PG_PASSWORD: &str = "pg_password";
+    const SALT: &[u8] = b"some-salt";
+    const ITERATIONS: u32 = 16;
+
+    fn startup_message(user: &str) -> Vec<u8> {
+        let mut body = 196608_i32.to_be_bytes().to_vec();
+        for field in ["user", user, "database", "default", ""] {
+            body.extend(field.as_bytes());
+            body.push(0);
+        }
+
+        let mut message = (body.len() as i32 + 4).to_be_bytes().to_vec();
+        message.extend(body);
+        message
+    }
+
+    // The PBKDF2 key derivation used by SCRAM
+    fn salted_password(password: &str) -> Vec<u8> {
+        let mut salt = SALT.to_vec();
+        salt.extend(1_i32.to_be_bytes());
+
+        let mut u = hmac_sha256(password.as_bytes(), &salt);
+        let mut result = u.clone();
+        for _ in 1..ITERATIONS {
+            u = hmac_sha256(password.as_bytes(), &u);
+            result.iter_mut().zip(&u).for_each(|(r, u)| *r ^= u);
+        }
+        result
+    }
+
+    fn scram_verifier(password: &str) -> String {
+        let salted_password = salted_password(password);
+        let client_key = hmac_sha256(&salted_password, b"Client Key");
+        let server_key = hmac_sha256(&salted_password, b"Server Key");
+
+        format!(
+            "{SCRAM_SHA_256}${ITERATIONS}:{}${}:{}",
+            STANDARD.encode(SALT),
+            STANDARD.encode(Sha256::digest(client_key)),
+            STANDARD.encode(server_key)
+        )
+    }
+
+    fn md5_hex(input: &[u8]) -> String {
+        hex::encode(Md5::digest(input))
+    }
+
+    fn users() -> Vec<PostgresUser> {
+        vec![
+            PostgresUser {
+                name: "writer".to_string(),
+                password: scram_verifier(PG_PASSWORD),
+            },
+            PostgresUser {
+                name: "reader".to_string(),
+                password: format!("md5{}", md5_hex(b"pg_passwordreader")),
+            },
+        ]
+    }
+
+    async fn read_message(stream: &mut DuplexStream) -> (u8, Vec<u8>) {
+        let tag = stream.read_u8().await.unwrap();
+        let length = stream.read_i32().await.unwrap();
+        let mut body = vec![0; length as usize - 4];
+        stream.read_exact(&mut body).await.unwrap();
+        (tag, body)
+    }
+
+    async fn read_authentication_request(stream: &mut DuplexStream) -> (i32, Vec<u8>) {
+        let (tag, body) = read_message(stream).await;
+        assert_eq!(tag, b'R');
+        let (code, data) = body.split_at(4);
+        (i32::from_be_bytes(code.try_into().unwrap()), data.to_vec())
+    }
+
+    async fn send_password(stream: &mut DuplexStream, password: &str) {
+        let mut body = password.as_bytes().to_vec();
+        body.push(0);
+        write_message(stream, b'p', &body).await.unwrap();
+    }
+
+    // Run the authentication on the server end, returning the principal and the message that
+    // is replayed to the connection
+    fn start_server(
+        stream: DuplexStream,
+        policy: AccessPolicy,
+    ) -> tokio::task::JoinHandle<Option<(Principal, Vec<u8>)>> {
+        tokio::spawn(async move {
+            let (mut stream, user_context) =
+                authenticate(stream, &policy, &users()).await.unwrap()?;
+
+            let mut replayed = vec![0; startup_message("").len()];
+            stream.read_exact(&mut replayed).await.unwrap();
+            Some((user_context.principal, replayed))
+        })
+    }
+
+    // Perform the client side of the SCRAM exchange, returning the final server message
+    async fn scram_client(stream: &mut DuplexStream, password: &str) -> (u8, Vec<u8>) {
+        let (code, mechanisms) = read_authentication_request(stream).await;
+        assert_eq!(code, AUTHENTICATION_SASL);
+        assert_eq!(mechanisms, b"SCRAM-SHA-256\0\0");
+
+        let client_first_bare = "n=,r=client-nonce";
+        let client_first = format!("n,,{client_first_bare}");
+        let mut body = b"SCRAM-SHA-256\0".to_vec();
+        body.extend((client_first.len() as i32).to_be_bytes());
+        body.extend(client_first.as_bytes());
+        write_message(stream, b'p', &body).await.unwrap();
+
+        let (code, server_first) = read_authentication_request(stream).await;
+        assert_eq!(code, AUTHENTICATION_SASL_CONTINUE);
+        let server_first = String::from_utf8(server_first).unwrap();
+        let nonce = scram_attribute(&server_first, 'r').unwrap();
+        assert!(nonce.starts_with("client-nonce"));
+        assert_eq!(scram_attribute(&server_first, 's'), Some("c29tZS1zYWx0"));
+        assert_eq!(scram_attribute(&server_first, 'i'), Some("16"));
+
+        let salted_password = salted_password(password);
+        let client_key = hmac_sha256(&salted_password, b"Client Key");
+        let client_final_without_proof = format!("c=biws,r={nonce}");
+        let auth_message =
+            format!("{client_first_bare},{server_first},{client_final_without_proof}");
+        let client_signature =
+            hmac_sha256(&Sha256::digest(&client_key), auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(client_signature)
+            .map(|(k, s)| k ^ s)
+            .collect();
+        write_message(
+            stream,
+            b'p',
+            format!("{client_final_without_proof},p={}", STANDARD.encode(proof))
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        let (tag, body) = read_message(stream).await;
+        if tag == b'R' {
+            let server_signature = hmac_sha256(
+                &hmac_sha256(&salted_password, b"Server Key"),
+                auth_message.as_bytes(),
+            );
+            let mut expected = AUTHENTICATION_SASL_FINAL.to_be_bytes().to_vec();
+            expected
+                .extend(format!("v={}", STANDARD.encode(server_signature)).as_bytes());
+            assert_eq!(body, expected);
+        }
+        (tag, body)
+    }
+
+    #[test]
+    fn test_parse_password_verifier() {
+        assert_eq!(
+            PasswordVerifier::parse("md5e8a48653851e28c69d0506508fb27fc5"),
+            Ok(PasswordVerifier::Md5(
+                "e8a48653851e28c69d0506508fb27fc5".to_string()
+            ))
+        );
+        assert!(matches!(
+            PasswordVerifier::parse(&scram_verifier(PG_PASSWORD)),
+            Ok(PasswordVerifier::ScramSha256 { iterations: 16, salt, .. }) if salt == SALT
+        ));
+
+        for invalid in [
+            "md5nothex",
+            "plaintext",
+            "SCRAM-SHA-256$4096:c2FsdA==",
+            "SCRAM-SHA-256$many:c2FsdA==$a2V5:a2V5",
+        ] {
+            assert!(PasswordVerifier::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_free_for_all_no_authentication() {
+        let (mut client, server) = duplex(4096);
+        let server = start_server(server, AccessPolicy::free_for_all());
+
+        // Encryption requests get declined
+        let mut ssl_request = 8_i32.to_be_bytes().to_vec();
+        ssl_request.extend(SSL_REQUEST_CODE.to_be_bytes());
+        client.write_all(&ssl_request).await.unwrap();
+        assert_eq!(client.read_u8().await.unwrap(), b'N');
+
+        client.write_all(&startup_message("")).await.unwrap();
+        assert_eq!(
+            server.await.unwrap(),
+            Some((Principal::Anonymous, startup_message("")))
+        );
+    }
+
+    #[rstest]
+    #[case::writer(WRITE_PW, Some(Principal::Writer))]
+    #[case::wrong_password("wrong_password", None)]
+    #[case::anonymous("", Some(Principal::Anonymous))]
+    #[tokio::test]
+    async fn test_cleartext_authentication(
+        #[case] password: &str,
+        #[case] expected: Option<Principal>,
+    ) {
+        let (mut client, server) = duplex(4096);
+        let server = start_server(
+            server,
+            AccessPolicy::free_for_all().with_write_password(WRITE_PW),
+        );
+
+        client.write_all(&startup_message("")).await.unwrap();
+        let (code, _) = read_authentication_request(&mut client).await;
+        assert_eq!(code, AUTHENTICATION_CLEARTEXT_PASSWORD);
+        send_password(&mut client, password).await;
+
+        match expected {
+            Some(principal) => assert_eq!(
+                server.await.unwrap(),
+                Some((principal, startup_message("")))
+            ),
+            None => {
+                let (tag, body) = read_message(&mut client).await;
+                assert_eq!(tag, b'E');
+                assert!(String::from_utf8_lossy(&body).contains(
+                    "password authentication failed for user \"\": INVALID_ACCESS_TOKEN"
+                ));
+                assert_eq!(server.await.unwrap(), None);
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::correct(PG_PASSWORD, true)]
+    #[case::wrong("wrong_password", false)]
+    #[tokio::test]
+    async fn test_md5_authentication(#[case] password: &str, #[case] success: bool) {
+        let (mut client, server) = duplex(4096);
+        let server = start_server(server, AccessPolicy::free_for_all());
+
+        client.write_all(&startup_message("reader")).await.unwrap();
+        let (code, salt) = read_authentication_request(&mut client).await;
+        assert_eq!(code, AUTHENTICATION_MD5_PASSWORD);
+
+        let mut response = md5_hex(format!("{password}reader").as_bytes()).into_bytes();
+        response.extend(salt);
+        send_password(&mut client, &format!("md5{}", md5_hex(&response))).await;
+
+        if success {
+            assert_eq!(
+                server.await.unwrap(),
+                Some((Principal::Reader, startup_message("reader")))
+            );
+        } else {
+            assert_eq!(read_message(&mut client).await.0, b'E');
+            assert_eq!(server.await.unwrap(), None);
+        }
+    }
+
+    #[rstest]
+    #[case::correct(PG_PASSWORD, true)]
+    #[case::wrong("wrong_password", false)]
+    #[tokio::test]
+    async fn test_scram_authentication(#[case] password: &str, #[case] success: bool) {
+        let (mut client, server) = duplex(4096);
+        let server = start_server(server, AccessPolicy::free_for_all());
+
+        client.write_all(&startup_message("writer")).await.unwrap();
+        let (tag, _) = scram_client(&mut client, password).await;
+
+        if success {
+            assert_eq!(tag, b'R');
+            assert_eq!(
+                server.await.unwrap(),
+                Some((Principal::Writer, startup_message("writer")))
+            );
+        } else {
+            assert_eq!(tag, b'E');
+            assert_eq!(server.await.unwrap(), None);
+        }
+    }
+}
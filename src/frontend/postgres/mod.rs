@@ -0,0 +1,245 @@
+mod auth;
+mod tls;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use convergence::{
+    connection::Connection,
+    engine::{Engine, Portal},
+    protocol::{ErrorResponse, FieldDescription, SqlState},
+    protocol_ext::DataRowBatch,
+};
+use convergence_arrow::table::{record_batch_to_rows, schema_to_field_desc};
+use datafusion::{
+    arrow::record_batch::RecordBatch, error::DataFusionError,
+    physical_plan::ExecutionPlan,
+};
+use futures::TryStreamExt;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tracing::{debug, warn};
+
+use crate::{
+    audit::{AuditEvent, AuditFrontend},
+    auth::{AccessPolicy, Principal, UserContext},
+    config::schema::{PostgresFrontend, PostgresUser},
+    context::{logical::is_read_only, SeafowlContext},
+    frontend::{http::authorize_plan, http_utils::ApiError},
+    queries::{ExecutionLimits, QueryGuard},
+};
+use sqlparser::ast::Statement;
+
+pub use auth::PasswordVerifier;
+
+pub struct SeafowlPortal {
+    plan: Arc<dyn ExecutionPlan>,
+    context: Arc<SeafowlContext>,
+    principal: Principal,
+    query: String,
+    limits: ExecutionLimits,
+    // Registered while planning; portals executed more than once get registered anew
+    running_query: Option<QueryGuard>,
+}
+
+fn df_err_to_sql(err: DataFusionError) -> ErrorResponse {
+    ErrorResponse::error(SqlState::DataException, err.to_string())
+}
+
+fn api_err_to_sql(err: ApiError) -> ErrorResponse {
+    ErrorResponse::error(SqlState::DataException, err.status_code_body().1)
+}
+
+#[async_trait]
+impl Portal for SeafowlPortal {
+    async fn fetch(&mut self, batch: &mut DataRowBatch) -> Result<(), ErrorResponse> {
+        let running_query = match self.running_query.take() {
+            Some(running_query) => running_query,
+            None => self
+                .context
+                .start_query(&self.principal, &self.query, self.limits)
+                .map_err(|e| api_err_to_sql(e.into()))?,
+        };
+        let arrow_batches: Vec<RecordBatch> = self
+            .context
+            .execute_query_stream(self.plan.clone(), running_query)
+            .await
+            .map_err(df_err_to_sql)?
+            .try_collect()
+            .await
+            .map_err(df_err_to_sql)?;
+        for arrow_batch in arrow_batches {
+            record_batch_to_rows(&arrow_batch, batch)?;
+        }
+        Ok(())
+    }
+}
+
+struct SeafowlConvergenceEngine {
+    context: Arc<SeafowlContext>,
+    user_context: UserContext,
+}
+
+#[async_trait]
+impl Engine for SeafowlConvergenceEngine {
+    type PortalType = SeafowlPortal;
+
+    async fn prepare(
+        &mut self,
+        statement: &Statement,
+    ) -> Result<Vec<FieldDescription>, ErrorResponse> {
+        let plan = self
+            .context
+            .create_logical_plan(&statement.to_string())
+            .await
+            .map_err(df_err_to_sql)?;
+
+        schema_to_field_desc(&plan.schema().as_ref().into())
+    }
+
+    async fn create_portal(
+        &mut self,
+        statement: &Statement,
+    ) -> Result<Self::PortalType, ErrorResponse> {
+        let query = statement.to_string();
+        let mut event = AuditEvent::query(
+            AuditFrontend::Postgres,
+            &self.user_context.principal,
+            &self.context.default_catalog,
+            &query,
+        );
+        // There's no way to pass per-query limits over the wire protocol, so only the configured
+        // ones apply
+        let limits = self.context.execution_limits(ExecutionLimits::default());
+        let mut running_query = None;
+        let result = limits
+            .with_timeout(self.plan_portal(
+                &query,
+                limits,
+                &mut event,
+                &mut running_query,
+            ))
+            .await
+            .unwrap_or_else(|e| Err(api_err_to_sql(e.into())));
+        let error = result.as_ref().err().map(|e| e.message.clone());
+        if let Some(running_query) = running_query.as_mut()
+            && let Some(error) = &error
+        {
+            running_query.fail(error);
+        }
+        self.context.audit(event.with_error(error));
+        result
+    }
+}
+
+impl SeafowlConvergenceEngine {
+    async fn plan_portal(
+        &self,
+        query: &str,
+        limits: ExecutionLimits,
+        event: &mut AuditEvent,
+        running_query: &mut Option<QueryGuard>,
+    ) -> Result<SeafowlPortal, ErrorResponse> {
+        *running_query = Some(
+            self.context
+                .start_query(&self.user_context.principal, query, limits)
+                .map_err(|e| api_err_to_sql(e.into()))?,
+        );
+        let plan = self
+            .context
+            .create_logical_plan(query)
+            .await
+            .map_err(df_err_to_sql)?;
+        event.read_only = is_read_only(&plan);
+        let plan = self
+            .context
+            .apply_access_policies(plan, &self.user_context)
+            .map_err(df_err_to_sql)?
+            .data;
+        authorize_plan(&self.context, &self.user_context, &plan)
+            .map_err(api_err_to_sql)?;
+
+        let physical = self
+            .context
+            .create_physical_plan(&plan)
+            .await
+            .map_err(df_err_to_sql)?;
+        event.table_versions = self.context.written_table_versions(&plan).await;
+        Ok(SeafowlPortal {
+            plan: physical,
+            context: self.context.clone(),
+            principal: self.user_context.principal.clone(),
+            query: query.to_string(),
+            limits,
+            running_query: running_query.take(),
+        })
+    }
+}
+
+pub async fn run_pg_server(context: Arc<SeafowlContext>, config: PostgresFrontend) {
+    let policy = AccessPolicy::from_frontend_config(&context.config.frontend);
+    let users = Arc::new(config.users.clone());
+    let acceptor = config
+        .tls_settings
+        .tls()
+        .expect("Invalid TLS settings")
+        .map(|tls| {
+            tls::build_acceptor(&tls).expect("Error loading the TLS certificates")
+        });
+    let listener = TcpListener::bind((config.bind_host.as_str(), config.bind_port))
+        .await
+        .unwrap();
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                warn!("Error accepting a PostgreSQL connection: {err}");
+                continue;
+            }
+        };
+
+        let context = context.clone();
+        let policy = policy.clone();
+        let users = users.clone();
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            match acceptor {
+                None => handle_connection(stream, context, &policy, &users).await,
+                Some(acceptor) => match tls::negotiate(stream, &acceptor).await {
+                    Ok(stream) => {
+                        handle_connection(stream, context, &policy, &users).await
+                    }
+                    Err(err) => {
+                        debug!("Error negotiating TLS for a PostgreSQL connection: {err}")
+                    }
+                },
+            }
+        });
+    }
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    context: Arc<SeafowlContext>,
+    policy: &AccessPolicy,
+    users: &[PostgresUser],
+) {
+    let (stream, user_context) = match auth::authenticate(stream, policy, users).await {
+        Ok(Some(authenticated)) => authenticated,
+        Ok(None) => return,
+        Err(err) => {
+            debug!("Error authenticating a PostgreSQL connection: {err}");
+            return;
+        }
+    };
+
+    let mut connection = Connection::new(SeafowlConvergenceEngine {
+        context,
+        user_context,
+    });
+    if let Err(err) = connection.run(stream).await {
+        debug!("PostgreSQL connection closed with an error: {err}");
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::schema::TlsConfig;
+
+use super::auth::{write_fatal_error, GSSENC_REQUEST_CODE, SSL_REQUEST_CODE};
+
+const PROTOCOL_VIOLATION_SQL_STATE: &str = "08P01";
+const INVALID_AUTHORIZATION_SQL_STATE: &str = "28000";
+
+fn invalid_input(path: &str, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Error loading {path}: {message}"),
+    )
+}
+
+fn open(path: &str) -> io::Result<BufReader<File>> {
+    Ok(BufReader::new(
+        File::open(path).map_err(|e| invalid_input(path, e))?,
+    ))
+}
+
+pub(super) fn build_acceptor(config: &TlsConfig) -> io::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut open(&config.cert_file)?)
+        .collect::<io::Result<Vec<_>>>()?;
+    let key = rustls_pemfile::private_key(&mut open(&config.key_file)?)?
+        .ok_or_else(|| invalid_input(&config.key_file, "No private key found"))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match &config.client_ca_file {
+        Some(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut open(ca_file)?) {
+                roots.add(cert?).map_err(|e| invalid_input(ca_file, e))?;
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .build()
+                .map_err(|e| invalid_input(ca_file, e))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let server_config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid_input(&config.cert_file, e))?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+// Once TLS is configured it is mandatory: the client has to open with an SSLRequest (declining
+// GSSAPI encryption beforehand is fine), after which we perform the handshake and the regular
+// startup/authentication flow continues on the encrypted stream.
+pub(super) async fn negotiate<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    acceptor: &TlsAcceptor,
+) -> io::Result<TlsStream<S>> {
+    loop {
+        let length = stream.read_i32().await?;
+        let code = stream.read_i32().await?;
+
+        if length == 8 && code == GSSENC_REQUEST_CODE {
+            stream.write_all(b"N").await?;
+            continue;
+        }
+
+        if length == 8 && code == SSL_REQUEST_CODE {
+            stream.write_all(b"S").await?;
+            return acceptor.accept(stream).await;
+        }
+
+        let (sql_state, message) = if length >= 8 {
+            (
+                INVALID_AUTHORIZATION_SQL_STATE,
+                "SSL is required to connect to this server",
+            )
+        } else {
+            (PROTOCOL_VIOLATION_SQL_STATE, "Invalid startup message")
+        };
+        write_fatal_error(&mut stream, sql_state, message).await?;
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+    use crate::config::schema::TlsConfig;
+
+    use super::{build_acceptor, negotiate, open, GSSENC_REQUEST_CODE, SSL_REQUEST_CODE};
+
+    // Holds a certificate for localhost, and the CA that signed it
+    const TLS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/tls");
+
+    fn acceptor() -> TlsAcceptor {
+        build_acceptor(&TlsConfig {
+            cert_file: format!("{TLS_DIR}/cert.pem"),
+            key_file: format!("{TLS_DIR}/key.pem"),
+            client_ca_file: None,
+        })
+        .unwrap()
+    }
+
+    fn connector() -> TlsConnector {
+        let mut roots = RootCertStore::empty();
+        for cert in
+            rustls_pemfile::certs(&mut open(&format!("{TLS_DIR}/ca.pem")).unwrap())
+        {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        TlsConnector::from(Arc::new(
+            ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        ))
+    }
+
+    fn request(code: i32) -> Vec<u8> {
+        [8_i32.to_be_bytes(), code.to_be_bytes()].concat()
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_ssl_request() {
+        let (mut client, server) = duplex(16 * 1024);
+        let server = tokio::spawn(async move {
+            let mut stream = negotiate(server, &acceptor()).await?;
+            let mut message = [0; 4];
+            stream.read_exact(&mut message).await?;
+            Ok::<_, io::Error>(message)
+        });
+
+        // GSSAPI encryption gets declined, after which the client asks for TLS instead
+        client
+            .write_all(&request(GSSENC_REQUEST_CODE))
+            .await
+            .unwrap();
+        assert_eq!(client.read_u8().await.unwrap(), b'N');
+        client.write_all(&request(SSL_REQUEST_CODE)).await.unwrap();
+        assert_eq!(client.read_u8().await.unwrap(), b'S');
+
+        // The rest of the session happens over TLS
+        let mut client = connector()
+            .connect(ServerName::try_from("localhost").unwrap(), client)
+            .await
+            .unwrap();
+        client.write_all(b"ping").await.unwrap();
+        client.flush().await.unwrap();
+        assert_eq!(&server.await.unwrap().unwrap(), b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_rejects_plaintext() {
+        let (mut client, server) = duplex(16 * 1024);
+        let server =
+            tokio::spawn(async move { negotiate(server, &acceptor()).await.map(|_| ()) });
+
+        // Send a startup message right away
+        let mut body = 196608_i32.to_be_bytes().to_vec();
+        body.extend(b"user\0alice\0\0");
+        let mut message = (body.len() as i32 + 4).to_be_bytes().to_vec();
+        message.extend(body);
+        client.write_all(&message).await.unwrap();
+
+        assert_eq!(
+            server.await.unwrap().unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        let mut response = vec![];
+        client.read_to_end(&mut response).await.unwrap();
+        assert_eq!(response[0], b'E');
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.contains("28000"));
+        assert!(response.contains("SSL is required to connect to this server"));
+    }
+}
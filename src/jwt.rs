@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet, KeyAlgorithm};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde_json::Value;
+
+use crate::config::schema::{Grant, GrantAccess, JwtConfig};
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    #[error("Error loading the JWKS file {path}: {reason}")]
+    InvalidKeySet { path: String, reason: String },
+
+    #[error("Invalid JWT: {0}")]
+    InvalidToken(String),
+}
+
+pub type JwtResult<T, E = JwtError> = Result<T, E>;
+
+struct JwtKey {
+    id: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+// The principal a validated token was issued to, alongside the databases it can access
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtIdentity {
+    pub principal: String,
+    pub grants: Vec<Grant>,
+}
+
+// Validates JWT bearer tokens against the keys from a local JWKS file, and maps their claims
+// to an identity. Only HS256 and RS256 keys are supported.
+pub struct JwtValidator {
+    config: JwtConfig,
+    keys: Vec<JwtKey>,
+}
+
+impl std::fmt::Debug for JwtValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtValidator")
+            .field("config", &self.config)
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl PartialEq for JwtValidator {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+
+impl Eq for JwtValidator {}
+
+impl JwtValidator {
+    pub fn try_new(config: &JwtConfig) -> JwtResult<Self> {
+        let invalid = |reason: String| JwtError::InvalidKeySet {
+            path: config.jwks_file.clone(),
+            reason,
+        };
+
+        let contents = std::fs::read_to_string(&config.jwks_file)
+            .map_err(|e| invalid(e.to_string()))?;
+        let jwks: JwkSet =
+            serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+
+        let keys = jwks
+            .keys
+            .iter()
+            .map(|jwk| {
+                let algorithm = match (&jwk.algorithm, jwk.common.key_algorithm) {
+                    (
+                        AlgorithmParameters::OctetKey(_),
+                        None | Some(KeyAlgorithm::HS256),
+                    ) => Algorithm::HS256,
+                    (AlgorithmParameters::RSA(_), None | Some(KeyAlgorithm::RS256)) => {
+                        Algorithm::RS256
+                    }
+                    (_, alg) => {
+                        return Err(invalid(format!(
+                            "Unsupported key {:?} ({alg:?}), expected HS256 or RS256",
+                            jwk.common.key_id
+                        )))
+                    }
+                };
+
+                Ok(JwtKey {
+                    id: jwk.common.key_id.clone(),
+                    algorithm,
+                    key: DecodingKey::from_jwk(jwk)
+                        .map_err(|e| invalid(e.to_string()))?,
+                })
+            })
+            .collect::<JwtResult<Vec<_>>>()?;
+
+        if keys.is_empty() {
+            return Err(invalid("No keys found".to_string()));
+        }
+
+        Ok(Self {
+            config: config.clone(),
+            keys,
+        })
+    }
+
+    // Whether the token is shaped like a JWT (header, payload and signature)
+    pub fn is_jwt(token: &str) -> bool {
+        token.split('.').count() == 3
+    }
+
+    pub fn validate(&self, token: &str) -> JwtResult<JwtIdentity> {
+        let header =
+            decode_header(token).map_err(|e| JwtError::InvalidToken(e.to_string()))?;
+
+        // If the token names its key use only that one, otherwise try all keys for the algorithm
+        let candidates = self.keys.iter().filter(|key| {
+            key.algorithm == header.alg && (header.kid.is_none() || key.id == header.kid)
+        });
+
+        let mut last_error = JwtError::InvalidToken(format!(
+            "No {:?} key found matching the key ID {:?}",
+            header.alg, header.kid
+        ));
+        for key in candidates {
+            match decode::<HashMap<String, Value>>(
+                token,
+                &key.key,
+                &self.validation(key.algorithm),
+            ) {
+                Ok(data) => return self.claims_to_identity(data.claims),
+                Err(e) => last_error = JwtError::InvalidToken(e.to_string()),
+            }
+        }
+
+        Err(last_error)
+    }
+
+    fn validation(&self, algorithm: Algorithm) -> Validation {
+        // Requires and checks `exp` by default
+        let mut validation = Validation::new(algorithm);
+        validation.validate_nbf = true;
+
+        if let Some(issuer) = &self.config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        validation
+    }
+
+    fn claims_to_identity(
+        &self,
+        claims: HashMap<String, Value>,
+    ) -> JwtResult<JwtIdentity> {
+        let principal = match claims.get(&self.config.principal_claim) {
+            Some(Value::String(principal)) => principal.clone(),
+            _ => {
+                return Err(JwtError::InvalidToken(format!(
+                    "Missing string claim {}",
+                    self.config.principal_claim
+                )))
+            }
+        };
+
+        let access = match claims.get(&self.config.access_claim) {
+            None => GrantAccess::Read,
+            Some(Value::String(access)) if access == "read" => GrantAccess::Read,
+            Some(Value::String(access)) if access == "write" => GrantAccess::Write,
+            Some(access) => {
+                return Err(JwtError::InvalidToken(format!(
+                    "Unsupported access level {access}, expected \"read\" or \"write\""
+                )))
+            }
+        };
+
+        let databases = match claims.get(&self.config.databases_claim) {
+            None => vec![],
+            Some(Value::String(database)) => vec![database.clone()],
+            Some(Value::Array(databases)) => databases
+                .iter()
+                .map(|database| match database {
+                    Value::String(database) => Ok(database.clone()),
+                    _ => Err(JwtError::InvalidToken(format!(
+                        "Claim {} must be a list of database names",
+                        self.config.databases_claim
+                    ))),
+                })
+                .collect::<JwtResult<_>>()?,
+            Some(_) => {
+                return Err(JwtError::InvalidToken(format!(
+                    "Claim {} must be a list of database names",
+                    self.config.databases_claim
+                )))
+            }
+        };
+
+        Ok(JwtIdentity {
+            principal,
+            grants: databases
+                .into_iter()
+                .map(|database| Grant {
+                    database,
+                    schema: None,
+                    access,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use rstest::rstest;
+    use serde_json::{json, Value};
+    use tempfile::NamedTempFile;
+
+    use crate::config::schema::{Grant, GrantAccess, JwtConfig};
+
+    use super::{JwtIdentity, JwtValidator};
+
+    pub const JWT_SECRET: &[u8] = b"super-secret-signing-key";
+    pub const JWT_KEY_ID: &str = "test-key";
+
+    pub fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    // Write out a JWKS file with a single HS256 key and return its config
+    pub fn jwt_config() -> (NamedTempFile, JwtConfig) {
+        let mut file = NamedTempFile::new().unwrap();
+        let jwks = json!({
+            "keys": [{
+                "kty": "oct",
+                "kid": JWT_KEY_ID,
+                "alg": "HS256",
+                "k": URL_SAFE_NO_PAD.encode(JWT_SECRET),
+            }]
+        });
+        file.write_all(jwks.to_string().as_bytes()).unwrap();
+
+        let config = JwtConfig {
+            jwks_file: file.path().to_str().unwrap().to_string(),
+            issuer: None,
+            audience: None,
+            principal_claim: "sub".to_string(),
+            databases_claim: "databases".to_string(),
+            access_claim: "access".to_string(),
+        };
+
+        (file, config)
+    }
+
+    pub fn sign(claims: Value) -> String {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = Some(JWT_KEY_ID.to_string());
+        encode(&header, &claims, &EncodingKey::from_secret(JWT_SECRET)).unwrap()
+    }
+
+    #[test]
+    fn test_jwt_valid_token() {
+        let (_file, config) = jwt_config();
+        let validator = JwtValidator::try_new(&config).unwrap();
+
+        let token = sign(json!({
+            "sub": "alice",
+            "exp": now() + 3600,
+            "databases": ["default", "analytics"],
+            "access": "write",
+        }));
+        assert!(JwtValidator::is_jwt(&token));
+
+        assert_eq!(
+            validator.validate(&token).unwrap(),
+            JwtIdentity {
+                principal: "alice".to_string(),
+                grants: vec![
+                    Grant {
+                        database: "default".to_string(),
+                        schema: None,
+                        access: GrantAccess::Write,
+                    },
+                    Grant {
+                        database: "analytics".to_string(),
+                        schema: None,
+                        access: GrantAccess::Write,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::expired(json!({"sub": "alice", "exp": now() - 3600}), "ExpiredSignature")]
+    #[case::not_yet_valid(
+        json!({"sub": "alice", "exp": now() + 7200, "nbf": now() + 3600}),
+        "ImmatureSignature"
+    )]
+    #[case::no_expiry(json!({"sub": "alice"}), "Missing required claim: exp")]
+    #[case::no_principal(json!({"exp": now() + 3600}), "Missing string claim sub")]
+    #[case::bad_access(
+        json!({"sub": "alice", "exp": now() + 3600, "access": "admin"}),
+        "Unsupported access level \"admin\""
+    )]
+    fn test_jwt_invalid_claims(#[case] claims: Value, #[case] error: &str) {
+        let (_file, config) = jwt_config();
+        let validator = JwtValidator::try_new(&config).unwrap();
+
+        let err = validator.validate(&sign(claims)).unwrap_err().to_string();
+        assert!(err.contains(error), "{err} doesn't contain {error}");
+    }
+
+    #[test]
+    fn test_jwt_wrong_key() {
+        let (_file, config) = jwt_config();
+        let validator = JwtValidator::try_new(&config).unwrap();
+
+        let token = encode(
+            &Header::new(jsonwebtoken::Algorithm::HS256),
+            &json!({"sub": "mallory", "exp": now() + 3600}),
+            &EncodingKey::from_secret(b"some-other-key"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            validator.validate(&token).unwrap_err().to_string(),
+            "Invalid JWT: InvalidSignature"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 #![feature(let_chains)]
 
+pub mod audit;
 pub mod auth;
 pub mod catalog;
 pub mod cli;
@@ -7,10 +8,12 @@ pub mod config;
 pub mod context;
 pub mod datafusion;
 pub mod frontend;
+pub mod jwt;
 pub mod memory_pool;
 pub mod nodes;
 pub mod object_store;
 pub mod provider;
+pub mod queries;
 pub mod repository;
 pub mod system_tables;
 pub mod utils;
@@ -209,9 +209,11 @@ async fn main() {
                     "Starting the PostgreSQL frontend on {}:{}",
                     pg.bind_host, pg.bind_port
                 );
-                warn!(
-                    "The PostgreSQL frontend doesn't have authentication or encryption and should only be used in development!"
-                );
+                if pg.tls_settings.tls_cert_file.is_none() {
+                    warn!(
+                        "The PostgreSQL frontend doesn't have TLS configured, so passwords are sent in cleartext and it should only be used in development!"
+                    );
+                }
                 select! {
                     e = run_pg_server(context, pg) => e,
                     _ = h.on_shutdown_requested() => (),
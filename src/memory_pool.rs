@@ -2,11 +2,15 @@
 /// deallocations, usage. Note that not all query operators register
 /// themselves with the pool, so this isn't an exact limit or breakdown
 /// of the memory used by the query processing.
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use datafusion::execution::memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation};
+use datafusion_common::DataFusionError;
 use metrics::{counter, describe_counter, describe_gauge, gauge, Gauge};
 
+use crate::queries::QueryLimitError;
+
 const ALLOCATIONS: &str = "seafowl_datafusion_memory_pool_allocated_bytes_total";
 const DEALLOCATIONS: &str = "seafowl_datafusion_memory_pool_freed_bytes_total";
 const RESERVED: &str = "seafowl_datafusion_memory_pool_reserved_bytes_current";
@@ -124,6 +128,80 @@ impl MemoryPool for MemoryPoolMetrics {
     }
 }
 
+// Per-query view of the shared memory pool, keeping track of how much of it the query has
+// reserved, so that it can be reported in `system.running_queries`, and optionally capping it
+#[derive(Debug)]
+pub struct QueryMemoryPool {
+    inner: Arc<dyn MemoryPool>,
+    reserved: AtomicUsize,
+    limit: Option<usize>,
+}
+
+impl QueryMemoryPool {
+    pub fn new(inner: Arc<dyn MemoryPool>, limit: Option<usize>) -> Self {
+        Self {
+            inner,
+            reserved: AtomicUsize::new(0),
+            limit,
+        }
+    }
+}
+
+impl MemoryPool for QueryMemoryPool {
+    fn register(&self, consumer: &MemoryConsumer) {
+        self.inner.register(consumer)
+    }
+
+    fn unregister(&self, consumer: &MemoryConsumer) {
+        self.inner.unregister(consumer)
+    }
+
+    fn grow(&self, reservation: &MemoryReservation, additional: usize) {
+        self.inner.grow(reservation, additional);
+        self.reserved.fetch_add(additional, Ordering::Relaxed);
+    }
+
+    fn shrink(&self, reservation: &MemoryReservation, shrink: usize) {
+        self.inner.shrink(reservation, shrink);
+        self.reserved.fetch_sub(shrink, Ordering::Relaxed);
+    }
+
+    fn try_grow(
+        &self,
+        reservation: &MemoryReservation,
+        additional: usize,
+    ) -> datafusion_common::Result<()> {
+        // Claim the memory against the query's limit first, so that concurrent partitions can't
+        // jointly exceed it
+        self.reserved
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |reserved| {
+                let new_reserved = reserved + additional;
+                (!self.limit.is_some_and(|limit| new_reserved > limit))
+                    .then_some(new_reserved)
+            })
+            .map_err(|reserved| {
+                // Raised as an external error, so that the frontends can tell it apart
+                DataFusionError::External(Box::new(QueryLimitError::TooMuchMemory {
+                    consumer: reservation.consumer().name().to_string(),
+                    additional,
+                    reserved,
+                    limit: self.limit.unwrap_or_default(),
+                }))
+            })?;
+
+        if let Err(err) = self.inner.try_grow(reservation, additional) {
+            self.reserved.fetch_sub(additional, Ordering::Relaxed);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // Only the memory reserved by this query
+    fn reserved(&self) -> usize {
+        self.reserved.load(Ordering::Relaxed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -136,7 +214,9 @@ mod tests {
 
     use crate::utils::assert_metric;
 
-    use super::{MemoryPoolMetrics, ALLOCATIONS, DEALLOCATIONS, RESERVED};
+    use super::{
+        MemoryPoolMetrics, QueryMemoryPool, ALLOCATIONS, DEALLOCATIONS, RESERVED,
+    };
 
     #[test]
     fn metrics() {
@@ -178,4 +258,39 @@ mod tests {
         );
         assert_metric(&recorder, RESERVED, 0);
     }
+
+    #[test]
+    fn query_memory_pool() {
+        let pool: Arc<dyn MemoryPool> = Arc::new(GreedyMemoryPool::new(16));
+        let query_pool: Arc<dyn MemoryPool> =
+            Arc::new(QueryMemoryPool::new(pool.clone(), None));
+
+        let mut other = MemoryConsumer::new("Other").register(&pool);
+        other.grow(4);
+
+        let mut reservation = MemoryConsumer::new("SomeConsumer").register(&query_pool);
+        reservation.try_grow(8).unwrap();
+        reservation.try_grow(8).unwrap_err();
+        assert_eq!(query_pool.reserved(), 8);
+        assert_eq!(pool.reserved(), 12);
+
+        reservation.shrink(2);
+        assert_eq!(query_pool.reserved(), 6);
+        reservation.free();
+        assert_eq!(query_pool.reserved(), 0);
+        assert_eq!(pool.reserved(), 4);
+
+        // The query can be capped below the capacity of the shared pool
+        let query_pool: Arc<dyn MemoryPool> =
+            Arc::new(QueryMemoryPool::new(pool.clone(), Some(6)));
+        let mut reservation = MemoryConsumer::new("SomeConsumer").register(&query_pool);
+        reservation.try_grow(4).unwrap();
+        assert_eq!(
+            reservation.try_grow(4).unwrap_err().to_string(),
+            "External error: Failed to allocate additional 4 bytes for SomeConsumer \
+            with 4 bytes already allocated for the query - maximum query memory is 6 bytes"
+        );
+        assert_eq!(query_pool.reserved(), 4);
+        assert_eq!(pool.reserved(), 8);
+    }
 }
@@ -1,6 +1,6 @@
-use datafusion::common::DFSchemaRef;
+use datafusion::common::{DFSchemaRef, TableReference};
 
-use arrow_schema::Schema;
+use arrow_schema::{Field, Schema};
 use std::hash::{Hash, Hasher};
 use std::{any::Any, fmt, sync::Arc, vec};
 
@@ -26,11 +26,96 @@ pub struct CreateTable {
     pub name: String,
     /// Option to not error if table already exists
     pub if_not_exists: bool,
+    /// Columns by which the table data is partitioned
+    pub partition_columns: Vec<String>,
+    /// Columns by which the table data is sorted on write
+    pub cluster_columns: Vec<String>,
+    /// The query to populate the table with (for CREATE TABLE ... AS)
+    pub input: Option<Arc<LogicalPlan>>,
 
     /// Dummy result schema for the plan (empty)
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreateMaterializedView {
+    /// The view name
+    pub name: String,
+    /// The original statement, persisted in the catalog for subsequent refreshes
+    pub definition: String,
+    /// The plan of the defining query
+    pub input: Arc<LogicalPlan>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct RefreshMaterializedView {
+    /// The view name
+    pub name: String,
+    /// The re-planned defining query
+    pub input: Arc<LogicalPlan>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Merge {
+    /// The target table name
+    pub name: String,
+    /// The join condition between the target and the source
+    pub merge_predicate: String,
+    /// The plan joining the target and the source, producing the new values of the target rows
+    pub input: Arc<LogicalPlan>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct AlteredColumn {
+    /// The column definition after the alteration
+    pub field: Field,
+    /// The existing column holding the data for this column (none for added columns)
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct AlterTable {
+    /// The table name
+    pub name: String,
+    /// The full list of table columns after the alteration
+    pub columns: Vec<AlteredColumn>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreatePolicy {
+    /// The policy name
+    pub name: String,
+    /// The table that the policy restricts
+    pub table_name: String,
+    /// The column that the policy masks, if any
+    pub column: Option<String>,
+    /// The SQL predicate that visible rows must satisfy, or the masked column values
+    pub expression: String,
+    /// The principals that the policy applies to (all of them if none)
+    pub principals: Option<Vec<String>>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct DropPolicy {
+    /// The policy name
+    pub name: String,
+    /// The table that the policy is defined on
+    pub table_name: String,
+    pub if_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct CreateFunction {
     /// The function name
@@ -69,6 +154,16 @@ pub struct Vacuum {
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Optimize {
+    /// The table to compact
+    pub table_name: String,
+    /// Optional filter restricting the compaction to a subset of the table files
+    pub predicate: Option<Expr>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Truncate {
     pub table_name: String,
@@ -76,21 +171,102 @@ pub struct Truncate {
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct KillQuery {
+    /// The id of the running query to cancel
+    pub id: u64,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(AsRefStr, Debug, Clone, Hash, PartialEq, Eq)]
 pub enum SeafowlExtensionNode {
     ConvertTable(ConvertTable),
     CreateTable(CreateTable),
+    CreateMaterializedView(CreateMaterializedView),
+    RefreshMaterializedView(RefreshMaterializedView),
+    Merge(Merge),
+    AlterTable(AlterTable),
+    CreatePolicy(CreatePolicy),
+    DropPolicy(DropPolicy),
     CreateFunction(CreateFunction),
     DropFunction(DropFunction),
     RenameTable(RenameTable),
     Truncate(Truncate),
     Vacuum(Vacuum),
+    Optimize(Optimize),
+    KillQuery(KillQuery),
 }
 
 impl SeafowlExtensionNode {
     pub fn from_dynamic(node: &Arc<dyn UserDefinedLogicalNode>) -> Option<&Self> {
         node.as_any().downcast_ref::<Self>()
     }
+
+    /// The tables that the node creates, modifies or drops; empty if the node isn't scoped to
+    /// particular tables (e.g. functions or database-wide vacuuming)
+    pub fn target_tables(&self) -> Vec<TableReference> {
+        match self {
+            SeafowlExtensionNode::ConvertTable(ConvertTable { name, .. })
+            | SeafowlExtensionNode::CreateTable(CreateTable { name, .. })
+            | SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                name,
+                ..
+            })
+            | SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                name,
+                ..
+            })
+            | SeafowlExtensionNode::Merge(Merge { name, .. })
+            | SeafowlExtensionNode::AlterTable(AlterTable { name, .. })
+            | SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                table_name: name, ..
+            })
+            | SeafowlExtensionNode::DropPolicy(DropPolicy {
+                table_name: name, ..
+            })
+            | SeafowlExtensionNode::Truncate(Truncate {
+                table_name: name, ..
+            })
+            | SeafowlExtensionNode::Vacuum(Vacuum {
+                table_name: Some(name),
+                ..
+            })
+            | SeafowlExtensionNode::Optimize(Optimize {
+                table_name: name, ..
+            }) => vec![TableReference::from(name.as_str())],
+            SeafowlExtensionNode::RenameTable(RenameTable {
+                old_name, new_name, ..
+            }) => vec![
+                TableReference::from(old_name.as_str()),
+                TableReference::from(new_name.as_str()),
+            ],
+            SeafowlExtensionNode::CreateFunction(_)
+            | SeafowlExtensionNode::DropFunction(_)
+            | SeafowlExtensionNode::Vacuum(_)
+            | SeafowlExtensionNode::KillQuery(_) => vec![],
+        }
+    }
+
+    /// The plan producing the data that the node writes out, if any. Note that it isn't exposed
+    /// through `inputs`, since we plan and execute these nodes ourselves.
+    pub fn data_input(&self) -> Option<&LogicalPlan> {
+        match self {
+            SeafowlExtensionNode::CreateTable(CreateTable { input, .. }) => {
+                input.as_deref()
+            }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                input,
+                ..
+            })
+            | SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                input,
+                ..
+            })
+            | SeafowlExtensionNode::Merge(Merge { input, .. }) => Some(input.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 impl UserDefinedLogicalNode for SeafowlExtensionNode {
@@ -118,6 +294,24 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::CreateTable(CreateTable { output_schema, .. }) => {
                 output_schema
             }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                output_schema,
+                ..
+            }) => output_schema,
+            SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                output_schema,
+                ..
+            }) => output_schema,
+            SeafowlExtensionNode::Merge(Merge { output_schema, .. }) => output_schema,
+            SeafowlExtensionNode::AlterTable(AlterTable { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                output_schema, ..
+            }) => output_schema,
+            SeafowlExtensionNode::DropPolicy(DropPolicy { output_schema, .. }) => {
+                output_schema
+            }
             SeafowlExtensionNode::CreateFunction(CreateFunction {
                 output_schema,
                 ..
@@ -132,6 +326,12 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
                 output_schema
             }
             SeafowlExtensionNode::Vacuum(Vacuum { output_schema, .. }) => output_schema,
+            SeafowlExtensionNode::Optimize(Optimize { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::KillQuery(KillQuery { output_schema, .. }) => {
+                output_schema
+            }
         }
     }
 
@@ -152,6 +352,34 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::CreateTable(CreateTable { name, .. }) => {
                 write!(f, "Create: {name}")
             }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                name,
+                ..
+            }) => {
+                write!(f, "CreateMaterializedView: {name}")
+            }
+            SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                name,
+                ..
+            }) => {
+                write!(f, "RefreshMaterializedView: {name}")
+            }
+            SeafowlExtensionNode::Merge(Merge { name, .. }) => {
+                write!(f, "Merge: {name}")
+            }
+            SeafowlExtensionNode::AlterTable(AlterTable { name, .. }) => {
+                write!(f, "AlterTable: {name}")
+            }
+            SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                name, table_name, ..
+            }) => {
+                write!(f, "CreatePolicy: {name} on {table_name}")
+            }
+            SeafowlExtensionNode::DropPolicy(DropPolicy {
+                name, table_name, ..
+            }) => {
+                write!(f, "DropPolicy: {name} on {table_name}")
+            }
             SeafowlExtensionNode::CreateFunction(CreateFunction { name, .. }) => {
                 write!(f, "CreateFunction: {name}")
             }
@@ -178,6 +406,12 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
                     }
                 )
             }
+            SeafowlExtensionNode::Optimize(Optimize { table_name, .. }) => {
+                write!(f, "Optimize: {table_name}")
+            }
+            SeafowlExtensionNode::KillQuery(KillQuery { id, .. }) => {
+                write!(f, "KillQuery: {id}")
+            }
         }
     }
 
@@ -55,6 +55,39 @@ impl CatalogProvider for SeafowlDatabase {
 pub struct SeafowlSchema {
     pub name: Arc<str>,
     pub tables: DashMap<Arc<str>, Arc<dyn TableProvider>>,
+    // View definitions keyed by view name; once a view is planned the resulting `ViewTable` is
+    // placed in `tables` under the same name.
+    pub views: DashMap<Arc<str>, Arc<str>>,
+    // Row-level security and column masking policies keyed by the name of the table they restrict
+    pub policies: HashMap<Arc<str>, Vec<TablePolicy>>,
+    // Versions to read instead of the latest one, keyed by table location URI
+    pub pinned_versions: Arc<HashMap<String, i64>>,
+}
+
+impl SeafowlSchema {
+    pub fn is_view(&self, name: &str) -> bool {
+        self.views.contains_key(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TablePolicy {
+    pub name: String,
+    // The column that the policy masks; if none, the policy filters rows instead
+    pub column: Option<String>,
+    // SQL expression that the rows need to satisfy in order to be visible, or for masking
+    // policies, the expression whose value is returned in place of the column value
+    pub expression: String,
+    // The names of the principals that the policy applies to; everyone if none
+    pub principals: Option<Vec<String>>,
+}
+
+impl TablePolicy {
+    pub fn applies_to(&self, principal: &str) -> bool {
+        self.principals
+            .as_ref()
+            .map_or(true, |principals| principals.iter().any(|p| p == principal))
+    }
 }
 
 #[async_trait]
@@ -67,6 +100,12 @@ impl SchemaProvider for SeafowlSchema {
         self.tables
             .iter()
             .map(|s| s.key().to_string())
+            .chain(
+                self.views
+                    .iter()
+                    .filter(|v| !self.tables.contains_key(v.key()))
+                    .map(|v| v.key().to_string()),
+            )
             .collect::<Vec<_>>()
     }
 
@@ -81,9 +120,11 @@ impl SchemaProvider for SeafowlSchema {
         // updating the existing table is to optimize potential multi-lookups during processing of
         // a single query.
         let mut delta_table = match self.tables.get(name) {
+            // Views that weren't planned ahead of time (e.g. because their definition is no
+            // longer valid) are skipped.
             None => return Ok(None),
             Some(table) => match table.as_any().downcast_ref::<DeltaTable>() {
-                // This shouldn't happen since we store only DeltaTable's in the map
+                // Planned views and versioned tables
                 None => return Ok(Some(table.clone())),
                 Some(delta_table) => {
                     if delta_table.version() != -1 {
@@ -98,7 +139,13 @@ impl SchemaProvider for SeafowlSchema {
             },
         };
 
-        delta_table.load().await?;
+        match self
+            .pinned_versions
+            .get(&delta_table.log_store().root_uri())
+        {
+            Some(version) => delta_table.load_version(*version).await?,
+            None => delta_table.load().await?,
+        }
 
         let table = Arc::from(delta_table) as Arc<dyn TableProvider>;
         self.tables.insert(Arc::from(name), table.clone());
@@ -121,7 +168,7 @@ impl SchemaProvider for SeafowlSchema {
     }
 
     fn table_exist(&self, name: &str) -> bool {
-        self.tables.contains_key(name)
+        self.tables.contains_key(name) || self.views.contains_key(name)
     }
 }
 
@@ -0,0 +1,664 @@
+//! Registry of the queries being executed, along with a bounded log of the finished ones,
+//! backing the `system.running_queries` and `system.query_log` tables and query cancellation,
+//! as well as enforcing the per-principal query limits and the per-query execution limits.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use arrow::record_batch::RecordBatch;
+use arrow_schema::SchemaRef;
+use chrono::{DateTime, Utc};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::memory_pool::MemoryPool;
+use datafusion::physical_plan::{RecordBatchStream, SendableRecordBatchStream};
+use futures::task::AtomicWaker;
+use futures::{Stream, StreamExt};
+use strum_macros::Display;
+use tokio::time::Sleep;
+
+use crate::config::schema::{QueryLimits, MEBIBYTES};
+use crate::memory_pool::QueryMemoryPool;
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq, Clone)]
+pub enum QueryLimitError {
+    #[error("Principal {principal:?} already has {limit} queries running")]
+    TooManyConcurrentQueries { principal: String, limit: usize },
+
+    #[error("Principal {principal:?} exceeded the limit of {limit} queries per minute")]
+    TooManyQueriesPerMinute { principal: String, limit: usize },
+
+    #[error(
+        "Failed to allocate additional {additional} bytes for {consumer} with {reserved} bytes \
+        already allocated for the query - maximum query memory is {limit} bytes"
+    )]
+    TooMuchMemory {
+        consumer: String,
+        additional: usize,
+        reserved: usize,
+        limit: usize,
+    },
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq, Clone)]
+pub enum ExecutionLimitError {
+    #[error("Query exceeded the statement timeout of {}ms", .timeout.as_millis())]
+    Timeout { timeout: Duration },
+
+    #[error("Query result exceeded the limit of {limit} rows")]
+    TooManyRows { limit: u64 },
+
+    #[error("Query result exceeded the limit of {limit} bytes")]
+    TooManyBytes { limit: u64 },
+}
+
+// Bounds on the duration and the result size of a single query, past which it gets aborted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    pub statement_timeout: Option<Duration>,
+    pub max_result_rows: Option<u64>,
+    // Measured as the in-memory size of the result batches
+    pub max_result_bytes: Option<u64>,
+}
+
+impl ExecutionLimits {
+    // Combine the limits with the ones requested by a client, so that the stricter one applies
+    pub fn restrict(self, other: ExecutionLimits) -> Self {
+        fn min<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        Self {
+            statement_timeout: min(self.statement_timeout, other.statement_timeout),
+            max_result_rows: min(self.max_result_rows, other.max_result_rows),
+            max_result_bytes: min(self.max_result_bytes, other.max_result_bytes),
+        }
+    }
+
+    // Bound the planning phase of the query by the statement timeout. Note that this includes
+    // any writes, since these get performed during planning.
+    pub async fn with_timeout<T>(
+        &self,
+        future: impl Future<Output = T>,
+    ) -> Result<T, ExecutionLimitError> {
+        match self.statement_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| ExecutionLimitError::Timeout { timeout }),
+            None => Ok(future.await),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum QueryStatus {
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug)]
+pub struct RunningQuery {
+    pub id: u64,
+    pub principal: String,
+    pub database: String,
+    pub sql: String,
+    pub start_time: DateTime<Utc>,
+    memory_pool: Arc<QueryMemoryPool>,
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl RunningQuery {
+    // Signal the query's result stream to stop; this only takes effect once the query reaches
+    // the execution phase, i.e. writes performed during planning aren't interrupted
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.waker.wake();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // Memory currently reserved by the query's operators in the shared memory pool
+    pub fn memory_reserved(&self) -> usize {
+        self.memory_pool.reserved()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinishedQuery {
+    pub id: u64,
+    pub principal: String,
+    pub database: String,
+    pub sql: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub status: QueryStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct QueryRegistry {
+    next_id: AtomicU64,
+    running: Mutex<BTreeMap<u64, Arc<RunningQuery>>>,
+    log: Mutex<VecDeque<FinishedQuery>>,
+    log_size: usize,
+    limits: QueryLimits,
+    // Start times of each principal's queries within the last rate limiting window
+    recent: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl QueryRegistry {
+    pub fn new(log_size: usize, limits: QueryLimits) -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            running: Mutex::new(BTreeMap::new()),
+            log: Mutex::new(VecDeque::with_capacity(log_size)),
+            log_size,
+            limits,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Register a new query, which stays in the list of running queries until the returned guard
+    // is dropped. Fails if the principal is already at its concurrency or rate limit.
+    pub fn start(
+        self: &Arc<Self>,
+        principal: &str,
+        database: &str,
+        sql: &str,
+        memory_pool: Arc<dyn MemoryPool>,
+    ) -> Result<QueryGuard, QueryLimitError> {
+        let limits = self.limits.for_principal(principal);
+
+        let mut running = self.running.lock().expect("query registry lock poisoned");
+        if let Some(limit) = limits.max_concurrent_queries
+            && running
+                .values()
+                .filter(|query| query.principal == principal)
+                .count()
+                >= limit
+        {
+            return Err(QueryLimitError::TooManyConcurrentQueries {
+                principal: principal.to_string(),
+                limit,
+            });
+        }
+
+        if let Some(limit) = limits.queries_per_minute {
+            let now = Instant::now();
+            let mut recent = self.recent.lock().expect("query registry lock poisoned");
+            // Prune the principals that have been idle for a while, so that the map stays small
+            recent.retain(|_, starts| {
+                starts
+                    .back()
+                    .is_some_and(|start| now.duration_since(*start) < RATE_LIMIT_WINDOW)
+            });
+
+            let starts = recent.entry(principal.to_string()).or_default();
+            while let Some(start) = starts.front()
+                && now.duration_since(*start) >= RATE_LIMIT_WINDOW
+            {
+                starts.pop_front();
+            }
+            if starts.len() >= limit {
+                return Err(QueryLimitError::TooManyQueriesPerMinute {
+                    principal: principal.to_string(),
+                    limit,
+                });
+            }
+            starts.push_back(now);
+        }
+
+        let memory_limit = limits
+            .max_query_memory
+            .map(|max_query_memory| (max_query_memory * MEBIBYTES) as usize);
+        let query = Arc::new(RunningQuery {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            principal: principal.to_string(),
+            database: database.to_string(),
+            sql: sql.to_string(),
+            start_time: Utc::now(),
+            memory_pool: Arc::new(QueryMemoryPool::new(memory_pool, memory_limit)),
+            cancelled: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        });
+        running.insert(query.id, query.clone());
+
+        Ok(QueryGuard {
+            query,
+            registry: self.clone(),
+            status: QueryStatus::Finished,
+            error: None,
+            started: Instant::now(),
+            limits: ExecutionLimits::default(),
+        })
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<RunningQuery>> {
+        self.running
+            .lock()
+            .expect("query registry lock poisoned")
+            .get(&id)
+            .cloned()
+    }
+
+    pub fn running(&self) -> Vec<Arc<RunningQuery>> {
+        self.running
+            .lock()
+            .expect("query registry lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub fn log(&self) -> Vec<FinishedQuery> {
+        self.log
+            .lock()
+            .expect("query registry lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    fn finish(&self, guard: &QueryGuard) {
+        let query = &guard.query;
+        self.running
+            .lock()
+            .expect("query registry lock poisoned")
+            .remove(&query.id);
+
+        if self.log_size == 0 {
+            return;
+        }
+        let mut log = self.log.lock().expect("query registry lock poisoned");
+        if log.len() == self.log_size {
+            log.pop_front();
+        }
+        log.push_back(FinishedQuery {
+            id: query.id,
+            principal: query.principal.clone(),
+            database: query.database.clone(),
+            sql: query.sql.clone(),
+            start_time: query.start_time,
+            end_time: Utc::now(),
+            status: guard.status,
+            error: guard.error.clone(),
+        });
+    }
+}
+
+// Handle on a registered query; moves the query over to the log once dropped
+#[derive(Debug)]
+pub struct QueryGuard {
+    query: Arc<RunningQuery>,
+    registry: Arc<QueryRegistry>,
+    status: QueryStatus,
+    error: Option<String>,
+    started: Instant,
+    limits: ExecutionLimits,
+}
+
+impl QueryGuard {
+    // Abort the query's result stream once it runs past any of the limits
+    pub fn with_limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn id(&self) -> u64 {
+        self.query.id
+    }
+
+    pub fn memory_pool(&self) -> Arc<dyn MemoryPool> {
+        self.query.memory_pool.clone()
+    }
+
+    pub fn fail(&mut self, error: impl ToString) {
+        self.status = QueryStatus::Failed;
+        self.error = Some(error.to_string());
+    }
+
+    // Wrap the query's result stream so that it can be cancelled and is subject to the execution
+    // limits, and keep the query registered until the stream is exhausted or dropped
+    pub fn track(self, stream: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        // The timeout counts from the start of the query, so that it includes the planning
+        let timeout = self.limits.statement_timeout.map(|timeout| {
+            Box::pin(tokio::time::sleep_until((self.started + timeout).into()))
+        });
+
+        Box::pin(TrackedStream {
+            schema: stream.schema(),
+            inner: Some(stream),
+            guard: self,
+            timeout,
+            rows: 0,
+            bytes: 0,
+        })
+    }
+}
+
+impl Drop for QueryGuard {
+    fn drop(&mut self) {
+        self.registry.finish(self);
+    }
+}
+
+struct TrackedStream {
+    schema: SchemaRef,
+    // Dropped as soon as the query is cancelled, which aborts the underlying execution
+    inner: Option<SendableRecordBatchStream>,
+    guard: QueryGuard,
+    timeout: Option<Pin<Box<Sleep>>>,
+    // Size of the results returned so far
+    rows: u64,
+    bytes: u64,
+}
+
+impl TrackedStream {
+    fn abort(&mut self, error: ExecutionLimitError) -> Poll<Option<Result<RecordBatch>>> {
+        self.inner = None;
+        self.guard.fail(&error);
+        Poll::Ready(Some(Err(DataFusionError::External(Box::new(error)))))
+    }
+}
+
+impl Stream for TrackedStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        this.guard.query.waker.register(cx.waker());
+        if this.guard.query.is_cancelled() {
+            this.inner = None;
+            this.guard.status = QueryStatus::Cancelled;
+            return Poll::Ready(Some(Err(DataFusionError::Execution(format!(
+                "Query {} was cancelled",
+                this.guard.id()
+            )))));
+        }
+
+        if let Some(timeout) = this.timeout.as_mut()
+            && timeout.as_mut().poll(cx).is_ready()
+        {
+            let timeout = this.guard.limits.statement_timeout.unwrap_or_default();
+            return this.abort(ExecutionLimitError::Timeout { timeout });
+        }
+
+        let poll = inner.poll_next_unpin(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(batch))) => {
+                this.rows += batch.num_rows() as u64;
+                this.bytes += batch.get_array_memory_size() as u64;
+
+                let limits = this.guard.limits;
+                if let Some(limit) = limits.max_result_rows
+                    && this.rows > limit
+                {
+                    return this.abort(ExecutionLimitError::TooManyRows { limit });
+                }
+                if let Some(limit) = limits.max_result_bytes
+                    && this.bytes > limit
+                {
+                    return this.abort(ExecutionLimitError::TooManyBytes { limit });
+                }
+            }
+            Poll::Ready(Some(Err(e))) => this.guard.fail(e),
+            Poll::Ready(None) => this.inner = None,
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl RecordBatchStream for TrackedStream {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use arrow::array::Int32Array;
+    use arrow::record_batch::RecordBatch;
+    use arrow_schema::{DataType, Field, Schema};
+    use datafusion::execution::memory_pool::UnboundedMemoryPool;
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::{stream, StreamExt};
+
+    use crate::config::schema::{PrincipalQueryLimits, QueryLimits};
+
+    use super::{
+        ExecutionLimitError, ExecutionLimits, QueryLimitError, QueryRegistry, QueryStatus,
+    };
+
+    #[tokio::test]
+    async fn test_query_cancellation() {
+        let registry = Arc::new(QueryRegistry::new(2, QueryLimits::default()));
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1]))],
+        )
+        .unwrap();
+
+        // An endless stream of batches
+        let query = registry
+            .start(
+                "alice",
+                "default",
+                "SELECT 1",
+                Arc::new(UnboundedMemoryPool::default()),
+            )
+            .unwrap();
+        let id = query.id();
+        let mut results = query.track(Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            stream::repeat(batch).map(Ok),
+        )));
+        assert!(results.next().await.unwrap().is_ok());
+        assert_eq!(registry.running().len(), 1);
+        assert!(registry.log().is_empty());
+
+        registry.get(id).unwrap().cancel();
+        assert_eq!(
+            results.next().await.unwrap().unwrap_err().to_string(),
+            format!("Execution error: Query {id} was cancelled")
+        );
+        assert!(results.next().await.is_none());
+
+        // The query is logged only once the stream is dropped
+        drop(results);
+        assert!(registry.running().is_empty());
+        let log = registry.log();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].id, id);
+        assert_eq!(log[0].principal, "alice");
+        assert_eq!(log[0].status, QueryStatus::Cancelled);
+
+        // The log only keeps the latest queries
+        for _ in 0..3 {
+            registry
+                .start(
+                    "bob",
+                    "default",
+                    "SELECT 2",
+                    Arc::new(UnboundedMemoryPool::default()),
+                )
+                .unwrap();
+        }
+        let log = registry.log();
+        assert_eq!(log.len(), 2);
+        assert!(log.iter().all(
+            |query| query.principal == "bob" && query.status == QueryStatus::Finished
+        ));
+    }
+
+    #[test]
+    fn test_query_limits() {
+        let registry = Arc::new(QueryRegistry::new(
+            0,
+            QueryLimits {
+                max_concurrent_queries: Some(2),
+                queries_per_minute: Some(3),
+                max_query_memory: None,
+                principals: vec![PrincipalQueryLimits {
+                    principal: "alice".to_string(),
+                    max_concurrent_queries: Some(1),
+                    queries_per_minute: None,
+                    max_query_memory: None,
+                }],
+            },
+        ));
+        let start = |principal: &str| {
+            registry.start(
+                principal,
+                "default",
+                "SELECT 1",
+                Arc::new(UnboundedMemoryPool::default()),
+            )
+        };
+
+        // Concurrency limits are per principal, with overrides taking precedence
+        let alice = start("alice").unwrap();
+        assert_eq!(
+            start("alice").unwrap_err(),
+            QueryLimitError::TooManyConcurrentQueries {
+                principal: "alice".to_string(),
+                limit: 1
+            }
+        );
+        let bob_1 = start("bob").unwrap();
+        let bob_2 = start("bob").unwrap();
+        assert_eq!(
+            start("bob").unwrap_err().to_string(),
+            "Principal \"bob\" already has 2 queries running"
+        );
+
+        // Finished queries free up a slot, but still count towards the rate limit
+        drop(bob_1);
+        drop(bob_2);
+        start("bob").unwrap();
+        assert_eq!(
+            start("bob").unwrap_err().to_string(),
+            "Principal \"bob\" exceeded the limit of 3 queries per minute"
+        );
+
+        drop(alice);
+        start("alice").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execution_limits() {
+        let registry = Arc::new(QueryRegistry::new(10, QueryLimits::default()));
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2]))],
+        )
+        .unwrap();
+        let start = |limits: ExecutionLimits| {
+            registry
+                .start(
+                    "alice",
+                    "default",
+                    "SELECT a FROM t",
+                    Arc::new(UnboundedMemoryPool::default()),
+                )
+                .unwrap()
+                .with_limits(limits)
+        };
+
+        // Requested limits can only make the configured ones stricter
+        let limits = ExecutionLimits {
+            statement_timeout: Some(Duration::from_millis(50)),
+            max_result_rows: Some(3),
+            max_result_bytes: None,
+        }
+        .restrict(ExecutionLimits {
+            statement_timeout: Some(Duration::from_secs(60)),
+            max_result_rows: None,
+            max_result_bytes: Some(1_000_000),
+        });
+        assert_eq!(
+            limits,
+            ExecutionLimits {
+                statement_timeout: Some(Duration::from_millis(50)),
+                max_result_rows: Some(3),
+                max_result_bytes: Some(1_000_000),
+            }
+        );
+
+        // The results get cut off once they grow past the row limit...
+        let mut results = start(limits).track(Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            stream::repeat(batch.clone()).map(Ok),
+        )));
+        assert!(results.next().await.unwrap().is_ok());
+        assert_eq!(
+            results.next().await.unwrap().unwrap_err().to_string(),
+            "External error: Query result exceeded the limit of 3 rows"
+        );
+        assert!(results.next().await.is_none());
+        drop(results);
+
+        // ... or the byte limit...
+        let mut results = start(ExecutionLimits {
+            max_result_bytes: Some(1),
+            ..Default::default()
+        })
+        .track(Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            stream::repeat(batch).map(Ok),
+        )));
+        assert_eq!(
+            results.next().await.unwrap().unwrap_err().to_string(),
+            "External error: Query result exceeded the limit of 1 bytes"
+        );
+        drop(results);
+
+        // ... and a query that doesn't produce results in time gets aborted as well
+        let mut results = start(limits).track(Box::pin(RecordBatchStreamAdapter::new(
+            schema.clone(),
+            stream::pending(),
+        )));
+        assert_eq!(
+            results.next().await.unwrap().unwrap_err().to_string(),
+            "External error: Query exceeded the statement timeout of 50ms"
+        );
+        drop(results);
+
+        assert_eq!(
+            limits.with_timeout(futures::future::pending::<()>()).await,
+            Err(ExecutionLimitError::Timeout {
+                timeout: Duration::from_millis(50)
+            })
+        );
+
+        let log = registry.log();
+        assert_eq!(log.len(), 3);
+        assert!(log.iter().all(|query| query.status == QueryStatus::Failed));
+    }
+}
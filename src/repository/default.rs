@@ -186,7 +186,7 @@ impl Repository for $repo {
     ) -> Result<TableRecord, Error> {
         let table = sqlx::query_as(
             r#"
-        SELECT "table".id, collection.id as collection_id, "table".name
+        SELECT "table".id, collection.id as collection_id, "table".name, "table".definition
         FROM "table"
         JOIN collection ON "table".collection_id = collection.id
         JOIN database ON collection.database_id = database.id
@@ -202,6 +202,21 @@ impl Repository for $repo {
         Ok(table)
     }
 
+    async fn get_table_by_uuid(&self, uuid: Uuid) -> Result<TableRecord, Error> {
+        let table = sqlx::query_as(
+            r#"
+        SELECT "table".id, "table".collection_id, "table".name, "table".definition
+        FROM "table"
+        WHERE "table".uuid = $1
+        "#,
+        )
+        .bind(uuid)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(table)
+    }
+
     async fn create_collection(
         &self,
         database_id: DatabaseId,
@@ -284,6 +299,20 @@ impl Repository for $repo {
         Ok(delete_result.rows_affected())
     }
 
+    async fn update_table_definition(
+        &self,
+        table_id: TableId,
+        definition: Option<&str>,
+    ) -> Result<(), Error> {
+        sqlx::query(r#"UPDATE "table" SET definition = $1 WHERE id = $2 RETURNING id"#)
+            .bind(definition)
+            .bind(table_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(())
+    }
+
     async fn create_new_version(
         &self,
         uuid: Uuid,
@@ -481,6 +510,147 @@ impl Repository for $repo {
         Ok(())
     }
 
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        or_replace: bool,
+    ) -> Result<ViewId, Error> {
+        let query = format!(
+            r#"INSERT INTO "view" (collection_id, name, definition) VALUES ($1, $2, $3){} RETURNING (id)"#,
+            if or_replace {
+                " ON CONFLICT (name, collection_id) DO UPDATE SET definition = EXCLUDED.definition"
+            } else {
+                ""
+            }
+        );
+
+        let new_view_id: i64 = sqlx::query(query.as_str())
+            .bind(collection_id)
+            .bind(view_name)
+            .bind(definition)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?
+            .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(new_view_id)
+    }
+
+    async fn get_view(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        view_name: &str,
+    ) -> Result<ViewRecord, Error> {
+        let view = sqlx::query_as(
+            r#"
+        SELECT "view".id, collection.id as collection_id, "view".name, "view".definition
+        FROM "view"
+        JOIN collection ON "view".collection_id = collection.id
+        JOIN database ON collection.database_id = database.id
+        WHERE database.name = $1 AND collection.name = $2 AND "view".name = $3
+        "#,
+        )
+        .bind(database_name)
+        .bind(collection_name)
+        .bind(view_name)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(view)
+    }
+
+    async fn get_all_views_in_database(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<AllDatabaseViewsResult>, Error> {
+        let views = sqlx::query_as(
+            r#"
+        SELECT
+            collection.name AS collection_name,
+            "view".name AS name,
+            "view".definition AS definition
+        FROM "view"
+        JOIN collection ON "view".collection_id = collection.id
+        JOIN database ON collection.database_id = database.id
+        WHERE database.name = $1
+        ORDER BY collection_name, name
+        "#)
+        .bind(database_name)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(views)
+    }
+
+    async fn delete_view(&self, view_id: ViewId) -> Result<(), Error> {
+        sqlx::query("DELETE FROM \"view\" WHERE id = $1 RETURNING id")
+            .bind(view_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+        column_name: Option<&str>,
+        predicate: &str,
+        principals: Option<&str>,
+    ) -> Result<PolicyId, Error> {
+        let new_policy_id: i64 = sqlx::query(
+            r#"INSERT INTO policy (table_id, name, column_name, predicate, principals) VALUES ($1, $2, $3, $4, $5) RETURNING (id)"#,
+        )
+            .bind(table_id)
+            .bind(policy_name)
+            .bind(column_name)
+            .bind(predicate)
+            .bind(principals)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?
+            .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(new_policy_id)
+    }
+
+    async fn get_all_policies_in_database(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<AllDatabasePoliciesResult>, Error> {
+        let policies = sqlx::query_as(
+            r#"
+        SELECT
+            collection.name AS collection_name,
+            "table".name AS table_name,
+            policy.name AS name,
+            policy.column_name AS column_name,
+            policy.predicate AS predicate,
+            policy.principals AS principals
+        FROM policy
+        JOIN "table" ON policy.table_id = "table".id
+        JOIN collection ON "table".collection_id = collection.id
+        JOIN database ON collection.database_id = database.id
+        WHERE database.name = $1
+        ORDER BY collection_name, table_name, name
+        "#)
+        .bind(database_name)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(policies)
+    }
+
+    async fn delete_policy(&self, table_id: TableId, policy_name: &str) -> Result<(), Error> {
+        sqlx::query("DELETE FROM policy WHERE table_id = $1 AND name = $2 RETURNING id")
+            .bind(table_id)
+            .bind(policy_name)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
     // Drop table/collection/database
 
     // In these methods, return the ID back so that we get an error if the
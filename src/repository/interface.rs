@@ -15,6 +15,8 @@ pub type TableId = i64;
 pub type TableVersionId = i64;
 pub type Timestamp = i64;
 pub type FunctionId = i64;
+pub type ViewId = i64;
+pub type PolicyId = i64;
 
 #[derive(sqlx::FromRow, Default, Debug, PartialEq, Eq)]
 pub struct DatabaseRecord {
@@ -34,6 +36,8 @@ pub struct TableRecord {
     pub id: TableId,
     pub collection_id: CollectionId,
     pub name: String,
+    // Defining query for materialized views
+    pub definition: Option<String>,
 }
 
 #[derive(sqlx::FromRow, Default, Debug, PartialEq, Eq)]
@@ -97,6 +101,33 @@ pub struct AllDatabaseFunctionsResult {
     pub volatility: String,
 }
 
+#[derive(sqlx::FromRow, Default, Debug, PartialEq, Eq)]
+pub struct ViewRecord {
+    pub id: ViewId,
+    pub collection_id: CollectionId,
+    pub name: String,
+    pub definition: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllDatabaseViewsResult {
+    pub collection_name: String,
+    pub name: String,
+    pub definition: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllDatabasePoliciesResult {
+    pub collection_name: String,
+    pub table_name: String,
+    pub name: String,
+    // The masked column for column masking policies, or none for row-level security policies
+    pub column_name: Option<String>,
+    pub predicate: String,
+    // JSON list of principal names, or none if the policy applies to everyone
+    pub principals: Option<String>,
+}
+
 /// Wrapper for conversion of database-specific error codes into actual errors
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -139,6 +170,8 @@ pub trait Repository: Send + Sync + Debug {
         table_name: &str,
     ) -> Result<TableRecord, Error>;
 
+    async fn get_table_by_uuid(&self, uuid: Uuid) -> Result<TableRecord, Error>;
+
     async fn create_database(&self, database_name: &str) -> Result<DatabaseId, Error>;
 
     async fn create_collection(
@@ -157,6 +190,12 @@ pub trait Repository: Send + Sync + Debug {
 
     async fn delete_old_versions(&self, table_id: TableId) -> Result<u64, Error>;
 
+    async fn update_table_definition(
+        &self,
+        table_id: TableId,
+        definition: Option<&str>,
+    ) -> Result<(), Error>;
+
     async fn create_new_version(
         &self,
         uuid: Uuid,
@@ -195,6 +234,48 @@ pub trait Repository: Send + Sync + Debug {
         func_names: &[String],
     ) -> Result<(), Error>;
 
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        or_replace: bool,
+    ) -> Result<ViewId, Error>;
+
+    async fn get_view(
+        &self,
+        database_name: &str,
+        collection_name: &str,
+        view_name: &str,
+    ) -> Result<ViewRecord, Error>;
+
+    async fn get_all_views_in_database(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<AllDatabaseViewsResult>, Error>;
+
+    async fn delete_view(&self, view_id: ViewId) -> Result<(), Error>;
+
+    async fn create_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+        column_name: Option<&str>,
+        predicate: &str,
+        principals: Option<&str>,
+    ) -> Result<PolicyId, Error>;
+
+    async fn get_all_policies_in_database(
+        &self,
+        database_name: &str,
+    ) -> Result<Vec<AllDatabasePoliciesResult>, Error>;
+
+    async fn delete_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+    ) -> Result<(), Error>;
+
     async fn delete_table(&self, table_id: TableId) -> Result<(), Error>;
 
     async fn delete_collection(&self, collection_id: CollectionId) -> Result<(), Error>;
@@ -278,6 +359,9 @@ pub mod tests {
         let (database_id, table_id, table_version_id) =
             test_create_database_collection_table(repository.clone()).await;
         test_create_functions(repository.clone(), database_id).await;
+        test_create_views(repository.clone()).await;
+        test_create_policies(repository.clone(), table_id).await;
+        test_update_table_definition(repository.clone(), table_id).await;
         test_rename_table(
             repository.clone(),
             database_id,
@@ -394,6 +478,16 @@ pub mod tests {
 
         assert_eq!(all_table_versions, vec![2, new_version_id]);
 
+        // Look up the tables by the UUID of their location
+        repository
+            .get_table_by_uuid(Uuid::default())
+            .await
+            .expect("Error getting table by UUID");
+        assert!(matches!(
+            repository.get_table_by_uuid(Uuid::new_v4()).await,
+            Err(Error::SqlxError(sqlx::Error::RowNotFound))
+        ));
+
         (database_id, table_id, table_version_id)
     }
 
@@ -483,6 +577,191 @@ pub mod tests {
         assert_eq!(all_functions, expected_functions);
     }
 
+    async fn test_create_views(repository: Arc<dyn Repository>) {
+        let collection = repository.get_collection(TEST_DB, "testcol").await.unwrap();
+
+        let view_id = repository
+            .create_view(
+                collection.id,
+                "testview",
+                "CREATE VIEW testview AS SELECT 1",
+                false,
+            )
+            .await
+            .unwrap();
+
+        // Creating the same view again without OR REPLACE fails
+        assert!(matches!(
+            repository
+                .create_view(
+                    collection.id,
+                    "testview",
+                    "CREATE VIEW testview AS SELECT 2",
+                    false,
+                )
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+
+        // With OR REPLACE only the definition gets updated
+        let new_view_id = repository
+            .create_view(
+                collection.id,
+                "testview",
+                "CREATE OR REPLACE VIEW testview AS SELECT 2",
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(new_view_id, view_id);
+
+        assert_eq!(
+            repository.get_all_views_in_database(TEST_DB).await.unwrap(),
+            vec![AllDatabaseViewsResult {
+                collection_name: "testcol".to_string(),
+                name: "testview".to_string(),
+                definition: "CREATE OR REPLACE VIEW testview AS SELECT 2".to_string(),
+            }]
+        );
+
+        let view = repository
+            .get_view(TEST_DB, "testcol", "testview")
+            .await
+            .unwrap();
+        assert_eq!(view.id, view_id);
+
+        repository.delete_view(view_id).await.unwrap();
+        assert_eq!(
+            repository.get_all_views_in_database(TEST_DB).await.unwrap(),
+            vec![]
+        );
+        assert!(matches!(
+            repository
+                .get_view(TEST_DB, "testcol", "testview")
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    async fn test_create_policies(repository: Arc<dyn Repository>, table_id: TableId) {
+        repository
+            .create_policy(table_id, "tenant", None, "tenant = current_user", None)
+            .await
+            .unwrap();
+        repository
+            .create_policy(table_id, "recent", None, "value > 0", Some(r#"["reader"]"#))
+            .await
+            .unwrap();
+        repository
+            .create_policy(table_id, "hidden_email", Some("email"), "NULL", None)
+            .await
+            .unwrap();
+
+        // Policy names are unique per table
+        assert!(matches!(
+            repository
+                .create_policy(table_id, "tenant", None, "true", None)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+
+        assert_eq!(
+            repository
+                .get_all_policies_in_database(TEST_DB)
+                .await
+                .unwrap(),
+            vec![
+                AllDatabasePoliciesResult {
+                    collection_name: "testcol".to_string(),
+                    table_name: "testtable".to_string(),
+                    name: "hidden_email".to_string(),
+                    column_name: Some("email".to_string()),
+                    predicate: "NULL".to_string(),
+                    principals: None,
+                },
+                AllDatabasePoliciesResult {
+                    collection_name: "testcol".to_string(),
+                    table_name: "testtable".to_string(),
+                    name: "recent".to_string(),
+                    column_name: None,
+                    predicate: "value > 0".to_string(),
+                    principals: Some(r#"["reader"]"#.to_string()),
+                },
+                AllDatabasePoliciesResult {
+                    collection_name: "testcol".to_string(),
+                    table_name: "testtable".to_string(),
+                    name: "tenant".to_string(),
+                    column_name: None,
+                    predicate: "tenant = current_user".to_string(),
+                    principals: None,
+                },
+            ]
+        );
+
+        repository
+            .delete_policy(table_id, "hidden_email")
+            .await
+            .unwrap();
+        repository.delete_policy(table_id, "recent").await.unwrap();
+        repository.delete_policy(table_id, "tenant").await.unwrap();
+        assert_eq!(
+            repository
+                .get_all_policies_in_database(TEST_DB)
+                .await
+                .unwrap(),
+            vec![]
+        );
+        assert!(matches!(
+            repository
+                .delete_policy(table_id, "tenant")
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    async fn test_update_table_definition(
+        repository: Arc<dyn Repository>,
+        table_id: TableId,
+    ) {
+        let table = repository
+            .get_table(TEST_DB, "testcol", "testtable")
+            .await
+            .unwrap();
+        assert_eq!(table.definition, None);
+
+        repository
+            .update_table_definition(table_id, Some("SELECT 1"))
+            .await
+            .unwrap();
+        let table = repository
+            .get_table(TEST_DB, "testcol", "testtable")
+            .await
+            .unwrap();
+        assert_eq!(table.definition, Some("SELECT 1".to_string()));
+
+        repository
+            .update_table_definition(table_id, None)
+            .await
+            .unwrap();
+        let table = repository
+            .get_table(TEST_DB, "testcol", "testtable")
+            .await
+            .unwrap();
+        assert_eq!(table.definition, None);
+
+        assert!(matches!(
+            repository
+                .update_table_definition(-1, None)
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+    }
+
     async fn test_rename_table(
         repository: Arc<dyn Repository>,
         database_id: DatabaseId,
@@ -15,10 +15,11 @@ use crate::{implement_repository, wasm_udf::data_types::CreateFunctionDetails};
 use super::{
     default::RepositoryQueries,
     interface::{
-        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, CollectionId,
-        CollectionRecord, DatabaseId, DatabaseRecord, DroppedTableDeletionStatus,
-        DroppedTablesResult, Error, FunctionId, Repository, Result, TableId, TableRecord,
-        TableVersionId, TableVersionsResult,
+        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, AllDatabasePoliciesResult,
+        AllDatabaseViewsResult, CollectionId, CollectionRecord, DatabaseId,
+        DatabaseRecord, DroppedTableDeletionStatus, DroppedTablesResult, Error,
+        FunctionId, PolicyId, Repository, Result, TableId, TableRecord, TableVersionId,
+        TableVersionsResult, ViewId, ViewRecord,
     },
 };
 
@@ -18,10 +18,11 @@ use crate::implement_repository;
 use super::{
     default::RepositoryQueries,
     interface::{
-        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, CollectionId,
-        CollectionRecord, DatabaseId, DatabaseRecord, DroppedTableDeletionStatus,
-        DroppedTablesResult, Error, FunctionId, Repository, Result, TableId, TableRecord,
-        TableVersionId, TableVersionsResult,
+        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, AllDatabasePoliciesResult,
+        AllDatabaseViewsResult, CollectionId, CollectionRecord, DatabaseId,
+        DatabaseRecord, DroppedTableDeletionStatus, DroppedTablesResult, Error,
+        FunctionId, PolicyId, Repository, Result, TableId, TableRecord, TableVersionId,
+        TableVersionsResult, ViewId, ViewRecord,
     },
 };
 
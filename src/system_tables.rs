@@ -2,8 +2,12 @@
 //! and datafusion's information_schema.
 
 use crate::catalog::TableStore;
+use crate::queries::QueryRegistry;
 use crate::repository::interface::DroppedTablesResult;
-use arrow::array::{Int64Builder, StringBuilder, StructBuilder, TimestampSecondBuilder};
+use arrow::array::{
+    Int64Builder, StringBuilder, StructBuilder, TimestampMillisecondBuilder,
+    TimestampSecondBuilder,
+};
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
@@ -20,17 +24,25 @@ use std::sync::Arc;
 pub const SYSTEM_SCHEMA: &str = "system";
 const TABLE_VERSIONS: &str = "table_versions";
 const DROPPED_TABLES: &str = "dropped_tables";
+pub(crate) const RUNNING_QUERIES: &str = "running_queries";
+pub(crate) const QUERY_LOG: &str = "query_log";
 
 pub struct SystemSchemaProvider {
     database: Arc<str>,
     table_catalog: Arc<dyn TableStore>,
+    queries: Arc<QueryRegistry>,
 }
 
 impl SystemSchemaProvider {
-    pub fn new(database: Arc<str>, table_catalog: Arc<dyn TableStore>) -> Self {
+    pub fn new(
+        database: Arc<str>,
+        table_catalog: Arc<dyn TableStore>,
+        queries: Arc<QueryRegistry>,
+    ) -> Self {
         Self {
             database,
             table_catalog,
+            queries,
         }
     }
 }
@@ -42,7 +54,12 @@ impl SchemaProvider for SystemSchemaProvider {
     }
 
     fn table_names(&self) -> Vec<String> {
-        vec![TABLE_VERSIONS.to_string(), DROPPED_TABLES.to_string()]
+        vec![
+            TABLE_VERSIONS.to_string(),
+            DROPPED_TABLES.to_string(),
+            RUNNING_QUERIES.to_string(),
+            QUERY_LOG.to_string(),
+        ]
     }
 
     async fn table(&self, name: &str) -> Result<Option<Arc<dyn TableProvider>>> {
@@ -66,6 +83,20 @@ impl SchemaProvider for SystemSchemaProvider {
                     table: Arc::new(table),
                 }))
             }
+            RUNNING_QUERIES => {
+                let table =
+                    RunningQueriesTable::new(self.database.clone(), self.queries.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            QUERY_LOG => {
+                let table =
+                    QueryLogTable::new(self.database.clone(), self.queries.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
             _ => None,
         })
     }
@@ -73,7 +104,7 @@ impl SchemaProvider for SystemSchemaProvider {
     fn table_exist(&self, name: &str) -> bool {
         matches!(
             name.to_ascii_lowercase().as_str(),
-            TABLE_VERSIONS | DROPPED_TABLES
+            TABLE_VERSIONS | DROPPED_TABLES | RUNNING_QUERIES | QUERY_LOG
         )
     }
 }
@@ -285,3 +316,175 @@ impl SeafowlSystemTable for DroppedTablesTable {
             .map_err(DataFusionError::from)
     }
 }
+
+// Table listing the queries currently being executed against the given database
+struct RunningQueriesTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    queries: Arc<QueryRegistry>,
+}
+
+impl RunningQueriesTable {
+    fn new(database: Arc<str>, queries: Arc<QueryRegistry>) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("query_id", DataType::Int64, false),
+                Field::new("principal", DataType::Utf8, false),
+                Field::new("query", DataType::Utf8, false),
+                Field::new(
+                    "start_time",
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    false,
+                ),
+                Field::new("memory_reserved", DataType::Int64, false),
+            ])),
+            queries,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for RunningQueriesTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self) -> Result<RecordBatch> {
+        let running_queries = self
+            .queries
+            .running()
+            .into_iter()
+            .filter(|query| query.database == self.database.as_ref())
+            .collect::<Vec<_>>();
+
+        let mut builder = StructBuilder::from_fields(
+            self.schema.fields().clone(),
+            running_queries.len(),
+        );
+
+        // Construct the table columns from the registered queries
+        for query in &running_queries {
+            builder
+                .field_builder::<Int64Builder>(0)
+                .unwrap()
+                .append_value(query.id as i64);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(&query.principal);
+            builder
+                .field_builder::<StringBuilder>(2)
+                .unwrap()
+                .append_value(&query.sql);
+            builder
+                .field_builder::<TimestampMillisecondBuilder>(3)
+                .unwrap()
+                .append_value(query.start_time.timestamp_millis());
+            builder
+                .field_builder::<Int64Builder>(4)
+                .unwrap()
+                .append_value(query.memory_reserved() as i64);
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the most recently finished queries against the given database
+struct QueryLogTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    queries: Arc<QueryRegistry>,
+}
+
+impl QueryLogTable {
+    fn new(database: Arc<str>, queries: Arc<QueryRegistry>) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("query_id", DataType::Int64, false),
+                Field::new("principal", DataType::Utf8, false),
+                Field::new("query", DataType::Utf8, false),
+                Field::new(
+                    "start_time",
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    false,
+                ),
+                Field::new(
+                    "end_time",
+                    DataType::Timestamp(TimeUnit::Millisecond, None),
+                    false,
+                ),
+                Field::new("status", DataType::Utf8, false),
+                Field::new("error", DataType::Utf8, true),
+            ])),
+            queries,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for QueryLogTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self) -> Result<RecordBatch> {
+        let finished_queries = self
+            .queries
+            .log()
+            .into_iter()
+            .filter(|query| query.database == self.database.as_ref())
+            .collect::<Vec<_>>();
+
+        let mut builder = StructBuilder::from_fields(
+            self.schema.fields().clone(),
+            finished_queries.len(),
+        );
+
+        // Construct the table columns from the logged queries
+        for query in &finished_queries {
+            builder
+                .field_builder::<Int64Builder>(0)
+                .unwrap()
+                .append_value(query.id as i64);
+            builder
+                .field_builder::<StringBuilder>(1)
+                .unwrap()
+                .append_value(&query.principal);
+            builder
+                .field_builder::<StringBuilder>(2)
+                .unwrap()
+                .append_value(&query.sql);
+            builder
+                .field_builder::<TimestampMillisecondBuilder>(3)
+                .unwrap()
+                .append_value(query.start_time.timestamp_millis());
+            builder
+                .field_builder::<TimestampMillisecondBuilder>(4)
+                .unwrap()
+                .append_value(query.end_time.timestamp_millis());
+            builder
+                .field_builder::<StringBuilder>(5)
+                .unwrap()
+                .append_value(query.status.to_string());
+            builder
+                .field_builder::<StringBuilder>(6)
+                .unwrap()
+                .append_option(query.error.as_ref());
+
+            builder.append(true);
+        }
+
+        let struct_array = builder.finish();
+
+        RecordBatch::try_new(self.schema.clone(), struct_array.columns().to_vec())
+            .map_err(DataFusionError::from)
+    }
+}
@@ -7,12 +7,25 @@ use sqlparser::ast::{
 use std::collections::HashSet;
 use std::ops::ControlFlow;
 
+// Name of the table function returning the rows changed between two table versions
+pub const TABLE_CHANGES: &str = "table_changes";
+
 // A struct for walking the query AST, visiting all tables and rewriting any table reference that
-// uses time travel syntax (i.e. table function syntax such as `table('2022-01-01 20:01:01Z')`).
+// uses time travel syntax (i.e. table function syntax such as `table('2022-01-01 20:01:01Z')` or
+// `table(3)`), as well as any `table_changes(table, from_version, to_version)` invocation.
 pub struct TableVersionProcessor {
     pub default_catalog: String,
     pub default_schema: String,
     pub table_versions: HashSet<(ObjectName, String)>,
+    pub table_changes: HashSet<(ObjectName, i64, i64)>,
+}
+
+// A table version specifier, as provided in the time travel syntax
+pub enum TableVersion {
+    // Delta table version number
+    Version(i64),
+    // Latest table version as of the specified point in time
+    Timestamp(DateTime<Utc>),
 }
 
 impl TableVersionProcessor {
@@ -21,6 +34,7 @@ impl TableVersionProcessor {
             default_catalog,
             default_schema,
             table_versions: HashSet::<(ObjectName, String)>::new(),
+            table_changes: HashSet::<(ObjectName, i64, i64)>::new(),
         }
     }
 
@@ -32,6 +46,25 @@ impl TableVersionProcessor {
         )
     }
 
+    pub fn table_with_changes(
+        name: &ObjectName,
+        from_version: i64,
+        to_version: i64,
+    ) -> String {
+        format!(
+            "{}:{TABLE_CHANGES}:{from_version}:{to_version}",
+            name.0.last().unwrap().value,
+        )
+    }
+
+    // Interpret the version specifier either as a Delta table version number, or as a timestamp
+    pub fn parse_version(version: &str) -> Result<TableVersion> {
+        match version.parse::<i64>() {
+            Ok(version) => Ok(TableVersion::Version(version)),
+            Err(_) => Ok(TableVersion::Timestamp(Self::version_to_datetime(version)?)),
+        }
+    }
+
     // Try to parse the specified version timestamp into a Unix epoch
     pub fn version_to_datetime(version: &str) -> Result<DateTime<Utc>> {
         let dt = if let Ok(dt_rfc3339) = DateTime::parse_from_rfc3339(version) {
@@ -55,34 +88,117 @@ impl TableVersionProcessor {
 
         Ok(DateTime::<Utc>::from(dt))
     }
+
+    // Resolve the (possibly partial) table name against the default catalog and schema
+    fn resolve_name(&self, unresolved_name: &str) -> ObjectName {
+        let resolved_ref = TableReference::from(unresolved_name)
+            .resolve(&self.default_catalog, &self.default_schema);
+        ObjectName(vec![
+            Ident::new(resolved_ref.catalog.as_ref()),
+            Ident::new(resolved_ref.schema.as_ref()),
+            Ident::new(resolved_ref.table.as_ref()),
+        ])
+    }
+
+    // Extract the table name and the version range from the `table_changes` arguments
+    fn parse_table_changes_args(
+        &self,
+        args: &[FunctionArg],
+    ) -> Result<(ObjectName, i64, i64)> {
+        let exprs = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        let Some(&[table, from_version, to_version]) = exprs.as_deref() else {
+            return Err(DataFusionError::Plan(format!(
+                "{TABLE_CHANGES} expects the arguments (table, from_version, to_version)"
+            )));
+        };
+
+        let table = match table {
+            Expr::Identifier(ident) => ident.to_string(),
+            Expr::CompoundIdentifier(idents) => ObjectName(idents.clone()).to_string(),
+            Expr::Value(Value::SingleQuotedString(name)) => name.clone(),
+            _ => {
+                return Err(DataFusionError::Plan(format!(
+                    "Unsupported table name {table} passed to {TABLE_CHANGES}"
+                )))
+            }
+        };
+
+        let parse_version_number = |version: &Expr| match version {
+            Expr::Value(Value::Number(number, _)) => {
+                number.parse::<i64>().map_err(|_| {
+                    DataFusionError::Plan(format!(
+                        "Unsupported table version {number} passed to {TABLE_CHANGES}"
+                    ))
+                })
+            }
+            _ => Err(DataFusionError::Plan(format!(
+                "Unsupported table version {version} passed to {TABLE_CHANGES}"
+            ))),
+        };
+
+        Ok((
+            self.resolve_name(&table),
+            parse_version_number(from_version)?,
+            parse_version_number(to_version)?,
+        ))
+    }
 }
 
 impl VisitorMut for TableVersionProcessor {
-    type Break = ();
+    type Break = DataFusionError;
 
     fn pre_visit_table_factor(
         &mut self,
         table_factor: &mut TableFactor,
-    ) -> ControlFlow<()> {
+    ) -> ControlFlow<DataFusionError> {
         if let TableFactor::Table {
             name, ref mut args, ..
         } = table_factor
         {
-            // If a function arg expression is a single string interpret this as a version specifier
+            if let [ident] = name.0.as_slice()
+                && ident.quote_style.is_none()
+                && ident.value.eq_ignore_ascii_case(TABLE_CHANGES)
+                && let Some(changes_args) = args.as_deref()
+            {
+                let (full_object_name, from_version, to_version) =
+                    match self.parse_table_changes_args(changes_args) {
+                        Ok(parsed) => parsed,
+                        Err(e) => return ControlFlow::Break(e),
+                    };
+
+                self.table_changes.insert((
+                    full_object_name.clone(),
+                    from_version,
+                    to_version,
+                ));
+                // Point the table factor to the (fully qualified) name of the changes table
+                let mut changes_name = full_object_name.clone();
+                changes_name.0.last_mut().unwrap().value =
+                    TableVersionProcessor::table_with_changes(
+                        &full_object_name,
+                        from_version,
+                        to_version,
+                    );
+                *name = changes_name;
+                *args = None;
+                return ControlFlow::Continue(());
+            }
+
+            // If a function arg expression is a single string or number interpret this as a
+            // version specifier
             if let Some(
                 [FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Value(
-                    Value::SingleQuotedString(value),
+                    Value::SingleQuotedString(value) | Value::Number(value, _),
                 )))],
             ) = &args.as_deref()
             {
-                let unresolved_name = name.to_string();
-                let resolved_ref = TableReference::from(unresolved_name.as_str())
-                    .resolve(&self.default_catalog, &self.default_schema);
-                let full_object_name = ObjectName(vec![
-                    Ident::new(resolved_ref.catalog.as_ref()),
-                    Ident::new(resolved_ref.schema.as_ref()),
-                    Ident::new(resolved_ref.table.as_ref()),
-                ]);
+                let full_object_name = self.resolve_name(&name.to_string());
 
                 self.table_versions
                     .insert((full_object_name.clone(), value.to_string()));
@@ -152,6 +268,65 @@ mod tests {
         )
     }
 
+    #[rstest]
+    #[case::version_number("SELECT * FROM test_table(3)", "SELECT * FROM test_table:3")]
+    #[case::for_system_time_as_of(
+        "SELECT * FROM some_schema.test_table FOR SYSTEM_TIME AS OF 'test_version' AS t",
+        "SELECT * FROM some_schema.test_table:test_version AS t"
+    )]
+    #[case::version_as_of(
+        "SELECT * FROM test_table VERSION AS OF 3 JOIN other_table ON true",
+        "SELECT * FROM test_table:3 JOIN other_table ON true"
+    )]
+    #[case::table_changes(
+        "SELECT * FROM table_changes('some_schema.test_table', 1, 3)",
+        "SELECT * FROM test_catalog.some_schema.test_table:table_changes:1:3"
+    )]
+    #[case::table_changes_identifier(
+        "SELECT * FROM table_changes(test_table, 0, 2) AS c",
+        "SELECT * FROM test_catalog.test_schema.test_table:table_changes:0:2 AS c"
+    )]
+    fn test_table_version_syntax_rewrite(#[case] query: &str, #[case] expected: &str) {
+        let stmts = DFParser::parse_sql(query).unwrap();
+
+        let Statement::Statement(stmt) = &stmts[0] else {
+            panic!("Expected Statement not matched!");
+        };
+        let SQLStatement::Query(query) = stmt.deref() else {
+            panic!("Expected Query not matched!");
+        };
+        let mut q = query.clone();
+
+        let mut rewriter = TableVersionProcessor::new(
+            "test_catalog".to_string(),
+            "test_schema".to_string(),
+        );
+        assert!(q.visit(&mut rewriter).is_continue());
+
+        assert_eq!(format!("{q}"), expected)
+    }
+
+    #[rstest]
+    #[case::too_few_args("SELECT * FROM table_changes('test_table', 1)")]
+    #[case::non_integer_version("SELECT * FROM table_changes('test_table', 1, 'latest')")]
+    fn test_table_changes_invalid_args(#[case] query: &str) {
+        let stmts = DFParser::parse_sql(query).unwrap();
+
+        let Statement::Statement(stmt) = &stmts[0] else {
+            panic!("Expected Statement not matched!");
+        };
+        let SQLStatement::Query(query) = stmt.deref() else {
+            panic!("Expected Query not matched!");
+        };
+        let mut q = query.clone();
+
+        let mut rewriter = TableVersionProcessor::new(
+            "test_catalog".to_string(),
+            "test_schema".to_string(),
+        );
+        assert!(q.visit(&mut rewriter).is_break());
+    }
+
     #[rstest]
     #[case::rfc_3339("2017-07-14T02:40:00+00:00")]
     #[case::rfc_3339_shifted("2017-07-14T04:40:00+02:00")]
@@ -83,18 +83,20 @@ fn test_cli_basic() -> std::io::Result<()> {
 
     writeln!(stdin, "\\d")?;
     expected_stdout.extend(vec![
-        "+---------------+--------------------+----------------+------------+",
-        "| table_catalog | table_schema       | table_name     | table_type |",
-        "+---------------+--------------------+----------------+------------+",
-        "| default       | public             | t              | BASE TABLE |",
-        "| default       | system             | table_versions | VIEW       |",
-        "| default       | system             | dropped_tables | VIEW       |",
-        "| default       | information_schema | tables         | VIEW       |",
-        "| default       | information_schema | views          | VIEW       |",
-        "| default       | information_schema | columns        | VIEW       |",
-        "| default       | information_schema | df_settings    | VIEW       |",
-        "| default       | information_schema | schemata       | VIEW       |",
-        "+---------------+--------------------+----------------+------------+",
+        "+---------------+--------------------+-----------------+------------+",
+        "| table_catalog | table_schema       | table_name      | table_type |",
+        "+---------------+--------------------+-----------------+------------+",
+        "| default       | public             | t               | BASE TABLE |",
+        "| default       | system             | table_versions  | VIEW       |",
+        "| default       | system             | dropped_tables  | VIEW       |",
+        "| default       | system             | running_queries | VIEW       |",
+        "| default       | system             | query_log       | VIEW       |",
+        "| default       | information_schema | tables          | VIEW       |",
+        "| default       | information_schema | views           | VIEW       |",
+        "| default       | information_schema | columns         | VIEW       |",
+        "| default       | information_schema | df_settings     | VIEW       |",
+        "| default       | information_schema | schemata        | VIEW       |",
+        "+---------------+--------------------+-----------------+------------+",
     ]);
 
     writeln!(stdin, "\\d t")?;
@@ -0,0 +1,235 @@
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+use tonic::Code;
+
+use crate::flight::*;
+
+const JWT_SECRET: &[u8] = b"flight-signing-key";
+
+fn sign(claims: Value) -> String {
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(JWT_SECRET),
+    )
+    .unwrap()
+}
+
+fn expiry(offset_s: i64) -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+        + offset_s
+}
+
+fn assert_status(err: FlightError, code: Code) {
+    match err {
+        FlightError::Tonic(status) => assert_eq!(status.code(), code, "{status}"),
+        err => panic!("Unexpected error {err}"),
+    }
+}
+
+fn jwks_file() -> std::io::Result<NamedTempFile> {
+    let mut jwks = NamedTempFile::new()?;
+    jwks.write_all(
+        json!({"keys": [{"kty": "oct", "k": URL_SAFE_NO_PAD.encode(JWT_SECRET)}]})
+            .to_string()
+            .as_bytes(),
+    )?;
+    Ok(jwks)
+}
+
+#[tokio::test]
+async fn test_flight_jwt_auth() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let jwks = jwks_file()?;
+
+    let (_context, mut client) = flight_server_with_config(
+        TestServerType::Memory,
+        &format!(
+            "[frontend.jwt]\njwks_file = \"{}\"",
+            jwks.path().to_str().unwrap()
+        ),
+    )
+    .await;
+
+    // Anonymous clients are subject to the default HTTP access settings, i.e. can only read
+    client.handshake("").await?;
+    get_flight_batches(&mut client, "SELECT 1".to_string()).await?;
+    let err = get_flight_batches(&mut client, "CREATE TABLE t AS SELECT 1".to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::PermissionDenied);
+
+    // Expired tokens are rejected already during the handshake
+    let expired = sign(json!({"sub": "alice", "exp": expiry(-3600)}));
+    client.add_header("authorization", &format!("Bearer {expired}"))?;
+    assert_status(
+        client.handshake("").await.unwrap_err(),
+        Code::Unauthenticated,
+    );
+
+    // A token with write access to another database can't write to this one
+    let other_db = sign(json!({
+        "sub": "alice",
+        "exp": expiry(3600),
+        "databases": ["other"],
+        "access": "write",
+    }));
+    client.add_header("authorization", &format!("Bearer {other_db}"))?;
+    client.handshake("").await?;
+    let err = get_flight_batches(&mut client, "CREATE TABLE t AS SELECT 1".to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::PermissionDenied);
+
+    // A token with write access to the default database can
+    let writer = sign(json!({
+        "sub": "alice",
+        "exp": expiry(3600),
+        "databases": ["default"],
+        "access": "write",
+    }));
+    client.add_header("authorization", &format!("Bearer {writer}"))?;
+    client.handshake("").await?;
+    get_flight_batches(&mut client, "CREATE TABLE t AS SELECT 1".to_string()).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flight_token_grants() -> std::result::Result<(), Box<dyn std::error::Error>>
+{
+    let jwks = jwks_file()?;
+
+    // The token is "sometoken", and can only read the public schema
+    let (context, mut client) = flight_server_with_config(
+        TestServerType::Memory,
+        &format!(
+            r#"[frontend.jwt]
+jwks_file = "{}"
+
+[frontend.http]
+
+[[frontend.http.tokens]]
+name = "product"
+sha256_hash = "9c928547a5dce2fcc2788de34fe163942f64e50fea570d33774822d5eacbf1ec"
+grants = [{{ database = "default", schema = "public", access = "read" }}]"#,
+            jwks.path().to_str().unwrap()
+        ),
+    )
+    .await;
+    context.plan_query("CREATE TABLE t AS VALUES (1)").await?;
+    context.plan_query("CREATE SCHEMA private").await?;
+    context
+        .plan_query("CREATE TABLE private.secret AS VALUES (2)")
+        .await?;
+
+    client.add_header("authorization", "Bearer sometoken")?;
+    client.handshake("").await?;
+    get_flight_batches(&mut client, "SELECT * FROM t".to_string()).await?;
+
+    // Tables outside of the granted schema can't be read, not even in a subquery
+    for query in [
+        "SELECT * FROM private.secret",
+        "SELECT 1 WHERE EXISTS (SELECT 1 FROM private.secret)",
+        "SELECT * FROM t WHERE column1 IN (SELECT column1 FROM private.secret)",
+        "SELECT * FROM system.table_versions",
+    ] {
+        let err = get_flight_batches(&mut client, query.to_string())
+            .await
+            .unwrap_err();
+        assert_status(err, Code::PermissionDenied);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flight_query_limits() -> std::result::Result<(), Box<dyn std::error::Error>>
+{
+    let (_context, mut client) = flight_server_with_config(
+        TestServerType::Memory,
+        "[runtime.limits]\nqueries_per_minute = 2",
+    )
+    .await;
+
+    client.handshake("").await?;
+    get_flight_batches(&mut client, "SELECT 1".to_string()).await?;
+    get_flight_batches(&mut client, "SELECT 2".to_string()).await?;
+    let err = get_flight_batches(&mut client, "SELECT 3".to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::ResourceExhausted);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flight_query_memory_limit(
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (_context, mut client) = flight_server_with_config(
+        TestServerType::Memory,
+        "[runtime.limits]\nmax_query_memory = 1",
+    )
+    .await;
+    client.handshake("").await?;
+
+    get_flight_batches(&mut client, "SELECT 1".to_string()).await?;
+    let err = get_flight_batches(
+        &mut client,
+        "WITH t AS (SELECT unnest(range(1000000)) AS v) \
+        SELECT count(*) FROM t a JOIN t b ON a.v = b.v"
+            .to_string(),
+    )
+    .await
+    .unwrap_err();
+    assert_status(err, Code::ResourceExhausted);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flight_execution_limits(
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (_context, mut client) = flight_server_with_config(
+        TestServerType::Memory,
+        "[runtime]\nmax_result_rows = 2",
+    )
+    .await;
+    client.handshake("").await?;
+
+    let query = "SELECT * FROM (VALUES (1), (2), (3))";
+    let err = get_flight_batches(&mut client, query.to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::ResourceExhausted);
+
+    // Clients can request stricter limits, but not looser ones
+    client.add_header("max-result-rows", "3")?;
+    let err = get_flight_batches(&mut client, query.to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::ResourceExhausted);
+
+    client.add_header("max-result-rows", "1")?;
+    let err = get_flight_batches(&mut client, "SELECT 1 UNION ALL SELECT 2".to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::ResourceExhausted);
+    get_flight_batches(&mut client, "SELECT 1".to_string()).await?;
+
+    client.add_header("max-result-rows", "many")?;
+    let err = get_flight_batches(&mut client, "SELECT 1".to_string())
+        .await
+        .unwrap_err();
+    assert_status(err, Code::InvalidArgument);
+
+    Ok(())
+}
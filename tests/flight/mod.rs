@@ -32,6 +32,7 @@ use seafowl::config::schema::load_config_from_string;
 use seafowl::context::SeafowlContext;
 use seafowl::frontend::flight::run_flight_server;
 
+mod auth;
 mod client;
 mod e2e;
 mod inline_metastore;
@@ -39,7 +40,10 @@ mod search_path;
 mod sync;
 mod sync_fail;
 
-async fn make_test_context(server_type: TestServerType) -> Arc<SeafowlContext> {
+async fn make_test_context(
+    server_type: TestServerType,
+    extra_config: &str,
+) -> Arc<SeafowlContext> {
     // let OS choose a free port
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
@@ -78,7 +82,9 @@ bind_port = {}
 [misc.sync_conf]
 max_in_memory_bytes = 2500
 max_replication_lag_s = 1
-flush_task_interval_s = 1"#,
+flush_task_interval_s = 1
+
+{extra_config}"#,
         addr.port()
     );
 
@@ -96,7 +102,14 @@ pub enum TestServerType {
 async fn flight_server(
     server_type: TestServerType,
 ) -> (Arc<SeafowlContext>, FlightClient) {
-    let context = make_test_context(server_type).await;
+    flight_server_with_config(server_type, "").await
+}
+
+async fn flight_server_with_config(
+    server_type: TestServerType,
+    extra_config: &str,
+) -> (Arc<SeafowlContext>, FlightClient) {
+    let context = make_test_context(server_type, extra_config).await;
 
     let flight_cfg = context
         .config
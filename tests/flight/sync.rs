@@ -1,5 +1,6 @@
 use crate::flight::*;
-use clade::sync::{ColumnDescriptor, ColumnRole};
+use arrow::array::{Int16Array, Int64Array};
+use clade::sync::{ColumnDescriptor, ColumnRole, SchemaMismatch};
 
 pub(crate) fn sync_cmd_to_flight_data(
     cmd: DataSyncCommand,
@@ -112,6 +113,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
         column_descriptors,
         origin: "42".to_string(),
         sequence_number: None,
+        operation: None,
     };
 
     // Changes are still in memory
@@ -123,6 +125,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: None, // sequence not in memory because of  `last: false`
             durable_sequence_number: None,
             first: true,
+            schema_mismatch: None,
         }
     );
 
@@ -217,6 +220,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: Some(1234),
             durable_sequence_number: None,
             first: false,
+            schema_mismatch: None,
         }
     );
 
@@ -256,6 +260,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: Some(1234),
             durable_sequence_number: Some(1234),
             first: false,
+            schema_mismatch: None,
         }
     );
 
@@ -299,6 +304,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: Some(5600),
             durable_sequence_number: Some(1234),
             first: false,
+            schema_mismatch: None,
         }
     );
 
@@ -363,6 +369,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: Some(5600), // again 78910 not in memory since `last = false`
             durable_sequence_number: Some(1234),
             first: false,
+            schema_mismatch: None,
         }
     );
 
@@ -380,6 +387,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: Some(5600),
             durable_sequence_number: Some(1234),
             first: false,
+            schema_mismatch: None,
         }
     );
 
@@ -455,6 +463,7 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
             memory_sequence_number: Some(78910),
             durable_sequence_number: Some(78910),
             first: false,
+            schema_mismatch: None,
         }
     );
 
@@ -481,3 +490,337 @@ async fn test_sync_happy_path() -> std::result::Result<(), Box<dyn std::error::E
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_sync_schema_evolution(
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (ctx, mut client) = flight_server(TestServerType::Memory).await;
+
+    let column_descriptors = |value_columns: &[&str]| {
+        [(ColumnRole::OldPk, "c1"), (ColumnRole::NewPk, "c1")]
+            .into_iter()
+            .chain(value_columns.iter().map(|name| (ColumnRole::Value, *name)))
+            .map(|(role, name)| ColumnDescriptor {
+                role: role as i32,
+                name: name.to_string(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    //
+    // Sync #1 creates the table with an integer value column
+    //
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("old_c1", DataType::Int32, true),
+        Field::new("new_c1", DataType::Int32, true),
+        Field::new("value_c2", DataType::Int32, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int32Array::from(vec![None, None])),
+            Arc::new(Int32Array::from(vec![1, 2])),
+            Arc::new(Int32Array::from(vec![10, 20])),
+        ],
+    )?;
+
+    let table_uuid = Uuid::new_v4();
+    let mut cmd = DataSyncCommand {
+        path: table_uuid.to_string(),
+        store: None,
+        column_descriptors: column_descriptors(&["c2"]),
+        origin: "42".to_string(),
+        sequence_number: Some(1),
+        operation: None,
+    };
+    let sync_result = do_put_sync(cmd.clone(), batch, &mut client).await?;
+    assert!(sync_result.accepted);
+    assert_eq!(sync_result.memory_sequence_number, Some(1));
+
+    // The sync mechanism doesn't register the table, so for the sake of testing do it here
+    ctx.metastore
+        .tables
+        .create(
+            &ctx.default_catalog,
+            &ctx.default_schema,
+            "evolving_table",
+            &Schema::new(vec![
+                Field::new("c1", DataType::Int32, true),
+                Field::new("c2", DataType::Int32, true),
+            ]),
+            table_uuid,
+        )
+        .await
+        .unwrap();
+
+    // Wait for the flush task to write out the first data file
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    //
+    // Sync #2 inserts a row with a narrower value for the existing column and adds a new one
+    //
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("old_c1", DataType::Int32, true),
+        Field::new("new_c1", DataType::Int32, true),
+        Field::new("value_c2", DataType::Int16, true),
+        Field::new("value_c3", DataType::Utf8, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int32Array::from(vec![None])),
+            Arc::new(Int32Array::from(vec![3])),
+            Arc::new(Int16Array::from(vec![300])),
+            Arc::new(StringArray::from(vec!["three"])),
+        ],
+    )?;
+
+    cmd.column_descriptors = column_descriptors(&["c2", "c3"]);
+    cmd.sequence_number = Some(2);
+    let sync_result = do_put_sync(cmd.clone(), batch, &mut client).await?;
+    assert!(sync_result.accepted);
+    assert_eq!(sync_result.memory_sequence_number, Some(2));
+    assert_eq!(sync_result.schema_mismatch, None);
+
+    // The new row goes into a second data file, leaving the first one as it is
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    //
+    // Sync #3 widens the value column, while only updating the new row
+    //
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("old_c1", DataType::Int32, true),
+        Field::new("new_c1", DataType::Int32, true),
+        Field::new("value_c2", DataType::Int64, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int32Array::from(vec![Some(3)])),
+            Arc::new(Int32Array::from(vec![3])),
+            Arc::new(Int64Array::from(vec![30_000_000_000])),
+        ],
+    )?;
+
+    cmd.column_descriptors = column_descriptors(&["c2"]);
+    cmd.sequence_number = Some(3);
+    let sync_result = do_put_sync(cmd.clone(), batch, &mut client).await?;
+    assert!(sync_result.accepted);
+    assert_eq!(sync_result.memory_sequence_number, Some(3));
+    assert_eq!(sync_result.schema_mismatch, None);
+
+    //
+    // Syncs #4 and #5 have incompatible changes and get rejected
+    //
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("old_c1", DataType::Int32, true),
+        Field::new("new_c1", DataType::Int32, true),
+        Field::new("value_c2", DataType::Utf8, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int32Array::from(vec![Some(1)])),
+            Arc::new(Int32Array::from(vec![1])),
+            Arc::new(StringArray::from(vec!["one hundred"])),
+        ],
+    )?;
+
+    cmd.sequence_number = Some(4);
+    let sync_result = do_put_sync(cmd.clone(), batch, &mut client).await?;
+    assert!(!sync_result.accepted);
+    assert_eq!(sync_result.memory_sequence_number, Some(3));
+    assert_eq!(
+        sync_result.schema_mismatch,
+        Some(SchemaMismatch {
+            column: "c2".to_string(),
+            reason: "type can't change from Int64 to Utf8".to_string(),
+        })
+    );
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("old_c1", DataType::Int64, true),
+        Field::new("new_c1", DataType::Int64, true),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(Int64Array::from(vec![Some(1)])),
+            Arc::new(Int64Array::from(vec![None])),
+        ],
+    )?;
+
+    cmd.column_descriptors = column_descriptors(&[]);
+    let sync_result = do_put_sync(cmd.clone(), batch, &mut client).await?;
+    assert!(!sync_result.accepted);
+    assert_eq!(
+        sync_result.schema_mismatch,
+        Some(SchemaMismatch {
+            column: "c1".to_string(),
+            reason: "primary key type can't change from Int32 to Int64".to_string(),
+        })
+    );
+
+    // Wait for the flush task to pick up the accepted syncs
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let plan = ctx.plan_query("SELECT * FROM evolving_table").await?;
+    let results = ctx.collect(plan).await?;
+
+    let expected = [
+        "+----+-------------+-------+",
+        "| c1 | c2          | c3    |",
+        "+----+-------------+-------+",
+        "| 1  | 10          |       |",
+        "| 2  | 20          |       |",
+        "| 3  | 30000000000 | three |",
+        "+----+-------------+-------+",
+    ];
+    assert_batches_sorted_eq!(expected, &results);
+    assert_eq!(
+        results[0].schema().field_with_name("c2")?.data_type(),
+        &DataType::Int64
+    );
+
+    // Widening the column rewrote the untouched file with the new type as well
+    let mut table = ctx.try_get_delta_table("evolving_table").await?;
+    table.load().await?;
+    assert_eq!(table.get_files_count(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_exchange() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (ctx, mut client) = flight_server(TestServerType::Memory).await;
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("old_c1", DataType::Int32, true),
+        Field::new("new_c1", DataType::Int32, true),
+        Field::new("value_c2", DataType::Utf8, true),
+    ]));
+    let column_descriptors = vec![
+        ColumnDescriptor {
+            role: ColumnRole::OldPk as i32,
+            name: "c1".to_string(),
+        },
+        ColumnDescriptor {
+            role: ColumnRole::NewPk as i32,
+            name: "c1".to_string(),
+        },
+        ColumnDescriptor {
+            role: ColumnRole::Value as i32,
+            name: "c2".to_string(),
+        },
+    ];
+
+    let table_uuid = Uuid::new_v4();
+    let mut cmd = DataSyncCommand {
+        path: table_uuid.to_string(),
+        store: None,
+        column_descriptors,
+        origin: "42".to_string(),
+        sequence_number: None,
+        operation: None,
+    };
+
+    // Stream two messages belonging to the same transaction over a single call, each carrying
+    // its command in the app metadata of the record batch
+    let batch_1 = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Int32Array::from(vec![None, None])),
+            Arc::new(Int32Array::from(vec![1, 2])),
+            Arc::new(StringArray::from(vec!["one", "two"])),
+        ],
+    )?;
+    let batch_2 = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Int32Array::from(vec![Some(2), None])),
+            Arc::new(Int32Array::from(vec![2, 3])),
+            Arc::new(StringArray::from(vec!["two #2", "three"])),
+        ],
+    )?;
+
+    let mut flight_data =
+        arrow_flight::utils::batches_to_flight_data(&schema, vec![batch_1, batch_2])?;
+    flight_data[1].app_metadata = cmd.encode_to_vec().into();
+    cmd.sequence_number = Some(1);
+    flight_data[2].app_metadata = cmd.encode_to_vec().into();
+
+    let responses = client
+        .inner_mut()
+        .do_exchange(futures::stream::iter(flight_data))
+        .await?
+        .into_inner()
+        .map_ok(|data| {
+            DataSyncResponse::decode(data.app_metadata).expect("DataSyncResponse")
+        })
+        .try_collect::<Vec<_>>();
+
+    // Responses get pushed as the changes land in memory, and then once the flush task persists
+    // them, after which the call completes
+    let responses = tokio::time::timeout(Duration::from_secs(10), responses).await??;
+    assert_eq!(
+        responses,
+        vec![
+            DataSyncResponse {
+                accepted: true,
+                memory_sequence_number: None,
+                durable_sequence_number: None,
+                first: true,
+                schema_mismatch: None,
+            },
+            DataSyncResponse {
+                accepted: true,
+                memory_sequence_number: Some(1),
+                durable_sequence_number: None,
+                first: false,
+                schema_mismatch: None,
+            },
+            DataSyncResponse {
+                accepted: true,
+                memory_sequence_number: Some(1),
+                durable_sequence_number: Some(1),
+                first: false,
+                schema_mismatch: None,
+            },
+        ]
+    );
+
+    ctx.metastore
+        .tables
+        .create(
+            &ctx.default_catalog,
+            &ctx.default_schema,
+            "exchanged_table",
+            &Schema::new(vec![
+                Field::new("c1", DataType::Int32, true),
+                Field::new("c2", DataType::Utf8, true),
+            ]),
+            table_uuid,
+        )
+        .await
+        .unwrap();
+
+    let plan = ctx.plan_query("SELECT * FROM exchanged_table").await?;
+    let results = ctx.collect(plan).await?;
+
+    let expected = [
+        "+----+--------+",
+        "| c1 | c2     |",
+        "+----+--------+",
+        "| 1  | one    |",
+        "| 2  | two #2 |",
+        "| 3  | three  |",
+        "+----+--------+",
+    ];
+    assert_batches_sorted_eq!(expected, &results);
+
+    Ok(())
+}
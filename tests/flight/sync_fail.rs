@@ -36,6 +36,7 @@ async fn test_sync_errors() -> std::result::Result<(), Box<dyn std::error::Error
         column_descriptors: vec![],
         origin: "1".to_string(),
         sequence_number: Some(42),
+        operation: None,
     };
 
     // No column descriptors provided
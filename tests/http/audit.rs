@@ -0,0 +1,117 @@
+use std::io::Write;
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+use crate::http::*;
+
+#[tokio::test]
+async fn test_http_audit_log() {
+    let dir = TempDir::new().unwrap();
+    let audit_log_path = dir.path().join("audit.jsonl");
+
+    let config_text = format!(
+        r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.http]
+# sha hash of "write_password"
+write_access = "b786e07f52fc72d32b2163b6f63aa16344fd8d2d84df87b6c231ab33cd5aa125"
+
+[misc.audit_log]
+path = "{}""#,
+        audit_log_path.display()
+    );
+
+    let config = load_config_from_string(&config_text, false, None).unwrap();
+    let context = Arc::from(build_context(config).await.unwrap());
+    let filters = filters(
+        context.clone(),
+        context.config.frontend.http.as_ref().unwrap().clone(),
+    );
+    let (tx, rx) = oneshot::channel();
+    let (addr, server) = warp::serve(filters).bind_with_graceful_shutdown(
+        "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        async {
+            rx.await.ok();
+        },
+    );
+    tokio::task::spawn(server);
+
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TABLE audited AS VALUES (1), (2)",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post_query(&client, &uri, "SELECT * FROM audited", None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post_query(&client, &uri, "DROP TABLE audited", None).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let mut csv_file = Builder::new().suffix(".csv").tempfile().unwrap();
+    csv_file.write_all(b"column1\n3\n4\n5\n").unwrap();
+    let status = Command::new("curl")
+        .args(["-s", "-H", "Authorization: Bearer write_password", "-F"])
+        .arg(format!("data=@{}", csv_file.path().display()))
+        .arg(format!("http://{addr}/upload/public/audited"))
+        .status()
+        .await
+        .unwrap();
+    assert!(status.success());
+
+    tx.send(()).unwrap();
+
+    let events: Vec<Value> = std::fs::read_to_string(&audit_log_path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 4);
+
+    assert_eq!(events[0]["principal"], "writer");
+    assert_eq!(events[0]["frontend"], "http");
+    assert_eq!(events[0]["operation"], "query");
+    assert_eq!(events[0]["database"], "default");
+    assert_eq!(events[0]["sql"], "CREATE TABLE audited AS VALUES (1), (2)");
+    assert_eq!(events[0]["read_only"], false);
+    // Creating the table and inserting the data each produce a version
+    assert_eq!(
+        events[0]["table_versions"],
+        serde_json::json!([{"table": "default.public.audited", "version": 1}])
+    );
+    assert_eq!(events[0]["error"], Value::Null);
+
+    assert_eq!(events[1]["principal"], "anonymous");
+    assert_eq!(events[1]["read_only"], true);
+    assert_eq!(events[1]["table_versions"], serde_json::json!([]));
+    assert_eq!(events[1]["error"], Value::Null);
+
+    assert_eq!(events[2]["principal"], "anonymous");
+    assert_eq!(events[2]["sql"], "DROP TABLE audited");
+    assert_eq!(events[2]["read_only"], false);
+    assert!(events[2]["error"].is_string());
+
+    assert_eq!(events[3]["principal"], "writer");
+    assert_eq!(events[3]["operation"], "upload");
+    assert_eq!(events[3]["sql"], Value::Null);
+    assert_eq!(events[3]["target"], "public.audited");
+    assert_eq!(events[3]["rows_affected"], 3);
+    assert_eq!(
+        events[3]["table_versions"],
+        serde_json::json!([{"table": "default.public.audited", "version": 2}])
+    );
+    assert_eq!(events[3]["error"], Value::Null);
+}
@@ -0,0 +1,112 @@
+use crate::http::*;
+
+#[tokio::test]
+async fn test_http_query_limits() {
+    let config_text = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.http]
+# sha hash of "write_password"
+write_access = "b786e07f52fc72d32b2163b6f63aa16344fd8d2d84df87b6c231ab33cd5aa125"
+
+[runtime.limits]
+queries_per_minute = 2
+
+[[runtime.limits.principals]]
+principal = "writer"
+queries_per_minute = 10"#;
+
+    let config = load_config_from_string(config_text, false, None).unwrap();
+    let context = Arc::from(build_context(config).await.unwrap());
+    let filters = filters(
+        context.clone(),
+        context.config.frontend.http.as_ref().unwrap().clone(),
+    );
+    let (tx, rx) = oneshot::channel();
+    let (addr, server) = warp::serve(filters).bind_with_graceful_shutdown(
+        "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        async {
+            rx.await.ok();
+        },
+    );
+    tokio::task::spawn(server);
+
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    for _ in 0..2 {
+        let resp = post_query(&client, &uri, "SELECT 1", None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    // Anonymous clients are over their quota now, but the writer has its own
+    let resp = post_query(&client, &uri, "SELECT 1", None).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        response_text(resp).await,
+        "Principal \"anonymous\" exceeded the limit of 2 queries per minute"
+    );
+
+    let resp = post_query(&client, &uri, "SELECT 1", Some("write_password")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    tx.send(()).unwrap();
+}
+
+#[tokio::test]
+async fn test_http_query_memory_limit() {
+    let config_text = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[frontend.http]
+
+[runtime.limits]
+max_query_memory = 1"#;
+
+    let config = load_config_from_string(config_text, false, None).unwrap();
+    let context = Arc::from(build_context(config).await.unwrap());
+    let filters = filters(
+        context.clone(),
+        context.config.frontend.http.as_ref().unwrap().clone(),
+    );
+    let (tx, rx) = oneshot::channel();
+    let (addr, server) = warp::serve(filters).bind_with_graceful_shutdown(
+        "127.0.0.1:0".parse::<SocketAddr>().unwrap(),
+        async {
+            rx.await.ok();
+        },
+    );
+    tokio::task::spawn(server);
+
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    let resp = post_query(&client, &uri, "SELECT 1", None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The build side of the join needs more than the 1MB the query can reserve
+    let resp = post_query(
+        &client,
+        &uri,
+        "WITH t AS (SELECT unnest(range(1000000)) AS v) \
+        SELECT count(*) FROM t a JOIN t b ON a.v = b.v",
+        None,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response_text(resp)
+        .await
+        .ends_with("maximum query memory is 1048576 bytes"));
+
+    tx.send(()).unwrap();
+}
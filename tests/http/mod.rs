@@ -1,3 +1,5 @@
+mod audit;
+mod limits;
 mod query;
 mod upload;
 
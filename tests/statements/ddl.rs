@@ -1,4 +1,5 @@
 use crate::statements::*;
+use datafusion_common::tree_node::{TreeNode, TreeNodeRecursion};
 
 #[rstest]
 #[tokio::test]
@@ -472,6 +473,916 @@ async fn test_create_table_schema_already_exists() {
     );
 }
 
+#[tokio::test]
+async fn test_create_view() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE VIEW test_view AS \
+            SELECT some_int_value, some_value FROM test_table WHERE some_int_value > 1111",
+        )
+        .await?;
+
+    // Views can reference other views
+    context
+        .plan_query(
+            "CREATE VIEW test_view_2 AS SELECT sum(some_int_value) AS total FROM test_view",
+        )
+        .await?;
+
+    // Unqualified names get resolved against the schema of the view
+    context.plan_query("CREATE SCHEMA new_schema").await?;
+    create_table_and_insert(&context, "new_schema.test_table").await;
+    context
+        .plan_query(
+            "CREATE VIEW new_schema.test_view AS SELECT count(*) AS count FROM test_table",
+        )
+        .await?;
+
+    let results = list_tables_query(&context).await;
+    let expected = [
+        "+--------------------+-------------+",
+        "| table_schema       | table_name  |",
+        "+--------------------+-------------+",
+        "| information_schema | columns     |",
+        "| information_schema | df_settings |",
+        "| information_schema | schemata    |",
+        "| information_schema | tables      |",
+        "| information_schema | views       |",
+        "| new_schema         | test_table  |",
+        "| new_schema         | test_view   |",
+        "| public             | test_table  |",
+        "| public             | test_view   |",
+        "| public             | test_view_2 |",
+        "+--------------------+-------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query("SELECT * FROM test_view ORDER BY some_int_value")
+        .await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+----------------+------------+",
+        "| some_int_value | some_value |",
+        "+----------------+------------+",
+        "| 2222           | 43.0       |",
+        "| 3333           | 44.0       |",
+        "+----------------+------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Views always reflect the latest table contents
+    context
+        .plan_query("INSERT INTO test_table (some_int_value) VALUES (4444)")
+        .await?;
+
+    let plan = context
+        .plan_query(
+            "SELECT v2.total, v.count FROM test_view_2 v2, new_schema.test_view v",
+        )
+        .await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+-------+-------+",
+        "| total | count |",
+        "+-------+-------+",
+        "| 9999  | 3     |",
+        "+-------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Re-creating an existing view or a view with the name of an existing table is rejected
+    let err = context
+        .plan_query("CREATE VIEW test_view AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: View \"test_view\" already exists"
+    );
+
+    let err = context
+        .plan_query("CREATE VIEW test_table AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Table test_table already exists, can't create a view with the same name"
+    );
+
+    // Replacing a view propagates to views that depend on it
+    context
+        .plan_query("CREATE OR REPLACE VIEW test_view AS SELECT 1 AS some_int_value")
+        .await?;
+
+    let plan = context.plan_query("SELECT * FROM test_view_2").await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+-------+",
+        "| total |",
+        "+-------+",
+        "| 1     |",
+        "+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Recursive views get detected at query time
+    context
+        .plan_query("CREATE OR REPLACE VIEW test_view AS SELECT total AS some_int_value FROM test_view_2")
+        .await?;
+    let err = context
+        .plan_query("SELECT * FROM test_view")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: View default.public.test_view references itself recursively"
+    );
+
+    // Drop the views
+    context.plan_query("DROP VIEW test_view").await?;
+    context.plan_query("DROP VIEW test_view_2").await?;
+    context.plan_query("DROP VIEW IF EXISTS test_view").await?;
+
+    let err = context.plan_query("DROP VIEW test_view").await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: View \"test_view\" doesn't exist"
+    );
+
+    // Dropping the schema also drops the views in it
+    context.plan_query("DROP SCHEMA new_schema").await?;
+
+    let results = list_tables_query(&context).await;
+    let expected = [
+        "+--------------------+-------------+",
+        "| table_schema       | table_name  |",
+        "+--------------------+-------------+",
+        "| information_schema | columns     |",
+        "| information_schema | df_settings |",
+        "| information_schema | schemata    |",
+        "| information_schema | tables      |",
+        "| information_schema | views       |",
+        "| public             | test_table  |",
+        "+--------------------+-------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_and_drop_policies() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE POLICY low_values ON test_table TO alice, Bob \
+            USING (some_int_value < 3000)",
+        )
+        .await?;
+    context
+        .plan_query(
+            "CREATE POLICY own_value ON test_table TO alice \
+            USING (CAST(some_value AS VARCHAR) = current_user OR some_value = 42)",
+        )
+        .await?;
+
+    let query = "SELECT some_int_value FROM test_table ORDER BY 1";
+
+    // All policies applying to the principal get combined
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 1111           |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(expected, &query_as(&context, "alice", query).await?);
+
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 1111           |",
+        "| 2222           |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(expected, &query_as(&context, "Bob", query).await?);
+
+    // Principal names are case-sensitive, even when unquoted
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 1111           |",
+        "| 2222           |",
+        "| 3333           |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(expected, &query_as(&context, "bob", query).await?);
+
+    // Invalid policies are rejected
+    let err = context
+        .plan_query("CREATE POLICY low_values ON test_table USING (TRUE)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Policy \"low_values\" on table \"test_table\" already exists"
+    );
+
+    let err = context
+        .plan_query("CREATE POLICY bad ON test_table USING (some_int_value + 1)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Policy predicate some_int_value + 1 must be a boolean expression, got Int64"
+    );
+
+    // Drop the policies
+    context
+        .plan_query("DROP POLICY low_values ON test_table")
+        .await?;
+    context
+        .plan_query("DROP POLICY own_value ON test_table")
+        .await?;
+    context
+        .plan_query("DROP POLICY IF EXISTS own_value ON test_table")
+        .await?;
+
+    let err = context
+        .plan_query("DROP POLICY own_value ON test_table")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Policy \"own_value\" on table \"test_table\" doesn't exist"
+    );
+
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 1111           |",
+        "| 2222           |",
+        "| 3333           |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(expected, &query_as(&context, "alice", query).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_column_masking_policies() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE POLICY hide_value ON test_table FOR COLUMN some_value USING (NULL)",
+        )
+        .await?;
+    context
+        .plan_query(
+            "CREATE POLICY truncate_int ON test_table FOR COLUMN some_int_value TO alice \
+            USING (some_int_value % 1000)",
+        )
+        .await?;
+
+    let expected = [
+        "+----------------+------------+",
+        "| some_int_value | some_value |",
+        "+----------------+------------+",
+        "| 111            |            |",
+        "| 222            |            |",
+        "| 333            |            |",
+        "+----------------+------------+",
+    ];
+    assert_batches_eq!(
+        expected,
+        &query_as(
+            &context,
+            "alice",
+            "SELECT some_int_value, some_value FROM test_table ORDER BY 1"
+        )
+        .await?
+    );
+
+    let expected = [
+        "+----------------+------------+",
+        "| some_int_value | some_value |",
+        "+----------------+------------+",
+        "| 1111           |            |",
+        "| 2222           |            |",
+        "| 3333           |            |",
+        "+----------------+------------+",
+    ];
+    assert_batches_eq!(
+        expected,
+        &query_as(
+            &context,
+            "bob",
+            "SELECT some_int_value, some_value FROM test_table ORDER BY 1"
+        )
+        .await?
+    );
+
+    // Filters apply to the masked values
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 333            |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(
+        expected,
+        &query_as(
+            &context,
+            "alice",
+            "SELECT some_int_value FROM test_table WHERE some_int_value > 300"
+        )
+        .await?
+    );
+
+    // The mask has to be convertible to the column type
+    let err = context
+        .plan_query(
+            "CREATE POLICY bad ON test_table FOR COLUMN some_time USING (some_bool_value)",
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Policy expression some_bool_value of type Boolean can't be used to mask column some_time of type Timestamp(Microsecond, None)"
+    );
+
+    context
+        .plan_query("DROP POLICY truncate_int ON test_table")
+        .await?;
+
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 3333           |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(
+        expected,
+        &query_as(
+            &context,
+            "alice",
+            "SELECT some_int_value FROM test_table WHERE some_int_value > 3000"
+        )
+        .await?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_policies_restrict_data_read_by_writes() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE POLICY low_values ON test_table TO alice \
+            USING (some_int_value < 3000)",
+        )
+        .await?;
+    context
+        .plan_query("CREATE TABLE target (some_int_value BIGINT)")
+        .await?;
+
+    let alice = UserContext {
+        principal: Principal::Token("alice".to_string()),
+        policy: AccessPolicy::free_for_all(),
+    };
+
+    // The source of a MERGE only yields the rows visible to the user
+    let plan = context
+        .create_logical_plan(
+            "MERGE INTO target USING test_table s \
+            ON target.some_int_value = s.some_int_value \
+            WHEN NOT MATCHED THEN INSERT VALUES (s.some_int_value)",
+        )
+        .await?;
+    let plan = context.apply_access_policies(plan, &alice)?.data;
+    context.create_physical_plan(&plan).await?;
+
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 1111           |",
+        "| 2222           |",
+        "+----------------+",
+    ];
+    let plan = context
+        .plan_query("SELECT * FROM target ORDER BY 1")
+        .await?;
+    assert_batches_eq!(expected, &context.collect(plan).await?);
+
+    // UPDATE and DELETE rewrite all the rows of their target, but the subqueries they use to
+    // pick the rows only see the rows visible to the user
+    for query in [
+        "UPDATE target SET some_int_value = 0 \
+        WHERE some_int_value IN (SELECT some_int_value FROM test_table)",
+        "DELETE FROM target \
+        WHERE some_int_value IN (SELECT some_int_value FROM test_table)",
+    ] {
+        let plan = context.create_logical_plan(query).await?;
+        let plan = context.apply_access_policies(plan, &alice)?;
+        assert!(plan.transformed);
+        assert_contains!(plan.data.display_indent().to_string(), "Int64(3000)");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_and_refresh_materialized_view() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE MATERIALIZED VIEW test_mv AS \
+            SELECT some_int_value, some_value FROM test_table WHERE some_int_value > 1111",
+        )
+        .await?;
+
+    let expected = [
+        "+----------------+------------+",
+        "| some_int_value | some_value |",
+        "+----------------+------------+",
+        "| 2222           | 43.0       |",
+        "| 3333           | 44.0       |",
+        "+----------------+------------+",
+    ];
+    let plan = context
+        .plan_query("SELECT * FROM test_mv ORDER BY some_int_value")
+        .await?;
+    assert_batches_eq!(expected, &context.collect(plan).await?);
+
+    // Changes to the underlying table only show up after a refresh
+    context
+        .plan_query(
+            "INSERT INTO test_table (some_int_value, some_value) VALUES (4444, 45)",
+        )
+        .await?;
+
+    let plan = context
+        .plan_query("SELECT * FROM test_mv ORDER BY some_int_value")
+        .await?;
+    assert_batches_eq!(expected, &context.collect(plan).await?);
+
+    context
+        .plan_query("REFRESH MATERIALIZED VIEW test_mv")
+        .await?;
+
+    let plan = context
+        .plan_query("SELECT * FROM test_mv ORDER BY some_int_value")
+        .await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+----------------+------------+",
+        "| some_int_value | some_value |",
+        "+----------------+------------+",
+        "| 2222           | 43.0       |",
+        "| 3333           | 44.0       |",
+        "| 4444           | 45.0       |",
+        "+----------------+------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The refresh is a new version of the same table, so the history is preserved
+    let table = context.try_get_delta_table("test_mv").await?;
+    assert_eq!(table.version(), 2);
+
+    let err = context
+        .plan_query("REFRESH MATERIALIZED VIEW test_table")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: test_table is not a materialized view"
+    );
+
+    // The statement that REFRESH is conveyed as can't be used to refresh the view
+    let err = context
+        .plan_query("MSCK REPAIR TABLE test_mv")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "SQL error: ParserError(\"MSCK is not supported\")"
+    );
+
+    let err = context
+        .plan_query("CREATE MATERIALIZED VIEW test_mv AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Table \"test_mv\" already exists"
+    );
+
+    // Materialized views are dropped like any other table
+    context.plan_query("DROP TABLE test_mv").await?;
+
+    let results = list_tables_query(&context).await;
+    let expected = [
+        "+--------------------+-------------+",
+        "| table_schema       | table_name  |",
+        "+--------------------+-------------+",
+        "| information_schema | columns     |",
+        "| information_schema | df_settings |",
+        "| information_schema | schemata    |",
+        "| information_schema | tables      |",
+        "| information_schema | views       |",
+        "| public             | test_table  |",
+        "+--------------------+-------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_materialized_view_failure_cleans_up() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    // The query only fails once executed, after the view has been created
+    let err = context
+        .plan_query(
+            "CREATE MATERIALIZED VIEW test_mv AS \
+            SELECT some_int_value / (some_int_value - some_int_value) AS ratio FROM test_table",
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Divide by zero"), "{err}");
+
+    let results = list_tables_query(&context).await;
+    let expected = [
+        "+--------------------+-------------+",
+        "| table_schema       | table_name  |",
+        "+--------------------+-------------+",
+        "| information_schema | columns     |",
+        "| information_schema | df_settings |",
+        "| information_schema | schemata    |",
+        "| information_schema | tables      |",
+        "| information_schema | views       |",
+        "| public             | test_table  |",
+        "+--------------------+-------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The name is free to be used again
+    context
+        .plan_query(
+            "CREATE MATERIALIZED VIEW test_mv AS SELECT some_int_value FROM test_table",
+        )
+        .await?;
+    let plan = context
+        .plan_query("SELECT COUNT(*) AS count FROM test_mv")
+        .await?;
+    let expected = [
+        "+-------+",
+        "| count |",
+        "+-------+",
+        "| 3     |",
+        "+-------+",
+    ];
+    assert_batches_eq!(expected, &context.collect(plan).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_alter_table_columns() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query("CREATE TABLE test_table (id INT, value VARCHAR)")
+        .await?;
+    context
+        .plan_query("INSERT INTO test_table VALUES (1, 'one'), (2, 'two')")
+        .await?;
+
+    // Existing rows get NULLs for the new column
+    context
+        .plan_query("ALTER TABLE test_table ADD COLUMN extra DOUBLE")
+        .await?;
+    context
+        .plan_query("INSERT INTO test_table VALUES (3, 'three', 3.3)")
+        .await?;
+    context
+        .plan_query("ALTER TABLE test_table RENAME COLUMN value TO name")
+        .await?;
+    context
+        .plan_query("ALTER TABLE test_table DROP COLUMN id")
+        .await?;
+
+    let plan = context
+        .plan_query("SELECT * FROM test_table ORDER BY name")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+-------+-------+",
+        "| name  | extra |",
+        "+-------+-------+",
+        "| one   |       |",
+        "| three | 3.3   |",
+        "| two   |       |",
+        "+-------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Each alteration is a separate table version, and older versions keep their schema
+    let mut table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(table.version(), 5);
+    let column_names = |schema: arrow::datatypes::SchemaRef| {
+        schema
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        column_names(TableProvider::schema(&table)),
+        vec!["name", "extra"]
+    );
+
+    table.load_version(1).await?;
+    assert_eq!(
+        column_names(TableProvider::schema(&table)),
+        vec!["id", "value"]
+    );
+    table.load_version(3).await?;
+    assert_eq!(
+        column_names(TableProvider::schema(&table)),
+        vec!["id", "value", "extra"]
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE test_table DROP COLUMN id")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Column id not found in table test_table"
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE test_table ADD COLUMN extra INT")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Column extra already exists in table test_table"
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE test_table ADD COLUMN mandatory INT NOT NULL")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Can't add a non-nullable column mandatory to table test_table"
+    );
+
+    // A column re-added with the name of a dropped one doesn't see the dropped values
+    context
+        .plan_query("ALTER TABLE test_table DROP COLUMN extra")
+        .await?;
+    context
+        .plan_query("ALTER TABLE test_table ADD COLUMN extra DOUBLE")
+        .await?;
+    let plan = context
+        .plan_query("SELECT * FROM test_table ORDER BY name")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+-------+-------+",
+        "| name  | extra |",
+        "+-------+-------+",
+        "| one   |       |",
+        "| three |       |",
+        "| two   |       |",
+        "+-------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_partitioned_table() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query(
+            "CREATE TABLE test_table (key INT, value VARCHAR, region VARCHAR) \
+            PARTITIONED BY (region)",
+        )
+        .await?;
+    context
+        .plan_query(
+            "INSERT INTO test_table VALUES \
+            (1, 'one', 'eu'), (2, 'two', 'us'), (3, 'three', 'eu'), (4, 'four', NULL)",
+        )
+        .await?;
+
+    // Each partition gets its own Hive-style directory
+    let table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(table.metadata()?.partition_columns, vec!["region"]);
+    let partition_files = table
+        .snapshot()?
+        .file_actions()?
+        .into_iter()
+        .map(|add| (add.path, add.partition_values["region"].clone()))
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(partition_files.len(), 3);
+    assert!(partition_files[0]
+        .0
+        .starts_with("region=__HIVE_DEFAULT_PARTITION__/part-00000-"));
+    assert_eq!(partition_files[0].1, None);
+    assert!(partition_files[1].0.starts_with("region=eu/part-00001-"));
+    assert_eq!(partition_files[1].1, Some("eu".to_string()));
+    assert!(partition_files[2].0.starts_with("region=us/part-00002-"));
+    assert_eq!(partition_files[2].1, Some("us".to_string()));
+
+    let plan = context
+        .plan_query(
+            "SELECT key, value, region FROM test_table WHERE region = 'eu' ORDER BY key",
+        )
+        .await?;
+
+    // Only the file of the matching partition gets scanned
+    let mut files_scanned = 0;
+    plan.apply(|node| {
+        if let Some(scanned) = node
+            .metrics()
+            .and_then(|metrics| metrics.sum_by_name("files_scanned"))
+        {
+            files_scanned += scanned.as_usize();
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    assert_eq!(files_scanned, 1);
+
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+-----+-------+--------+",
+        "| key | value | region |",
+        "+-----+-------+--------+",
+        "| 1   | one   | eu     |",
+        "| 3   | three | eu     |",
+        "+-----+-------+--------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // CREATE TABLE AS partitions the data too
+    context
+        .plan_query(
+            "CREATE TABLE test_ctas PARTITIONED BY (region) AS \
+            SELECT key, region FROM test_table WHERE key < 4",
+        )
+        .await?;
+
+    let table = context.try_get_delta_table("test_ctas").await?;
+    assert_eq!(table.metadata()?.partition_columns, vec!["region"]);
+    assert_eq!(table.snapshot()?.file_actions()?.len(), 2);
+
+    let plan = context
+        .plan_query("SELECT key, region FROM test_ctas ORDER BY key")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+-----+--------+",
+        "| key | region |",
+        "+-----+--------+",
+        "| 1   | eu     |",
+        "| 2   | us     |",
+        "| 3   | eu     |",
+        "+-----+--------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("CREATE TABLE test_missing (key INT) PARTITIONED BY (region)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Partition column region not found in the table"
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE test_table DROP COLUMN region")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Can't drop or rename partition column region of table test_table"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_clustered_table() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query("CREATE TABLE test_table (ts INT, value VARCHAR) CLUSTER BY (ts)")
+        .await?;
+    context
+        .plan_query("INSERT INTO test_table VALUES (3, 'a'), (1, 'c'), (2, 'b')")
+        .await?;
+
+    let table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(
+        table.metadata()?.configuration["seafowl.clusterBy"],
+        Some(r#"["ts"]"#.to_string())
+    );
+
+    // The rows are written out sorted by the clustering key
+    let plan = context
+        .plan_query("SELECT ts, value FROM test_table")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+----+-------+",
+        "| ts | value |",
+        "+----+-------+",
+        "| 1  | c     |",
+        "| 2  | b     |",
+        "| 3  | a     |",
+        "+----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    context
+        .plan_query(
+            "CREATE TABLE test_ctas CLUSTER BY (value) AS SELECT ts, value FROM test_table",
+        )
+        .await?;
+
+    let plan = context
+        .plan_query("SELECT ts, value FROM test_ctas")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+----+-------+",
+        "| ts | value |",
+        "+----+-------+",
+        "| 3  | a     |",
+        "| 2  | b     |",
+        "| 1  | c     |",
+        "+----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("CREATE TABLE test_missing (ts INT) CLUSTER BY (value)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Clustering column value not found in the table"
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE test_table RENAME COLUMN ts TO time")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Can't drop or rename clustering column ts of table test_table"
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_create_table_in_staging_schema() {
     let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
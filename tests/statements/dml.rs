@@ -529,6 +529,140 @@ async fn test_update_statement_errors() {
         .contains("Cannot cast string 'nope' to value of Int64 type"));
 }
 
+#[tokio::test]
+async fn test_merge_statement() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query("CREATE TABLE target (id INT, value VARCHAR, updates INT)")
+        .await?;
+    context
+        .plan_query(
+            "INSERT INTO target VALUES (1, 'one', 0), (2, 'two', 0), (3, 'three', 0)",
+        )
+        .await?;
+    context
+        .plan_query("CREATE TABLE source (id INT, value VARCHAR)")
+        .await?;
+    context
+        .plan_query("INSERT INTO source VALUES (2, 'TWO'), (3, NULL), (4, 'FOUR')")
+        .await?;
+
+    context
+        .plan_query(
+            "MERGE INTO target t USING source s ON t.id = s.id
+            WHEN MATCHED AND s.value IS NULL THEN DELETE
+            WHEN MATCHED THEN UPDATE SET value = s.value, updates = t.updates + 1
+            WHEN NOT MATCHED THEN INSERT (id, value) VALUES (s.id, s.value)",
+        )
+        .await?;
+
+    let plan = context
+        .plan_query("SELECT * FROM target ORDER BY id")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+----+-------+---------+",
+        "| id | value | updates |",
+        "+----+-------+---------+",
+        "| 1  | one   | 0       |",
+        "| 2  | TWO   | 1       |",
+        "| 4  | FOUR  |         |",
+        "+----+-------+---------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // All the changes are committed in a single table version
+    let table = context.try_get_delta_table("target").await?;
+    assert_eq!(table.version(), 2);
+
+    let err = context
+        .plan_query(
+            "MERGE INTO target USING source ON target.id = source.id \
+            WHEN MATCHED THEN UPDATE SET nonexistent = 1",
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Column nonexistent not found in table target"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merge_statement_rewrites_affected_files() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query("CREATE TABLE target (id INT, value VARCHAR)")
+        .await?;
+    context
+        .plan_query("INSERT INTO target VALUES (1, 'one')")
+        .await?;
+    let mut table = context.try_get_delta_table("target").await?;
+    table.load().await?;
+    let untouched_file = table.snapshot()?.file_actions()?[0].path.clone();
+
+    context
+        .plan_query("INSERT INTO target VALUES (2, 'two'), (3, 'three')")
+        .await?;
+    context
+        .plan_query("CREATE TABLE source (id INT, value VARCHAR)")
+        .await?;
+    context
+        .plan_query("INSERT INTO source VALUES (3, 'THREE'), (4, 'FOUR')")
+        .await?;
+
+    let merge = "MERGE INTO target t USING source s ON t.id = s.id
+        WHEN MATCHED THEN UPDATE SET value = s.value
+        WHEN NOT MATCHED THEN INSERT VALUES (s.id, s.value)";
+    context.plan_query(merge).await?;
+
+    let plan = context
+        .plan_query("SELECT * FROM target ORDER BY id")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+----+-------+",
+        "| id | value |",
+        "+----+-------+",
+        "| 1  | one   |",
+        "| 2  | two   |",
+        "| 3  | THREE |",
+        "| 4  | FOUR  |",
+        "+----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Only the file with the matched row got replaced
+    table.load().await?;
+    let files = table.snapshot()?.file_actions()?;
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().any(|add| add.path == untouched_file));
+
+    // A target row matched by multiple source rows can't be merged unambiguously
+    context
+        .plan_query("INSERT INTO source VALUES (1, 'ONE'), (1, 'UNO')")
+        .await?;
+    let err = context.plan_query(merge).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Execution error: MERGE matched a target row with more than one source row"
+    );
+
+    let plan = context
+        .plan_query("SELECT * FROM target ORDER BY id")
+        .await?;
+    let results = context.collect(plan).await?;
+    assert_batches_eq!(expected, &results);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_copy_to_statement() -> Result<()> {
     let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
@@ -27,6 +27,7 @@ use rstest::rstest;
 use tempfile::TempDir;
 
 use crate::fixtures::{fake_gcs_creds, FAKE_GCS_CREDS_PATH};
+use seafowl::auth::{AccessPolicy, Principal, UserContext};
 use seafowl::config::context::build_context;
 use seafowl::config::schema::load_config_from_string;
 use seafowl::context::SeafowlContext;
@@ -36,6 +37,7 @@ use seafowl::system_tables::SYSTEM_SCHEMA;
 
 mod ddl;
 mod dml;
+mod optimize;
 mod query;
 // Hack because integration tests do not set cfg(test)
 // https://users.rust-lang.org/t/sharing-helper-function-between-unit-and-integration-tests/9941/2
@@ -209,6 +211,22 @@ pub async fn create_table_and_insert(context: &SeafowlContext, table_name: &str)
         .unwrap();
 }
 
+// Run the query on behalf of a named token bearer without write access
+pub async fn query_as(
+    context: &SeafowlContext,
+    principal: &str,
+    query: &str,
+) -> Result<Vec<RecordBatch>> {
+    let user_context = UserContext {
+        principal: Principal::Token(principal.to_string()),
+        policy: AccessPolicy::free_for_all().with_write_disabled(),
+    };
+    let plan = context.create_logical_plan(query).await?;
+    let plan = context.apply_access_policies(plan, &user_context)?.data;
+    let plan = context.create_physical_plan(&plan).await?;
+    context.collect(plan).await
+}
+
 pub async fn create_table_and_some_partitions(
     context: &SeafowlContext,
     table_name: &str,
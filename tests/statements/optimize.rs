@@ -0,0 +1,97 @@
+use crate::statements::*;
+
+#[tokio::test]
+async fn test_optimize_table() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query(
+            "CREATE TABLE test_table (key INT, region VARCHAR) PARTITIONED BY (region)",
+        )
+        .await?;
+    context
+        .plan_query("INSERT INTO test_table VALUES (1, 'eu'), (2, 'us')")
+        .await?;
+    context
+        .plan_query("INSERT INTO test_table VALUES (3, 'eu')")
+        .await?;
+    context
+        .plan_query("INSERT INTO test_table VALUES (4, 'eu'), (5, 'us')")
+        .await?;
+
+    let partition_file_counts = |table: &deltalake::DeltaTable| -> Result<Vec<_>> {
+        Ok(table
+            .snapshot()?
+            .file_actions()?
+            .into_iter()
+            .map(|add| add.partition_values["region"].clone().unwrap())
+            .counts()
+            .into_iter()
+            .sorted()
+            .collect())
+    };
+
+    let table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(table.version(), 3);
+    assert_eq!(
+        partition_file_counts(&table)?,
+        vec![("eu".to_string(), 3), ("us".to_string(), 2)]
+    );
+
+    // No files match the filter, so there's nothing to do
+    context
+        .plan_query("OPTIMIZE test_table WHERE key > 100")
+        .await?;
+    let table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(table.version(), 3);
+
+    // Only the files of the `eu` partition can be compacted here
+    context
+        .plan_query("OPTIMIZE test_table WHERE key >= 3")
+        .await?;
+    let table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(table.version(), 4);
+    assert_eq!(
+        partition_file_counts(&table)?,
+        vec![("eu".to_string(), 2), ("us".to_string(), 2)]
+    );
+
+    context.plan_query("OPTIMIZE test_table").await?;
+    let table = context.try_get_delta_table("test_table").await?;
+    assert_eq!(table.version(), 5);
+    assert_eq!(
+        partition_file_counts(&table)?,
+        vec![("eu".to_string(), 1), ("us".to_string(), 1)]
+    );
+
+    // The table data is unchanged
+    let plan = context
+        .plan_query("SELECT key, region FROM test_table ORDER BY key")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+-----+--------+",
+        "| key | region |",
+        "+-----+--------+",
+        "| 1   | eu     |",
+        "| 2   | us     |",
+        "| 3   | eu     |",
+        "| 4   | eu     |",
+        "| 5   | us     |",
+        "+-----+--------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The statement that OPTIMIZE is conveyed as can't be used to compact the table
+    let err = context
+        .plan_query("ANALYZE TABLE test_table")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "SQL error: ParserError(\"ANALYZE is not supported\")"
+    );
+
+    Ok(())
+}
@@ -13,17 +13,19 @@ async fn test_information_schema() {
     let results = context.collect(plan).await.unwrap();
 
     let expected = [
-        "+---------------+--------------------+----------------+------------+",
-        "| table_catalog | table_schema       | table_name     | table_type |",
-        "+---------------+--------------------+----------------+------------+",
-        "| default       | information_schema | columns        | VIEW       |",
-        "| default       | information_schema | df_settings    | VIEW       |",
-        "| default       | system             | dropped_tables | VIEW       |",
-        "| default       | information_schema | schemata       | VIEW       |",
-        "| default       | system             | table_versions | VIEW       |",
-        "| default       | information_schema | tables         | VIEW       |",
-        "| default       | information_schema | views          | VIEW       |",
-        "+---------------+--------------------+----------------+------------+",
+        "+---------------+--------------------+-----------------+------------+",
+        "| table_catalog | table_schema       | table_name      | table_type |",
+        "+---------------+--------------------+-----------------+------------+",
+        "| default       | information_schema | columns         | VIEW       |",
+        "| default       | information_schema | df_settings     | VIEW       |",
+        "| default       | system             | dropped_tables  | VIEW       |",
+        "| default       | system             | query_log       | VIEW       |",
+        "| default       | system             | running_queries | VIEW       |",
+        "| default       | information_schema | schemata        | VIEW       |",
+        "| default       | system             | table_versions  | VIEW       |",
+        "| default       | information_schema | tables          | VIEW       |",
+        "| default       | information_schema | views           | VIEW       |",
+        "+---------------+--------------------+-----------------+------------+",
     ];
 
     assert_batches_eq!(expected, &results);
@@ -43,20 +45,32 @@ async fn test_information_schema() {
     let results = context.collect(plan).await.unwrap();
 
     let expected = vec![
-        "+--------------+----------------+------------------+-------------------------+-------------+",
-        "| table_schema | table_name     | column_name      | data_type               | is_nullable |",
-        "+--------------+----------------+------------------+-------------------------+-------------+",
-        "| system       | dropped_tables | table_schema     | Utf8                    | NO          |",
-        "| system       | dropped_tables | table_name       | Utf8                    | NO          |",
-        "| system       | dropped_tables | uuid             | Utf8                    | NO          |",
-        "| system       | dropped_tables | deletion_status  | Utf8                    | NO          |",
-        "| system       | dropped_tables | drop_time        | Timestamp(Second, None) | NO          |",
-        "| system       | table_versions | table_schema     | Utf8                    | NO          |",
-        "| system       | table_versions | table_name       | Utf8                    | NO          |",
-        "| system       | table_versions | table_version_id | Int64                   | NO          |",
-        "| system       | table_versions | version          | Int64                   | NO          |",
-        "| system       | table_versions | creation_time    | Timestamp(Second, None) | NO          |",
-        "+--------------+----------------+------------------+-------------------------+-------------+",
+        "+--------------+-----------------+------------------+------------------------------+-------------+",
+        "| table_schema | table_name      | column_name      | data_type                    | is_nullable |",
+        "+--------------+-----------------+------------------+------------------------------+-------------+",
+        "| system       | dropped_tables  | table_schema     | Utf8                         | NO          |",
+        "| system       | dropped_tables  | table_name       | Utf8                         | NO          |",
+        "| system       | dropped_tables  | uuid             | Utf8                         | NO          |",
+        "| system       | dropped_tables  | deletion_status  | Utf8                         | NO          |",
+        "| system       | dropped_tables  | drop_time        | Timestamp(Second, None)      | NO          |",
+        "| system       | query_log       | query_id         | Int64                        | NO          |",
+        "| system       | query_log       | principal        | Utf8                         | NO          |",
+        "| system       | query_log       | query            | Utf8                         | NO          |",
+        "| system       | query_log       | start_time       | Timestamp(Millisecond, None) | NO          |",
+        "| system       | query_log       | end_time         | Timestamp(Millisecond, None) | NO          |",
+        "| system       | query_log       | status           | Utf8                         | NO          |",
+        "| system       | query_log       | error            | Utf8                         | YES         |",
+        "| system       | running_queries | query_id         | Int64                        | NO          |",
+        "| system       | running_queries | principal        | Utf8                         | NO          |",
+        "| system       | running_queries | query            | Utf8                         | NO          |",
+        "| system       | running_queries | start_time       | Timestamp(Millisecond, None) | NO          |",
+        "| system       | running_queries | memory_reserved  | Int64                        | NO          |",
+        "| system       | table_versions  | table_schema     | Utf8                         | NO          |",
+        "| system       | table_versions  | table_name       | Utf8                         | NO          |",
+        "| system       | table_versions  | table_version_id | Int64                        | NO          |",
+        "| system       | table_versions  | version          | Int64                        | NO          |",
+        "| system       | table_versions  | creation_time    | Timestamp(Second, None)      | NO          |",
+        "+--------------+-----------------+------------------+------------------------------+-------------+",
     ];
     assert_batches_eq!(expected, &results);
 }
@@ -318,3 +318,97 @@ async fn test_write_time_travel() {
     ];
     assert_batches_eq!(expected, &results);
 }
+
+#[tokio::test]
+async fn test_read_time_travel_by_version() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_some_partitions(&context, "test_table", None).await;
+
+    let plan = context
+        .plan_query(
+            "SELECT \
+                (SELECT count(*) FROM test_table(1)) AS v1, \
+                (SELECT count(*) FROM test_table VERSION AS OF 2) AS v2, \
+                (SELECT count(*) FROM test_table FOR SYSTEM_TIME AS OF 3) AS v3, \
+                (SELECT count(*) FROM test_table) AS latest",
+        )
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+----+----+----+--------+",
+        "| v1 | v2 | v3 | latest |",
+        "+----+----+----+--------+",
+        "| 3  | 6  | 9  | 12     |",
+        "+----+----+----+--------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("SELECT * FROM test_table VERSION AS OF 10")
+        .await
+        .unwrap_err();
+    assert_contains!(err.to_string(), "missing version");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_table_changes() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_some_partitions(&context, "test_table", None).await;
+
+    // Version 5 re-writes the files from versions 2 and 3 without the deleted rows
+    context
+        .plan_query("DELETE FROM test_table WHERE some_value = 47")
+        .await?;
+
+    let plan = context
+        .plan_query(
+            "SELECT some_value, some_other_value, _change_type \
+            FROM table_changes('test_table', 2, 5) \
+            ORDER BY _change_type, some_value",
+        )
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+------------+------------------+--------------+",
+        "| some_value | some_other_value | _change_type |",
+        "+------------+------------------+--------------+",
+        "| 47.0       | 2.0000000000     | delete       |",
+        "| 40.0       | 4.0000000000     | insert       |",
+        "| 41.0       | 4.0000000000     | insert       |",
+        "| 42.0       | 4.0000000000     | insert       |",
+        "| 46.0       | 3.0000000000     | insert       |",
+        "| 48.0       | 3.0000000000     | insert       |",
+        "+------------+------------------+--------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // No changes between a version and itself
+    let plan = context
+        .plan_query("SELECT count(*) AS count FROM table_changes(test_table, 3, 3)")
+        .await?;
+    let results = context.collect(plan).await?;
+
+    let expected = [
+        "+-------+",
+        "| count |",
+        "+-------+",
+        "| 0     |",
+        "+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("SELECT * FROM table_changes('test_table', 3, 1)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Starting version 3 is greater than the ending version 1"
+    );
+
+    Ok(())
+}